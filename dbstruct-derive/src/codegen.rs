@@ -1,14 +1,23 @@
 use proc_macro2::TokenStream;
 use quote::quote;
 
-use crate::ir::{Accessor, Ir, NewMethod, Struct};
+use crate::ir::{Accessor, Builder, FlushMethod, Ir, Kind, NewMethod, Struct, Transaction};
 
 pub fn codegen(ir: Ir) -> TokenStream {
     #![allow(unused_variables)]
     let struct_ident = ir.definition.ident.clone();
     let definition = definition(ir.definition, &ir.bounds);
     let accessors = accessor_impl(ir.accessors);
-    let new_impl = new_impl(ir.new);
+    let new_impl = new_impl(&ir.new, ir.builder.as_ref());
+    let builder = ir
+        .builder
+        .map(|builder| self::builder(builder, &ir.new))
+        .unwrap_or_default();
+    let transaction = ir
+        .transaction
+        .map(|tx| transaction(tx, &struct_ident))
+        .unwrap_or_default();
+    let flush = flush_method(ir.flush, &struct_ident);
     let bounds = ir.bounds;
 
     match bounds {
@@ -19,6 +28,10 @@ pub fn codegen(ir: Ir) -> TokenStream {
                 #new_impl
                 #accessors
             }
+
+            #transaction
+            #flush
+            #builder
         ),
         None => quote!(
             #definition
@@ -27,29 +40,346 @@ pub fn codegen(ir: Ir) -> TokenStream {
                 #new_impl
                 #accessors
             }
+
+            #transaction
+            #flush
+            #builder
+        ),
+    }
+}
+
+fn flush_method(flush: FlushMethod, struct_ident: &syn::Ident) -> TokenStream {
+    let FlushMethod {
+        vis,
+        is_async,
+        ds,
+        bounds,
+    } = flush;
+    let method = if is_async {
+        quote!(
+            /// Writes every change made so far to disk, completes once
+            /// they are
+            #vis async fn flush_async(
+                &self,
+            ) -> Result<(), dbstruct::Error<<#ds as ::dbstruct::traits::AsyncFlushable>::Error>> {
+                ::dbstruct::traits::AsyncFlushable::flush(&self.ds).await?;
+                Ok(())
+            }
+        )
+    } else {
+        quote!(
+            /// Writes every change made so far to disk, returns once they are
+            #vis fn flush(
+                &self,
+            ) -> Result<(), dbstruct::Error<<#ds as ::dbstruct::traits::Flushable>::Error>> {
+                ::dbstruct::traits::Flushable::flush(&self.ds)?;
+                Ok(())
+            }
+        )
+    };
+
+    match bounds {
+        Some(bounds) => quote!(
+            impl<DS> #struct_ident<DS> #bounds {
+                #method
+            }
+        ),
+        None => quote!(
+            impl #struct_ident {
+                #method
+            }
         ),
     }
 }
 
-fn new_impl(new: NewMethod) -> TokenStream {
+fn transaction(tx: Transaction, struct_ident: &syn::Ident) -> TokenStream {
+    let Transaction {
+        ident,
+        vis,
+        ds,
+        generic,
+        bounds,
+        len_vars,
+        len_tys,
+        accessors,
+        error_ty,
+        flush,
+    } = tx;
+    let accessors = accessor_impl(accessors);
+    // the fields in the transaction do not flush themselves
+    let flush = flush.then(|| {
+        quote!(
+            ::dbstruct::traits::Flushable::flush(&self.ds)
+                .map_err(::dbstruct::traits::TransactionError::Database)?;
+        )
+    });
+    let lens = (0..len_vars.len()).map(syn::Index::from);
+    let lock = quote!(.lock().unwrap_or_else(std::sync::PoisonError::into_inner));
+    let lens_pat = if len_vars.is_empty() {
+        quote!(_)
+    } else {
+        quote!(lens)
+    };
+
+    let (generics, tx_ty, struct_ty) = if generic {
+        (
+            quote!(<'tx, DS: ::dbstruct::traits::Transactional + 'tx>),
+            quote!(#ident<'tx, DS>),
+            quote!(#struct_ident<DS>),
+        )
+    } else {
+        (quote!(<'tx>), quote!(#ident<'tx>), quote!(#struct_ident))
+    };
+    let impl_generics = if generic { quote!(<DS>) } else { quote!() };
+    let arg_ty = if generic {
+        quote!(#ident<'_, DS>)
+    } else {
+        quote!(#ident<'_>)
+    };
+
+    quote!(
+        /// Gives access to the fields inside a transaction, see `transaction`
+        #vis struct #ident #generics {
+            ds: #ds,
+            prefix: ::dbstruct::wrapper::Prefix,
+            #(#len_vars: std::sync::Arc<std::sync::Mutex<#len_tys>>),*
+        }
+
+        impl #generics #tx_ty {
+            #accessors
+        }
+
+        impl #impl_generics #struct_ty #bounds {
+            /// Changes multiple fields atomically. If `f` returns an error none
+            /// of the changes are applied. `f` can run multiple times if the
+            /// transaction conflicts with another. Do not access the fields
+            /// through `self` in `f`, that can deadlock.
+            #vis fn transaction<T, E>(
+                &self,
+                f: impl Fn(&#arg_ty) -> Result<T, E>,
+            ) -> Result<T, ::dbstruct::traits::TransactionError<E, #error_ty>> {
+                // no one can change the vecs while the transaction runs
+                #(let mut #len_vars = self.#len_vars #lock;)*
+                let (value, #lens_pat) = ::dbstruct::traits::Transactional::transaction(&self.ds, |ds| {
+                    let tx = #ident {
+                        ds: ds.clone(),
+                        prefix: self.prefix.clone(),
+                        #(#len_vars: std::sync::Arc::new(std::sync::Mutex::new(*#len_vars))),*
+                    };
+                    let value = f(&tx)?;
+                    let lens = (#(*tx.#len_vars #lock,)*);
+                    Ok((value, lens))
+                })?;
+                #(*#len_vars = lens.#lens;)*
+                #flush
+                Ok(value)
+            }
+        }
+    )
+}
+
+/// the statements opening the struct, `ty` is the type of the struct
+fn open_body(new: &NewMethod, ty: &TokenStream) -> TokenStream {
+    let NewMethod {
+        kind,
+        prelude,
+        stmts,
+        inits,
+        fields,
+        error_ty,
+        ..
+    } = new;
+    match kind {
+        Kind::Blocking => quote!(
+            #(#stmts)*
+            #ty::open_nested(ds, ::dbstruct::wrapper::Prefix::root())
+        ),
+        Kind::SpawnBlocking => quote!(
+            #(#prelude)*
+            ::dbstruct::stores::run_blocking(move || -> Result<#ty, dbstruct::Error<#error_ty>> {
+                #(#stmts)*
+                let prefix = ::dbstruct::wrapper::Prefix::root();
+                #(#inits)*
+                Ok(#ty {
+                    ds: ::dbstruct::stores::Blocking::new(ds),
+                    prefix,
+                    #(#fields),*
+                })
+            })
+            .await
+        ),
+        Kind::Async => quote!(
+            #(#stmts)*
+            let prefix = ::dbstruct::wrapper::Prefix::root();
+            #(#inits)*
+            Ok(#ty {
+                ds,
+                prefix,
+                #(#fields),*
+            })
+        ),
+    }
+}
+
+fn new_impl(new: &NewMethod, builder: Option<&Builder>) -> TokenStream {
     let NewMethod {
-        locals,
+        kind,
+        inits,
         fields,
+        ds_ty,
         vis,
         arg,
         error_ty,
+        ..
     } = new;
+    let asyncness = (*kind != Kind::Blocking).then(|| quote!(async));
+    let new_fn = match builder {
+        Some(Builder { ident, .. }) => {
+            let dot_await = (*kind != Kind::Blocking).then(|| quote!(.await));
+            quote!(
+                #vis #asyncness fn new(#arg) -> Result<Self, dbstruct::Error<#error_ty>> {
+                    Self::builder().path(path).open()#dot_await
+                }
+
+                /// Configures how the database is opened, `new` opens it with
+                /// the default configuration
+                #vis fn builder() -> #ident {
+                    #ident::default()
+                }
+            )
+        }
+        None => {
+            let body = open_body(new, &quote!(Self));
+            quote!(
+                #vis #asyncness fn new(#arg) -> Result<Self, dbstruct::Error<#error_ty>> {
+                    #body
+                }
+            )
+        }
+    };
+    if *kind != Kind::Blocking {
+        return new_fn;
+    }
+
     quote!(
-        #vis fn new(#arg) -> Result<Self, dbstruct::Error<#error_ty>> {
-            #(#locals)*
+        #new_fn
+
+        /// Opens the struct as a field of another, its keys start with
+        /// `prefix`. Used by `#[dbstruct(nested)]`.
+        #[doc(hidden)]
+        pub fn open_nested(
+            ds: #ds_ty,
+            prefix: ::dbstruct::wrapper::Prefix,
+        ) -> Result<Self, dbstruct::Error<#error_ty>> {
+            #(#inits)*
             Ok(Self {
                 ds,
+                prefix,
                 #(#fields),*
             })
         }
     )
 }
 
+fn builder(builder: Builder, new: &NewMethod) -> TokenStream {
+    let Builder {
+        ident,
+        vis,
+        struct_ident,
+    } = builder;
+    let error_ty = &new.error_ty;
+    let asyncness = (new.kind != Kind::Blocking).then(|| quote!(async));
+    let body = open_body(new, &quote!(#struct_ident));
+
+    quote!(
+        /// Configures how the sled database of the struct is opened, see
+        /// `builder`
+        #vis struct #ident {
+            config: ::dbstruct::sled::Config,
+            db: Option<::dbstruct::sled::Db>,
+            tree_name: String,
+        }
+
+        impl Default for #ident {
+            fn default() -> Self {
+                Self {
+                    config: ::dbstruct::sled::Config::default(),
+                    db: None,
+                    tree_name: "DbStruct".to_owned(),
+                }
+            }
+        }
+
+        impl #ident {
+            /// Opens the struct in a tree of a database that is already open,
+            /// the options of the database are ignored. Give each struct its
+            /// own `tree_name` to share a database between them.
+            #vis fn from_db(db: &::dbstruct::sled::Db) -> Self {
+                Self {
+                    db: Some(db.clone()),
+                    ..Self::default()
+                }
+            }
+
+            /// The directory the database is stored in
+            #vis fn path(mut self, path: impl AsRef<std::path::Path>) -> Self {
+                self.config = self.config.path(path);
+                self
+            }
+
+            /// Maximum size in bytes of sled's page cache
+            #vis fn cache_capacity(mut self, bytes: u64) -> Self {
+                self.config = self.config.cache_capacity(bytes);
+                self
+            }
+
+            /// Compress the data using zstd, needs sled's `compression` feature
+            #vis fn use_compression(mut self, compress: bool) -> Self {
+                self.config = self.config.use_compression(compress);
+                self
+            }
+
+            /// The zstd compression level, from 1 up to 22
+            #vis fn compression_factor(mut self, factor: i32) -> Self {
+                self.config = self.config.compression_factor(factor);
+                self
+            }
+
+            /// How often sled flushes in the background, `None` disables it
+            #vis fn flush_every_ms(mut self, every_ms: Option<u64>) -> Self {
+                self.config = self.config.flush_every_ms(every_ms);
+                self
+            }
+
+            /// Delete the database when it is dropped
+            #vis fn temporary(mut self, temporary: bool) -> Self {
+                self.config = self.config.temporary(temporary);
+                self
+            }
+
+            /// The tree the fields are stored in, `DbStruct` by default
+            #vis fn tree_name(mut self, name: impl Into<String>) -> Self {
+                self.tree_name = name.into();
+                self
+            }
+
+            fn open_tree(self) -> Result<::dbstruct::sled::Tree, ::dbstruct::sled::Error> {
+                let db = match self.db {
+                    Some(db) => db,
+                    None => self.config.open()?,
+                };
+                let tree = db.open_tree(self.tree_name)?;
+                tree.set_merge_operator(::dbstruct::stores::counter_merge);
+                Ok(tree)
+            }
+
+            #vis #asyncness fn open(self) -> Result<#struct_ident, dbstruct::Error<#error_ty>> {
+                #body
+            }
+        }
+    )
+}
+
 fn accessor_fn(
     Accessor {
         vis,
@@ -73,6 +403,8 @@ fn definition(definition: Struct, bounds: &Option<syn::WhereClause>) -> TokenStr
         ident,
         vis,
         len_vars: extra_vars,
+        nested,
+        flush_policies,
         db,
     } = definition;
     match bounds {
@@ -81,17 +413,22 @@ fn definition(definition: Struct, bounds: &Option<syn::WhereClause>) -> TokenStr
             quote!(
                 #vis struct #ident<#predicates> {
                     ds: DS,
-                    #(#extra_vars),*
+                    prefix: ::dbstruct::wrapper::Prefix,
+                    #(#extra_vars,)*
+                    #(#nested,)*
+                    #(#flush_policies),*
                 }
             )
         }
         None => quote!(
         #vis struct #ident {
             #db,
-            #(#extra_vars),*
+            prefix: ::dbstruct::wrapper::Prefix,
+            #(#extra_vars,)*
+            #(#nested,)*
+            #(#flush_policies),*
         }),
     }
-    .into()
 }
 
 #[cfg(test)]
@@ -109,10 +446,12 @@ mod tests {
             ident: parse_quote!(Test),
             vis: parse_quote!(pub),
             len_vars: fields
-                .into_iter()
+                .iter()
                 .map(|s| parser.parse_str(s))
                 .map(Result::unwrap)
                 .collect(),
+            nested: vec![parser.parse_str("settings_nested: Settings").unwrap()],
+            flush_policies: Vec::new(),
             db: parser.parse_str("ds: DS").unwrap(),
         }
     }
@@ -139,7 +478,11 @@ mod tests {
             ident: parse_quote!(queue),
             returns: parse_quote!(dbstruct::wrapper::Vec<u32>),
             body: parse_quote!({
-                dbstruct::wrapper::Vec::new(self.ds.clone(), 2, self.queue_len.clone())
+                dbstruct::wrapper::Vec::new(
+                    self.ds.clone(),
+                    self.prefix.field(2),
+                    self.queue_len.clone(),
+                )
             }),
         }
     }
@@ -153,9 +496,13 @@ mod tests {
 
     fn test_new_impl() -> NewMethod {
         NewMethod {
+            kind: Kind::Blocking,
+            prelude: Vec::new(),
             fields: vec![parse_quote!(u8field: 0)],
             vis: parse_quote!(pub),
-            locals: Vec::new(),
+            stmts: Vec::new(),
+            inits: Vec::new(),
+            ds_ty: parse_quote!(DS),
             arg: Some(parse_quote!(ds: DS)),
             error_ty: parse_quote!(DS),
         }
@@ -163,9 +510,9 @@ mod tests {
 
     #[test]
     fn new_impl_is_function_item() {
-        let rust = new_impl(test_new_impl());
+        let rust = new_impl(&test_new_impl(), None);
         println!("{rust}");
-        assert!(syn::parse2::<syn::ItemFn>(rust).is_ok())
+        assert!(syn::parse2::<syn::ItemImpl>(quote!(impl Test { #rust })).is_ok())
     }
 
     #[test]
@@ -173,8 +520,18 @@ mod tests {
         let ir = Ir {
             definition: test_struct(&["u8field: u8"]),
             new: test_new_impl(),
+            builder: None,
             accessors: vec![test_accessor()],
             bounds: parse_quote!(where DS: dbstruct::DataStore + std::clone::Clone),
+            transaction: None,
+            flush: FlushMethod {
+                vis: parse_quote!(pub),
+                is_async: false,
+                ds: parse_quote!(DS),
+                bounds: parse_quote!(
+                    where DS: dbstruct::DataStore + std::clone::Clone + dbstruct::traits::Flushable
+                ),
+            },
         };
         let rust = codegen(ir);
         println!("{rust}");
@@ -204,6 +561,57 @@ pub struct Test {
         assert!(syn::parse2::<syn::File>(rust).is_ok())
     }
 
+    #[test]
+    fn rocksdb_end_to_end() {
+        use crate::model::Model;
+        use syn::parse_str;
+
+        let input_attr = proc_macro2::TokenStream::from_str("db=rocksdb").unwrap();
+        let input_struct: syn::ItemStruct = parse_str(
+            "
+pub struct Test {
+    primes: Vec<u32>,
+    names: HashMap<u8, String>,
+}",
+        )
+        .unwrap();
+
+        let model = Model::try_from(input_struct, input_attr).unwrap();
+        let ir = Ir::from(model);
+        let rust = codegen(ir);
+
+        println!("{rust}");
+        assert!(syn::parse2::<syn::File>(rust).is_ok())
+    }
+
+    #[test]
+    fn async_end_to_end() {
+        use crate::model::Model;
+        use syn::parse_str;
+
+        for attr in ["db=sled, async", "db=trait, async"] {
+            let input_attr = proc_macro2::TokenStream::from_str(attr).unwrap();
+            let input_struct: syn::ItemStruct = parse_str(
+                "
+pub struct Test {
+    primes: Vec<u32>,
+    #[dbstruct(Default)]
+    counter: u8,
+}",
+            )
+            .unwrap();
+
+            let model = Model::try_from(input_struct, input_attr).unwrap();
+            let ir = Ir::from(model);
+            let rust = codegen(ir);
+
+            println!("{rust}");
+            let file = syn::parse2::<syn::File>(rust).unwrap();
+            let code = quote!(#file).to_string();
+            assert!(code.contains("async fn new"), "{code}");
+        }
+    }
+
     #[test]
     fn default_vec_and_map () {
         use crate::model::Model;
@@ -1,31 +1,59 @@
 mod accessor;
+mod builder;
+mod flush_method;
 mod new_method;
 mod struct_def;
+mod transaction;
 
-pub use accessor::Accessor;
-pub use new_method::NewMethod;
+pub use accessor::{Accessor, Store};
+pub use builder::Builder;
+pub use flush_method::FlushMethod;
+pub use new_method::{Kind, NewMethod};
 pub use struct_def::Struct;
+pub use transaction::Transaction;
 use syn::parse_quote;
 
 use crate::model::backend::{Backend, ExtraBound};
-use crate::model::Model;
+use crate::model::codec::Codec;
+use crate::model::flush::Flush;
+use crate::model::key::Key;
+use crate::model::{Field, Model, Wrapper};
 
 pub struct Ir {
     pub definition: Struct,
     pub new: NewMethod,
+    pub builder: Option<Builder>,
     pub accessors: Vec<Accessor>,
     pub bounds: Option<syn::WhereClause>,
+    pub transaction: Option<Transaction>,
+    pub flush: FlushMethod,
 }
 
 fn bound_to_ir(bound: &ExtraBound) -> syn::TraitBound {
     match bound {
         ExtraBound::Atomic => parse_quote!(dbstruct::traits::data_store::Atomic),
         ExtraBound::Ordered => parse_quote!(dbstruct::traits::data_store::Ordered),
+        ExtraBound::Watch => parse_quote!(dbstruct::traits::data_store::Watch),
     }
 }
 
 fn bounds_from(model: &Model) -> Option<syn::WhereClause> {
     match &model.backend {
+        // the async wrappers do not need the extra traits, opening does need
+        // the last key of a vec to find its length
+        Backend::Trait { .. } if model.is_async => {
+            let has_vec = model
+                .fields
+                .iter()
+                .any(|f| matches!(f.wrapper, Wrapper::Vec { .. }));
+            if has_vec {
+                parse_quote!(where DS: dbstruct::traits::AsyncDataStore
+                    + dbstruct::traits::AsyncOrdered
+                    + std::clone::Clone)
+            } else {
+                parse_quote!(where DS: dbstruct::traits::AsyncDataStore + std::clone::Clone)
+            }
+        }
         Backend::Trait { bounds } => {
             let bounds = bounds.iter().map(bound_to_ir);
             parse_quote!(where DS: dbstruct::DataStore + std::clone::Clone + #(#bounds),*)
@@ -37,6 +65,9 @@ fn bounds_from(model: &Model) -> Option<syn::WhereClause> {
 fn backend_type(backend: &Backend) -> syn::Type {
     match backend {
         Backend::Sled => parse_quote!(::dbstruct::sled::Tree),
+        Backend::RocksDB => parse_quote!(
+            ::dbstruct::stores::Watched<std::sync::Arc<::dbstruct::rocksdb::TransactionDB>>
+        ),
         Backend::HashMap => parse_quote!(::dbstruct::stores::HashMap),
         Backend::BTreeMap => parse_quote!(::dbstruct::stores::BTreeMap),
         Backend::Trait { .. } => parse_quote!(DS),
@@ -45,23 +76,82 @@ fn backend_type(backend: &Backend) -> syn::Type {
     }
 }
 
+fn codec_type(codec: Codec) -> syn::Type {
+    match codec {
+        Codec::Bincode => parse_quote!(::dbstruct::codec::Bincode),
+        Codec::Postcard => parse_quote!(::dbstruct::codec::Postcard),
+        Codec::MessagePack => parse_quote!(::dbstruct::codec::MessagePack),
+        Codec::Json => parse_quote!(::dbstruct::codec::Json),
+    }
+}
+
+/// The prefix of the field with `key` in the struct stored at `parent`
+fn field_prefix(parent: syn::Expr, key: Key) -> syn::Expr {
+    match key {
+        Key::Byte(_) => parse_quote!(#parent.field(#key)),
+        Key::Wide(_) => parse_quote!(#parent.wide_field(#key)),
+    }
+}
+
+/// The store the generated struct holds, with the `async` option a blocking
+/// backend is wrapped so it can be used from async code
+fn store_type(model: &Model) -> syn::Type {
+    let backend = backend_type(&model.backend);
+    match model.backend {
+        Backend::Trait { .. } => backend,
+        _ if model.is_async => parse_quote!(::dbstruct::stores::Blocking<#backend>),
+        _ => backend,
+    }
+}
+
+/// The store the wrapper of a field uses, fields with a codec or flush
+/// policy wrap the store of the struct
+fn field_store(model: &Model, field: &Field) -> Store {
+    if field.codec.is_none() && field.flush == Flush::Never {
+        return Store::plain(store_type(model));
+    }
+    let mut store = match model.is_async {
+        true => Store::unblocked(backend_type(&model.backend)),
+        false => Store::plain(store_type(model)),
+    };
+    if let Some(codec) = field.codec {
+        store = store.codec(codec);
+    }
+    if field.flush != Flush::Never {
+        store = store.flushing(struct_def::as_flush_ident(&field.ident));
+    }
+    match model.is_async {
+        true => store.blocking(),
+        false => store,
+    }
+}
+
 impl Ir {
     pub fn from(model: Model) -> Self {
         let definition = Struct::from(&model);
         let new = NewMethod::from(&model, &definition);
+        let builder = Builder::from(&model);
         let bounds = bounds_from(&model);
-        let backend_ty = backend_type(&model.backend);
+        let transaction = Transaction::from(&model, &bounds);
+        let flush = FlushMethod::from(&model, &bounds);
         let accessors = model
             .fields
-            .into_iter()
-            .map(|f| Accessor::from(f, backend_ty.clone()))
+            .iter()
+            .map(|f| (f.clone(), field_store(&model, f)))
+            .map(|(f, store)| match model.is_async {
+                true => Accessor::from_async(f, store),
+                false => Accessor::from(f, store),
+            })
             .collect();
 
         Self {
             definition,
             new,
+            builder,
             accessors,
             bounds,
+            transaction,
+            flush,
         }
     }
 }
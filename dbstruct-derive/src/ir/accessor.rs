@@ -1,10 +1,75 @@
-use proc_macro2::Span;
-use quote::format_ident;
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, ToTokens};
 use syn::spanned::Spanned;
 use syn::{parse_quote, parse_quote_spanned};
 
+use crate::model::codec::Codec;
 use crate::model::{Field, Wrapper};
 
+use super::codec_type;
+
+/// The store a wrapper is created with
+#[derive(Clone)]
+pub struct Store {
+    pub ty: syn::Type,
+    /// creates the store from `self.ds`
+    pub expr: syn::Expr,
+}
+
+impl Store {
+    pub fn plain(ty: syn::Type) -> Self {
+        Self {
+            ty,
+            expr: parse_quote!(self.ds.clone()),
+        }
+    }
+
+    /// `self.ds` storing values using codec
+    pub fn with_codec(ty: syn::Type, codec: Codec) -> Self {
+        Self::plain(ty).codec(codec)
+    }
+
+    /// the blocking `backend` inside `self.ds`, which is a
+    /// `stores::Blocking` used from async code
+    pub fn unblocked(backend: syn::Type) -> Self {
+        Self {
+            ty: backend,
+            expr: parse_quote!(self.ds.clone().into_inner()),
+        }
+    }
+
+    /// stores values using codec
+    pub fn codec(self, codec: Codec) -> Self {
+        let Self { ty, expr } = self;
+        let codec = codec_type(codec);
+        Self {
+            ty: parse_quote!(::dbstruct::codec::WithCodec<#ty, #codec>),
+            expr: parse_quote!(::dbstruct::codec::WithCodec::new(#expr)),
+        }
+    }
+
+    /// flushes after writes following the policy in `self.<policy>`
+    pub fn flushing(self, policy: syn::Ident) -> Self {
+        let Self { ty, expr } = self;
+        Self {
+            ty: parse_quote!(::dbstruct::stores::Flushing<#ty>),
+            expr: parse_quote!(::dbstruct::stores::Flushing::new(
+                #expr,
+                self.#policy.clone()
+            )),
+        }
+    }
+
+    /// makes the blocking store usable from async code
+    pub fn blocking(self) -> Self {
+        let Self { ty, expr } = self;
+        Self {
+            ty: parse_quote!(::dbstruct::stores::Blocking<#ty>),
+            expr: parse_quote!(::dbstruct::stores::Blocking::new(#expr)),
+        }
+    }
+}
+
 pub struct Accessor {
     pub vis: syn::Visibility,
     pub ident: syn::Ident,
@@ -13,56 +78,128 @@ pub struct Accessor {
 }
 
 impl Accessor {
-    pub fn from(field: Field, ds: syn::Type) -> Self {
-        let key = field.key;
+    pub fn from(field: Field, store: Store) -> Self {
+        Self::with_wrappers(field, store, parse_quote!(dbstruct::wrapper))
+    }
+
+    /// accessor returning the async version of the wrapper
+    pub fn from_async(field: Field, store: Store) -> Self {
+        Self::with_wrappers(field, store, parse_quote!(dbstruct::wrapper::asynchronous))
+    }
+
+    fn with_wrappers(field: Field, store: Store, wrappers: syn::Path) -> Self {
+        let Store { ty: ds, expr: ds_expr } = store;
+        // type errors are reported at the span of the wrapper path
+        let at = |span: Span| -> TokenStream {
+            wrappers
+                .to_token_stream()
+                .into_iter()
+                .map(|mut token| {
+                    token.set_span(span);
+                    token
+                })
+                .collect()
+        };
+        let key = super::field_prefix(parse_quote!(self.prefix), field.key);
         let (body, returns) = match field.wrapper {
             #[allow(unused_variables)]
             Wrapper::Vec { ty } => {
                 let len_ident = format_ident!("{}_len", field.ident);
                 let body = parse_quote!({
-                    dbstruct::wrapper::Vec::new(self.ds.clone(), #key, self.#len_ident.clone())
+                    #wrappers::Vec::new(#ds_expr, #key, self.#len_ident.clone())
+                });
+                let wrappers = at(ty.span());
+                let returns = parse_quote_spanned!(ty.span()=> #wrappers::Vec<#ty, #ds>);
+                (body, returns)
+            }
+            #[allow(unused_variables)]
+            Wrapper::VecDeque { ty } => {
+                let ends_ident = format_ident!("{}_len", field.ident);
+                let body = parse_quote!({
+                    #wrappers::VecDeque::new(#ds_expr, #key, self.#ends_ident.clone())
                 });
-                let returns = parse_quote_spanned!(ty.span()=> dbstruct::wrapper::Vec<#ty, #ds>);
+                let wrappers = at(ty.span());
+                let returns = parse_quote_spanned!(ty.span()=> #wrappers::VecDeque<#ty, #ds>);
                 (body, returns)
             }
             #[allow(unused_variables)]
             Wrapper::Map { key_ty, val_ty } => {
                 let body = parse_quote!({
-                    dbstruct::wrapper::Map::new(self.ds.clone(), #key)
+                    #wrappers::Map::new(#ds_expr, #key)
                 });
                 // Using proc_macro2 version until
                 // https://github.com/rust-lang/rust/issues/54725 stabalizes
                 let span = key_ty.span().join(val_ty.span()).unwrap_or(Span::call_site());
+                let wrappers = at(span);
                 let returns =
-                    parse_quote_spanned!(span=> dbstruct::wrapper::Map<#key_ty, #val_ty, #ds>);
+                    parse_quote_spanned!(span=> #wrappers::Map<#key_ty, #val_ty, #ds>);
+                (body, returns)
+            }
+            #[allow(unused_variables)]
+            Wrapper::BTreeMap { key_ty, val_ty } => {
+                let body = parse_quote!({
+                    #wrappers::BTreeMap::new(#ds_expr, #key)
+                });
+                let span = key_ty.span().join(val_ty.span()).unwrap_or(Span::call_site());
+                let wrappers = at(span);
+                let returns =
+                    parse_quote_spanned!(span=> #wrappers::BTreeMap<#key_ty, #val_ty, #ds>);
+                (body, returns)
+            }
+            #[allow(unused_variables)]
+            Wrapper::Set { ty, .. } => {
+                let body = parse_quote!({
+                    #wrappers::Set::new(#ds_expr, #key)
+                });
+                let wrappers = at(ty.span());
+                let returns = parse_quote_spanned!(ty.span()=> #wrappers::Set<#ty, #ds>);
                 (body, returns)
             }
             #[allow(unused_variables)]
             Wrapper::DefaultTrait { ty } => {
                 let body = parse_quote!({
-                    dbstruct::wrapper::DefaultTrait::new(self.ds.clone(), #key)
+                    #wrappers::DefaultTrait::new(#ds_expr, #key)
                 });
+                let wrappers = at(ty.span());
                 let returns =
-                    parse_quote_spanned!(ty.span()=> dbstruct::wrapper::DefaultTrait<#ty, #ds>);
+                    parse_quote_spanned!(ty.span()=> #wrappers::DefaultTrait<#ty, #ds>);
                 (body, returns)
             }
             #[allow(unused_variables)]
             Wrapper::DefaultValue { ty, value } => {
+                let wrappers = at(ty.span());
                 let body = parse_quote_spanned!(ty.span()=> {
                     let default_value = #value;
-                    dbstruct::wrapper::DefaultValue::new(self.ds.clone(), #key, default_value)
+                    #wrappers::DefaultValue::new(#ds_expr, #key, default_value)
                 });
                 let returns =
-                    parse_quote_spanned!(ty.span()=> dbstruct::wrapper::DefaultValue<#ty, #ds>);
+                    parse_quote_spanned!(ty.span()=> #wrappers::DefaultValue<#ty, #ds>);
                 (body, returns)
             }
             #[allow(unused_variables)]
             Wrapper::Option { ty } => {
                 let body = parse_quote!({
-                    dbstruct::wrapper::OptionValue::new(self.ds.clone(), #key)
+                    #wrappers::OptionValue::new(#ds_expr, #key)
                 });
+                let wrappers = at(ty.span());
                 let returns =
-                    parse_quote_spanned!(ty.span()=> dbstruct::wrapper::OptionValue<#ty, #ds>);
+                    parse_quote_spanned!(ty.span()=> #wrappers::OptionValue<#ty, #ds>);
+                (body, returns)
+            }
+            #[allow(unused_variables)]
+            Wrapper::Counter { ty } => {
+                let body = parse_quote!({
+                    #wrappers::Counter::new(#ds_expr, #key)
+                });
+                let wrappers = at(ty.span());
+                let returns = parse_quote_spanned!(ty.span()=> #wrappers::Counter<#ty, #ds>);
+                (body, returns)
+            }
+            // opened together with the struct, it caches lengths of its own
+            Wrapper::Nested { ty } => {
+                let nested_ident = format_ident!("{}_nested", field.ident);
+                let body = parse_quote!({ &self.#nested_ident });
+                let returns = parse_quote_spanned!(ty.span()=> &#ty);
                 (body, returns)
             }
         };
@@ -79,6 +216,8 @@ impl Accessor {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::model::flush::Flush;
+    use crate::model::key::Key;
 
     #[test]
     fn default() {
@@ -88,10 +227,11 @@ mod tests {
             wrapper: Wrapper::DefaultTrait {
                 ty: parse_quote!(u8),
             },
-            key: 1,
+            key: Key::Byte(1),
+            codec: None,
+            flush: Flush::Never,
         };
-        let ds_ty = parse_quote!(DS);
-        let _a = Accessor::from(field, ds_ty);
+        let _a = Accessor::from(field, Store::plain(parse_quote!(DS)));
     }
 
     #[test]
@@ -103,10 +243,11 @@ mod tests {
                 ty: parse_quote!(u8),
                 value: parse_quote!(5 + 12),
             },
-            key: 1,
+            key: Key::Byte(1),
+            codec: None,
+            flush: Flush::Never,
         };
-        let ds_ty = parse_quote!(DS);
-        let _a = Accessor::from(field, ds_ty);
+        let _a = Accessor::from(field, Store::plain(parse_quote!(DS)));
     }
 
     #[test]
@@ -117,10 +258,11 @@ mod tests {
             wrapper: Wrapper::Option {
                 ty: parse_quote!(u8),
             },
-            key: 1,
+            key: Key::Byte(1),
+            codec: None,
+            flush: Flush::Never,
         };
-        let ds_ty = parse_quote!(DS);
-        let _a = Accessor::from(field, ds_ty);
+        let _a = Accessor::from(field, Store::plain(parse_quote!(DS)));
     }
 
     #[test]
@@ -131,10 +273,11 @@ mod tests {
             wrapper: Wrapper::Vec {
                 ty: parse_quote!(u8),
             },
-            key: 1,
+            key: Key::Byte(1),
+            codec: None,
+            flush: Flush::Never,
         };
-        let ds_ty = parse_quote!(DS);
-        let _a = Accessor::from(field, ds_ty);
+        let _a = Accessor::from(field, Store::plain(parse_quote!(DS)));
     }
 
     #[test]
@@ -146,9 +289,86 @@ mod tests {
                 key_ty: parse_quote!(u8),
                 val_ty: parse_quote!(u16),
             },
-            key: 1,
+            key: Key::Byte(1),
+            codec: None,
+            flush: Flush::Never,
+        };
+        let _a = Accessor::from(field, Store::plain(parse_quote!(DS)));
+    }
+
+    #[test]
+    fn btreemap() {
+        let field = Field {
+            ident: parse_quote!(test_a),
+            vis: parse_quote!(pub),
+            wrapper: Wrapper::BTreeMap {
+                key_ty: parse_quote!(u8),
+                val_ty: parse_quote!(u16),
+            },
+            key: Key::Byte(1),
+            codec: None,
+            flush: Flush::Never,
+        };
+        let a = Accessor::from(field, Store::plain(parse_quote!(DS)));
+        let returns = a.returns.to_token_stream().to_string();
+        assert!(returns.contains("BTreeMap"), "{returns}");
+    }
+
+    #[test]
+    fn async_vec() {
+        let field = Field {
+            ident: parse_quote!(test_a),
+            vis: parse_quote!(pub),
+            wrapper: Wrapper::Vec {
+                ty: parse_quote!(u8),
+            },
+            key: Key::Byte(1),
+            codec: None,
+            flush: Flush::Never,
+        };
+        let a = Accessor::from_async(field, Store::plain(parse_quote!(DS)));
+        let returns = a.returns.to_token_stream().to_string();
+        assert!(returns.contains("asynchronous"), "{returns}");
+    }
+
+    #[test]
+    fn codec_wraps_store() {
+        let field = Field {
+            ident: parse_quote!(test_a),
+            vis: parse_quote!(pub),
+            wrapper: Wrapper::Option {
+                ty: parse_quote!(u8),
+            },
+            key: Key::Byte(1),
+            codec: Some(Codec::Json),
+            flush: Flush::Never,
+        };
+        let store = Store::with_codec(parse_quote!(DS), Codec::Json);
+        let a = Accessor::from(field, store);
+        let returns = a.returns.to_token_stream().to_string();
+        assert!(returns.contains("WithCodec"), "{returns}");
+        let body = a.body.to_token_stream().to_string();
+        assert!(body.contains("WithCodec"), "{body}");
+    }
+
+    #[test]
+    fn flush_wraps_store() {
+        let field = Field {
+            ident: parse_quote!(test_a),
+            vis: parse_quote!(pub),
+            wrapper: Wrapper::Option {
+                ty: parse_quote!(u8),
+            },
+            key: Key::Byte(1),
+            codec: Some(Codec::Json),
+            flush: Flush::EveryWrite,
         };
-        let ds_ty = parse_quote!(DS);
-        let _a = Accessor::from(field, ds_ty);
+        let store =
+            Store::with_codec(parse_quote!(DS), Codec::Json).flushing(parse_quote!(test_a_flush));
+        let a = Accessor::from(field, store);
+        let returns = a.returns.to_token_stream().to_string();
+        assert!(returns.contains("Flushing < :: dbstruct :: codec :: WithCodec"), "{returns}");
+        let body = a.body.to_token_stream().to_string();
+        assert!(body.contains("self . test_a_flush . clone ()"), "{body}");
     }
 }
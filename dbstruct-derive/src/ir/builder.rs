@@ -0,0 +1,38 @@
+use quote::format_ident;
+
+use crate::model::backend::Backend;
+use crate::model::Model;
+
+/// Configures how sled is opened, `new` uses it with the default
+/// configuration. Only generated for `db=sled`.
+pub struct Builder {
+    pub ident: syn::Ident,
+    pub vis: syn::Visibility,
+    /// the struct the builder opens
+    pub struct_ident: syn::Ident,
+}
+
+impl Builder {
+    pub fn from(model: &Model) -> Option<Self> {
+        if !matches!(model.backend, Backend::Sled) {
+            return None;
+        }
+        Some(Self {
+            ident: format_ident!("{}Builder", model.ident),
+            vis: model.vis.clone(),
+            struct_ident: model.ident.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_for_sled() {
+        let model = Model::mock_u8field();
+        let builder = Builder::from(&model).unwrap();
+        assert_eq!(builder.ident, "TestBuilder");
+    }
+}
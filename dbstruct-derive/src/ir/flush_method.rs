@@ -0,0 +1,51 @@
+use syn::parse_quote;
+
+use crate::model::backend::Backend;
+use crate::model::Model;
+
+/// `flush`, or `flush_async` with the `async` option, in an impl block of
+/// its own so `db=trait` structs only get it if the store is flushable
+pub struct FlushMethod {
+    pub vis: syn::Visibility,
+    pub is_async: bool,
+    /// the store of the struct
+    pub ds: syn::Type,
+    /// bounds for the impl block, `None` if the struct is not generic
+    pub bounds: Option<syn::WhereClause>,
+}
+
+impl FlushMethod {
+    pub fn from(model: &Model, bounds: &Option<syn::WhereClause>) -> Self {
+        let flushable: syn::WherePredicate = match model.is_async {
+            true => parse_quote!(DS: ::dbstruct::traits::AsyncFlushable),
+            false => parse_quote!(DS: ::dbstruct::traits::Flushable),
+        };
+        let bounds = match model.backend {
+            Backend::Trait { .. } => bounds.clone().map(|mut bounds| {
+                bounds.predicates.push(flushable);
+                bounds
+            }),
+            _ => None,
+        };
+
+        Self {
+            vis: model.vis.clone(),
+            is_async: model.is_async,
+            ds: super::store_type(model),
+            bounds,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sled_is_not_generic() {
+        let model = Model::mock_u8field();
+        let flush = FlushMethod::from(&model, &None);
+        assert!(flush.bounds.is_none());
+        assert!(!flush.is_async);
+    }
+}
@@ -1,46 +1,120 @@
 use proc_macro2::Span;
+use quote::ToTokens;
 use syn::punctuated::Punctuated;
+use syn::visit_mut::VisitMut;
 use syn::{parse_quote, LocalInit, Pat, PathArguments, Token};
 
 use crate::model::backend::Backend;
+use crate::model::codec::Codec;
+use crate::model::flush::Flush;
+use crate::model::key::Key;
 use crate::model::{Field, Model, Wrapper};
 
-use super::struct_def::{as_len_ident, Struct};
+use super::struct_def::{as_flush_ident, as_len_ident, as_nested_ident, Struct};
+
+/// How the generated `new` runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Blocking,
+    /// async, opens the blocking backend on a thread pool then wraps it in
+    /// `stores::Blocking`
+    SpawnBlocking,
+    /// async, the store passed in is async
+    Async,
+}
 
 pub struct NewMethod {
-    pub locals: Vec<syn::Local>,
+    pub kind: Kind,
+    /// runs before the statements are moved to the thread pool
+    pub prelude: Vec<syn::Stmt>,
+    /// opens the database and checks the schema
+    pub stmts: Vec<syn::Stmt>,
+    /// reads the fields of the struct at `prefix` from `ds`
+    pub inits: Vec<syn::Stmt>,
     pub fields: Vec<syn::FieldValue>,
+    /// the store of the struct, nested structs are opened with it
+    pub ds_ty: syn::Type,
     pub vis: syn::Visibility,
     pub arg: Option<syn::FnArg>,
     pub error_ty: syn::Type,
 }
 
-fn as_len_value(ident: syn::Ident) -> syn::FieldValue {
+fn as_len_value(ident: syn::Ident, kind: Kind) -> syn::FieldValue {
     let colon: syn::token::Colon = syn::Token![:](Span::call_site());
+    let expr = match kind {
+        Kind::Blocking => parse_quote!(std::sync::Arc::new(std::sync::Mutex::new(#ident))),
+        Kind::SpawnBlocking | Kind::Async => parse_quote!(
+            std::sync::Arc::new(::dbstruct::wrapper::asynchronous::Len::new(#ident))
+        ),
+    };
     syn::FieldValue {
         attrs: Vec::new(),
         member: syn::Member::Named(ident.clone()),
         colon_token: Some(colon),
-        expr: parse_quote!(std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(#ident))),
+        expr,
     }
 }
 
-fn len_expr(ty: &syn::Type, prefix: u8) -> Box<syn::Expr> {
-    let expr: syn::Expr = parse_quote!(
-        ::dbstruct::traits::data_store::Ordered::get_lt(
-                &ds,
-                &::dbstruct::wrapper::Prefixed::max(#prefix),
-            )?
-            .map(|(key, _): (::dbstruct::wrapper::Prefixed, #ty)| key)
-            .map(|key| key.index() + 1) // a vecs len is index + 1
-            .unwrap_or(0)
-    );
+fn as_flush_value(field: &Field) -> Option<syn::FieldValue> {
+    let ident = as_flush_ident(&field.ident);
+    let expr: syn::Expr = match field.flush {
+        Flush::Never => return None,
+        Flush::EveryWrite => parse_quote!(::dbstruct::stores::FlushPolicy::every_write()),
+        Flush::Interval { ms } => parse_quote!(::dbstruct::stores::FlushPolicy::interval(
+            std::time::Duration::from_millis(#ms)
+        )),
+    };
+    Some(parse_quote!(#ident: #expr))
+}
+
+fn len_expr(ty: &syn::Type, field: &Field, kind: Kind) -> Box<syn::Expr> {
+    if let Wrapper::VecDeque { .. } = field.wrapper {
+        return ends_expr(field, kind);
+    }
+    let prefix = super::field_prefix(parse_quote!(prefix), field.key);
+    let expr: syn::Expr = match (kind, field.codec) {
+        (Kind::Blocking | Kind::SpawnBlocking, None) => parse_quote!(
+            ::dbstruct::wrapper::stored_len::<#ty, _>(&ds, #prefix)?
+        ),
+        // the length is stored using the codec of the field
+        (Kind::Blocking | Kind::SpawnBlocking, Some(codec)) => {
+            let codec = super::codec_type(codec);
+            parse_quote!(::dbstruct::wrapper::stored_len::<#ty, _>(
+                &::dbstruct::codec::WithCodec::<_, #codec>::new(ds.clone()),
+                #prefix,
+            )?)
+        }
+        (Kind::Async, _) => parse_quote!(
+            ::dbstruct::wrapper::asynchronous::stored_len::<#ty, _>(&ds, #prefix).await?
+        ),
+    };
+    Box::new(expr)
+}
+
+fn ends_expr(field: &Field, kind: Kind) -> Box<syn::Expr> {
+    let prefix = super::field_prefix(parse_quote!(prefix), field.key);
+    let expr: syn::Expr = match (kind, field.codec) {
+        (Kind::Blocking | Kind::SpawnBlocking, None) => parse_quote!(
+            ::dbstruct::wrapper::stored_ends(&ds, #prefix)?
+        ),
+        // the ends are stored using the codec of the field
+        (Kind::Blocking | Kind::SpawnBlocking, Some(codec)) => {
+            let codec = super::codec_type(codec);
+            parse_quote!(::dbstruct::wrapper::stored_ends(
+                &::dbstruct::codec::WithCodec::<_, #codec>::new(ds.clone()),
+                #prefix,
+            )?)
+        }
+        (Kind::Async, _) => parse_quote!(
+            ::dbstruct::wrapper::asynchronous::stored_ends(&ds, #prefix).await?
+        ),
+    };
     Box::new(expr)
 }
 
-fn len_init(field: &Field) -> Option<syn::Local> {
+fn len_init(field: &Field, kind: Kind) -> Option<syn::Local> {
     let ty = match &field.wrapper {
-        Wrapper::Vec { ty } => ty,
+        Wrapper::Vec { ty } | Wrapper::VecDeque { ty } => ty,
         _ => return None,
     };
 
@@ -57,7 +131,7 @@ fn len_init(field: &Field) -> Option<syn::Local> {
         qself: None,
         path: ident,
     };
-    let expr = len_expr(ty, field.key);
+    let expr = len_expr(ty, field, kind);
     let eq_token = Token![=](Span::call_site());
     Some(syn::Local {
         attrs: Vec::new(),
@@ -72,12 +146,168 @@ fn len_init(field: &Field) -> Option<syn::Local> {
     })
 }
 
-fn sled_from_path() -> syn::Local {
+/// opens the struct of a nested field, the model rejects them in async structs
+fn nested_init(field: &Field) -> Option<syn::Stmt> {
+    let Wrapper::Nested { ty } = &field.wrapper else {
+        return None;
+    };
+    let ident = as_nested_ident(&field.ident);
+    let prefix = super::field_prefix(parse_quote!(prefix), field.key);
+    Some(parse_quote!(
+        let #ident = <#ty>::open_nested(ds.clone(), #prefix)?;
+    ))
+}
+
+/// FNV-1a, the hasher in std is not guaranteed to give the same
+/// output between releases.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Strips the `std`, `core` and `alloc` module path from types so that
+/// `std::string::String` and `String` get the same fingerprint.
+struct StripStdPaths;
+
+impl VisitMut for StripStdPaths {
+    fn visit_type_path_mut(&mut self, ty: &mut syn::TypePath) {
+        syn::visit_mut::visit_type_path_mut(self, ty);
+        let path = &mut ty.path;
+        let from_std = matches!(path.segments.first(),
+            Some(s) if ["std", "core", "alloc"].iter().any(|m| s.ident == m));
+        if ty.qself.is_none() && path.segments.len() > 1 && from_std {
+            let last = path.segments.pop().expect("path has segments").into_value();
+            path.leading_colon = None;
+            path.segments = Punctuated::from_iter(std::iter::once(last));
+        }
+    }
+}
+
+fn normalized(ty: &syn::Type) -> String {
+    let mut ty = ty.clone();
+    StripStdPaths.visit_type_mut(&mut ty);
+    ty.to_token_stream().to_string()
+}
+
+/// hash of the type and codec that end up in the database for this field.
+/// Types are compared by name, a type alias gets a different fingerprint
+/// than the type it names and changing what an alias names goes unnoticed.
+fn fingerprint(field: &Field) -> u64 {
+    let stored = match &field.wrapper {
+        Wrapper::Vec { ty } => format!("Vec<{}>", normalized(ty)),
+        Wrapper::VecDeque { ty } => format!("VecDeque<{}>", normalized(ty)),
+        // the fields of the nested struct are not part of the schema
+        Wrapper::Nested { ty } => format!("Nested<{}>", normalized(ty)),
+        // both maps store their elements the same way
+        Wrapper::Map { key_ty, val_ty } | Wrapper::BTreeMap { key_ty, val_ty } => {
+            format!("Map<{}, {}>", normalized(key_ty), normalized(val_ty))
+        }
+        // the codec is not used
+        Wrapper::Counter { ty } => format!("Counter<{}>", normalized(ty)),
+        // stored like a map without values
+        Wrapper::Set { ty, .. } => format!("Map<{}, ()>", normalized(ty)),
+        Wrapper::DefaultTrait { ty }
+        | Wrapper::DefaultValue { ty, .. }
+        | Wrapper::Option { ty } => normalized(ty),
+    };
+    // bincode is what the stores use, it does not change the fingerprint
+    let stored = match field.codec {
+        None | Some(Codec::Bincode) => stored,
+        Some(codec) => format!("{stored} as {codec}"),
+    };
+    fnv1a(stored.as_bytes())
+}
+
+fn schema_check(model: &Model, error_ty: &syn::Type, kind: Kind) -> syn::Stmt {
+    let version = model.schema.version;
+    let fields = model.fields.iter().map(|field| {
+        let name = field.ident.to_string();
+        let key = field.key;
+        let fingerprint = fingerprint(field);
+        quote::quote!((#name, #key, #fingerprint))
+    });
+    if kind == Kind::Async {
+        return parse_quote!(
+        ::dbstruct::schema::check_async::<_, #error_ty, _>(
+            &ds,
+            &::dbstruct::schema::Schema {
+                version: #version,
+                fields: &[#(#fields),*],
+            },
+        )
+        .await?;
+        );
+    }
+
+    let ds = super::backend_type(&model.backend);
+    let migrations = model.schema.migrations.iter().map(|migration| {
+        let from = migration.from;
+        let op = &migration.op;
+        quote::quote!(::dbstruct::schema::migration::<#ds>(#from, #op))
+    });
+    // dbstruct 0.4 only supported sled
+    let legacy_fields: Vec<_> = match model.backend {
+        Backend::Sled => model.fields.iter().filter_map(legacy_field).collect(),
+        _ => Vec::new(),
+    };
+    let rewrite = (!legacy_fields.is_empty()).then(|| {
+        quote::quote!(
+            ::dbstruct::schema::rewrite_legacy_keys(&ds, &schema, &[#(#legacy_fields),*])?;
+        )
+    });
+    parse_quote!(
+    {
+        let schema = ::dbstruct::schema::Schema {
+            version: #version,
+            fields: &[#(#fields),*],
+        };
+        #rewrite
+        ::dbstruct::schema::check::<_, #error_ty, _>(
+            &ds,
+            &schema,
+            vec![#(#migrations),*],
+        )?;
+    }
+    )
+}
+
+/// The fields that dbstruct 0.4 stored under keys that are encoded
+/// differently now, it had no `wide_keys`
+fn legacy_field(field: &Field) -> Option<syn::Expr> {
+    let key = match field.key {
+        Key::Byte(key) => key,
+        Key::Wide(_) => return None,
+    };
+    match &field.wrapper {
+        Wrapper::Vec { .. } => Some(parse_quote!(::dbstruct::schema::LegacyField::vec(#key))),
+        Wrapper::Map { key_ty, .. } => Some(parse_quote!(
+            ::dbstruct::schema::LegacyField::map::<#key_ty>(#key)
+        )),
+        _ => None,
+    }
+}
+
+/// the tree is opened by the generated builder, see `ir::Builder`
+fn sled_from_builder() -> syn::Local {
+    let stmt: syn::Stmt = parse_quote!(
+    let ds = self.open_tree()?;
+    );
+    match stmt {
+        syn::Stmt::Local(local) => local,
+        _ => unreachable!(),
+    }
+}
+
+fn rocksdb_from_path() -> syn::Local {
     let stmt: syn::Stmt = parse_quote!(
-    let ds = ::dbstruct::sled::Config::default()
-        .path(path)
-        .open()?
-        .open_tree("DbStruct")?;
+    let ds = ::dbstruct::stores::Watched::new(std::sync::Arc::new(
+        ::dbstruct::rocksdb::TransactionDB::<::dbstruct::rocksdb::SingleThreaded>::open(
+            &::dbstruct::stores::rocksdb_options(),
+            &::dbstruct::rocksdb::TransactionDBOptions::default(),
+            path,
+        )?,
+    ));
     );
     match stmt {
         syn::Stmt::Local(local) => local,
@@ -107,31 +337,46 @@ fn btreemap() -> syn::Local {
 
 impl NewMethod {
     pub fn from(model: &Model, struct_def: &Struct) -> Self {
-        let fields: Vec<_> = struct_def
+        let kind = match model.backend {
+            _ if !model.is_async => Kind::Blocking,
+            Backend::Trait { .. } => Kind::Async,
+            _ => Kind::SpawnBlocking,
+        };
+        let mut fields: Vec<_> = struct_def
             .len_vars
             .iter()
             .map(|def| def.ident.clone())
             .map(|ident| ident.expect("ident is None"))
-            .map(as_len_value)
+            .map(|ident| as_len_value(ident, kind))
             .collect();
+        fields.extend(struct_def.nested.iter().map(|def| -> syn::FieldValue {
+            let ident = def.ident.as_ref().expect("ident is None");
+            parse_quote!(#ident)
+        }));
+        fields.extend(model.fields.iter().filter_map(as_flush_value));
 
-        let mut locals = Vec::new();
+        let mut stmts = Vec::new();
 
         let arg;
         let error_ty;
         match model.backend {
             Backend::Sled => {
-                locals.push(sled_from_path());
+                stmts.push(syn::Stmt::Local(sled_from_builder()));
                 arg = Some(parse_quote!(path: impl AsRef<std::path::Path>));
                 error_ty = parse_quote!(::dbstruct::sled::Error);
             }
+            Backend::RocksDB => {
+                stmts.push(syn::Stmt::Local(rocksdb_from_path()));
+                arg = Some(parse_quote!(path: impl AsRef<std::path::Path>));
+                error_ty = parse_quote!(::dbstruct::rocksdb::Error);
+            }
             Backend::HashMap => {
-                locals.push(hashmap());
+                stmts.push(syn::Stmt::Local(hashmap()));
                 arg = None;
                 error_ty = parse_quote!(::dbstruct::stores::HashMapError);
             }
             Backend::BTreeMap => {
-                locals.push(btreemap());
+                stmts.push(syn::Stmt::Local(btreemap()));
                 arg = None;
                 error_ty = parse_quote!(::dbstruct::stores::BTreeMapError);
             }
@@ -143,12 +388,31 @@ impl NewMethod {
             Backend::Test => unreachable!("test not used in new method"),
         };
 
-        let inits = model.fields.iter().filter_map(|field| len_init(field));
-        locals.extend(inits);
+        stmts.push(schema_check(model, &error_ty, kind));
+        let mut inits: Vec<_> = model
+            .fields
+            .iter()
+            .filter_map(|f| len_init(f, kind))
+            .map(syn::Stmt::Local)
+            .collect();
+        inits.extend(model.fields.iter().filter_map(nested_init));
+
+        // the path has to move to the thread pool, for sled the builder
+        // takes it before that
+        let prelude = match (kind, &model.backend) {
+            (Kind::SpawnBlocking, Backend::RocksDB) => vec![parse_quote!(
+                let path = path.as_ref().to_owned();
+            )],
+            _ => Vec::new(),
+        };
 
         Self {
-            locals,
+            kind,
+            prelude,
+            stmts,
+            inits,
             fields,
+            ds_ty: struct_def.db.ty.clone(),
             vis: model.vis.clone(),
             arg,
             error_ty,
@@ -158,16 +422,42 @@ impl NewMethod {
 
 #[cfg(test)]
 mod tests {
+    use std::str::FromStr;
+
     use quote::ToTokens;
 
     use super::*;
 
     #[test]
-    fn has_one_local() {
+    fn opens_checks_and_inits() {
         let model = Model::mock_vec();
         let struct_def = Struct::from(&model);
         let new_method = NewMethod::from(&model, &struct_def);
-        assert!(new_method.locals.len() == 2);
+        assert!(new_method.stmts.len() == 2);
+        assert!(new_method.inits.len() == 1);
+    }
+
+    #[test]
+    fn fingerprint_ignores_std_paths() {
+        let fingerprint_of = |field: &str| {
+            let input_attr = proc_macro2::TokenStream::from_str("db=sled").unwrap();
+            let input_struct = format!("pub struct Test {{ {field} }}");
+            let input_struct: syn::ItemStruct = syn::parse_str(&input_struct).unwrap();
+            let model = Model::try_from(input_struct, input_attr).unwrap();
+            fingerprint(&model.fields[0])
+        };
+        assert_eq!(
+            fingerprint_of("#[dbstruct(Default)] a: u32,"),
+            fingerprint_of("#[dbstruct(Default)] a: core::primitive::u32,"),
+        );
+        assert_eq!(
+            fingerprint_of("a: Vec<Option<String>>,"),
+            fingerprint_of("a: Vec<std::option::Option<::std::string::String>>,"),
+        );
+        assert_ne!(
+            fingerprint_of("#[dbstruct(Default)] a: u32,"),
+            fingerprint_of("#[dbstruct(Default)] a: u64,"),
+        );
     }
 
     #[test]
@@ -176,11 +466,8 @@ mod tests {
         let struct_def = Struct::from(&model);
         let new_method = NewMethod::from(&model, &struct_def);
 
-        let stmts: Vec<_> = new_method
-            .locals
-            .into_iter()
-            .map(syn::Stmt::Local)
-            .collect();
+        let mut stmts = new_method.stmts;
+        stmts.extend(new_method.inits);
         let block = syn::Block {
             brace_token: syn::token::Brace(proc_macro2::Span::call_site()),
             stmts,
@@ -1,14 +1,18 @@
 use syn::parse_quote;
 
-use crate::model::backend::Backend;
-use crate::model::{Field, Model};
+use crate::model::flush::Flush;
+use crate::model::{Field, Model, Wrapper};
 
 pub struct Struct {
     pub ident: syn::Ident,
     pub vis: syn::Visibility,
     /// extra variables such as the current length
-    /// of the vector wrapper
+    /// of the vector wrapper or the ends of the deque
     pub len_vars: Vec<syn::Field>,
+    /// the structs of nested fields
+    pub nested: Vec<syn::Field>,
+    /// the flush policies of fields with the flush option
+    pub flush_policies: Vec<syn::Field>,
     pub db: syn::Field,
 }
 
@@ -17,13 +21,56 @@ pub fn as_len_ident(ident: &syn::Ident) -> syn::Ident {
     syn::Ident::new(&name, proc_macro2::Span::call_site())
 }
 
-fn as_len_field(field: &Field) -> syn::Field {
+pub fn as_nested_ident(ident: &syn::Ident) -> syn::Ident {
+    let name = format!("{}_nested", ident);
+    syn::Ident::new(&name, proc_macro2::Span::call_site())
+}
+
+pub fn as_flush_ident(ident: &syn::Ident) -> syn::Ident {
+    let name = format!("{}_flush", ident);
+    syn::Ident::new(&name, proc_macro2::Span::call_site())
+}
+
+fn as_flush_field(field: &Field) -> Option<syn::Field> {
+    if field.flush == Flush::Never {
+        return None;
+    }
+    Some(syn::Field {
+        attrs: Vec::new(),
+        vis: syn::Visibility::Inherited,
+        ident: Some(as_flush_ident(&field.ident)),
+        colon_token: None,
+        ty: parse_quote!(::dbstruct::stores::FlushPolicy),
+        mutability: syn::FieldMutability::None,
+    })
+}
+
+fn as_nested_field(field: &Field) -> Option<syn::Field> {
+    let Wrapper::Nested { ty } = &field.wrapper else {
+        return None;
+    };
+    Some(syn::Field {
+        attrs: Vec::new(),
+        vis: syn::Visibility::Inherited,
+        ident: Some(as_nested_ident(&field.ident)),
+        colon_token: None,
+        ty: ty.clone(),
+        mutability: syn::FieldMutability::None,
+    })
+}
+
+fn as_len_field(field: &Field, cached: syn::Type, is_async: bool) -> syn::Field {
+    let ty = if is_async {
+        parse_quote!(std::sync::Arc<::dbstruct::wrapper::asynchronous::Len<#cached>>)
+    } else {
+        parse_quote!(std::sync::Arc<std::sync::Mutex<#cached>>)
+    };
     syn::Field {
         attrs: Vec::new(),
         vis: syn::Visibility::Inherited,
         ident: Some(as_len_ident(&field.ident)),
         colon_token: None,
-        ty: parse_quote!(std::sync::Arc<std::sync::atomic::AtomicUsize>),
+        ty,
         mutability: syn::FieldMutability::None,
     }
 }
@@ -33,18 +80,13 @@ impl From<&Model> for Struct {
         let len_vars = model
             .fields
             .iter()
-            .filter(|f| f.is_vec())
-            .map(as_len_field)
+            .filter_map(|f| Some((f, f.cached_ty()?)))
+            .map(|(f, cached)| as_len_field(f, cached, model.is_async))
             .collect();
+        let nested = model.fields.iter().filter_map(as_nested_field).collect();
+        let flush_policies = model.fields.iter().filter_map(as_flush_field).collect();
 
-        let ty = match model.backend {
-            Backend::Sled => parse_quote!(::dbstruct::sled::Tree),
-            Backend::HashMap => parse_quote!(::dbstruct::stores::HashMap),
-            Backend::BTreeMap => parse_quote!(::dbstruct::stores::BTreeMap),
-            Backend::Trait { .. } => parse_quote!(DS),
-            #[cfg(test)]
-            Backend::Test => unreachable!("Test backend is not supported for codegen"),
-        };
+        let ty = super::store_type(model);
 
         let db = syn::Field {
             attrs: Vec::new(),
@@ -59,6 +101,8 @@ impl From<&Model> for Struct {
             ident: model.ident.clone(),
             vis: model.vis.clone(),
             len_vars,
+            nested,
+            flush_policies,
             db,
         }
     }
@@ -0,0 +1,109 @@
+use quote::format_ident;
+use syn::parse_quote;
+
+use crate::model::backend::Backend;
+use crate::model::flush::Flush;
+use crate::model::{Model, Wrapper};
+
+use super::struct_def::as_len_ident;
+use super::{Accessor, Store};
+
+/// A struct with the same accessors as the generated struct that
+/// writes to a transaction instead of the database
+pub struct Transaction {
+    pub ident: syn::Ident,
+    pub vis: syn::Visibility,
+    /// the store inside the transaction
+    pub ds: syn::Type,
+    /// the struct is generic over the store (`db=trait`)
+    pub generic: bool,
+    /// bounds for the impl block of the transaction method
+    pub bounds: Option<syn::WhereClause>,
+    pub len_vars: Vec<syn::Ident>,
+    /// the type of the value cached in each of the `len_vars`
+    pub len_tys: Vec<syn::Type>,
+    pub accessors: Vec<Accessor>,
+    pub error_ty: syn::Type,
+    /// flush after the transaction commits, some fields have a flush policy
+    pub flush: bool,
+}
+
+fn store(backend: &Backend) -> Option<syn::Type> {
+    match backend {
+        Backend::Sled => Some(parse_quote!(::dbstruct::sled::Tree)),
+        Backend::RocksDB => Some(parse_quote!(
+            ::dbstruct::stores::Watched<std::sync::Arc<::dbstruct::rocksdb::TransactionDB>>
+        )),
+        Backend::Trait { .. } => Some(parse_quote!(DS)),
+        Backend::HashMap | Backend::BTreeMap => None,
+        #[cfg(test)]
+        Backend::Test => None,
+    }
+}
+
+impl Transaction {
+    pub fn from(model: &Model, bounds: &Option<syn::WhereClause>) -> Option<Self> {
+        // transactions run a blocking closure
+        if model.is_async {
+            return None;
+        }
+        let store = store(&model.backend)?;
+        let ds: syn::Type = parse_quote!(<#store as ::dbstruct::traits::Transactional>::Tx<'tx>);
+        let error_ty = parse_quote!(<#store as ::dbstruct::traits::Transactional>::Error);
+
+        let generic = matches!(model.backend, Backend::Trait { .. });
+        let bounds = bounds.clone().map(|mut bounds| {
+            bounds
+                .predicates
+                .push(parse_quote!(DS: ::dbstruct::traits::Transactional));
+            bounds
+        });
+
+        let (len_vars, len_tys) = model
+            .fields
+            .iter()
+            .filter_map(|f| Some((as_len_ident(&f.ident), f.cached_ty()?)))
+            .unzip();
+        let accessors = model
+            .fields
+            .iter()
+            // nested structs are not opened on the transaction's store
+            .filter(|f| !matches!(f.wrapper, Wrapper::Nested { .. }))
+            .cloned()
+            .map(|f| {
+                let store = match f.codec {
+                    Some(codec) => Store::with_codec(ds.clone(), codec),
+                    None => Store::plain(ds.clone()),
+                };
+                Accessor::from(f, store)
+            })
+            .collect();
+
+        Some(Self {
+            ident: format_ident!("{}Transaction", model.ident),
+            vis: model.vis.clone(),
+            ds,
+            generic,
+            bounds,
+            len_vars,
+            len_tys,
+            accessors,
+            error_ty,
+            flush: model.fields.iter().any(|f| f.flush != Flush::Never),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sled_supports_transactions() {
+        let model = Model::mock_u8field();
+        let tx = Transaction::from(&model, &None).unwrap();
+        assert_eq!(tx.ident, "TestTransaction");
+        assert_eq!(tx.accessors.len(), 1);
+        assert!(!tx.generic);
+    }
+}
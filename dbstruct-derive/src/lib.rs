@@ -33,7 +33,7 @@ pub fn dbstruct(
 fn emit_and_abort(err: model::Error) -> ! {
     match err {
         model::Error::DbKey(e) => {
-            abort!(e.span(), "{}", e)
+            abort!(e.span(), e.to_string(); help =? e.help(););
         }
         model::Error::Field(mut errs) => {
             let last = errs.pop().expect("minimum err vec len is one");
@@ -48,6 +48,15 @@ fn emit_and_abort(err: model::Error) -> ! {
         model::Error::Backend(e) => {
             abort!(e.span(), e.to_string(); help =? e.help(););
         }
+        model::Error::Schema(e) => {
+            abort!(e.span(), e.to_string(); help =? e.help(););
+        }
+        model::Error::Codec(e) => {
+            abort!(e.span(), e.to_string(); help =? e.help(););
+        }
+        model::Error::Flush(e) => {
+            abort!(e.span(), e.to_string(); help =? e.help(););
+        }
     }
 }
 
@@ -1,7 +1,10 @@
 mod attribute;
 pub mod backend;
+pub mod codec;
 mod field;
+pub mod flush;
 pub mod key;
+pub mod schema;
 
 pub use field::Field;
 pub use field::Wrapper;
@@ -11,7 +14,10 @@ pub use key::DbKey;
 use proc_macro2::Ident;
 use syn::Visibility;
 
+use self::attribute::Options;
 use self::backend::Backend;
+use self::key::{KeyMode, KeyWidth};
+use self::schema::Schema;
 
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
@@ -23,6 +29,12 @@ pub enum Error {
     Attribute(#[from] attribute::Error),
     #[error(transparent)]
     Backend(#[from] backend::Error),
+    #[error(transparent)]
+    Schema(#[from] schema::Error),
+    #[error(transparent)]
+    Codec(#[from] codec::Error),
+    #[error(transparent)]
+    Flush(#[from] flush::Error),
 }
 
 #[derive(Debug)]
@@ -30,18 +42,18 @@ pub struct Model {
     pub ident: Ident,
     pub vis: Visibility,
     pub fields: Vec<Field>,
-    pub keys: DbKey,
     pub backend: Backend,
+    pub schema: Schema,
+    /// generate async wrappers and an async `new`
+    pub is_async: bool,
 }
 
 impl Model {
     pub fn try_from(input: syn::ItemStruct, attr: proc_macro2::TokenStream) -> Result<Self, Error> {
-        let keys = DbKey::new(&input.fields)?;
-
         let (fields, errors): (Vec<_>, Vec<_>) = input
             .fields
             .into_iter()
-            .map(|f| Field::analyze(f, &keys))
+            .map(Field::analyze)
             .partition_result();
 
         if !errors.is_empty() {
@@ -49,14 +61,43 @@ impl Model {
         }
 
         let options = attribute::parse(attr)?;
+        let key_mode = if options.iter().any(|o| matches!(o, Options::ExplicitKeys)) {
+            KeyMode::Explicit
+        } else {
+            KeyMode::Alphabetical
+        };
+        let key_width = if options.iter().any(|o| matches!(o, Options::WideKeys)) {
+            KeyWidth::Wide
+        } else {
+            KeyWidth::Byte
+        };
+        let keys = DbKey::new(&fields, key_mode, key_width)?;
+        let codec = codec::try_from(&options, &fields)?;
+        let flush = flush::try_from(&options, &fields)?;
+        let fields: Vec<_> = fields
+            .into_iter()
+            .map(|f| f.with_key(&keys, codec, flush))
+            .collect();
         let backend = Backend::try_from(&options, &fields)?;
+        let is_async = options.iter().any(|o| matches!(o, Options::Async));
+        if is_async {
+            let errors: Vec<_> = fields
+                .iter()
+                .filter_map(|f| f.wrapper.check_async().err())
+                .collect();
+            if !errors.is_empty() {
+                return Err(Error::Field(errors));
+            }
+        }
+        let schema = Schema::try_from(options)?;
 
         Ok(Self {
             vis: input.vis,
             ident: input.ident,
-            keys,
             fields,
             backend,
+            schema,
+            is_async,
         })
     }
 }
@@ -2,16 +2,18 @@ mod errors;
 pub use errors::{Error, ErrorVariant};
 use proc_macro2::Span;
 
-pub use super::field::Field;
-pub use super::field::Wrapper;
 use std::iter::Peekable;
 
-pub use super::key::DbKey;
-use proc_macro2::TokenTree;
+use proc_macro2::{Delimiter, TokenTree};
+use syn::parse::{ParseStream, Parser};
+
+use super::codec::{Codec, CodecOption};
+use super::flush::{Flush, FlushOption};
 
 #[derive(Debug, Clone, Copy)]
 pub enum BackendOptionVariant {
     Sled,
+    RocksDB,
     HashMap,
     BTreeMap,
     Trait,
@@ -25,10 +27,24 @@ pub struct BackendOption {
     pub span: Span,
 }
 
+#[derive(Debug)]
+pub struct Migration {
+    /// the version this migration upgrades from
+    pub from: u32,
+    pub op: syn::Expr,
+    pub span: Span,
+}
+
 #[derive(Debug)]
 pub enum Options {
     Backend(BackendOption),
     Async,
+    ExplicitKeys,
+    WideKeys,
+    Version { version: u32, span: Span },
+    Migration(Migration),
+    Codec(CodecOption),
+    Flush(FlushOption),
 }
 
 /// attrs is the tokenstream returned by Attribute::parse_args();
@@ -57,19 +73,20 @@ fn parse_db(
     match tokens.peek() {
         None => {
             tokens.next();
-            return Err(MissingDb.with_span(span));
+            Err(MissingDb.with_span(span))
         }
         Some(TokenTree::Punct(punct)) if punct.as_char() == ',' => {
             tokens.next();
-            return Err(MissingDb.with_span(span));
+            Err(MissingDb.with_span(span))
         }
         Some(TokenTree::Punct(punct)) if punct.as_char() == '=' => {
             let punct = punct.span();
             match tokens.nth(1) {
-                None => return Err(MissingBackendValue.with_span(punct)),
+                None => Err(MissingBackendValue.with_span(punct)),
                 Some(TokenTree::Ident(ident)) => {
                     let backend = match ident.to_string().as_str() {
                         "sled" => Sled,
+                        "rocksdb" => RocksDB,
                         "hashmap" => HashMap,
                         "btreemap" => BTreeMap,
                         "trait" => Trait,
@@ -77,15 +94,120 @@ fn parse_db(
                         "test" => Test,
                         _ => return Err(NotABackend(ident).has_span()),
                     };
-                    return Ok(BackendOption {
+                    Ok(BackendOption {
                         backend,
                         span: ident.span(),
-                    });
+                    })
                 }
-                Some(other) => return Err(InvalidBackendSyntax.with_span(other)),
+                Some(other) => Err(InvalidBackendSyntax.with_span(other)),
+            }
+        }
+        Some(other) => Err(InvalidBackendSyntax.with_span(other)),
+    }
+}
+
+fn parse_version(
+    span: proc_macro2::Span,
+    tokens: &mut Peekable<impl Iterator<Item = TokenTree>>,
+) -> Result<Options, Error> {
+    use ErrorVariant::*;
+
+    match tokens.next() {
+        Some(TokenTree::Punct(punct)) if punct.as_char() == '=' => match tokens.next() {
+            None => Err(MissingVersionValue.with_span(punct)),
+            Some(TokenTree::Literal(lit)) => {
+                let version = syn::LitInt::from(lit.clone())
+                    .base10_parse::<u32>()
+                    .map_err(|_| InvalidVersion.with_span(&lit))?;
+                Ok(Options::Version {
+                    version,
+                    span: lit.span(),
+                })
             }
+            Some(other) => Err(InvalidVersion.with_span(other)),
+        },
+        _ => Err(MissingVersionValue.with_span(span)),
+    }
+}
+
+fn parse_codec(
+    span: proc_macro2::Span,
+    tokens: &mut Peekable<impl Iterator<Item = TokenTree>>,
+) -> Result<Options, Error> {
+    use ErrorVariant::*;
+
+    match tokens.next() {
+        Some(TokenTree::Punct(punct)) if punct.as_char() == '=' => match tokens.next() {
+            None => Err(MissingCodecValue.with_span(punct)),
+            Some(TokenTree::Ident(ident)) => {
+                let codec = Codec::from_name(&ident.to_string())
+                    .ok_or_else(|| NotACodec(ident.clone()).has_span())?;
+                Ok(Options::Codec(CodecOption {
+                    codec,
+                    span: ident.span(),
+                }))
+            }
+            Some(other) => Err(MissingCodecValue.with_span(other)),
+        },
+        _ => Err(MissingCodecValue.with_span(span)),
+    }
+}
+
+fn parse_flush(
+    span: proc_macro2::Span,
+    tokens: &mut Peekable<impl Iterator<Item = TokenTree>>,
+) -> Result<Options, Error> {
+    use ErrorVariant::*;
+
+    match tokens.next() {
+        Some(TokenTree::Punct(punct)) if punct.as_char() == '=' => match tokens.next() {
+            None => Err(MissingFlushValue.with_span(punct)),
+            Some(TokenTree::Literal(lit)) => {
+                let flush = Flush::from_literal(&lit).ok_or_else(|| InvalidFlush.with_span(&lit))?;
+                Ok(Options::Flush(FlushOption {
+                    flush,
+                    span: lit.span(),
+                }))
+            }
+            Some(other) => Err(InvalidFlush.with_span(other)),
+        },
+        _ => Err(MissingFlushValue.with_span(span)),
+    }
+}
+
+fn parse_migration_args(input: ParseStream) -> syn::Result<(u32, syn::Expr)> {
+    let from: syn::Ident = input.parse()?;
+    if from != "from" {
+        return Err(syn::Error::new(from.span(), "expected `from`"));
+    }
+    input.parse::<syn::Token![=]>()?;
+    let version: syn::LitInt = input.parse()?;
+    let version = version.base10_parse()?;
+    input.parse::<syn::Token![,]>()?;
+    let op = input.parse()?;
+    input.parse::<Option<syn::Token![,]>>()?;
+    Ok((version, op))
+}
+
+fn parse_migration(
+    span: proc_macro2::Span,
+    tokens: &mut Peekable<impl Iterator<Item = TokenTree>>,
+) -> Result<Options, Error> {
+    use ErrorVariant::*;
+
+    match tokens.next() {
+        Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Parenthesis => {
+            let (from, op) = parse_migration_args
+                .parse2(group.stream())
+                .map_err(|e| InvalidMigration(e).has_span())?;
+            Ok(Options::Migration(Migration {
+                from,
+                op,
+                span: group.span(),
+            }))
         }
-        Some(other) => return Err(InvalidBackendSyntax.with_span(other)),
+        Some(other) => Err(MigrationSyntax.with_span(other)),
+        None => Err(MigrationSyntax.with_span(span)),
     }
 }
 
@@ -95,13 +217,19 @@ fn parse_item(tokens: &mut Peekable<impl Iterator<Item = TokenTree>>) -> Result<
         .next()
         .expect("should only get here if peek returned Some");
     match first_token {
-        TokenTree::Ident(ident) if ident.to_string() == "db" => {
+        TokenTree::Ident(ident) if ident == "db" => {
             let backend = parse_db(ident.span(), tokens)?;
             Ok(Options::Backend(backend))
         }
-        TokenTree::Ident(ident) if ident.to_string() == "async" => Ok(Options::Async),
-        TokenTree::Ident(ident) => return Err(NotAnOption(ident).has_span()),
-        _ => return Err(InvalidSyntax(first_token).has_span()),
+        TokenTree::Ident(ident) if ident == "async" => Ok(Options::Async),
+        TokenTree::Ident(ident) if ident == "explicit_keys" => Ok(Options::ExplicitKeys),
+        TokenTree::Ident(ident) if ident == "wide_keys" => Ok(Options::WideKeys),
+        TokenTree::Ident(ident) if ident == "version" => parse_version(ident.span(), tokens),
+        TokenTree::Ident(ident) if ident == "migrate" => parse_migration(ident.span(), tokens),
+        TokenTree::Ident(ident) if ident == "codec" => parse_codec(ident.span(), tokens),
+        TokenTree::Ident(ident) if ident == "flush" => parse_flush(ident.span(), tokens),
+        TokenTree::Ident(ident) => Err(NotAnOption(ident).has_span()),
+        _ => Err(InvalidSyntax(first_token).has_span()),
     }
 }
 
@@ -123,6 +251,19 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn parse_db_option_rocksdb() {
+        let attr = proc_macro2::TokenStream::from_str("db=rocksdb").unwrap();
+        let attribute = parse(attr).unwrap().pop().unwrap();
+        assert!(matches!(
+            attribute,
+            Options::Backend(BackendOption {
+                backend: BackendOptionVariant::RocksDB,
+                span: _span
+            })
+        ));
+    }
+
     #[test]
     fn parse_db_option_btreemap() {
         let attr = proc_macro2::TokenStream::from_str("db=btreemap").unwrap();
@@ -136,6 +277,38 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn parse_codec_option() {
+        let attr = proc_macro2::TokenStream::from_str("codec=postcard").unwrap();
+        let attribute = parse(attr).unwrap().pop().unwrap();
+        assert!(matches!(
+            attribute,
+            Options::Codec(CodecOption {
+                codec: Codec::Postcard,
+                span: _span
+            })
+        ));
+
+        let attr = proc_macro2::TokenStream::from_str("codec=yaml").unwrap();
+        assert!(parse(attr).is_err());
+    }
+
+    #[test]
+    fn parse_flush_option() {
+        let attr = proc_macro2::TokenStream::from_str("flush = \"interval_ms=500\"").unwrap();
+        let attribute = parse(attr).unwrap().pop().unwrap();
+        assert!(matches!(
+            attribute,
+            Options::Flush(FlushOption {
+                flush: Flush::Interval { ms: 500 },
+                span: _span
+            })
+        ));
+
+        let attr = proc_macro2::TokenStream::from_str("flush = every_write").unwrap();
+        assert!(parse(attr).is_err());
+    }
+
     #[test]
     fn parse_multiple_option() {
         let attr = proc_macro2::TokenStream::from_str("db=sled,async").unwrap();
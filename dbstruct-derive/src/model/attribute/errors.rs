@@ -18,6 +18,22 @@ pub enum ErrorVariant {
     InvalidSyntax(TokenTree),
     #[error("Not a known database backend: `{0}`")]
     NotABackend(proc_macro2::Ident),
+    #[error("version option has no value set")]
+    MissingVersionValue,
+    #[error("the version must be a positive integer")]
+    InvalidVersion,
+    #[error("incorrect syntax for migration")]
+    MigrationSyntax,
+    #[error("invalid migration: {0}")]
+    InvalidMigration(syn::Error),
+    #[error("codec option has no value set")]
+    MissingCodecValue,
+    #[error("Not a known codec: `{0}`")]
+    NotACodec(proc_macro2::Ident),
+    #[error("flush option has no value set")]
+    MissingFlushValue,
+    #[error("not a known flush policy")]
+    InvalidFlush,
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -39,9 +55,21 @@ impl Help for Error {
             MissingDb => "try specifying an db, for example: `db=sled`",
             MissingBackendValue => "try setting a supported backend, for example `db=sled`",
             InvalidBackendSyntax => "a backend should be a single world not enclosed in \"",
-            NotAnOption(_) => "the only supported option currently is: db",
+            NotAnOption(_) => {
+                "the supported options are: db, async, explicit_keys, wide_keys, version, migrate, codec and flush"
+            }
             InvalidSyntax(_) => "the option should be a single word not enclosed in \"",
-            NotABackend(_) => "try sled as database backend",
+            NotABackend(_) => "the supported backends are: sled, rocksdb, btreemap, hashmap and trait",
+            MissingVersionValue | InvalidVersion => "set the version like this: `version = 2`",
+            MigrationSyntax | InvalidMigration(_) => {
+                "a migration looks like this: `migrate(from = 1, |ds| { Ok(()) })`"
+            }
+            MissingCodecValue | NotACodec(_) => {
+                "the supported codecs are: bincode, postcard, msgpack and json"
+            }
+            MissingFlushValue | InvalidFlush => {
+                "set a flush policy like this: `flush = \"every_write\"`, `flush = \"interval_ms=500\"` or `flush = \"never\"`"
+            }
         }.to_owned())
     }
 }
@@ -53,6 +81,8 @@ impl Error {
             (NotAnOption(item), None) => item.span(),
             (InvalidSyntax(item), None) => item.span(),
             (NotABackend(item), None) => item.span(),
+            (NotACodec(item), None) => item.span(),
+            (InvalidMigration(err), None) => err.span(),
             (_, Some(span)) => span,
             (_var, _) => unreachable!(
                 "error should track a span for {_var:?} as 
@@ -74,11 +74,13 @@ impl Error {
 pub enum ExtraBound {
     Atomic,
     Ordered,
+    Watch,
 }
 
 #[derive(Debug, Clone)]
 pub enum Backend {
     Sled,
+    RocksDB,
     HashMap,
     BTreeMap,
     Trait {
@@ -92,6 +94,7 @@ impl fmt::Display for Backend {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Backend::Sled => write!(f, "sled"),
+            Backend::RocksDB => write!(f, "rocksdb"),
             Backend::HashMap => write!(f, "hashmap"),
             Backend::BTreeMap => write!(f, "btreemap"),
             Backend::Trait { .. } => write!(f, "trait"),
@@ -125,14 +128,14 @@ impl Backend {
                 return Ok(Backend::Trait {
                     bounds: fields
                         .iter()
-                        .map(|f| f.wrapper.needed_traits().into_iter())
-                        .flatten()
+                        .flat_map(|f| f.wrapper.needed_traits().into_iter())
                         .collect(),
                 })
             }
             HashMap => Backend::HashMap,
             BTreeMap => Backend::BTreeMap,
             Sled => Backend::Sled,
+            RocksDB => Backend::RocksDB,
             #[cfg(test)]
             Test => Backend::Test,
         };
@@ -148,12 +151,17 @@ impl Backend {
         Ok(backend)
     }
 
+    /// The byte_store traits the store of the backend implements. This table
+    /// is written by hand, update it when a store gains or loses a trait. The
+    /// `backends_implement_their_traits` test in `dbstruct::stores` mirrors it
+    /// by hand too and fails if the two disagree with the stores.
     fn traits(&self) -> HashSet<ExtraBound> {
         use ExtraBound::*;
         match self {
-            Backend::Sled => vec![Atomic, Ordered].into_iter(),
-            Backend::HashMap => vec![].into_iter(),
-            Backend::BTreeMap => vec![].into_iter(),
+            Backend::Sled => vec![Atomic, Ordered, Watch].into_iter(),
+            Backend::RocksDB => vec![Atomic, Ordered, Watch].into_iter(),
+            Backend::HashMap => vec![Atomic, Watch].into_iter(),
+            Backend::BTreeMap => vec![Atomic, Ordered, Watch].into_iter(),
             Backend::Trait { .. } => unreachable!("should never be called when backend is Trait"),
             #[cfg(test)]
             Backend::Test => vec![].into_iter(),
@@ -161,14 +169,21 @@ impl Backend {
         .collect()
     }
 
-    fn provided() -> [Backend; 2] {
-        [Backend::Sled, Backend::HashMap]
+    fn provided() -> [Backend; 4] {
+        [
+            Backend::Sled,
+            Backend::RocksDB,
+            Backend::BTreeMap,
+            Backend::HashMap,
+        ]
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use attribute::Wrapper;
+    use crate::model::flush::Flush;
+    use crate::model::key::Key;
+    use crate::model::Wrapper;
     use syn::parse_quote;
     use ExtraBound::*;
 
@@ -188,7 +203,9 @@ mod tests {
             wrapper: Wrapper::Vec {
                 ty: parse_quote!(u8),
             },
-            key: 1,
+            key: Key::Byte(1),
+            codec: None,
+            flush: Flush::Never,
         }];
         let err = Backend::try_from(&options, &fields).unwrap_err();
         match err.variant {
@@ -215,12 +232,26 @@ mod tests {
             wrapper: Wrapper::Vec {
                 ty: parse_quote!(u8),
             },
-            key: 1,
+            key: Key::Byte(1),
+            codec: None,
+            flush: Flush::Never,
         }];
         let backend = Backend::try_from(&options, &fields).unwrap();
         assert!(matches!(backend, Backend::Sled));
     }
 
+    #[test]
+    fn btreemap_supports_all_fields() {
+        let needed = [Atomic, Ordered].into_iter().collect();
+        assert!(Backend::BTreeMap.traits().is_superset(&needed));
+        let compatible: Vec<_> = Backend::provided()
+            .into_iter()
+            .filter(|b| b.traits().is_superset(&needed))
+            .map(|b| b.to_string())
+            .collect();
+        assert_eq!(compatible, ["sled", "rocksdb", "btreemap"]);
+    }
+
     #[test]
     fn reject_double_backend() {
         let span = Span::call_site();
@@ -240,7 +271,9 @@ mod tests {
             wrapper: Wrapper::Vec {
                 ty: parse_quote!(u8),
             },
-            key: 1,
+            key: Key::Byte(1),
+            codec: None,
+            flush: Flush::Never,
         }];
         let err = Backend::try_from(&options, &fields).unwrap_err();
         assert!(matches!(err.variant, ErrorVariant::MultipleBackends));
@@ -0,0 +1,152 @@
+use core::fmt;
+
+use proc_macro2::Span;
+
+use crate::errors::{GetSpan, Help};
+
+use super::attribute::{BackendOptionVariant, Options};
+use super::field::Unkeyed;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ErrorVariant {
+    #[error("multiple codecs specified")]
+    MultipleCodecs,
+    #[error("a codec can not be set with `db=trait`")]
+    CodecOnTrait,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub struct Error {
+    variant: ErrorVariant,
+    span: Span,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.variant.fmt(f)
+    }
+}
+
+impl GetSpan for Error {
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+impl Help for Error {
+    fn help(&self) -> Option<String> {
+        use ErrorVariant::*;
+        Some(match &self.variant {
+            MultipleCodecs => "remove one of the codecs".to_owned(),
+            CodecOnTrait => "the store picks the codec, set `ByteStore::Codec` or wrap \
+                the store in `dbstruct::codec::WithCodec`"
+                .to_owned(),
+        })
+    }
+}
+
+impl ErrorVariant {
+    fn with_span(self, item: impl GetSpan) -> Error {
+        Error {
+            variant: self,
+            span: item.span(),
+        }
+    }
+}
+
+/// The format values are stored in, set for the whole struct or a single
+/// field using `codec = <name>`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Codec {
+    Bincode,
+    Postcard,
+    MessagePack,
+    Json,
+}
+
+impl fmt::Display for Codec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Codec::Bincode => write!(f, "bincode"),
+            Codec::Postcard => write!(f, "postcard"),
+            Codec::MessagePack => write!(f, "msgpack"),
+            Codec::Json => write!(f, "json"),
+        }
+    }
+}
+
+impl Codec {
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "bincode" => Codec::Bincode,
+            "postcard" => Codec::Postcard,
+            "msgpack" => Codec::MessagePack,
+            "json" => Codec::Json,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct CodecOption {
+    pub codec: Codec,
+    pub span: Span,
+}
+
+/// The codec set on the struct, fields without a codec of their own use it
+pub fn try_from(options: &[Options], fields: &[Unkeyed]) -> Result<Option<Codec>, Error> {
+    use ErrorVariant::*;
+
+    let mut codecs = options.iter().filter_map(|opt| match opt {
+        Options::Codec(c) => Some(c),
+        _ => None,
+    });
+    let codec = match (codecs.next(), codecs.next()) {
+        (None, _) => None,
+        (Some(c), None) => Some(*c),
+        (Some(_), Some(c1)) => return Err(MultipleCodecs.with_span(c1.span)),
+    };
+
+    let is_trait = options.iter().any(
+        |o| matches!(o, Options::Backend(b) if matches!(b.backend, BackendOptionVariant::Trait)),
+    );
+    if is_trait {
+        let set = codec.or_else(|| fields.iter().find_map(|f| f.codec));
+        if let Some(set) = set {
+            return Err(CodecOnTrait.with_span(set.span));
+        }
+    }
+
+    Ok(codec.map(|c| c.codec))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::attribute;
+    use std::str::FromStr;
+
+    fn parse(attr: &str) -> Result<Option<Codec>, Error> {
+        let attr = proc_macro2::TokenStream::from_str(attr).unwrap();
+        let options = attribute::parse(attr).unwrap();
+        try_from(&options, &[])
+    }
+
+    #[test]
+    fn struct_codec() {
+        assert_eq!(parse("db=sled, codec=json").unwrap(), Some(Codec::Json));
+        assert_eq!(parse("db=sled").unwrap(), None);
+    }
+
+    #[test]
+    fn reject_multiple_codecs() {
+        let err = parse("db=sled, codec=json, codec=postcard").unwrap_err();
+        assert!(matches!(err.variant, ErrorVariant::MultipleCodecs));
+    }
+
+    #[test]
+    fn reject_codec_on_trait() {
+        let err = parse("db=trait, codec=msgpack").unwrap_err();
+        assert!(matches!(err.variant, ErrorVariant::CodecOnTrait));
+    }
+}
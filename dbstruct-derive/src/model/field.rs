@@ -1,36 +1,82 @@
 mod wrapper;
 pub use wrapper::Error;
+pub use wrapper::PinnedKey;
 pub use wrapper::Wrapper;
 
+use super::codec::{Codec, CodecOption};
+use super::flush::{Flush, FlushOption};
+use super::key::Key;
 use super::DbKey;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Field {
     pub ident: syn::Ident,
     pub vis: syn::Visibility,
     pub wrapper: Wrapper,
-    pub key: u8,
+    pub key: Key,
+    /// None if the codec of the store is used
+    pub codec: Option<Codec>,
+    pub flush: Flush,
+}
+
+/// A field that still needs a key, we can only assign those
+/// once we know the key every other field pinned.
+#[derive(Debug)]
+pub struct Unkeyed {
+    pub ident: syn::Ident,
+    pub vis: syn::Visibility,
+    pub wrapper: Wrapper,
+    pub pinned_key: Option<PinnedKey>,
+    pub codec: Option<CodecOption>,
+    pub flush: Option<FlushOption>,
 }
 
 impl Field {
-    pub fn is_vec(&self) -> bool {
-        matches!(&self.wrapper, Wrapper::Vec { .. })
+    /// The type of the value the wrapper caches in the generated struct,
+    /// the length of a vec or the ends of a deque.
+    pub fn cached_ty(&self) -> Option<syn::Type> {
+        match &self.wrapper {
+            Wrapper::Vec { .. } => Some(syn::parse_quote!(usize)),
+            Wrapper::VecDeque { .. } => Some(syn::parse_quote!(::dbstruct::wrapper::Ends)),
+            _ => None,
+        }
     }
 }
 
 impl Field {
-    pub fn analyze(mut field: syn::Field, keys: &DbKey) -> Result<Self, Error> {
-        let wrapper = Wrapper::try_from(&mut field.attrs, field.ty)?;
+    pub fn analyze(mut field: syn::Field) -> Result<Unkeyed, Error> {
+        let (wrapper, options) = Wrapper::try_from(&mut field.attrs, field.ty)?;
         let ident = field
             .ident
             .expect("every struct field should have an Ident");
-        let key = keys.prefix(&ident);
 
-        Ok(Self {
+        Ok(Unkeyed {
             ident,
             vis: field.vis,
             wrapper,
-            key,
+            pinned_key: options.key,
+            codec: options.codec,
+            flush: options.flush,
         })
     }
 }
+
+impl Unkeyed {
+    /// `codec` and `flush` are used if the field did not set its own
+    pub fn with_key(self, keys: &DbKey, codec: Option<Codec>, flush: Flush) -> Field {
+        let key = keys.prefix(&self.ident);
+        // the nested struct picks its own codec and flush policy
+        let (codec, flush) = match self.wrapper {
+            Wrapper::Nested { .. } => (None, Flush::Never),
+            _ => (codec, flush),
+        };
+        Field {
+            ident: self.ident,
+            vis: self.vis,
+            wrapper: self.wrapper,
+            key,
+            codec: self.codec.map(|c| c.codec).or(codec),
+            flush: self.flush.map_or(flush, |f| f.flush),
+        }
+    }
+}
@@ -8,16 +8,30 @@ mod errors;
 pub use errors::{Error, ErrorVariant};
 
 use crate::model::backend::ExtraBound;
+use crate::model::codec::{Codec, CodecOption};
+use crate::model::flush::{Flush, FlushOption};
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Wrapper {
     Vec {
         ty: syn::Type,
     },
+    VecDeque {
+        ty: syn::Type,
+    },
     Map {
         key_ty: syn::Type,
         val_ty: syn::Type,
     },
+    BTreeMap {
+        key_ty: syn::Type,
+        val_ty: syn::Type,
+    },
+    Set {
+        ty: syn::Type,
+        /// a `BTreeSet`, needs an ordered store
+        ordered: bool,
+    },
     DefaultTrait {
         ty: syn::Type,
     },
@@ -28,12 +42,40 @@ pub enum Wrapper {
     Option {
         ty: syn::Type,
     },
+    /// a struct that is itself a `#[dbstruct]`
+    Nested {
+        ty: syn::Type,
+    },
+    /// an integer changed by adding to it
+    Counter {
+        ty: syn::Type,
+    },
 }
 
 #[derive(Debug)]
 pub enum Attribute {
     DefaultTrait { span: proc_macro2::Span },
+    Nested { span: proc_macro2::Span },
+    Counter { span: proc_macro2::Span },
     DefaultValue { expr: syn::Expr },
+    Key(PinnedKey),
+    Codec(CodecOption),
+    Flush(FlushOption),
+}
+
+/// A prefix set by the user through `#[dbstruct(key = <N>)]`
+#[derive(Debug, Clone, Copy)]
+pub struct PinnedKey {
+    pub key: u16,
+    pub span: proc_macro2::Span,
+}
+
+/// Field attributes that do not change the wrapper
+#[derive(Debug, Default)]
+pub struct Options {
+    pub key: Option<PinnedKey>,
+    pub codec: Option<CodecOption>,
+    pub flush: Option<FlushOption>,
 }
 
 fn is_relevant(att: &syn::Attribute) -> bool {
@@ -50,8 +92,7 @@ fn is_relevant(att: &syn::Attribute) -> bool {
  * * <01-08-22, dvdsk noreply@davidsk.dev> */
 fn unescape_literal(s: &str) -> String {
     let s = s.trim_matches('"');
-    let s = s.replace(r#"\"#, r#""#);
-    s
+    s.replace(r#"\"#, r#""#)
 }
 
 fn parse_default(
@@ -60,29 +101,93 @@ fn parse_default(
 ) -> Result<Attribute, Error> {
     use ErrorVariant::*;
     match tokens.peek() {
-        None => {
-            tokens.next();
-            return Ok(Attribute::DefaultTrait { span });
-        }
+        None => Ok(Attribute::DefaultTrait { span }),
         Some(TokenTree::Punct(punct)) if punct.as_char() == ',' => {
-            tokens.next();
-            return Ok(Attribute::DefaultTrait { span });
+            Ok(Attribute::DefaultTrait { span })
         }
         Some(TokenTree::Punct(punct)) if punct.as_char() == '=' => {
             let punct = punct.span();
             match tokens.nth(1) {
-                None => return Err(MissingDefaultValue.with_span(punct)),
+                None => Err(MissingDefaultValue.with_span(punct)),
                 Some(TokenTree::Literal(lit)) => {
                     let expr = lit.to_string();
                     let expr = unescape_literal(&expr);
                     let expr: syn::Expr = syn::parse_str(&expr)
                         .map_err(|err| ValueNotExpression(err).with_span(lit))?;
-                    return Ok(Attribute::DefaultValue { expr });
+                    Ok(Attribute::DefaultValue { expr })
                 }
-                Some(other) => return Err(InvalidDefaultArg.with_span(other)),
+                Some(other) => Err(InvalidDefaultArg.with_span(other)),
             }
         }
-        Some(_other) => return Err(InvalidDefaultArg.with_span(_other)),
+        Some(other) => Err(InvalidDefaultArg.with_span(other)),
+    }
+}
+
+fn parse_key(
+    span: proc_macro2::Span,
+    tokens: &mut Peekable<impl Iterator<Item = TokenTree>>,
+) -> Result<Attribute, Error> {
+    use ErrorVariant::*;
+    match tokens.next() {
+        Some(TokenTree::Punct(punct)) if punct.as_char() == '=' => match tokens.next() {
+            None => Err(MissingKeyValue.with_span(punct)),
+            Some(TokenTree::Literal(lit)) => {
+                let key = syn::LitInt::from(lit.clone())
+                    .base10_parse::<u16>()
+                    .ok()
+                    .filter(|key| *key < u16::MAX)
+                    .ok_or_else(|| InvalidKey.with_span(&lit))?;
+                Ok(Attribute::Key(PinnedKey {
+                    key,
+                    span: lit.span(),
+                }))
+            }
+            Some(other) => Err(InvalidKey.with_span(other)),
+        },
+        _ => Err(MissingKeyValue.with_span(span)),
+    }
+}
+
+fn parse_codec(
+    span: proc_macro2::Span,
+    tokens: &mut Peekable<impl Iterator<Item = TokenTree>>,
+) -> Result<Attribute, Error> {
+    use ErrorVariant::*;
+    match tokens.next() {
+        Some(TokenTree::Punct(punct)) if punct.as_char() == '=' => match tokens.next() {
+            None => Err(MissingCodecValue.with_span(punct)),
+            Some(TokenTree::Ident(ident)) => {
+                let codec = Codec::from_name(&ident.to_string())
+                    .ok_or_else(|| NotACodec(ident.clone()).has_span())?;
+                Ok(Attribute::Codec(CodecOption {
+                    codec,
+                    span: ident.span(),
+                }))
+            }
+            Some(other) => Err(MissingCodecValue.with_span(other)),
+        },
+        _ => Err(MissingCodecValue.with_span(span)),
+    }
+}
+
+fn parse_flush(
+    span: proc_macro2::Span,
+    tokens: &mut Peekable<impl Iterator<Item = TokenTree>>,
+) -> Result<Attribute, Error> {
+    use ErrorVariant::*;
+    match tokens.next() {
+        Some(TokenTree::Punct(punct)) if punct.as_char() == '=' => match tokens.next() {
+            None => Err(MissingFlushValue.with_span(punct)),
+            Some(TokenTree::Literal(lit)) => {
+                let flush = Flush::from_literal(&lit).ok_or_else(|| InvalidFlush.with_span(&lit))?;
+                Ok(Attribute::Flush(FlushOption {
+                    flush,
+                    span: lit.span(),
+                }))
+            }
+            Some(other) => Err(InvalidFlush.with_span(other)),
+        },
+        _ => Err(MissingFlushValue.with_span(span)),
     }
 }
 
@@ -92,15 +197,22 @@ fn parse(tokens: &mut Peekable<impl Iterator<Item = TokenTree>>) -> Result<Attri
         .next()
         .expect("should only get here if peek returned Some");
     match first_token {
-        TokenTree::Ident(ident) if ident.to_string() == "Default" => {
-            parse_default(ident.span(), tokens)
-        }
-        TokenTree::Ident(ident) => return Err(NotAWrapper(ident).has_span()),
-        _ => return Err(InvalidSyntax(first_token).has_span()),
+        TokenTree::Ident(ident) if ident == "Default" => parse_default(ident.span(), tokens),
+        TokenTree::Ident(ident) if ident == "key" => parse_key(ident.span(), tokens),
+        TokenTree::Ident(ident) if ident == "codec" => parse_codec(ident.span(), tokens),
+        TokenTree::Ident(ident) if ident == "flush" => parse_flush(ident.span(), tokens),
+        TokenTree::Ident(ident) if ident == "nested" => Ok(Attribute::Nested {
+            span: ident.span(),
+        }),
+        TokenTree::Ident(ident) if ident == "Counter" => Ok(Attribute::Counter {
+            span: ident.span(),
+        }),
+        TokenTree::Ident(ident) => Err(NotAWrapper(ident).has_span()),
+        _ => Err(InvalidSyntax(first_token).has_span()),
     }
 }
 
-fn as_wrapper(att: syn::Attribute) -> Result<Option<Attribute>, Error> {
+fn parse_attribute(att: syn::Attribute) -> Result<Vec<Attribute>, Error> {
     use ErrorVariant::*;
     let tokens = match att.meta {
         syn::Meta::Path(_) => return Err(EmptyAttribute.with_span(att)),
@@ -108,21 +220,54 @@ fn as_wrapper(att: syn::Attribute) -> Result<Option<Attribute>, Error> {
         syn::Meta::NameValue(_) => return Err(InvalidTokenTree.with_span(att)),
     };
 
-    let mut res = None;
+    let mut res = Vec::new();
     let mut tokens = tokens.into_iter().peekable();
-    while let Some(token) = tokens.peek() {
-        if res.is_none() {
-            res = Some(parse(&mut tokens)?);
-        } else {
-            return Err(MultipleWrapperAttributes.with_span(token));
+    while tokens.peek().is_some() {
+        res.push(parse(&mut tokens)?);
+        match tokens.next() {
+            Some(TokenTree::Punct(punct)) if punct.as_char() == ',' => continue,
+            Some(other) => return Err(MultipleWrapperAttributes.with_span(other)),
+            None => break,
         }
     }
     Ok(res)
 }
 
+/// splits the parsed attributes into the one determining
+/// the wrapper and the other options
+fn split(attributes: Vec<Attribute>) -> Result<(Option<Attribute>, Options), Error> {
+    use ErrorVariant::*;
+
+    let mut wrapper = None;
+    let mut options = Options::default();
+    for attribute in attributes {
+        match attribute {
+            Attribute::Key(key) if options.key.is_some() => {
+                return Err(MultipleKeys.with_span(key.span))
+            }
+            Attribute::Key(key) => options.key = Some(key),
+            Attribute::Codec(codec) if options.codec.is_some() => {
+                return Err(MultipleCodecs.with_span(codec.span))
+            }
+            Attribute::Codec(codec) => options.codec = Some(codec),
+            Attribute::Flush(flush) if options.flush.is_some() => {
+                return Err(MultipleFlush.with_span(flush.span))
+            }
+            Attribute::Flush(flush) => options.flush = Some(flush),
+            other if wrapper.is_some() => return Err(MultipleWrapperAttributes.with_span(other)),
+            other => wrapper = Some(other),
+        }
+    }
+    Ok((wrapper, options))
+}
+
 impl Wrapper {
     /// takes relevant attributes from `attributes` and determines the wrapper
-    pub fn try_from(attributes: &mut Vec<syn::Attribute>, ty: syn::Type) -> Result<Self, Error> {
+    /// and any other options set on the field
+    pub fn try_from(
+        attributes: &mut Vec<syn::Attribute>,
+        ty: syn::Type,
+    ) -> Result<(Self, Options), Error> {
         use Attribute::*;
         use ErrorVariant::*;
 
@@ -131,15 +276,28 @@ impl Wrapper {
         *attributes = other; /* TODO: use drain_filter when it stabilizes <31-07-22> */
         /* TODO: emit error when other (non dbstruct) attributes are present <27-08-22, dvdsk> */
 
-        let attribute = relevant.pop().map(as_wrapper).transpose()?.flatten();
+        let attributes = relevant.pop().map(parse_attribute).transpose()?;
         if let Some(other) = relevant.pop() {
             return Err(MultipleAttributes.with_span(&other));
         }
+        let (attribute, options) = split(attributes.unwrap_or_default())?;
+        if let (Some(Nested { .. }), Some(codec)) = (&attribute, options.codec) {
+            return Err(CodecOnNested.with_span(codec.span));
+        }
+        if let (Some(Nested { .. }), Some(flush)) = (&attribute, options.flush) {
+            return Err(FlushOnNested.with_span(flush.span));
+        }
+        if let (Some(Counter { .. }), Some(codec)) = (&attribute, options.codec) {
+            return Err(CodecOnCounter.with_span(codec.span));
+        }
 
-        Ok(match (outer_type(&ty)?.as_str(), attribute) {
+        let wrapper = match (outer_type(&ty)?.as_str(), attribute) {
             ("Vec", None) => Self::Vec {
                 ty: inner_type(&ty, "Vec")?,
             },
+            ("VecDeque", None) => Self::VecDeque {
+                ty: inner_type(&ty, "VecDeque")?,
+            },
             ("Option", None) => Self::Option {
                 ty: inner_type(&ty, "Option")?,
             },
@@ -147,19 +305,51 @@ impl Wrapper {
             // error at the type and the default trait attribute
             ("Option", Some(DefaultTrait { span })) => return Err(OptionNotAllowed.with_span(span)),
             ("HashMap", None) => {
-                let (key_ty, val_ty) = hashmap_types(&ty)?;
+                let (key_ty, val_ty) = map_types(&ty, "HashMap")?;
                 Self::Map { key_ty, val_ty }
             }
+            ("BTreeMap", None) => {
+                let (key_ty, val_ty) = map_types(&ty, "BTreeMap")?;
+                Self::BTreeMap { key_ty, val_ty }
+            }
+            ("HashSet", None) => Self::Set {
+                ty: inner_type(&ty, "HashSet")?,
+                ordered: false,
+            },
+            ("BTreeSet", None) => Self::Set {
+                ty: inner_type(&ty, "BTreeSet")?,
+                ordered: true,
+            },
             (_, None) => return Err(NoDefaultType.with_span(ty)),
             (_, Some(DefaultTrait { .. })) => Self::DefaultTrait { ty },
+            (_, Some(Nested { .. })) => Self::Nested { ty },
+            (_, Some(Counter { .. })) => Self::Counter { ty },
             (_, Some(DefaultValue { expr })) => Self::DefaultValue { ty, value: expr },
-        })
+            (_, Some(Key(_) | Codec(_) | Flush(_))) => {
+                unreachable!("split never returns a key, codec or flush as wrapper attribute")
+            }
+        };
+        Ok((wrapper, options))
+    }
+
+    /// The fields of a nested struct are opened by a blocking function and
+    /// counters have no async wrapper, they can not be used with the async
+    /// option yet.
+    pub(crate) fn check_async(&self) -> Result<(), Error> {
+        match self {
+            Wrapper::Nested { ty } => Err(ErrorVariant::NestedAsync.with_span(ty)),
+            Wrapper::Counter { ty } => Err(ErrorVariant::CounterAsync.with_span(ty)),
+            _ => Ok(()),
+        }
     }
 
     pub(crate) fn needed_traits(&self) -> HashSet<ExtraBound> {
         use ExtraBound::*;
         match self {
-            Wrapper::Vec { .. } => vec![Ordered].into_iter(),
+            Wrapper::Vec { .. }
+            | Wrapper::BTreeMap { .. }
+            | Wrapper::Set { ordered: true, .. } => vec![Ordered].into_iter(),
+            Wrapper::Counter { .. } => vec![Atomic].into_iter(),
             _ => vec![].into_iter(),
         }
         .collect()
@@ -190,13 +380,13 @@ fn inner_type(ty: &syn::Type, outer_ty: &'static str) -> Result<syn::Type, Error
     Ok(ty)
 }
 
-fn hashmap_types(ty: &syn::Type) -> Result<(syn::Type, syn::Type), Error> {
-    let mut generics = generic_types(ty, "HashMap", 2)?;
+fn map_types(ty: &syn::Type, outer_ty: &'static str) -> Result<(syn::Type, syn::Type), Error> {
+    let mut generics = generic_types(ty, outer_ty, 2)?;
     let key_ty = generics
         .next()
         .ok_or(
             ErrorVariant::TooFewGenerics {
-                ty: "HashMap",
+                ty: outer_ty,
                 n_needed: 2,
             }
             .with_span(ty),
@@ -206,7 +396,7 @@ fn hashmap_types(ty: &syn::Type) -> Result<(syn::Type, syn::Type), Error> {
         .next()
         .ok_or(
             ErrorVariant::TooFewGenerics {
-                ty: "HashMap",
+                ty: outer_ty,
                 n_needed: 2,
             }
             .with_span(ty),
@@ -215,7 +405,7 @@ fn hashmap_types(ty: &syn::Type) -> Result<(syn::Type, syn::Type), Error> {
 
     if let Some(other_generic) = generics.next() {
         return Err(ErrorVariant::TooManyGenerics {
-            ty: "HashMap",
+            ty: outer_ty,
             n_needed: 2,
         }
         .with_span(other_generic?));
@@ -234,7 +424,7 @@ fn generic_types<'a>(
             path: syn::Path { segments, .. },
             ..
         }) => segments,
-        _ => unreachable!("should only run in match arm when matching HashMap"),
+        _ => unreachable!("should only run in match arm when matching a generic type"),
     };
 
     // aliasing HashMap can result in a macro panic. The person making
@@ -289,7 +479,7 @@ mod tests {
             let attributes: &[syn::Attribute] =
                 &[parse_quote!(#[dbstruct(Default)]), parse_quote!(#[b])];
             let ty_u8: syn::Type = parse_quote!(u8);
-            let wrapper = Wrapper::try_from(&mut attributes.to_vec(), ty_u8.clone()).unwrap();
+            let (wrapper, _) = Wrapper::try_from(&mut attributes.to_vec(), ty_u8.clone()).unwrap();
             assert_eq!(wrapper, Wrapper::DefaultTrait { ty: ty_u8 })
         }
 
@@ -298,7 +488,8 @@ mod tests {
             let attributes: &[syn::Attribute] =
                 &[parse_quote!(#[dbstruct(Default)]), parse_quote!(#[b])];
             let field_ty: syn::Type = parse_quote!(Vec<u8>);
-            let wrapper = Wrapper::try_from(&mut attributes.to_vec(), field_ty.clone()).unwrap();
+            let (wrapper, _) =
+                Wrapper::try_from(&mut attributes.to_vec(), field_ty.clone()).unwrap();
             assert_eq!(wrapper, Wrapper::DefaultTrait { ty: field_ty })
         }
     }
@@ -320,27 +511,105 @@ mod tests {
     fn vec() {
         let inner_ty: syn::Type = parse_quote!(u32);
         let ty_vec: syn::Type = parse_quote!(Vec<u32>);
-        let wrapper = Wrapper::try_from(&mut Vec::new(), ty_vec.clone()).unwrap();
+        let (wrapper, _) = Wrapper::try_from(&mut Vec::new(), ty_vec.clone()).unwrap();
         assert_eq!(wrapper, Wrapper::Vec { ty: inner_ty })
     }
 
+    #[test]
+    fn vec_deque() {
+        let inner_ty: syn::Type = parse_quote!(String);
+        let ty: syn::Type = parse_quote!(VecDeque<String>);
+        let (wrapper, _) = Wrapper::try_from(&mut Vec::new(), ty).unwrap();
+        assert_eq!(wrapper, Wrapper::VecDeque { ty: inner_ty });
+        assert!(wrapper.needed_traits().is_empty());
+    }
+
     #[test]
     fn map() {
         let key_ty: syn::Type = parse_quote!(u8);
         let val_ty: syn::Type = parse_quote!(Vec<u16>);
         let ty_hashmap: syn::Type = parse_quote!(HashMap<u8, Vec<u16>>);
-        let wrapper = Wrapper::try_from(&mut Vec::new(), ty_hashmap.clone()).unwrap();
+        let (wrapper, _) = Wrapper::try_from(&mut Vec::new(), ty_hashmap.clone()).unwrap();
         assert_eq!(wrapper, Wrapper::Map { key_ty, val_ty })
     }
 
+    #[test]
+    fn btreemap() {
+        let key_ty: syn::Type = parse_quote!(u8);
+        let val_ty: syn::Type = parse_quote!(String);
+        let ty: syn::Type = parse_quote!(BTreeMap<u8, String>);
+        let (wrapper, _) = Wrapper::try_from(&mut Vec::new(), ty).unwrap();
+        assert_eq!(wrapper, Wrapper::BTreeMap { key_ty, val_ty })
+    }
+
+    #[test]
+    fn sets() {
+        let inner_ty: syn::Type = parse_quote!(String);
+        let ty: syn::Type = parse_quote!(HashSet<String>);
+        let (wrapper, _) = Wrapper::try_from(&mut Vec::new(), ty).unwrap();
+        let expected = Wrapper::Set {
+            ty: inner_ty.clone(),
+            ordered: false,
+        };
+        assert_eq!(wrapper, expected);
+        assert!(wrapper.needed_traits().is_empty());
+
+        let ty: syn::Type = parse_quote!(BTreeSet<String>);
+        let (wrapper, _) = Wrapper::try_from(&mut Vec::new(), ty).unwrap();
+        let expected = Wrapper::Set {
+            ty: inner_ty,
+            ordered: true,
+        };
+        assert_eq!(wrapper, expected);
+        assert!(wrapper.needed_traits().contains(&ExtraBound::Ordered));
+    }
+
     #[test]
     fn option() {
         let inner_ty: syn::Type = parse_quote!(u16);
         let ty: syn::Type = parse_quote!(Option<u16>);
-        let wrapper = Wrapper::try_from(&mut Vec::new(), ty).unwrap();
+        let (wrapper, _) = Wrapper::try_from(&mut Vec::new(), ty).unwrap();
         assert_eq!(wrapper, Wrapper::Option { ty: inner_ty })
     }
 
+    #[test]
+    fn nested() {
+        let attributes: &[syn::Attribute] = &[parse_quote!(#[dbstruct(nested)])];
+        let ty: syn::Type = parse_quote!(Settings);
+        let (wrapper, _) = Wrapper::try_from(&mut attributes.to_vec(), ty.clone()).unwrap();
+        assert_eq!(wrapper, Wrapper::Nested { ty });
+        assert!(wrapper.check_async().is_err());
+
+        let attributes: &[syn::Attribute] = &[parse_quote!(#[dbstruct(nested, codec = json)])];
+        let ty: syn::Type = parse_quote!(Settings);
+        let err = Wrapper::try_from(&mut attributes.to_vec(), ty).unwrap_err();
+        assert!(matches!(err.variant, ErrorVariant::CodecOnNested));
+    }
+
+    #[test]
+    fn counter() {
+        let attributes: &[syn::Attribute] = &[parse_quote!(#[dbstruct(Counter, key = 2)])];
+        let ty: syn::Type = parse_quote!(u64);
+        let (wrapper, _) = Wrapper::try_from(&mut attributes.to_vec(), ty.clone()).unwrap();
+        assert_eq!(wrapper, Wrapper::Counter { ty });
+        assert!(wrapper.needed_traits().contains(&ExtraBound::Atomic));
+        assert!(wrapper.check_async().is_err());
+
+        let attributes: &[syn::Attribute] = &[parse_quote!(#[dbstruct(Counter, codec = json)])];
+        let ty: syn::Type = parse_quote!(u64);
+        let err = Wrapper::try_from(&mut attributes.to_vec(), ty).unwrap_err();
+        assert!(matches!(err.variant, ErrorVariant::CodecOnCounter));
+    }
+
+    #[test]
+    fn codec() {
+        let attributes: &[syn::Attribute] = &[parse_quote!(#[dbstruct(Default, codec = json)])];
+        let ty_u8: syn::Type = parse_quote!(u8);
+        let (wrapper, options) = Wrapper::try_from(&mut attributes.to_vec(), ty_u8.clone()).unwrap();
+        assert_eq!(wrapper, Wrapper::DefaultTrait { ty: ty_u8 });
+        assert_eq!(options.codec.map(|c| c.codec), Some(Codec::Json));
+    }
+
     mod default_value {
         use super::*;
 
@@ -351,7 +620,7 @@ mod tests {
                 syn::parse_quote!(#[b]),
             ];
             let ty_u8: syn::Type = syn::parse_quote!(u8);
-            let wrapper = Wrapper::try_from(&mut attributes.to_vec(), ty_u8.clone()).unwrap();
+            let (wrapper, _) = Wrapper::try_from(&mut attributes.to_vec(), ty_u8.clone()).unwrap();
             let value: syn::Expr = syn::parse_quote!(5u8);
             assert_eq!(wrapper, Wrapper::DefaultValue { ty: ty_u8, value })
         }
@@ -363,7 +632,7 @@ mod tests {
                 syn::parse_quote!(#[b]),
             ];
             let ty_u8: syn::Type = syn::parse_quote!(u8);
-            let wrapper = Wrapper::try_from(&mut attributes.to_vec(), ty_u8.clone()).unwrap();
+            let (wrapper, _) = Wrapper::try_from(&mut attributes.to_vec(), ty_u8.clone()).unwrap();
             let value: syn::Expr = syn::parse_quote!(format!("hello, {}", 5u8));
             assert_eq!(wrapper, Wrapper::DefaultValue { ty: ty_u8, value })
         }
@@ -36,6 +36,34 @@ pub enum ErrorVariant {
     TooFewGenerics { ty: &'static str, n_needed: u8 },
     #[error("Too many generics for {ty}, expected {n_needed}")]
     TooManyGenerics { ty: &'static str, n_needed: u8 },
+    #[error("Invalid syntax: missing a value for the key")]
+    MissingKeyValue,
+    #[error("A key must be an integer between 0 and 254")]
+    InvalidKey,
+    #[error("Each field can only have one key")]
+    MultipleKeys,
+    #[error("Invalid syntax: missing a codec")]
+    MissingCodecValue,
+    #[error("Not a known codec: `{0}`")]
+    NotACodec(proc_macro2::Ident),
+    #[error("Each field can only have one codec")]
+    MultipleCodecs,
+    #[error("A nested struct can not have a codec")]
+    CodecOnNested,
+    #[error("Invalid syntax: missing a flush policy")]
+    MissingFlushValue,
+    #[error("Not a known flush policy")]
+    InvalidFlush,
+    #[error("Each field can only have one flush policy")]
+    MultipleFlush,
+    #[error("A nested struct can not have a flush policy")]
+    FlushOnNested,
+    #[error("Nested structs can not be used with the async option")]
+    NestedAsync,
+    #[error("A counter is stored as a little endian integer, it can not have a codec")]
+    CodecOnCounter,
+    #[error("Counters can not be used with the async option")]
+    CounterAsync,
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -47,22 +75,41 @@ pub struct Error {
 impl Help for Error {
     fn help(&self) -> Option<String> {
         use ErrorVariant::*;
-        Some(match self.variant {
-            NoDefaultType => {
-                "you can wrap the type in an Option, add an attribute to use the Default trait: 
+        Some(
+            match self.variant {
+                NoDefaultType => {
+                    "you can wrap the type in an Option, add an attribute to use the Default trait: 
                 `#[dbstruct(Default)]` or provide an expression to generate a default 
                 value: `#[dbstruct(Default=<expr>)]"
+                }
+                InvalidSyntax(_) => {
+                    "try one of these: `#[dbstruct(Default)]`, `#[dbstruct(Default=\"<expr>\"]`"
+                }
+                OptionNotAllowed => "try removing the attribute",
+                MissingKeyValue | InvalidKey => {
+                    "try setting a key like this: `#[dbstruct(key = 3)]`"
+                }
+                MultipleKeys => "remove one of the keys",
+                MissingCodecValue | NotACodec(_) => {
+                    "the supported codecs are: bincode, postcard, msgpack and json"
+                }
+                MultipleCodecs => "remove one of the codecs",
+                CodecOnNested => "set the codec on the nested struct instead",
+                MissingFlushValue | InvalidFlush => {
+                    "try one of these: `flush = \"every_write\"`, `flush = \"interval_ms=500\"` or `flush = \"never\"`"
+                }
+                MultipleFlush => "remove one of the flush policies",
+                FlushOnNested => "set the flush option on the nested struct instead",
+                NestedAsync => "remove the async option or store the struct as a single value",
+                CodecOnCounter => "remove the codec",
+                CounterAsync => "remove the async option or use `#[dbstruct(Default)]` and `update`",
+                MultipleWrapperAttributes => {
+                    "when using Default=\"<expr>\" make sure the <expr> string is properly escaped"
+                }
+                _ => return None,
             }
-            InvalidSyntax(_) => {
-                "try one of these: `#[dbstruct(Default)]`, `#[dbstruct(Default=\"<expr>\"]`"
-            }
-            OptionNotAllowed => "try removing the attribute",
-            MultipleWrapperAttributes => {
-                "when using Default=\"<expr>\" make sure the <expr> string is properly escaped"
-            }
-            _ => return None,
-        }
-        .to_owned())
+            .to_owned(),
+        )
     }
 }
 
@@ -78,6 +125,7 @@ impl Error {
             (ErrorVariant::NotAWrapper(item), None) => item.span(),
             (ErrorVariant::InvalidSyntax(item), None) => item.span(),
             (ErrorVariant::ValueNotExpression(item), None) => item.span(),
+            (ErrorVariant::NotACodec(item), None) => item.span(),
             (_, Some(span)) => span,
             (_var, _) => panic!(
                 "error should track a span for {_var:?} as 
@@ -93,7 +141,12 @@ impl GetSpan for super::Attribute {
         match self {
             // NoWrap { span } => *span,
             DefaultTrait { span } => *span,
+            Nested { span } => *span,
+            Counter { span } => *span,
             DefaultValue { expr } => syn::spanned::Spanned::span(expr),
+            Key(key) => key.span,
+            Codec(codec) => codec.span,
+            Flush(flush) => flush.span,
         }
     }
 }
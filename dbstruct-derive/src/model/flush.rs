@@ -0,0 +1,167 @@
+use core::fmt;
+
+use proc_macro2::Span;
+
+use crate::errors::{GetSpan, Help};
+
+use super::attribute::{BackendOptionVariant, Options};
+use super::field::Unkeyed;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ErrorVariant {
+    #[error("multiple flush options specified")]
+    MultipleFlush,
+    #[error("the flush option can not be set with `db=trait`")]
+    FlushOnTrait,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub struct Error {
+    variant: ErrorVariant,
+    span: Span,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.variant.fmt(f)
+    }
+}
+
+impl GetSpan for Error {
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+impl Help for Error {
+    fn help(&self) -> Option<String> {
+        use ErrorVariant::*;
+        Some(match &self.variant {
+            MultipleFlush => "remove one of the flush options".to_owned(),
+            FlushOnTrait => "wrap the store in `dbstruct::stores::Flushing` instead".to_owned(),
+        })
+    }
+}
+
+impl ErrorVariant {
+    fn with_span(self, item: impl GetSpan) -> Error {
+        Error {
+            variant: self,
+            span: item.span(),
+        }
+    }
+}
+
+/// When the writes to a field are flushed, set for the whole struct or a
+/// single field using `flush = "<policy>"`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flush {
+    /// only when `flush` is called, the default
+    Never,
+    EveryWrite,
+    Interval {
+        ms: u64,
+    },
+}
+
+impl Flush {
+    /// parses `never`, `every_write` or `interval_ms=<N>`
+    pub fn from_value(value: &str) -> Option<Self> {
+        Some(match value {
+            "never" => Flush::Never,
+            "every_write" => Flush::EveryWrite,
+            _ => {
+                let ms = value.strip_prefix("interval_ms=")?;
+                Flush::Interval {
+                    ms: ms.trim().parse().ok()?,
+                }
+            }
+        })
+    }
+
+    /// parses the string literal of a flush option
+    pub fn from_literal(lit: &proc_macro2::Literal) -> Option<Self> {
+        match syn::Lit::new(lit.clone()) {
+            syn::Lit::Str(value) => Self::from_value(&value.value()),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct FlushOption {
+    pub flush: Flush,
+    pub span: Span,
+}
+
+/// The flush policy set on the struct, fields without one of their own use it
+pub fn try_from(options: &[Options], fields: &[Unkeyed]) -> Result<Flush, Error> {
+    use ErrorVariant::*;
+
+    let mut flushes = options.iter().filter_map(|opt| match opt {
+        Options::Flush(f) => Some(f),
+        _ => None,
+    });
+    let flush = match (flushes.next(), flushes.next()) {
+        (None, _) => None,
+        (Some(f), None) => Some(*f),
+        (Some(_), Some(f1)) => return Err(MultipleFlush.with_span(f1.span)),
+    };
+
+    let is_trait = options.iter().any(
+        |o| matches!(o, Options::Backend(b) if matches!(b.backend, BackendOptionVariant::Trait)),
+    );
+    if is_trait {
+        let set = flush.or_else(|| fields.iter().find_map(|f| f.flush));
+        if let Some(set) = set {
+            return Err(FlushOnTrait.with_span(set.span));
+        }
+    }
+
+    Ok(flush.map_or(Flush::Never, |f| f.flush))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::attribute;
+    use std::str::FromStr;
+
+    fn parse(attr: &str) -> Result<Flush, Error> {
+        let attr = proc_macro2::TokenStream::from_str(attr).unwrap();
+        let options = attribute::parse(attr).unwrap();
+        try_from(&options, &[])
+    }
+
+    #[test]
+    fn policies() {
+        assert_eq!(Flush::from_value("every_write"), Some(Flush::EveryWrite));
+        assert_eq!(
+            Flush::from_value("interval_ms=500"),
+            Some(Flush::Interval { ms: 500 })
+        );
+        assert_eq!(Flush::from_value("interval_ms=soon"), None);
+        assert_eq!(Flush::from_value("always"), None);
+    }
+
+    #[test]
+    fn struct_flush() {
+        assert_eq!(
+            parse("db=sled, flush = \"every_write\"").unwrap(),
+            Flush::EveryWrite
+        );
+        assert_eq!(parse("db=sled").unwrap(), Flush::Never);
+    }
+
+    #[test]
+    fn reject_multiple_flush() {
+        let err = parse("db=sled, flush = \"never\", flush = \"every_write\"").unwrap_err();
+        assert!(matches!(err.variant, ErrorVariant::MultipleFlush));
+    }
+
+    #[test]
+    fn reject_flush_on_trait() {
+        let err = parse("db=trait, flush = \"every_write\"").unwrap_err();
+        assert!(matches!(err.variant, ErrorVariant::FlushOnTrait));
+    }
+}
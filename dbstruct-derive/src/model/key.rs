@@ -1,49 +1,276 @@
+use core::fmt;
+use proc_macro2::TokenStream;
+use quote::ToTokens;
 use std::collections::HashMap;
 use syn::Ident;
 
-use crate::errors::GetSpan;
+use crate::errors::{GetSpan, Help};
+
+use super::field::Unkeyed;
+
+#[derive(thiserror::Error, Debug)]
+pub enum ErrorVariant {
+    #[error("A dbstruct can only have {} fields", .0.n_keys())]
+    TooManyFields(KeyWidth),
+    #[error("Key {key} is already used by field `{other}`")]
+    DuplicateKey { key: u16, other: Ident },
+    #[error("Key {0} does not fit in a single byte")]
+    KeyTooLarge(u16),
+    #[error("Field `{0}` has no key while the struct requires explicit keys")]
+    MissingKey(Ident),
+}
 
 #[derive(thiserror::Error, Debug)]
-#[error("A dbstruct can only have 254 fields")]
 pub struct Error {
+    variant: ErrorVariant,
     span: proc_macro2::Span,
 }
 
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.variant.fmt(f)
+    }
+}
+
 impl GetSpan for Error {
     fn span(&self) -> proc_macro2::Span {
         self.span
     }
 }
 
-type Prefix = u8;
+impl Help for Error {
+    fn help(&self) -> Option<String> {
+        use ErrorVariant::*;
+        Some(match &self.variant {
+            TooManyFields(KeyWidth::Byte) | KeyTooLarge(_) => {
+                "use the struct option `wide_keys` to store keys in two bytes".to_owned()
+            }
+            TooManyFields(KeyWidth::Wide) => return None,
+            DuplicateKey { .. } => "every field needs a unique key".to_owned(),
+            MissingKey(_) => "pin the key using `#[dbstruct(key = <N>)]`".to_owned(),
+        })
+    }
+}
+
+impl ErrorVariant {
+    fn with_span(self, item: impl GetSpan) -> Error {
+        Error {
+            variant: self,
+            span: item.span(),
+        }
+    }
+}
+
+/// Determines how fields without a pinned key are handled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyMode {
+    /// Fields without a pinned key get the first free key in
+    /// alphabetical order. Adding a field can change the
+    /// key of other un-pinned fields.
+    Alphabetical,
+    /// Every field must pin its key
+    Explicit,
+}
+
+/// How many bytes the key of a field takes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyWidth {
+    /// A single byte, the layout databases have always used
+    Byte,
+    /// Two bytes, set through the `wide_keys` struct option
+    Wide,
+}
+
+impl KeyWidth {
+    /// Keys run from 0 up to, not including, this. With single byte keys
+    /// 0xFF is left out because the schema is stored under it. With
+    /// `wide_keys` the schema key is still the single byte 0xFF, shorter than
+    /// any field key, so no wide key collides with it. There 0xFFFF is left
+    /// out only because the count has to fit in an `u16`, the `key` attribute
+    /// rejects it for the same reason.
+    fn n_keys(&self) -> u16 {
+        match self {
+            KeyWidth::Byte => u8::MAX as u16,
+            KeyWidth::Wide => u16::MAX,
+        }
+    }
+
+    fn key(&self, key: u16) -> Key {
+        match self {
+            KeyWidth::Byte => Key::Byte(key as u8),
+            KeyWidth::Wide => Key::Wide(key),
+        }
+    }
+}
+
+/// The key of a field, the first bytes of every key of the field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    Byte(u8),
+    Wide(u16),
+}
+
+/// A suffixed literal so the generated code picks the right type
+impl ToTokens for Key {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        match self {
+            Key::Byte(key) => key.to_tokens(tokens),
+            Key::Wide(key) => key.to_tokens(tokens),
+        }
+    }
+}
+
 #[derive(Debug)]
-pub struct DbKey(HashMap<Ident, Prefix>);
+pub struct DbKey(HashMap<Ident, Key>);
 
 impl DbKey {
-    pub(crate) fn new(fields: &syn::Fields) -> Result<Self, Error> {
+    pub(crate) fn new(fields: &[Unkeyed], mode: KeyMode, width: KeyWidth) -> Result<Self, Error> {
+        use ErrorVariant::*;
+
+        let mut map = HashMap::new();
+        let mut taken: HashMap<u16, &Ident> = HashMap::new();
+        for field in fields {
+            let Some(pinned) = field.pinned_key else {
+                continue;
+            };
+            if pinned.key >= width.n_keys() {
+                return Err(KeyTooLarge(pinned.key).with_span(pinned.span));
+            }
+            if let Some(other) = taken.insert(pinned.key, &field.ident) {
+                let other = other.clone();
+                return Err(DuplicateKey {
+                    key: pinned.key,
+                    other,
+                }
+                .with_span(pinned.span));
+            }
+            map.insert(field.ident.clone(), width.key(pinned.key));
+        }
+
         let mut idents: Vec<_> = fields
             .iter()
-            .map(|f| f.ident.clone())
-            .map(|i| i.expect("should already be verified this is a named struct"))
+            .filter(|f| f.pinned_key.is_none())
+            .map(|f| &f.ident)
             .collect();
         idents.sort();
 
-        if let Some(ident) = idents.get(u8::MAX as usize) {
-            return Err(Error { span: ident.span() });
-        };
+        if mode == KeyMode::Explicit {
+            if let Some(ident) = idents.first() {
+                return Err(MissingKey((*ident).clone()).with_span(*ident));
+            }
+        }
 
-        let map = idents
-            .into_iter()
-            .enumerate()
-            .map(|(id, ident)| (ident, id as Prefix))
-            .collect();
+        let mut free = (0..width.n_keys()).filter(|key| !taken.contains_key(key));
+        for ident in idents {
+            let Some(key) = free.next() else {
+                return Err(TooManyFields(width).with_span(ident));
+            };
+            map.insert(ident.clone(), width.key(key));
+        }
         Ok(Self(map))
     }
 
-    pub fn prefix(&self, ident: &Ident) -> Prefix {
+    pub fn prefix(&self, ident: &Ident) -> Key {
         *self
             .0
             .get(ident)
             .expect("every field's ident should be in the DbKey map")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::field::PinnedKey;
+    use crate::model::Wrapper;
+    use proc_macro2::Span;
+    use syn::parse_quote;
+
+    fn field(ident: Ident, pinned: Option<u16>) -> Unkeyed {
+        Unkeyed {
+            ident,
+            vis: parse_quote!(pub),
+            wrapper: Wrapper::Option {
+                ty: parse_quote!(u8),
+            },
+            pinned_key: pinned.map(|key| PinnedKey {
+                key,
+                span: Span::call_site(),
+            }),
+            codec: None,
+            flush: None,
+        }
+    }
+
+    #[test]
+    fn alphabetical_without_pins() {
+        let fields = [field(parse_quote!(b), None), field(parse_quote!(a), None)];
+        let keys = DbKey::new(&fields, KeyMode::Alphabetical, KeyWidth::Byte).unwrap();
+        assert_eq!(keys.prefix(&parse_quote!(a)), Key::Byte(0));
+        assert_eq!(keys.prefix(&parse_quote!(b)), Key::Byte(1));
+    }
+
+    #[test]
+    fn pinned_keys_are_skipped() {
+        let fields = [
+            field(parse_quote!(b), None),
+            field(parse_quote!(a), Some(0)),
+            field(parse_quote!(aa), None),
+        ];
+        let keys = DbKey::new(&fields, KeyMode::Alphabetical, KeyWidth::Byte).unwrap();
+        assert_eq!(keys.prefix(&parse_quote!(a)), Key::Byte(0));
+        assert_eq!(keys.prefix(&parse_quote!(aa)), Key::Byte(1));
+        assert_eq!(keys.prefix(&parse_quote!(b)), Key::Byte(2));
+    }
+
+    #[test]
+    fn duplicate_pin() {
+        let fields = [
+            field(parse_quote!(a), Some(3)),
+            field(parse_quote!(b), Some(3)),
+        ];
+        let err = DbKey::new(&fields, KeyMode::Alphabetical, KeyWidth::Byte).unwrap_err();
+        assert!(matches!(
+            err.variant,
+            ErrorVariant::DuplicateKey { key: 3, .. }
+        ));
+    }
+
+    #[test]
+    fn explicit_needs_every_key() {
+        let fields = [
+            field(parse_quote!(a), Some(3)),
+            field(parse_quote!(b), None),
+        ];
+        let err = DbKey::new(&fields, KeyMode::Explicit, KeyWidth::Byte).unwrap_err();
+        assert!(matches!(err.variant, ErrorVariant::MissingKey(_)));
+    }
+
+    fn many_fields(n: usize) -> Vec<Unkeyed> {
+        (0..n)
+            .map(|i| field(quote::format_ident!("f{i:03}"), None))
+            .collect()
+    }
+
+    #[test]
+    fn byte_keys_run_out() {
+        let err = DbKey::new(&many_fields(256), KeyMode::Alphabetical, KeyWidth::Byte).unwrap_err();
+        assert!(matches!(
+            err.variant,
+            ErrorVariant::TooManyFields(KeyWidth::Byte)
+        ));
+    }
+
+    #[test]
+    fn wide_keys() {
+        let mut fields = many_fields(300);
+        fields.push(field(parse_quote!(pinned), Some(1000)));
+        let keys = DbKey::new(&fields, KeyMode::Alphabetical, KeyWidth::Wide).unwrap();
+        assert_eq!(keys.prefix(&parse_quote!(f299)), Key::Wide(299));
+        assert_eq!(keys.prefix(&parse_quote!(pinned)), Key::Wide(1000));
+
+        let fields = [field(parse_quote!(pinned), Some(1000))];
+        let err = DbKey::new(&fields, KeyMode::Alphabetical, KeyWidth::Byte).unwrap_err();
+        assert!(matches!(err.variant, ErrorVariant::KeyTooLarge(1000)));
+    }
+}
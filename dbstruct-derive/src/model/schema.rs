@@ -0,0 +1,180 @@
+use core::fmt;
+
+use proc_macro2::Span;
+
+use crate::errors::{GetSpan, Help};
+
+use super::attribute::{BackendOptionVariant, Migration, Options};
+
+#[derive(Debug, thiserror::Error)]
+pub enum ErrorVariant {
+    #[error("multiple versions specified")]
+    MultipleVersions,
+    #[error("there is already a migration from version {0}")]
+    DuplicateMigration(u32),
+    #[error("migration from version {from} while the struct is at version {version}")]
+    MigrationFromCurrent { from: u32, version: u32 },
+    #[error("migrations are not supported with an async store (`db=trait, async`)")]
+    AsyncMigration,
+    #[error("versions are not supported with an async store (`db=trait, async`)")]
+    AsyncVersion,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub struct Error {
+    variant: ErrorVariant,
+    span: Span,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.variant.fmt(f)
+    }
+}
+
+impl GetSpan for Error {
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+impl Help for Error {
+    fn help(&self) -> Option<String> {
+        use ErrorVariant::*;
+        Some(match &self.variant {
+            MultipleVersions => "remove one of the versions".to_owned(),
+            DuplicateMigration(_) => "remove one of the migrations".to_owned(),
+            MigrationFromCurrent { version, .. } => format!(
+                "a migration brings the database from one version to the next, \
+                 you can only migrate from versions below {version}"
+            ),
+            AsyncMigration | AsyncVersion => "migrate the database using a blocking store \
+                before opening it with an async one"
+                .to_owned(),
+        })
+    }
+}
+
+impl ErrorVariant {
+    fn with_span(self, item: impl GetSpan) -> Error {
+        Error {
+            variant: self,
+            span: item.span(),
+        }
+    }
+}
+
+/// The version of the struct and the migrations to reach it
+#[derive(Debug)]
+pub struct Schema {
+    pub version: u32,
+    pub migrations: Vec<Migration>,
+}
+
+impl Schema {
+    pub fn try_from(options: Vec<Options>) -> Result<Self, Error> {
+        use ErrorVariant::*;
+
+        let mut version = None;
+        let mut version_span = None;
+        let mut migrations: Vec<Migration> = Vec::new();
+        // migrations are blocking closures, they can not use an async store.
+        // Without migrations a newer version could never be opened.
+        let async_store = options.iter().any(|o| matches!(o, Options::Async))
+            && options.iter().any(|o| {
+                matches!(o, Options::Backend(b) if matches!(b.backend, BackendOptionVariant::Trait))
+            });
+        for option in options {
+            match option {
+                Options::Version { span, .. } if version.is_some() => {
+                    return Err(MultipleVersions.with_span(span))
+                }
+                Options::Version { version: v, span } => {
+                    version = Some(v);
+                    version_span = Some(span);
+                }
+                Options::Migration(migration) => {
+                    if migrations.iter().any(|m| m.from == migration.from) {
+                        return Err(DuplicateMigration(migration.from).with_span(migration.span));
+                    }
+                    migrations.push(migration);
+                }
+                _ => (),
+            }
+        }
+
+        if let (true, Some(migration)) = (async_store, migrations.first()) {
+            return Err(AsyncMigration.with_span(migration.span));
+        }
+        if let (true, Some(span)) = (async_store, version_span) {
+            return Err(AsyncVersion.with_span(span));
+        }
+
+        let version = version.unwrap_or(0);
+        if let Some(migration) = migrations.iter().find(|m| m.from >= version) {
+            return Err(MigrationFromCurrent {
+                from: migration.from,
+                version,
+            }
+            .with_span(migration.span));
+        }
+
+        Ok(Self {
+            version,
+            migrations,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::attribute;
+    use std::str::FromStr;
+
+    fn schema(attr: &str) -> Result<Schema, Error> {
+        let attr = proc_macro2::TokenStream::from_str(attr).unwrap();
+        Schema::try_from(attribute::parse(attr).unwrap())
+    }
+
+    #[test]
+    fn defaults_to_version_zero() {
+        let schema = schema("db=sled").unwrap();
+        assert_eq!(schema.version, 0);
+        assert!(schema.migrations.is_empty());
+    }
+
+    #[test]
+    fn version_and_migrations() {
+        let schema = schema("db=sled, version=2, migrate(from=0, |_| Ok(())), migrate(from=1, up)")
+            .unwrap();
+        assert_eq!(schema.version, 2);
+        assert_eq!(schema.migrations.len(), 2);
+    }
+
+    #[test]
+    fn reject_migration_on_async_store() {
+        let err = schema("db=trait, async, version=1, migrate(from=0, up)").unwrap_err();
+        assert!(matches!(err.variant, ErrorVariant::AsyncMigration));
+        assert!(schema("db=sled, async, version=1, migrate(from=0, up)").is_ok());
+    }
+
+    #[test]
+    fn reject_version_on_async_store() {
+        let err = schema("db=trait, async, version=1").unwrap_err();
+        assert!(matches!(err.variant, ErrorVariant::AsyncVersion));
+        assert!(schema("db=sled, async, version=1").is_ok());
+    }
+
+    #[test]
+    fn reject_migration_from_current() {
+        let err = schema("db=sled, version=1, migrate(from=1, up)").unwrap_err();
+        assert!(matches!(
+            err.variant,
+            ErrorVariant::MigrationFromCurrent {
+                from: 1,
+                version: 1
+            }
+        ));
+    }
+}
@@ -68,8 +68,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     assert_eq!(&"What is the Universe".to_owned(), second);
 
     let computers = db.computers().get().unwrap();
-    let earth = computers.get(&"Earth".to_owned());
-    assert_eq!(earth.unwrap().secret, true);
+    let earth = computers.get("Earth");
+    assert!(earth.unwrap().secret);
 
     Ok(())
 }
@@ -0,0 +1,274 @@
+//! The format values are stored in.
+//!
+//! Every [`ByteStore`] picks the [`Codec`] used for its values, the stores in
+//! this crate use [`Bincode`]. Pick another codec for a whole struct using
+//! `#[dbstruct(db=sled, codec=postcard)]` or for a single field using
+//! `#[dbstruct(codec=json)]`. The available codecs are:
+//!
+//!| Codec           | option            | feature    |
+//!|-----------------|-------------------|------------|
+//!| [`Bincode`]     | `codec=bincode`   |            |
+//!| [`Postcard`]    | `codec=postcard`  | `postcard` |
+//!| [`MessagePack`] | `codec=msgpack`   | `msgpack`  |
+//!| [`Json`]        | `codec=json`      | `json`     |
+//!
+//! Keys do not use the codec, they are encoded such that the database keeps
+//! them in order, see [`key`].
+//!
+//! Note that migrations get the database using its own codec. Wrap it in
+//! [`WithCodec`] to access fields that use another codec.
+//!
+//!```rust
+//!#[dbstruct::dbstruct(db=btreemap, codec=bincode)]
+//!pub struct Test {
+//!    #[dbstruct(Default)]
+//!    the_awnser: u8,
+//!}
+//!```
+
+use core::marker::PhantomData;
+use core::ops::Bound;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::traits::byte_store::{self, ByteStore, KeyValue};
+use crate::traits::{CasOutcome, Flushable};
+
+pub mod key;
+
+/// The error returned by a [`Codec`]
+pub type Error = Box<dyn std::error::Error + Send + Sync>;
+
+/// Turns values into bytes and back
+pub trait Codec {
+    fn serialize<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, Error>;
+    fn deserialize<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, Error>;
+}
+
+/// [bincode](https://crates.io/crates/bincode), compact and fast. Used by
+/// every store in this crate.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Bincode;
+
+impl Codec for Bincode {
+    fn serialize<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, Error> {
+        Ok(bincode::serialize(value)?)
+    }
+
+    fn deserialize<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, Error> {
+        Ok(bincode::deserialize(bytes)?)
+    }
+}
+
+/// [postcard](https://crates.io/crates/postcard), compact and has a stable
+/// specification.
+#[cfg(feature = "postcard")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Postcard;
+
+#[cfg(feature = "postcard")]
+impl Codec for Postcard {
+    fn serialize<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, Error> {
+        Ok(postcard::to_allocvec(value)?)
+    }
+
+    fn deserialize<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, Error> {
+        Ok(postcard::from_bytes(bytes)?)
+    }
+}
+
+/// [MessagePack](https://msgpack.org), structs are stored as maps so fields
+/// can be added without breaking existing data.
+#[cfg(feature = "msgpack")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MessagePack;
+
+#[cfg(feature = "msgpack")]
+impl Codec for MessagePack {
+    fn serialize<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, Error> {
+        Ok(rmp_serde::to_vec_named(value)?)
+    }
+
+    fn deserialize<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, Error> {
+        Ok(rmp_serde::from_slice(bytes)?)
+    }
+}
+
+/// [JSON](https://www.json.org), readable by nearly every other tool.
+#[cfg(feature = "json")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Json;
+
+#[cfg(feature = "json")]
+impl Codec for Json {
+    fn serialize<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, Error> {
+        Ok(serde_json::to_vec(value)?)
+    }
+
+    fn deserialize<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, Error> {
+        Ok(serde_json::from_slice(bytes)?)
+    }
+}
+
+pub(crate) fn serialize_key<K: Serialize + ?Sized>(key: &K) -> Result<Vec<u8>, Error> {
+    Ok(key::to_vec(key)?)
+}
+
+pub(crate) fn deserialize_key<K: DeserializeOwned>(bytes: &[u8]) -> Result<K, Error> {
+    Ok(key::from_slice(bytes)?)
+}
+
+/// Stores values in `BS` using codec `C` instead of the codec of `BS`
+#[derive(Debug, Clone)]
+pub struct WithCodec<BS, C> {
+    store: BS,
+    codec: PhantomData<C>,
+}
+
+impl<BS, C> WithCodec<BS, C> {
+    pub fn new(store: BS) -> Self {
+        Self {
+            store,
+            codec: PhantomData,
+        }
+    }
+
+    pub fn into_inner(self) -> BS {
+        self.store
+    }
+}
+
+impl<BS: ByteStore, C: Codec> ByteStore for WithCodec<BS, C> {
+    type Error = BS::Error;
+    type Bytes = BS::Bytes;
+    type Codec = C;
+
+    fn get(&self, key: &[u8]) -> Result<Option<Self::Bytes>, Self::Error> {
+        self.store.get(key)
+    }
+
+    fn remove(&self, key: &[u8]) -> Result<Option<Self::Bytes>, Self::Error> {
+        self.store.remove(key)
+    }
+
+    fn insert(&self, key: &[u8], val: &[u8]) -> Result<Option<Self::Bytes>, Self::Error> {
+        self.store.insert(key, val)
+    }
+
+    fn write_batch(&self, batch: &[(&[u8], Option<&[u8]>)]) -> Result<(), Self::Error> {
+        self.store.write_batch(batch)
+    }
+}
+
+impl<BS: byte_store::Atomic, C: Codec> byte_store::Atomic for WithCodec<BS, C> {
+    fn atomic_update(
+        &self,
+        key: &[u8],
+        op: impl FnMut(Option<&[u8]>) -> Option<Vec<u8>>,
+    ) -> Result<(), Self::Error> {
+        self.store.atomic_update(key, op)
+    }
+
+    fn counter_add(&self, key: &[u8], delta: &[u8]) -> Result<(), Self::Error> {
+        self.store.counter_add(key, delta)
+    }
+
+    fn conditional_update(
+        &self,
+        key: &[u8],
+        new: Option<&[u8]>,
+        expected: Option<&[u8]>,
+    ) -> Result<CasOutcome<Self::Bytes>, Self::Error> {
+        self.store.conditional_update(key, new, expected)
+    }
+}
+
+impl<BS: byte_store::Watch, C: Codec> byte_store::Watch for WithCodec<BS, C> {
+    type Subscriber = BS::Subscriber;
+
+    fn watch_prefix(&self, prefix: &[u8]) -> Result<Self::Subscriber, Self::Error> {
+        self.store.watch_prefix(prefix)
+    }
+}
+
+impl<BS: Flushable, C> Flushable for WithCodec<BS, C> {
+    type Error = BS::Error;
+
+    fn flush(&self) -> Result<(), Self::Error> {
+        self.store.flush()
+    }
+}
+
+impl<BS: byte_store::Ordered, C: Codec> byte_store::Ordered for WithCodec<BS, C> {
+    type Range<'a>
+        = BS::Range<'a>
+    where
+        Self: 'a;
+
+    fn get_lt(&self, key: &[u8]) -> Result<Option<KeyValue<Self>>, Self::Error> {
+        self.store.get_lt(key)
+    }
+
+    fn get_gt(&self, key: &[u8]) -> Result<Option<KeyValue<Self>>, Self::Error> {
+        self.store.get_gt(key)
+    }
+
+    fn range(&self, start: Bound<&[u8]>, end: Bound<&[u8]>) -> Self::Range<'_> {
+        self.store.range(start, end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stores;
+    use crate::DataStore;
+
+    #[derive(Debug, PartialEq, Serialize, serde::Deserialize)]
+    struct Point {
+        x: i32,
+        label: String,
+    }
+
+    fn round_trip<C: Codec>() {
+        let point = Point {
+            x: -3,
+            label: "origin".to_owned(),
+        };
+        let bytes = C::serialize(&point).unwrap();
+        assert_eq!(C::deserialize::<Point>(&bytes).unwrap(), point);
+    }
+
+    #[test]
+    fn codecs_round_trip() {
+        round_trip::<Bincode>();
+        #[cfg(feature = "postcard")]
+        round_trip::<Postcard>();
+        #[cfg(feature = "msgpack")]
+        round_trip::<MessagePack>();
+        #[cfg(feature = "json")]
+        round_trip::<Json>();
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn values_use_the_codec() {
+        let ds = stores::BTreeMap::new();
+        let json: WithCodec<_, Json> = WithCodec::new(ds.clone());
+        DataStore::insert(&json, &1u8, &vec![1u8, 2]).unwrap();
+
+        let key = serialize_key(&1u8).unwrap();
+        let stored = ByteStore::get(&ds, &key).unwrap().unwrap();
+        assert_eq!(stored, b"[1,2]");
+        assert_eq!(DataStore::get(&json, &1u8).unwrap(), Some(vec![1u8, 2]));
+    }
+
+    #[test]
+    fn wrong_type_is_an_error() {
+        let ds = stores::BTreeMap::new();
+        DataStore::insert(&ds, &1u8, &u64::MAX).unwrap();
+        let res: Result<Option<String>, _> = DataStore::get(&ds, &1u8);
+        assert!(matches!(res, Err(crate::Error::DeSerializingVal(_))));
+    }
+}
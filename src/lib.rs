@@ -1,4 +1,4 @@
-//! Create a typed embedded database by defining a struct. Interact with the database through getters and setters. Choose how values missing in the database are represented. Standard library types `Vec`, `HashMap` and `Option` have special getters and setters to mimic their standard library functionality. You can push and pop from vecs.
+//! Create a typed embedded database by defining a struct. Interact with the database through getters and setters. Choose how values missing in the database are represented. Standard library types `Vec`, `VecDeque`, `HashMap`, `BTreeMap`, `HashSet`, `BTreeSet` and `Option` have special getters and setters to mimic their standard library functionality. You can push and pop from vecs and from both ends of a deque.
 //!
 //! Choose out of various popular key-value databases then instantiate the struct providing only the db path. Alternatively pass any object that implements `dbstruct::DataStore`.
 //!
@@ -24,15 +24,15 @@
 //!    let db = Test::new(&Path::new("the_db2")).unwrap();
 //!
 //!    db.the_awnser().set(&42).unwrap();
-//!	   assert_eq!(42u8, db.the_awnser().get().unwrap());
+//!    assert_eq!(42u8, db.the_awnser().get().unwrap());
 //!
 //!    db.primes().push(&2).unwrap();
 //!    db.primes().push(&3).unwrap();
 //!    db.primes().push(&5).unwrap();
-//!	   db.primes().push(&7).unwrap();
-//!	   assert_eq!(Some(7), db.primes().pop().unwrap());
+//!    db.primes().push(&7).unwrap();
+//!    assert_eq!(Some(7), db.primes().pop().unwrap());
 //!
-//!	   assert_eq!(String::from("42"), db.the_result().get().unwrap());
+//!    assert_eq!(String::from("42"), db.the_result().get().unwrap());
 //!}
 //!```
 //!
@@ -52,6 +52,29 @@
 //!| Name                                    | advantage | attribute option |
 //!|-----------------------------------------|-----------|------------------|
 //!| [Sled](https://crates.io/crates/sled)   | pure Rust | `db=sled`        |
+//!| [RocksDB](https://crates.io/crates/rocksdb) | battle tested, needs the `rocksdb` feature | `db=rocksdb` |
+//!
+//! With `db=sled` a `<Name>Builder` is generated next to `new` to configure sled, for example its
+//! cache size, compression or the tree the fields are stored in. Use `from_db` to store multiple
+//! structs in one sled database, each in a tree of its own.
+//!
+//!```rust
+//!#[dbstruct::dbstruct(db=sled)]
+//!pub struct Settings {
+//!    #[dbstruct(Default)]
+//!    volume: u8,
+//!}
+//!
+//!let db = Settings::builder()
+//!    .temporary(true)
+//!    .cache_capacity(64 * 1024 * 1024)
+//!    .tree_name("settings")
+//!    .open()
+//!    .unwrap();
+//!
+//!let sled = dbstruct::sled::Config::new().temporary(true).open().unwrap();
+//!let shared = SettingsBuilder::from_db(&sled).tree_name("settings").open().unwrap();
+//!```
 //!
 //! ## How it works
 //! dbstruct replaces the *fields* in your struct *with methods*. Each method returns a [`wrapper`]
@@ -65,6 +88,41 @@
 //! [`Option`]. Then dbstruct will return None if the value is missing. Alternatively you can instruct dbstruct
 //! to use the types [`Default`] implementation or set an expression to generate a default value.
 //!
+//! Updating a missing value starts from its default, `update` on an [`Option`] gets and returns
+//! an [`Option`]. Use `fetch_update` or `update_and_fetch` to get the value before or after the
+//! update.
+//!
+//!```rust
+//!#[dbstruct::dbstruct(db=btreemap)]
+//!pub struct Stats {
+//!    #[dbstruct(Default = "10")]
+//!    credits: u32,
+//!    last_seen: Option<u64>,
+//!}
+//!
+//!let db = Stats::new().unwrap();
+//!assert_eq!(db.credits().update_and_fetch(|c| c - 1).unwrap(), 9);
+//!let before = db.last_seen().fetch_update(|_| Some(42)).unwrap();
+//!assert_eq!(before, None);
+//!```
+//!
+//! To go back to the default remove the value with `clear` or `take`, this writes no default
+//! into the database. `is_set` tells if a value is stored.
+//!
+//!```rust
+//!#[dbstruct::dbstruct(db=btreemap)]
+//!pub struct Stats {
+//!    #[dbstruct(Default = "10")]
+//!    credits: u32,
+//!}
+//!
+//!let db = Stats::new().unwrap();
+//!assert_eq!(db.credits().replace(&3).unwrap(), 10);
+//!db.credits().clear().unwrap();
+//!assert!(!db.credits().is_set().unwrap());
+//!assert_eq!(db.credits().get().unwrap(), 10);
+//!```
+//!
 //! ##### Special wrapper
 //! Some fields get methods that return special wrapper. These wrappers mimic the fields type and
 //! handle missing values on their own. Struct fields with type Vec are transformed into methods that
@@ -72,34 +130,163 @@
 //! how to handle missing values (see above)
 //!
 //! See [`wrapper`] for a complete list.
+//!
+//! ##### Counters
+//! Mark an integer field with `#[dbstruct(Counter)]` to get a [`wrapper::Counter`]. On sled and
+//! RocksDB it adds to the value using a merge operator, without reading it, and falls back to an
+//! atomic update on other stores. A stored value that is not as wide as the field's integer type
+//! is left unchanged by adding, reading it fails. Counters can not be used with `async`.
+//!
+//!```rust
+//!#[dbstruct::dbstruct(db=btreemap)]
+//!pub struct Stats {
+//!    #[dbstruct(Counter)]
+//!    visits: u64,
+//!}
+//!
+//!let db = Stats::new().unwrap();
+//!db.visits().increment().unwrap();
+//!db.visits().add(2).unwrap();
+//!assert_eq!(db.visits().get().unwrap(), 3);
+//!```
+//!
+//! ##### Keys
+//! Every field is stored under its own prefix. By default fields get their prefix in
+//! alphabetical order, adding a field can therefore move the data of other fields. Pin the
+//! prefix of a field using `#[dbstruct(key = <N>)]`, `N` can be any number between 0 and 254.
+//! Fields without a pinned key get the lowest free key. Set the struct option
+//! `explicit_keys` (`#[dbstruct(db=sled, explicit_keys)]`) to require a pinned key on every
+//! field.
+//!
+//! A key takes a single byte, limiting a struct to 255 fields. The struct option `wide_keys`
+//! stores keys in two bytes, keys can then go up to 65534. Switching an existing database
+//! between the two moves every field.
+//!
+//!```rust
+//!#[dbstruct::dbstruct(db=btreemap, explicit_keys)]
+//!pub struct Test {
+//!    #[dbstruct(Default, key = 1)]
+//!    the_awnser: u8,
+//!    #[dbstruct(key = 0)]
+//!    the_question: Option<String>,
+//!}
+//!```
+//!
+//! ##### Nested structs
+//! A field can be another dbstruct struct using the same `db`. Mark it with
+//! `#[dbstruct(nested)]`, its accessor returns a reference to the nested struct. The fields
+//! of the nested struct are stored under the prefix of the field. Nested structs can not be
+//! used with `async` and are not available inside a transaction.
+//!
+//!```rust
+//!#[dbstruct::dbstruct(db=btreemap)]
+//!pub struct Settings {
+//!    #[dbstruct(Default)]
+//!    volume: u8,
+//!}
+//!
+//!#[dbstruct::dbstruct(db=btreemap)]
+//!pub struct Player {
+//!    song: Option<String>,
+//!    #[dbstruct(nested)]
+//!    settings: Settings,
+//!}
+//!
+//!let db = Player::new().unwrap();
+//!db.settings().volume().set(&3).unwrap();
+//!assert_eq!(db.settings().volume().get().unwrap(), 3);
+//!```
+//!
+//! ##### Watching fields
+//! Every wrapper except [`wrapper::Counter`] has a `watch` method returning a
+//! [`Watcher`](wrapper::Watcher) of the changes made to the field from then on, for example
+//! `Pushed` and `Popped` for a `Vec`. With `db=trait` it needs a store implementing
+//! [`traits::data_store::Watch`]. Sled notifies watchers itself, the other backends only see
+//! changes made in the same process (see [`stores::Watched`]).
+//!
+//!```rust
+//!use dbstruct::wrapper::ValueEvent;
+//!
+//!#[dbstruct::dbstruct(db=btreemap)]
+//!pub struct Test {
+//!    #[dbstruct(Default)]
+//!    volume: u8,
+//!}
+//!
+//!let db = Test::new().unwrap();
+//!let mut changes = db.volume().watch().unwrap();
+//!db.volume().set(&7).unwrap();
+//!assert_eq!(changes.next().unwrap().unwrap(), ValueEvent::Set(7));
+//!```
+//!
+//! ##### Codec
+//! Values are stored using [bincode](https://crates.io/crates/bincode). Set `codec=<name>` on
+//! the struct or on a field to use postcard, MessagePack or JSON instead. See [`codec`] for
+//! the available codecs and the features that enable them.
+//!
+//! ##### Flushing
+//! The generated `flush` method returns once every change made so far is on disk, with
+//! `async` it is called `flush_async`. With `db=trait` it needs a store implementing
+//! [`traits::Flushable`]. To flush automatically set `flush = "every_write"` or
+//! `flush = "interval_ms=<N>"` on the struct or on a field, a field can opt out with
+//! `flush = "never"`. An interval flushes in the background, a write is on disk at most `N`
+//! milliseconds after it was made, see [`stores::FlushPolicy::interval`].
+//!
+//!```rust
+//!#[dbstruct::dbstruct(db=btreemap, flush = "interval_ms=500")]
+//!pub struct Test {
+//!    #[dbstruct(Default, flush = "every_write")]
+//!    balance: u64,
+//!    owner: Option<String>,
+//!}
+//!
+//!let db = Test::new().unwrap();
+//!db.balance().set(&10).unwrap();
+//!db.flush().unwrap();
+//!```
+//!
+//! ##### Async
+//! With the `async` feature enabled the struct option `async` generates an async API: `new`
+//! and every wrapper method become async. See `wrapper::asynchronous` for the wrappers and
+//! how blocking databases are used.
 
 use core::fmt;
 
 #[doc(hidden)]
 pub use dbstruct_derive::*;
 
+pub mod codec;
+pub mod schema;
 pub mod stores;
 pub mod traits;
 pub use traits::{ByteStore, DataStore};
-pub use traits::{TryExtend};
+#[cfg(feature = "async")]
+pub use traits::AsyncDataStore;
+pub use traits::{CasOutcome, TransactionError, Transactional, TryExtend};
 pub mod wrapper;
 
 pub use sled;
+#[cfg(feature = "rocksdb")]
+pub use rocksdb;
 
 /// An Error type encapulating various issues that may come up during database operation or
 /// (de)serializing
 #[derive(Debug, thiserror::Error)]
 pub enum Error<DbError: fmt::Debug> {
-    #[error("value could not be deserialized using bincode")]
-    DeSerializingVal(bincode::Error),
-    #[error("key could not be deserialized using bincode")]
-    DeSerializingKey(bincode::Error),
-    #[error("value could not be serialized using bincode")]
-    SerializingValue(bincode::Error),
-    #[error("could not serialize key using bincode")]
-    SerializingKey(bincode::Error),
+    #[error("value could not be deserialized")]
+    DeSerializingVal(codec::Error),
+    #[error("key could not be deserialized")]
+    DeSerializingKey(codec::Error),
+    #[error("value could not be serialized")]
+    SerializingValue(codec::Error),
+    #[error("could not serialize key")]
+    SerializingKey(codec::Error),
     #[error("the database returned an error")]
     Database(#[from] DbError),
+    #[error("the database schema does not match the struct: {0}")]
+    Schema(schema::Error),
+    #[error("index {index} is out of range for a vec of length {len}")]
+    IndexOutOfRange { index: usize, len: usize },
 }
 
 #[doc = include_str!("../Readme.md")]
@@ -0,0 +1,352 @@
+//! Records the layout a database was written with and runs migrations when
+//! that layout changes.
+//!
+//! The generated `new` method stores the schema version together with a
+//! fingerprint of each field's type under a reserved key. On the next open
+//! the stored schema is compared against the struct:
+//!  - if the stored version is older the migrations registered using
+//!    `#[dbstruct(migrate(from = <version>, <closure>))]` run in order,
+//!  - if it is the same but a field's type changed opening fails,
+//!  - if the stored version is newer opening fails.
+//!
+//! The fingerprint is made from the name of the type, `std::string::String`
+//! and `String` are the same but a type alias is not resolved. Changing the
+//! type an alias names is not detected.
+//!
+//! Structs with `db=trait, async` can not set a version or migrations.
+//!
+//! Databases written by dbstruct 0.4 and earlier have no schema and stored
+//! the keys of vecs and maps in another encoding. With `db=sled` those keys
+//! are rewritten when the database is opened. A store passed in with
+//! `db=trait` can not be told apart from a new database, call
+//! [`rewrite_legacy_keys`] on it before opening.
+//!
+//! A migration gets the database and can use the
+//! [`DataStore`](crate::DataStore) methods to rewrite the fields. A field
+//! with key `N` is stored under key `N` (as `u8`, or as `u16` with the
+//! `wide_keys` struct option). Note that
+//! [`DataStore::insert`](crate::DataStore::insert) returns the previous value
+//! deserialized as the new type, remove a value before changing its type.
+//! Turning `wide_keys` on or off moves every field to another key, it needs
+//! a new version too.
+//!
+//!```rust
+//!#[dbstruct::dbstruct(db=btreemap, version=1, migrate(from=0, |ds| {
+//!    use dbstruct::DataStore;
+//!    // the_awnser used to be an `u8`, take it out before storing
+//!    // it as an `u64`
+//!    if let Some(old) = ds.remove::<u8, u8>(&0)? {
+//!        ds.insert::<u8, u64>(&0, &(old as u64))?;
+//!    }
+//!    Ok(())
+//!}))]
+//!pub struct Test {
+//!    #[dbstruct(Default, key = 0)]
+//!    the_awnser: u64,
+//!}
+//!
+//!let db = Test::new().unwrap();
+//!assert_eq!(db.the_awnser().get().unwrap(), 0);
+//!```
+
+use core::fmt;
+
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info};
+
+use crate::traits::DataStore;
+
+mod legacy;
+
+pub use legacy::{rewrite_legacy_keys, LegacyField};
+
+/// The key under which the schema is stored, no field can use it
+pub const KEY: u8 = u8::MAX;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("database was written by a newer version ({stored}) of the struct, current version is {current}")]
+    NewerVersion { stored: u32, current: u32 },
+    #[error("no migration from version {from} to the next version")]
+    MissingMigration { from: u32 },
+    #[error("the type of field `{field}` changed without increasing the version")]
+    FieldChanged { field: &'static str },
+    #[error("the `wide_keys` option changed without increasing the version, the fields are stored under other keys")]
+    KeyWidthChanged,
+}
+
+/// The layout of a struct, generated by `#[dbstruct]`. `K` is the type of
+/// the field keys, `u16` for structs using `wide_keys`.
+#[doc(hidden)]
+pub struct Schema<K: 'static = u8> {
+    pub version: u32,
+    /// the name, key and type fingerprint of each field
+    pub fields: &'static [(&'static str, K, u64)],
+}
+
+/// Stored the same way for both key widths, a struct that switches to or
+/// from `wide_keys` can still read it.
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct Stored {
+    version: u32,
+    wide_keys: bool,
+    /// key and type fingerprint of each field
+    fields: std::vec::Vec<(u16, u64)>,
+}
+
+/// The type of the field keys
+#[doc(hidden)]
+pub trait Key: Copy + Into<u16> {
+    const WIDE: bool;
+}
+impl Key for u8 {
+    const WIDE: bool = false;
+}
+impl Key for u16 {
+    const WIDE: bool = true;
+}
+
+impl<K: Key> Schema<K> {
+    fn to_stored(&self) -> Stored {
+        Stored {
+            version: self.version,
+            wide_keys: K::WIDE,
+            fields: self
+                .fields
+                .iter()
+                .map(|(_, key, fp)| ((*key).into(), *fp))
+                .collect(),
+        }
+    }
+
+    fn changed_field(&self, stored: &Stored) -> Option<&'static str> {
+        self.fields
+            .iter()
+            .find(|(_, key, fp)| {
+                stored
+                    .fields
+                    .iter()
+                    .any(|(stored_key, stored_fp)| *stored_key == (*key).into() && stored_fp != fp)
+            })
+            .map(|(name, ..)| *name)
+    }
+}
+
+/// Brings the database from version `from` to version `from + 1`
+#[doc(hidden)]
+pub struct Migration<'a, DS: DataStore> {
+    from: u32,
+    #[allow(clippy::type_complexity)]
+    op: Box<dyn Fn(&DS) -> Result<(), DS::Error> + 'a>,
+}
+
+#[doc(hidden)]
+pub fn migration<'a, DS: DataStore>(
+    from: u32,
+    op: impl Fn(&DS) -> Result<(), DS::Error> + 'a,
+) -> Migration<'a, DS> {
+    Migration {
+        from,
+        op: Box::new(op),
+    }
+}
+
+/// The versions that need to be migrated from, in order
+fn needs_migration<K: Key>(
+    stored: &Stored,
+    schema: &Schema<K>,
+) -> Result<core::ops::Range<u32>, Error> {
+    let current = schema.version;
+    if stored.version > current {
+        return Err(Error::NewerVersion {
+            stored: stored.version,
+            current,
+        });
+    }
+
+    if stored.version == current {
+        if stored.wide_keys != K::WIDE {
+            return Err(Error::KeyWidthChanged);
+        }
+        if let Some(field) = schema.changed_field(stored) {
+            return Err(Error::FieldChanged { field });
+        }
+    }
+    Ok(stored.version..current)
+}
+
+/// Compares the schema in the database with `schema`, runs the needed
+/// migrations and stores the new schema.
+#[doc(hidden)]
+pub fn check<DS, E, K>(
+    ds: &DS,
+    schema: &Schema<K>,
+    mut migrations: std::vec::Vec<Migration<'_, DS>>,
+) -> Result<(), crate::Error<E>>
+where
+    K: Key,
+    DS: DataStore,
+    E: fmt::Debug,
+    crate::Error<E>: From<DS::Error>,
+{
+    let current = schema.to_stored();
+    let Some(stored) = ds.get::<_, Stored>(&KEY)? else {
+        debug!("no schema in database, storing version {}", current.version);
+        ds.insert(&KEY, &current)?;
+        return Ok(());
+    };
+
+    let to_migrate = needs_migration(&stored, schema).map_err(crate::Error::Schema)?;
+    migrations.sort_by_key(|m| m.from);
+    for from in to_migrate {
+        let Some(migration) = migrations.iter().find(|m| m.from == from) else {
+            return Err(crate::Error::Schema(Error::MissingMigration { from }));
+        };
+        info!("migrating database from version {from} to {}", from + 1);
+        (migration.op)(ds)?;
+    }
+
+    if stored != current {
+        ds.insert(&KEY, &current)?;
+    }
+    Ok(())
+}
+
+/// Async version of [`check`] for async stores. The derive rejects `version`
+/// and `migrate` for those so the stored version is never older.
+#[doc(hidden)]
+#[cfg(feature = "async")]
+pub async fn check_async<DS, E, K>(ds: &DS, schema: &Schema<K>) -> Result<(), crate::Error<E>>
+where
+    K: Key + Send + Sync,
+    DS: crate::traits::AsyncDataStore,
+    E: fmt::Debug,
+    crate::Error<E>: From<DS::Error>,
+{
+    let current = schema.to_stored();
+    let Some(stored) = ds.get::<_, Stored>(&KEY).await? else {
+        debug!("no schema in database, storing version {}", current.version);
+        ds.insert(&KEY, &current).await?;
+        return Ok(());
+    };
+
+    let mut to_migrate = needs_migration(&stored, schema).map_err(crate::Error::Schema)?;
+    if let Some(from) = to_migrate.next() {
+        return Err(crate::Error::Schema(Error::MissingMigration { from }));
+    }
+
+    if stored != current {
+        ds.insert(&KEY, &current).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stores;
+
+    const V0: Schema = Schema {
+        version: 0,
+        fields: &[("a", 0, 1)],
+    };
+    const V0_CHANGED: Schema = Schema {
+        version: 0,
+        fields: &[("a", 0, 2)],
+    };
+    const V1: Schema = Schema {
+        version: 1,
+        fields: &[("a", 0, 2)],
+    };
+
+    #[test]
+    fn type_change_needs_new_version() {
+        let ds = stores::BTreeMap::new();
+        check::<_, stores::BTreeMapError, _>(&ds, &V0, vec![]).unwrap();
+        let err = check::<_, stores::BTreeMapError, _>(&ds, &V0_CHANGED, vec![]).unwrap_err();
+        assert!(matches!(
+            err,
+            crate::Error::Schema(Error::FieldChanged { field: "a" })
+        ));
+    }
+
+    #[test]
+    fn migration_runs_once() {
+        let ds = stores::BTreeMap::new();
+        check::<_, stores::BTreeMapError, _>(&ds, &V0, vec![]).unwrap();
+        let migrate = || {
+            migration(0, |ds: &stores::BTreeMap| {
+                ds.insert(&0u8, &42u64)?;
+                Ok(())
+            })
+        };
+        check::<_, stores::BTreeMapError, _>(&ds, &V1, vec![migrate()]).unwrap();
+        assert_eq!(ds.get::<_, u64>(&0u8).unwrap(), Some(42));
+
+        ds.insert(&0u8, &0u64).unwrap();
+        check::<_, stores::BTreeMapError, _>(&ds, &V1, vec![migrate()]).unwrap();
+        assert_eq!(ds.get::<_, u64>(&0u8).unwrap(), Some(0));
+    }
+
+    #[test]
+    fn missing_migration() {
+        let ds = stores::BTreeMap::new();
+        check::<_, stores::BTreeMapError, _>(&ds, &V0, vec![]).unwrap();
+        let err = check::<_, stores::BTreeMapError, _>(&ds, &V1, vec![]).unwrap_err();
+        assert!(matches!(
+            err,
+            crate::Error::Schema(Error::MissingMigration { from: 0 })
+        ));
+    }
+
+    #[test]
+    fn wide_keys() {
+        const WIDE: Schema<u16> = Schema {
+            version: 0,
+            fields: &[("a", 300, 1)],
+        };
+        const WIDE_CHANGED: Schema<u16> = Schema {
+            version: 0,
+            fields: &[("a", 300, 2)],
+        };
+        let ds = stores::BTreeMap::new();
+        check::<_, stores::BTreeMapError, _>(&ds, &WIDE, vec![]).unwrap();
+        check::<_, stores::BTreeMapError, _>(&ds, &WIDE, vec![]).unwrap();
+        let err = check::<_, stores::BTreeMapError, _>(&ds, &WIDE_CHANGED, vec![]).unwrap_err();
+        assert!(matches!(
+            err,
+            crate::Error::Schema(Error::FieldChanged { field: "a" })
+        ));
+    }
+
+    #[test]
+    fn key_width_change_needs_new_version() {
+        const WIDE: Schema<u16> = Schema {
+            version: 0,
+            fields: &[("a", 0, 1)],
+        };
+        let ds = stores::BTreeMap::new();
+        check::<_, stores::BTreeMapError, _>(&ds, &V0, vec![]).unwrap();
+        let err = check::<_, stores::BTreeMapError, _>(&ds, &WIDE, vec![]).unwrap_err();
+        assert!(matches!(err, crate::Error::Schema(Error::KeyWidthChanged)));
+
+        let ds = stores::BTreeMap::new();
+        check::<_, stores::BTreeMapError, _>(&ds, &WIDE, vec![]).unwrap();
+        let err = check::<_, stores::BTreeMapError, _>(&ds, &V0, vec![]).unwrap_err();
+        assert!(matches!(err, crate::Error::Schema(Error::KeyWidthChanged)));
+    }
+
+    #[test]
+    fn refuse_newer_version() {
+        let ds = stores::BTreeMap::new();
+        check::<_, stores::BTreeMapError, _>(&ds, &V1, vec![]).unwrap();
+        let err = check::<_, stores::BTreeMapError, _>(&ds, &V0, vec![]).unwrap_err();
+        assert!(matches!(
+            err,
+            crate::Error::Schema(Error::NewerVersion {
+                stored: 1,
+                current: 0
+            })
+        ));
+    }
+}
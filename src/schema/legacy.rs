@@ -0,0 +1,203 @@
+//! Rewrites databases written by dbstruct 0.4 and earlier. Those stored keys
+//! using bincode, since then keys are encoded such that they sort like the
+//! keys themselves, see [`codec::key`](crate::codec::key). Values did not
+//! change.
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tracing::info;
+
+use crate::codec::{self, Bincode, Codec};
+use crate::traits::byte_store::{self, ByteStore};
+use crate::wrapper::{Prefix, Prefixed};
+
+use super::{Key, Schema, KEY};
+
+/// A field of which the keys contain an encoded index or map key, the other
+/// fields are stored under just their key.
+pub struct LegacyField {
+    prefix: Prefix,
+    /// turns the bincode encoded part of a key after the prefix into the
+    /// current encoding
+    rewrite: fn(&[u8]) -> Result<Vec<u8>, codec::Error>,
+    /// dbstruct 0.4 did not store the length of a vec
+    is_vec: bool,
+}
+
+impl LegacyField {
+    /// The vec field with `key`, its elements were stored under their index
+    /// as `u64`
+    pub fn vec(key: u8) -> Self {
+        Self {
+            prefix: Prefix::from(key),
+            rewrite: rewrite::<u64>,
+            is_vec: true,
+        }
+    }
+
+    /// The map field with `key`, the keys of the map are of type `K`
+    pub fn map<K: Serialize + DeserializeOwned>(key: u8) -> Self {
+        Self {
+            prefix: Prefix::from(key),
+            rewrite: rewrite::<K>,
+            is_vec: false,
+        }
+    }
+}
+
+fn rewrite<K: Serialize + DeserializeOwned>(old: &[u8]) -> Result<Vec<u8>, codec::Error> {
+    let key: K = Bincode::deserialize(old)?;
+    codec::serialize_key(&key)
+}
+
+/// Rewrites the keys of `fields` if the database was written by dbstruct 0.4
+/// or earlier. The rewritten keys and the length of every vec, one past its
+/// last index, are written together with the schema in one batch that is
+/// atomic if the store supports it. After that the database has a schema, a
+/// database that already has one is never rewritten.
+pub fn rewrite_legacy_keys<BS, K>(
+    ds: &BS,
+    schema: &Schema<K>,
+    fields: &[LegacyField],
+) -> Result<(), crate::Error<BS::Error>>
+where
+    K: Key,
+    BS: byte_store::Ordered,
+{
+    let schema_key = codec::serialize_key(&KEY).map_err(crate::Error::SerializingKey)?;
+    if ByteStore::get(ds, &schema_key)?.is_some() {
+        return Ok(());
+    }
+
+    let mut removed = Vec::new();
+    let mut inserted = Vec::new();
+    for field in fields {
+        let prefix = field.prefix.as_bytes();
+        let mut len = 0;
+        for pair in byte_store::Ordered::scan_prefix(ds, prefix) {
+            let (key, value) = pair?;
+            let key = key.as_ref();
+            let rewritten =
+                (field.rewrite)(&key[prefix.len()..]).map_err(crate::Error::DeSerializingKey)?;
+            if field.is_vec {
+                let index: usize =
+                    codec::deserialize_key(&rewritten).map_err(crate::Error::DeSerializingKey)?;
+                len = len.max(index + 1);
+            }
+            let new_key = [prefix, &rewritten].concat();
+            if new_key != key {
+                removed.push(key.to_vec());
+                inserted.push((new_key, value.as_ref().to_vec()));
+            }
+        }
+        if len > 0 {
+            let key = Prefixed::len(field.prefix.clone());
+            let key = codec::serialize_key(&key).map_err(crate::Error::SerializingKey)?;
+            let len = BS::Codec::serialize(&len).map_err(crate::Error::SerializingValue)?;
+            inserted.push((key, len));
+        }
+    }
+    if inserted.is_empty() {
+        return Ok(());
+    }
+
+    info!(
+        "rewriting {} keys written by dbstruct 0.4 or earlier",
+        removed.len()
+    );
+    let stored =
+        BS::Codec::serialize(&schema.to_stored()).map_err(crate::Error::SerializingValue)?;
+    // removing first, a rewritten key can equal an old key that is not
+    // rewritten yet
+    let mut batch: Vec<(&[u8], Option<&[u8]>)> =
+        removed.iter().map(|key| (key.as_slice(), None)).collect();
+    batch.extend(
+        inserted
+            .iter()
+            .map(|(key, value)| (key.as_slice(), Some(value.as_slice()))),
+    );
+    batch.push((&schema_key, Some(&stored)));
+    ds.write_batch(&batch)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stores;
+    use crate::traits::DataStore;
+
+    const SCHEMA: Schema = Schema {
+        version: 0,
+        fields: &[("queue", 1, 1), ("names", 2, 2)],
+    };
+
+    fn fields() -> [LegacyField; 2] {
+        [LegacyField::vec(1), LegacyField::map::<String>(2)]
+    }
+
+    /// Writes a vec and a map the way dbstruct 0.4 did
+    fn legacy_db() -> stores::BTreeMap {
+        let ds = stores::BTreeMap::new();
+        for index in 0..3u64 {
+            let key = bincode::serialize(&(1u8, index)).unwrap();
+            let value = bincode::serialize(&(index as u32 * 10)).unwrap();
+            ByteStore::insert(&ds, &key, &value).unwrap();
+        }
+        let key = bincode::serialize(&(2u8, "one")).unwrap();
+        let value = bincode::serialize(&1u8).unwrap();
+        ByteStore::insert(&ds, &key, &value).unwrap();
+        ds
+    }
+
+    fn all_pairs(ds: &stores::BTreeMap) -> std::vec::Vec<(std::vec::Vec<u8>, std::vec::Vec<u8>)> {
+        byte_store::Ordered::scan_prefix(ds, &[])
+            .map(Result::unwrap)
+            .collect()
+    }
+
+    #[test]
+    fn keys_are_rewritten() {
+        let ds = legacy_db();
+        rewrite_legacy_keys(&ds, &SCHEMA, &fields()).unwrap();
+
+        let queue = Prefix::from(1);
+        let len = crate::wrapper::stored_len::<u32, _>(&ds, queue.clone()).unwrap();
+        assert_eq!(len, 3);
+        let key = crate::wrapper::Prefixed::new(queue, 2);
+        let element: Option<u32> = DataStore::get(&ds, &key).unwrap();
+        assert_eq!(element, Some(20));
+        let names = crate::wrapper::Map::<String, u8, _>::new(ds.clone(), Prefix::from(2));
+        assert_eq!(names.get(&"one".to_owned()).unwrap(), Some(1));
+
+        // the schema is stored, rewriting again would corrupt the keys
+        super::super::check::<_, stores::BTreeMapError, _>(&ds, &SCHEMA, vec![]).unwrap();
+        let before = all_pairs(&ds);
+        rewrite_legacy_keys(&ds, &SCHEMA, &fields()).unwrap();
+        assert_eq!(before, all_pairs(&ds));
+    }
+
+    #[test]
+    fn vec_with_hole_keeps_its_length() {
+        let ds = legacy_db();
+        // the element at index 1 is missing
+        let key = bincode::serialize(&(1u8, 1u64)).unwrap();
+        ByteStore::remove(&ds, &key).unwrap();
+        rewrite_legacy_keys(&ds, &SCHEMA, &fields()).unwrap();
+
+        let queue = Prefix::from(1);
+        let len: Option<usize> = DataStore::get(&ds, &Prefixed::len(queue.clone())).unwrap();
+        assert_eq!(len, Some(3));
+        let hole: Option<u32> = DataStore::get(&ds, &Prefixed::new(queue.clone(), 1)).unwrap();
+        assert_eq!(hole, None);
+        let last: Option<u32> = DataStore::get(&ds, &Prefixed::new(queue, 2)).unwrap();
+        assert_eq!(last, Some(20));
+    }
+
+    #[test]
+    fn new_database_is_untouched() {
+        let ds = stores::BTreeMap::new();
+        rewrite_legacy_keys(&ds, &SCHEMA, &fields()).unwrap();
+        assert!(all_pairs(&ds).is_empty());
+    }
+}
@@ -7,15 +7,85 @@ mod rocksdb;
 
 mod hashmap;
 mod btreemap;
+mod broadcast;
+mod counter;
+mod flushing;
+mod watched;
+#[cfg(feature = "async")]
+mod blocking;
 
 #[deprecated(
-    since = "0.3",
+    since = "0.3.0",
     note = "Use BTreeMap test backend instead"
 )]
 pub use hashmap::HashMap;
 pub use hashmap::Error as HashMapError;
 pub use btreemap::BTreeMap;
 pub use btreemap::Error as BTreeMapError;
+pub use broadcast::{Broadcast, BroadcastSubscriber};
+pub use counter::counter_merge;
+pub use flushing::{FlushPolicy, Flushing};
+pub use watched::{Watched, WatchedTransaction};
+#[cfg(feature = "sled")]
+pub use self::sled::{SledSubscriber, SledTransaction};
+#[cfg(feature = "rocksdb")]
+pub use self::rocksdb::{options as rocksdb_options, RocksTransaction};
+#[cfg(feature = "async")]
+pub use blocking::Blocking;
+#[cfg(feature = "async")]
+#[doc(hidden)]
+pub use blocking::run as run_blocking;
 // intresting discussion about key value db alternatives to sled: 
 // https://gitlab.com/famedly/conduit/-/issues/74
 // one intresting one is heed (wraps LMDB)
+
+#[cfg(test)]
+mod tests {
+    use crate::traits::byte_store::{Atomic, Ordered, Watch};
+
+    fn atomic<BS: Atomic>() {}
+    fn ordered<BS: Ordered>() {}
+    fn watch<BS: Watch>() {}
+
+    /// Does not compile if `$ty` implements `$trait`, the call is ambiguous
+    /// then as both impls apply
+    macro_rules! not {
+        ($trait:path, $ty:ty) => {{
+            trait AmbiguousIfImpl<A> {
+                fn check() {}
+            }
+            impl<T: ?Sized> AmbiguousIfImpl<()> for T {}
+            struct Implemented;
+            impl<T: ?Sized + $trait> AmbiguousIfImpl<Implemented> for T {}
+            <$ty as AmbiguousIfImpl<_>>::check();
+        }};
+    }
+
+    /// A hand written copy of the table in `Backend::traits` in
+    /// dbstruct-derive, neither is generated. Update both when a store gains
+    /// or loses a trait. A trait missing from the table is checked to not be
+    /// implemented.
+    #[test]
+    #[allow(deprecated)]
+    fn backends_implement_their_traits() {
+        #[cfg(feature = "sled")]
+        {
+            atomic::<sled::Tree>();
+            ordered::<sled::Tree>();
+            watch::<sled::Tree>();
+        }
+        #[cfg(feature = "rocksdb")]
+        {
+            type Rocks = super::Watched<std::sync::Arc<rocksdb::TransactionDB>>;
+            atomic::<Rocks>();
+            ordered::<Rocks>();
+            watch::<Rocks>();
+        }
+        atomic::<super::HashMap>();
+        not!(Ordered, super::HashMap);
+        watch::<super::HashMap>();
+        atomic::<super::BTreeMap>();
+        ordered::<super::BTreeMap>();
+        watch::<super::BTreeMap>();
+    }
+}
@@ -0,0 +1,313 @@
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tracing::{instrument, trace};
+
+use crate::codec::{self, Codec};
+use core::ops::Bound;
+
+use crate::traits::async_data_store::{
+    AsyncAtomic, AsyncDataStore, AsyncFlushable, AsyncOrdered, AsyncWatch,
+};
+use crate::traits::byte_store::{self, serialize_optional, serialize_write};
+use crate::traits::data_store::Write;
+use crate::traits::{ByteStore, CasOutcome, Flushable};
+use crate::Error;
+
+/// Makes a blocking store usable from async code, every call to the store
+/// runs on tokio's blocking thread pool. The struct generated with the
+/// `async` option uses this for all backends except `db=trait`.
+#[derive(Debug, Clone)]
+pub struct Blocking<BS>(BS);
+
+impl<BS> Blocking<BS> {
+    pub fn new(store: BS) -> Self {
+        Self(store)
+    }
+
+    pub fn into_inner(self) -> BS {
+        self.0
+    }
+}
+
+/// Runs `f` on tokio's blocking thread pool, a panic in `f` is passed on.
+#[doc(hidden)]
+pub async fn run<T>(f: impl FnOnce() -> T + Send + 'static) -> T
+where
+    T: Send + 'static,
+{
+    match tokio::task::spawn_blocking(f).await {
+        Ok(value) => value,
+        Err(e) => match e.try_into_panic() {
+            Ok(panic) => std::panic::resume_unwind(panic),
+            Err(e) => panic!("blocking store call did not finish: {e}"),
+        },
+    }
+}
+
+fn deserialize<C, V, B, E>(bytes: Option<B>) -> Result<Option<V>, Error<E>>
+where
+    C: Codec,
+    V: DeserializeOwned,
+    B: AsRef<[u8]>,
+    E: core::fmt::Debug,
+{
+    match bytes {
+        Some(bytes) => {
+            trace!("bytes of value: {:?}", bytes.as_ref());
+            let val = C::deserialize(bytes.as_ref()).map_err(Error::DeSerializingVal)?;
+            Ok(Some(val))
+        }
+        None => Ok(None),
+    }
+}
+
+#[async_trait]
+impl<BS> AsyncDataStore for Blocking<BS>
+where
+    BS: ByteStore + Clone + Send + Sync + 'static,
+    BS::Error: Send + 'static,
+    BS::Bytes: Send + 'static,
+{
+    type Error = Error<BS::Error>;
+
+    #[instrument(skip_all, level = "trace", err)]
+    async fn get<K, V>(&self, key: &K) -> Result<Option<V>, Self::Error>
+    where
+        K: Serialize + Sync,
+        V: DeserializeOwned + Send,
+    {
+        let key = codec::serialize_key(key).map_err(Error::SerializingKey)?;
+        let store = self.0.clone();
+        let val = run(move || ByteStore::get(&store, &key)).await?;
+        deserialize::<BS::Codec, _, _, _>(val)
+    }
+
+    #[instrument(skip_all, level = "trace", err)]
+    async fn remove<K, V>(&self, key: &K) -> Result<Option<V>, Self::Error>
+    where
+        K: Serialize + Sync,
+        V: DeserializeOwned + Send,
+    {
+        let key = codec::serialize_key(key).map_err(Error::SerializingKey)?;
+        let store = self.0.clone();
+        let val = run(move || ByteStore::remove(&store, &key)).await?;
+        deserialize::<BS::Codec, _, _, _>(val)
+    }
+
+    #[instrument(skip_all, level = "trace", err)]
+    async fn insert<K, V>(&self, key: &K, val: &V) -> Result<Option<V>, Self::Error>
+    where
+        K: Serialize + Sync,
+        V: Serialize + DeserializeOwned + Send + Sync,
+    {
+        let key = codec::serialize_key(key).map_err(Error::SerializingKey)?;
+        let val = BS::Codec::serialize(val).map_err(Error::SerializingValue)?;
+        let store = self.0.clone();
+        let existing = run(move || ByteStore::insert(&store, &key, &val)).await?;
+        deserialize::<BS::Codec, _, _, _>(existing)
+    }
+
+    #[instrument(skip_all, level = "trace", err)]
+    async fn write_pair<K1, V1, K2, V2>(
+        &self,
+        first: Write<'_, K1, V1>,
+        second: Write<'_, K2, V2>,
+    ) -> Result<(), Self::Error>
+    where
+        K1: Serialize + Sync,
+        V1: Serialize + DeserializeOwned + Send + Sync,
+        K2: Serialize + Sync,
+        V2: Serialize + DeserializeOwned + Send + Sync,
+    {
+        let first = serialize_write::<BS::Codec, _, _, _>(first)?;
+        let second = serialize_write::<BS::Codec, _, _, _>(second)?;
+        let store = self.0.clone();
+        run(move || {
+            let batch = [
+                (first.0.as_slice(), first.1.as_deref()),
+                (second.0.as_slice(), second.1.as_deref()),
+            ];
+            ByteStore::write_batch(&store, &batch)
+        })
+        .await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<BS> AsyncAtomic for Blocking<BS>
+where
+    BS: byte_store::Atomic + Clone + Send + Sync + 'static,
+    BS::Error: Send + 'static,
+    BS::Bytes: Send + 'static,
+{
+    #[instrument(skip_all, level = "trace", err)]
+    async fn atomic_update<K, V, F>(&self, key: &K, op: F) -> Result<(), Self::Error>
+    where
+        K: Serialize + Sync,
+        V: Serialize + DeserializeOwned + Send,
+        F: FnMut(V) -> V + Clone + Send + 'static,
+    {
+        let key = codec::serialize_key(key).map_err(Error::SerializingKey)?;
+        let store = self.0.clone();
+        run(move || byte_store::atomic_update_bytes(&store, &key, op)).await
+    }
+
+    #[instrument(skip_all, level = "trace", err)]
+    async fn atomic_fetch_update<K, V, F>(
+        &self,
+        key: &K,
+        op: F,
+    ) -> Result<(Option<V>, Option<V>), Self::Error>
+    where
+        K: Serialize + Sync,
+        V: Serialize + DeserializeOwned + Send,
+        F: FnMut(Option<V>) -> Option<V> + Clone + Send + 'static,
+    {
+        let key = codec::serialize_key(key).map_err(Error::SerializingKey)?;
+        let store = self.0.clone();
+        let fetched = run(move || byte_store::fetch_update_bytes(&store, &key, op)).await?;
+        byte_store::deserialize_fetched::<BS::Codec, _, _>(fetched)
+    }
+
+    #[instrument(skip_all, level = "trace", err)]
+    async fn conditional_update<K, V>(
+        &self,
+        key: &K,
+        new: Option<&V>,
+        expected: Option<&V>,
+    ) -> Result<CasOutcome<V>, Self::Error>
+    where
+        K: Serialize + Sync,
+        V: Serialize + DeserializeOwned + Sync,
+    {
+        let key = codec::serialize_key(key).map_err(Error::SerializingKey)?;
+        let new = serialize_optional::<BS::Codec, _>(new).map_err(Error::SerializingValue)?;
+        let expected =
+            serialize_optional::<BS::Codec, _>(expected).map_err(Error::SerializingValue)?;
+        let store = self.0.clone();
+        let outcome = run(move || {
+            byte_store::Atomic::conditional_update(
+                &store,
+                &key,
+                new.as_deref(),
+                expected.as_deref(),
+            )
+        })
+        .await?;
+        outcome
+            .try_map(|current| BS::Codec::deserialize(current.as_ref()))
+            .map_err(Error::DeSerializingVal)
+    }
+}
+
+#[async_trait]
+impl<BS> AsyncOrdered for Blocking<BS>
+where
+    BS: byte_store::Ordered + Clone + Send + Sync + 'static,
+    BS::Error: Send + 'static,
+    BS::Bytes: Send + 'static,
+{
+    type Codec = BS::Codec;
+
+    #[instrument(skip_all, level = "trace", err)]
+    async fn next_in_prefix(
+        &self,
+        prefix: &[u8],
+        after: Option<&[u8]>,
+    ) -> Result<Option<(Vec<u8>, Vec<u8>)>, Self::Error> {
+        let prefix = prefix.to_vec();
+        let after = after.map(<[u8]>::to_vec);
+        let store = self.0.clone();
+        let next = run(move || {
+            let start = match &after {
+                Some(after) => Bound::Excluded(after.as_slice()),
+                None => Bound::Included(prefix.as_slice()),
+            };
+            let end = byte_store::prefix_end(&prefix);
+            let end = match &end {
+                Some(end) => Bound::Excluded(end.as_slice()),
+                None => Bound::Unbounded,
+            };
+            let next = byte_store::Ordered::range(&store, start, end).next();
+            next.transpose().map(|pair| {
+                pair.map(|(key, val)| (key.as_ref().to_vec(), val.as_ref().to_vec()))
+            })
+        })
+        .await?;
+        Ok(next)
+    }
+
+    #[instrument(skip_all, level = "trace", err)]
+    async fn last_key_in_prefix<Prefix, Suffix>(
+        &self,
+        prefix: &Prefix,
+    ) -> Result<Option<Suffix>, Self::Error>
+    where
+        Prefix: Serialize + Sync,
+        Suffix: DeserializeOwned + Send,
+    {
+        let prefix = codec::serialize_key(prefix).map_err(Error::SerializingKey)?;
+        let store = self.0.clone();
+        let suffix = run(move || {
+            let last = byte_store::Ordered::last_in_prefix(&store, &prefix)?;
+            Ok::<_, BS::Error>(last.map(|(key, _)| key.as_ref()[prefix.len()..].to_vec()))
+        })
+        .await?;
+        suffix
+            .map(|suffix| codec::deserialize_key(&suffix))
+            .transpose()
+            .map_err(Error::DeSerializingKey)
+    }
+}
+
+/// Subscribing does not block, the subscriber is used as a future.
+impl<BS> AsyncWatch for Blocking<BS>
+where
+    BS: byte_store::Watch + Clone + Send + Sync + 'static,
+    BS::Error: Send + 'static,
+    BS::Bytes: Send + 'static,
+{
+    type Codec = BS::Codec;
+    type Subscriber = BS::Subscriber;
+
+    #[instrument(skip_all, level = "trace", err)]
+    fn watch_prefix<K: Serialize>(&self, prefix: &K) -> Result<Self::Subscriber, Self::Error> {
+        let prefix = codec::serialize_key(prefix).map_err(Error::SerializingKey)?;
+        trace!("watching prefix: {prefix:?}");
+        Ok(byte_store::Watch::watch_prefix(&self.0, &prefix)?)
+    }
+}
+
+#[async_trait]
+impl<BS> AsyncFlushable for Blocking<BS>
+where
+    BS: Flushable + Clone + Send + Sync + 'static,
+    BS::Error: Send + 'static,
+{
+    type Error = BS::Error;
+
+    #[instrument(skip_all, level = "trace", err(Debug))]
+    async fn flush(&self) -> Result<(), Self::Error> {
+        let store = self.0.clone();
+        run(move || store.flush()).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stores;
+
+    #[tokio::test]
+    async fn runs_store_calls() {
+        let ds = Blocking::new(stores::BTreeMap::new());
+        assert_eq!(ds.insert(&1u8, &2u16).await.unwrap(), None);
+        assert_eq!(ds.get::<_, u16>(&1u8).await.unwrap(), Some(2));
+        ds.write_pair(Write::Insert(&1u8, &3u16), Write::<_, u16>::remove(&2u8))
+            .await
+            .unwrap();
+        assert_eq!(ds.remove::<_, u16>(&1u8).await.unwrap(), Some(3));
+    }
+}
@@ -0,0 +1,194 @@
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+use std::collections::VecDeque;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError, Weak};
+
+use crate::traits::data_store::Change;
+
+/// Sends the changes made to a store to everyone watching, for stores that
+/// can not watch keys themselves. Call [`publish`](Broadcast::publish) for
+/// every write, while holding whatever lock orders the writes, and return
+/// [`subscribe`](Broadcast::subscribe) from
+/// [`byte_store::Watch`](crate::traits::byte_store::Watch).
+///
+/// Only changes made through this process are seen. The subscribers end
+/// once every clone of the `Broadcast` is dropped.
+#[derive(Default, Clone)]
+pub struct Broadcast(Arc<Channels>);
+
+#[derive(Default)]
+struct Channels(Mutex<Vec<Weak<Channel>>>);
+
+struct Channel {
+    prefix: Vec<u8>,
+    state: Mutex<State>,
+    changed: Condvar,
+}
+
+#[derive(Default)]
+struct State {
+    changes: VecDeque<Change>,
+    waker: Option<Waker>,
+    closed: bool,
+}
+
+impl Channel {
+    /// Nothing panics while holding the lock, it can not be poisoned
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn update(&self, f: impl FnOnce(&mut State)) {
+        let mut state = self.lock();
+        f(&mut state);
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+        self.changed.notify_all();
+    }
+}
+
+impl Broadcast {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the changes to keys starting with `prefix` published after
+    /// this call
+    pub fn subscribe(&self, prefix: &[u8]) -> BroadcastSubscriber {
+        let channel = Arc::new(Channel {
+            prefix: prefix.to_vec(),
+            state: Mutex::default(),
+            changed: Condvar::new(),
+        });
+        let mut channels = self.0.lock();
+        channels.retain(|channel| channel.strong_count() > 0);
+        channels.push(Arc::downgrade(&channel));
+        BroadcastSubscriber(channel)
+    }
+
+    /// Whether anyone subscribed to a prefix of `key`, use it to skip
+    /// reading a value only needed to publish it
+    pub fn is_watched(&self, key: &[u8]) -> bool {
+        self.0
+            .lock()
+            .iter()
+            .filter_map(Weak::upgrade)
+            .any(|channel| key.starts_with(&channel.prefix))
+    }
+
+    /// Sends an insert (`Some`) or removal (`None`) of `key` to its
+    /// subscribers
+    pub fn publish(&self, key: &[u8], value: Option<&[u8]>) {
+        let channels = self.0.lock();
+        for channel in channels.iter().filter_map(Weak::upgrade) {
+            if !key.starts_with(&channel.prefix) {
+                continue;
+            }
+            let change = match value {
+                Some(value) => Change::Insert {
+                    key: key.to_vec(),
+                    value: value.to_vec(),
+                },
+                None => Change::Remove { key: key.to_vec() },
+            };
+            channel.update(|state| state.changes.push_back(change));
+        }
+    }
+}
+
+impl Channels {
+    fn lock(&self) -> MutexGuard<'_, Vec<Weak<Channel>>> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Drop for Channels {
+    fn drop(&mut self) {
+        for channel in self.lock().iter().filter_map(Weak::upgrade) {
+            channel.update(|state| state.closed = true);
+        }
+    }
+}
+
+/// The changes to the keys under a prefix, see [`Broadcast::subscribe`]
+pub struct BroadcastSubscriber(Arc<Channel>);
+
+impl Iterator for BroadcastSubscriber {
+    type Item = Change;
+
+    /// Blocks until the next change
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut state = self.0.lock();
+        loop {
+            if let Some(change) = state.changes.pop_front() {
+                return Some(change);
+            }
+            if state.closed {
+                return None;
+            }
+            state = self
+                .0
+                .changed
+                .wait(state)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+    }
+}
+
+impl Future for BroadcastSubscriber {
+    type Output = Option<Change>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.0.lock();
+        if let Some(change) = state.changes.pop_front() {
+            return Poll::Ready(Some(change));
+        }
+        if state.closed {
+            return Poll::Ready(None);
+        }
+        state.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_sends_prefix() {
+        let broadcast = Broadcast::new();
+        let mut subscriber = broadcast.subscribe(&[1]);
+        broadcast.publish(&[2, 0], Some(&[5]));
+        broadcast.publish(&[1, 0], Some(&[6]));
+        broadcast.publish(&[1], None);
+        drop(broadcast);
+
+        let changes: Vec<_> = subscriber.by_ref().collect();
+        assert_eq!(
+            changes,
+            vec![
+                Change::Insert {
+                    key: vec![1, 0],
+                    value: vec![6]
+                },
+                Change::Remove { key: vec![1] },
+            ]
+        );
+    }
+
+    #[test]
+    fn wakes_blocked_subscriber() {
+        let broadcast = Broadcast::new();
+        let mut subscriber = broadcast.subscribe(&[]);
+        let handle = std::thread::spawn(move || subscriber.next());
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        broadcast.publish(&[3], None);
+        assert_eq!(
+            handle.join().unwrap(),
+            Some(Change::Remove { key: vec![3] })
+        );
+    }
+}
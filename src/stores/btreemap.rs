@@ -1,7 +1,10 @@
 use std::collections;
+use std::ops::Bound;
 use std::sync::{Arc, RwLock};
 
-use crate::traits::ByteStore;
+use super::broadcast::{Broadcast, BroadcastSubscriber};
+use crate::traits::byte_store::{self, KeyValue};
+use crate::traits::{self, ByteStore, CasOutcome};
 
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
@@ -15,8 +18,12 @@ pub enum Error {
 /// ### ALL CHANGES ARE LOST WHEN THE OBJECT IS DROPPED
 /// again: use for testing the api only
 ///
+/// Changes can be watched using an in-process [`Broadcast`].
 #[derive(Default, Clone)]
-pub struct BTreeMap(Arc<RwLock<collections::BTreeMap<Vec<u8>, Vec<u8>>>>);
+pub struct BTreeMap {
+    map: Arc<RwLock<collections::BTreeMap<Vec<u8>, Vec<u8>>>>,
+    watchers: Broadcast,
+}
 
 impl BTreeMap {
     pub fn new() -> Self {
@@ -27,28 +34,48 @@ impl BTreeMap {
 impl ByteStore for BTreeMap {
     type Error = Error;
     type Bytes = Vec<u8>;
+    type Codec = crate::codec::Bincode;
 
     fn get(&self, key: &[u8]) -> Result<Option<Self::Bytes>, Self::Error> {
-        let map = self.0.read().map_err(|_| Self::Error::Poisoned)?;
+        let map = self.map.read().map_err(|_| Self::Error::Poisoned)?;
         Ok(map.get(key).cloned())
     }
 
     fn remove(&self, key: &[u8]) -> Result<Option<Self::Bytes>, Self::Error> {
-        let mut map = self.0.write().map_err(|_| Self::Error::Poisoned)?;
-        Ok(map.remove(key))
+        let mut map = self.map.write().map_err(|_| Self::Error::Poisoned)?;
+        let existing = map.remove(key);
+        if existing.is_some() {
+            self.watchers.publish(key, None);
+        }
+        Ok(existing)
     }
 
     fn insert(&self, key: &[u8], val: &[u8]) -> Result<Option<Self::Bytes>, Self::Error> {
-        let mut map = self.0.write().map_err(|_| Self::Error::Poisoned)?;
+        let mut map = self.map.write().map_err(|_| Self::Error::Poisoned)?;
+        self.watchers.publish(key, Some(val));
         Ok(map.insert(key.to_vec(), val.to_vec()))
     }
+
+    fn write_batch(&self, batch: &[(&[u8], Option<&[u8]>)]) -> Result<(), Self::Error> {
+        let mut map = self.map.write().map_err(|_| Self::Error::Poisoned)?;
+        for (key, val) in batch {
+            let existing = match val {
+                Some(val) => map.insert(key.to_vec(), val.to_vec()),
+                None => map.remove(*key),
+            };
+            if val.is_some() || existing.is_some() {
+                self.watchers.publish(key, *val);
+            }
+        }
+        Ok(())
+    }
 }
 
 #[cfg(test)]
 impl BTreeMap {
     pub(crate) fn force_error(&self) {
         // poison the lock such that we get an error on the next use of self
-        let map = self.0.clone();
+        let map = self.map.clone();
         let handle = std::thread::spawn(move || {
             let _lock = map.write().unwrap();
             panic!("panicking here to poinson the lock")
@@ -59,32 +86,153 @@ impl BTreeMap {
     }
 }
 
-impl crate::traits::byte_store::Ordered for BTreeMap {
-    fn get_lt(&self, key: &[u8]) -> Result<Option<(Self::Bytes, Self::Bytes)>, Self::Error> {
-        let map = self.0.write().map_err(|_| Self::Error::Poisoned)?;
-        let zero = vec![0];
-        let range = zero..=key.to_vec();
+/// Iterator over a range of the [`BTreeMap`] store. Takes the lock for every
+/// item, changes made while iterating are seen.
+pub struct Range<'a> {
+    store: &'a BTreeMap,
+    start: Bound<Vec<u8>>,
+    end: Bound<Vec<u8>>,
+    done: bool,
+}
+
+impl Iterator for Range<'_> {
+    type Item = Result<KeyValue<BTreeMap>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done || is_empty(&self.start, &self.end) {
+            return None;
+        }
+        let map = match self.store.map.read() {
+            Ok(map) => map,
+            Err(_) => {
+                self.done = true;
+                return Some(Err(Error::Poisoned));
+            }
+        };
+        let range = (self.start.clone(), self.end.clone());
+        let Some((k, v)) = map.range(range).next() else {
+            self.done = true;
+            return None;
+        };
+        self.start = Bound::Excluded(k.clone());
+        Some(Ok((k.clone(), v.clone())))
+    }
+}
+
+fn owned(bound: Bound<&[u8]>) -> Bound<Vec<u8>> {
+    match bound {
+        Bound::Included(key) => Bound::Included(key.to_vec()),
+        Bound::Excluded(key) => Bound::Excluded(key.to_vec()),
+        Bound::Unbounded => Bound::Unbounded,
+    }
+}
+
+/// `collections::BTreeMap::range` panics on these
+fn is_empty(start: &Bound<Vec<u8>>, end: &Bound<Vec<u8>>) -> bool {
+    use Bound::*;
+    match (start, end) {
+        (Excluded(s), Excluded(e)) => s >= e,
+        (Included(s) | Excluded(s), Included(e) | Excluded(e)) => s > e,
+        _ => false,
+    }
+}
+
+impl byte_store::Ordered for BTreeMap {
+    type Range<'a> = Range<'a>;
+
+    fn get_lt(&self, key: &[u8]) -> Result<Option<KeyValue<Self>>, Self::Error> {
+        use std::ops::Bound::*;
+        let map = self.map.write().map_err(|_| Self::Error::Poisoned)?;
+        let range = (Unbounded, Excluded(key.to_vec()));
         let Some((k,v)) = map.range(range).next_back() else {
             return Ok(None);
         };
         Ok(Some((k.to_vec(), v.to_vec())))
     }
-    fn get_gt(&self, key: &[u8]) -> Result<Option<(Self::Bytes, Self::Bytes)>, Self::Error> {
+    fn get_gt(&self, key: &[u8]) -> Result<Option<KeyValue<Self>>, Self::Error> {
         use std::ops::Bound::*;
-        let map = self.0.write().map_err(|_| Self::Error::Poisoned)?;
+        let map = self.map.write().map_err(|_| Self::Error::Poisoned)?;
         let range = (Excluded(key.to_vec()), Unbounded);
         let Some((k,v)) = map.range(range).next() else {
             return Ok(None);
         };
         Ok(Some((k.to_vec(), v.to_vec())))
     }
+    fn range(&self, start: Bound<&[u8]>, end: Bound<&[u8]>) -> Self::Range<'_> {
+        Range {
+            store: self,
+            start: owned(start),
+            end: owned(end),
+            done: false,
+        }
+    }
+}
+
+/// Holds the write lock for the whole update
+impl byte_store::Atomic for BTreeMap {
+    fn atomic_update(
+        &self,
+        key: &[u8],
+        mut op: impl FnMut(Option<&[u8]>) -> Option<Vec<u8>>,
+    ) -> Result<(), Self::Error> {
+        let mut map = self.map.write().map_err(|_| Self::Error::Poisoned)?;
+        let old = map.get(key).map(Vec::as_slice);
+        let new = op(old);
+        if old != new.as_deref() {
+            self.watchers.publish(key, new.as_deref());
+        }
+        match new {
+            Some(new) => map.insert(key.to_vec(), new),
+            None => map.remove(key),
+        };
+        Ok(())
+    }
+
+    fn conditional_update(
+        &self,
+        key: &[u8],
+        new: Option<&[u8]>,
+        expected: Option<&[u8]>,
+    ) -> Result<CasOutcome<Self::Bytes>, Self::Error> {
+        let mut map = self.map.write().map_err(|_| Self::Error::Poisoned)?;
+        let current = map.get(key);
+        if current.map(Vec::as_slice) != expected {
+            let current = current.cloned();
+            return Ok(CasOutcome::Mismatch { current });
+        }
+        if new != expected {
+            self.watchers.publish(key, new);
+        }
+        match new {
+            Some(new) => map.insert(key.to_vec(), new.to_vec()),
+            None => map.remove(key),
+        };
+        Ok(CasOutcome::Swapped)
+    }
+}
+
+impl byte_store::Watch for BTreeMap {
+    type Subscriber = BroadcastSubscriber;
+
+    fn watch_prefix(&self, prefix: &[u8]) -> Result<Self::Subscriber, Self::Error> {
+        Ok(self.watchers.subscribe(prefix))
+    }
+}
+
+/// Nothing is persisted, flushing does nothing
+impl traits::Flushable for BTreeMap {
+    type Error = Error;
+
+    fn flush(&self) -> Result<(), Self::Error> {
+        Ok(())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::BTreeMap;
     use crate::traits::data_store::Ordered;
-    use crate::traits::DataStore;
+    use crate::traits::{CasOutcome, DataStore};
 
     #[test]
     fn get_then_insert() {
@@ -95,13 +243,43 @@ mod tests {
         assert_eq!(val, 2);
     }
 
+    #[test]
+    fn atomic_update() {
+        use crate::traits::data_store::Atomic;
+        let ds = BTreeMap::new();
+        ds.atomic_update(&1u8, |v: u8| v + 1).unwrap();
+        assert_eq!(ds.get::<u8, u8>(&1).unwrap(), None);
+        ds.insert(&1u8, &2u8).unwrap();
+        ds.atomic_update(&1u8, |v: u8| v + 1).unwrap();
+        let outcome = ds.conditional_update(&1u8, Some(&9u8), Some(&2)).unwrap();
+        assert_eq!(outcome, CasOutcome::Mismatch { current: Some(3) });
+        let outcome = ds.conditional_update(&1u8, Some(&9u8), Some(&3)).unwrap();
+        assert_eq!(outcome, CasOutcome::Swapped);
+        assert_eq!(ds.get::<u8, u8>(&1).unwrap(), Some(9));
+    }
+
+    #[test]
+    fn conditional_update_absent() {
+        use crate::traits::data_store::Atomic;
+        let ds = BTreeMap::new();
+        let outcome = ds.conditional_update(&1u8, None, Some(&2u8)).unwrap();
+        assert_eq!(outcome, CasOutcome::Mismatch { current: None });
+        let outcome = ds.conditional_update(&1u8, Some(&2u8), None).unwrap();
+        assert_eq!(outcome, CasOutcome::Swapped);
+        let outcome = ds.conditional_update(&1u8, Some(&3u8), None).unwrap();
+        assert_eq!(outcome, CasOutcome::Mismatch { current: Some(2) });
+        let outcome = ds.conditional_update(&1u8, None, Some(&2u8)).unwrap();
+        assert_eq!(outcome, CasOutcome::Swapped);
+        assert_eq!(ds.get::<u8, u8>(&1).unwrap(), None);
+    }
+
     #[test]
     fn get_lt() {
         let ds = BTreeMap::new();
-        ds.insert(&1, &2).unwrap();
-        ds.insert(&10, &4).unwrap();
-        ds.insert(&20, &8).unwrap();
-        let (key, val): (u8, u8) = ds.get_lt(&11).unwrap().unwrap();
+        ds.insert(&1u8, &2).unwrap();
+        ds.insert(&10u8, &4).unwrap();
+        ds.insert(&20u8, &8).unwrap();
+        let (key, val): (u8, u8) = ds.get_lt(&11u8).unwrap().unwrap();
         assert_eq!(key, 10);
         assert_eq!(val, 4);
     }
@@ -109,11 +287,51 @@ mod tests {
     #[test]
     fn get_gt() {
         let ds = BTreeMap::new();
-        ds.insert(&1, &2).unwrap();
-        ds.insert(&10, &4).unwrap();
-        ds.insert(&20, &8).unwrap();
-        let (key, val): (u8, u8) = ds.get_gt(&10).unwrap().unwrap();
+        ds.insert(&1u8, &2).unwrap();
+        ds.insert(&10u8, &4).unwrap();
+        ds.insert(&20u8, &8).unwrap();
+        let (key, val): (u8, u8) = ds.get_gt(&10u8).unwrap().unwrap();
         assert_eq!(key, 20);
         assert_eq!(val, 8);
     }
+
+    #[test]
+    fn removing_nothing_is_not_published() {
+        use crate::traits::byte_store::{ByteStore, Watch};
+        use crate::traits::data_store::Change;
+        let ds = BTreeMap::new();
+        let mut changes = ds.watch_prefix(&[]).unwrap();
+        ByteStore::remove(&ds, &[1]).unwrap();
+        ByteStore::write_batch(&ds, &[(&[2], None), (&[3], Some(&[4]))]).unwrap();
+        ByteStore::remove(&ds, &[3]).unwrap();
+        drop(ds);
+
+        let changes: Vec<_> = changes.by_ref().collect();
+        assert_eq!(
+            changes,
+            vec![
+                Change::Insert {
+                    key: vec![3],
+                    value: vec![4]
+                },
+                Change::Remove { key: vec![3] },
+            ]
+        );
+    }
+
+    #[test]
+    fn scan_prefix() {
+        use crate::traits::byte_store::Ordered;
+        let ds = BTreeMap::new();
+        ds.insert(&[1u8, 0], &0u8).unwrap();
+        ds.insert(&[2u8, 0], &1u8).unwrap();
+        ds.insert(&[2u8, 1], &2u8).unwrap();
+        ds.insert(&[3u8, 0], &3u8).unwrap();
+        let keys: Vec<_> = ds
+            .scan_prefix(&[2])
+            .map(Result::unwrap)
+            .map(|(key, _)| key)
+            .collect();
+        assert_eq!(keys, vec![vec![2, 0], vec![2, 1]]);
+    }
 }
@@ -0,0 +1,59 @@
+/// The merge operator for the values of a [`Counter`](crate::wrapper::Counter).
+/// Adds `delta` to the value, both are little endian integers of the same
+/// width, wrapping on overflow. A missing value counts as zero, a value of
+/// another width is left unchanged, a merge operator can not fail. Reading
+/// that value through the `Counter` fails instead.
+///
+/// The structs generated by dbstruct install it. A sled tree you opened
+/// yourself and pass with `db=trait` falls back to an atomic update unless
+/// it is passed to `sled::Tree::set_merge_operator`. A rocksdb
+/// `TransactionDB` needs to be opened with `stores::rocksdb_options` or
+/// another merge operator calling this.
+pub fn counter_merge(_key: &[u8], old: Option<&[u8]>, delta: &[u8]) -> Option<Vec<u8>> {
+    let Some(old) = old else {
+        return Some(delta.to_vec());
+    };
+    if old.len() != delta.len() {
+        return Some(old.to_vec());
+    }
+
+    let mut carry = 0;
+    let sum = old
+        .iter()
+        .zip(delta)
+        .map(|(a, b)| {
+            let sum = u16::from(*a) + u16::from(*b) + carry;
+            carry = sum >> 8;
+            sum as u8
+        })
+        .collect();
+    Some(sum)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn adds_with_carry() {
+        let old = 255u16.to_le_bytes();
+        let delta = 3u16.to_le_bytes();
+        let sum = counter_merge(&[], Some(&old), &delta).unwrap();
+        assert_eq!(sum, 258u16.to_le_bytes());
+    }
+
+    #[test]
+    fn wraps_around() {
+        let old = 1i32.to_le_bytes();
+        let delta = (-2i32).to_le_bytes();
+        let sum = counter_merge(&[], Some(&old), &delta).unwrap();
+        assert_eq!(sum, (-1i32).to_le_bytes());
+    }
+
+    #[test]
+    fn missing_is_zero() {
+        let delta = 7u64.to_le_bytes();
+        let sum = counter_merge(&[], None, &delta).unwrap();
+        assert_eq!(sum, delta);
+    }
+}
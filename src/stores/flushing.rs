@@ -0,0 +1,343 @@
+use core::fmt;
+use core::ops::Bound;
+use core::time::Duration;
+use std::sync::{Arc, Condvar, Mutex, PoisonError};
+use std::thread::{self, JoinHandle};
+
+use tracing::error;
+
+use crate::traits::byte_store::{self, KeyValue};
+use crate::traits::{ByteStore, CasOutcome, Flushable};
+
+/// When a [`Flushing`] store flushes, set per field using
+/// `#[dbstruct(flush = "...")]`
+#[derive(Debug, Clone)]
+pub struct FlushPolicy(Policy);
+
+#[derive(Debug, Clone)]
+enum Policy {
+    EveryWrite,
+    Interval {
+        interval: Duration,
+        /// shared by the clones of the policy
+        timer: Arc<Timer>,
+    },
+}
+
+impl FlushPolicy {
+    /// Flush after every write
+    pub fn every_write() -> Self {
+        Self(Policy::EveryWrite)
+    }
+
+    /// Flush at most `interval` after a write. The first write after a
+    /// flush schedules one on a background thread, the writes made until
+    /// then are flushed with it.
+    ///
+    /// Dropping the last clone of the policy runs a scheduled flush right
+    /// away and waits for it.
+    pub fn interval(interval: Duration) -> Self {
+        Self(Policy::Interval {
+            interval,
+            timer: Arc::default(),
+        })
+    }
+}
+
+/// Runs the flushes scheduled by an interval policy
+#[derive(Debug, Default)]
+struct Timer {
+    shared: Arc<Shared>,
+    threads: Mutex<Vec<JoinHandle<()>>>,
+}
+
+#[derive(Default)]
+struct Shared {
+    state: Mutex<State>,
+    /// notified when the timer is dropped
+    stopping: Condvar,
+}
+
+impl fmt::Debug for Shared {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Shared").finish_non_exhaustive()
+    }
+}
+
+#[derive(Default)]
+struct State {
+    /// the flush that is scheduled, `None` if there is none
+    scheduled: Option<Box<dyn FnOnce() + Send>>,
+    stopping: bool,
+}
+
+impl Timer {
+    /// Schedules the flush returned by `flush` to run after `interval`,
+    /// unless a flush is already scheduled. That one runs after the write
+    /// so it flushes the write too.
+    fn schedule(&self, interval: Duration, flush: impl FnOnce() -> Box<dyn FnOnce() + Send>) {
+        let mut state = self
+            .shared
+            .state
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if state.scheduled.is_some() {
+            return;
+        }
+        state.scheduled = Some(flush());
+        drop(state);
+
+        let shared = Arc::clone(&self.shared);
+        let thread = thread::spawn(move || {
+            let state = shared.state.lock().unwrap_or_else(PoisonError::into_inner);
+            let (mut state, _) = shared
+                .stopping
+                .wait_timeout_while(state, interval, |state| !state.stopping)
+                .unwrap_or_else(PoisonError::into_inner);
+            let flush = state.scheduled.take();
+            drop(state);
+            if let Some(flush) = flush {
+                flush();
+            }
+        });
+        let mut threads = self.threads.lock().unwrap_or_else(PoisonError::into_inner);
+        threads.retain(|thread| !thread.is_finished());
+        threads.push(thread);
+    }
+}
+
+impl Drop for Timer {
+    fn drop(&mut self) {
+        let mut state = self
+            .shared
+            .state
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        state.stopping = true;
+        drop(state);
+        self.shared.stopping.notify_all();
+
+        let threads = self
+            .threads
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner);
+        for thread in threads.drain(..) {
+            // a panic in the flush has already been reported
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Flushes the store it wraps after writes according to a [`FlushPolicy`].
+/// The struct generated with the `flush` option uses this for the fields
+/// the option applies to.
+#[derive(Debug, Clone)]
+pub struct Flushing<BS> {
+    store: BS,
+    policy: FlushPolicy,
+}
+
+impl<BS> Flushing<BS> {
+    pub fn new(store: BS, policy: FlushPolicy) -> Self {
+        Self { store, policy }
+    }
+
+    pub fn into_inner(self) -> BS {
+        self.store
+    }
+}
+
+impl<BS> Flushing<BS>
+where
+    BS: ByteStore + Flushable<Error = <BS as ByteStore>::Error> + Clone + Send + 'static,
+{
+    fn written<T>(&self, res: T) -> Result<T, <BS as ByteStore>::Error> {
+        match &self.policy.0 {
+            Policy::EveryWrite => self.store.flush()?,
+            Policy::Interval { interval, timer } => timer.schedule(*interval, || {
+                let store = self.store.clone();
+                Box::new(move || {
+                    if let Err(e) = store.flush() {
+                        error!("could not flush in the background: {e:?}");
+                    }
+                })
+            }),
+        }
+        Ok(res)
+    }
+}
+
+impl<BS> ByteStore for Flushing<BS>
+where
+    BS: ByteStore + Flushable<Error = <BS as ByteStore>::Error> + Clone + Send + 'static,
+{
+    type Error = <BS as ByteStore>::Error;
+    type Bytes = BS::Bytes;
+    type Codec = BS::Codec;
+
+    fn get(&self, key: &[u8]) -> Result<Option<Self::Bytes>, Self::Error> {
+        self.store.get(key)
+    }
+
+    fn remove(&self, key: &[u8]) -> Result<Option<Self::Bytes>, Self::Error> {
+        let existing = self.store.remove(key)?;
+        self.written(existing)
+    }
+
+    fn insert(&self, key: &[u8], val: &[u8]) -> Result<Option<Self::Bytes>, Self::Error> {
+        let existing = self.store.insert(key, val)?;
+        self.written(existing)
+    }
+
+    fn write_batch(&self, batch: &[(&[u8], Option<&[u8]>)]) -> Result<(), Self::Error> {
+        self.store.write_batch(batch)?;
+        self.written(())
+    }
+}
+
+impl<BS> byte_store::Atomic for Flushing<BS>
+where
+    BS: byte_store::Atomic + Flushable<Error = <BS as ByteStore>::Error> + Clone + Send + 'static,
+{
+    fn atomic_update(
+        &self,
+        key: &[u8],
+        op: impl FnMut(Option<&[u8]>) -> Option<Vec<u8>>,
+    ) -> Result<(), Self::Error> {
+        self.store.atomic_update(key, op)?;
+        self.written(())
+    }
+
+    fn counter_add(&self, key: &[u8], delta: &[u8]) -> Result<(), Self::Error> {
+        self.store.counter_add(key, delta)?;
+        self.written(())
+    }
+
+    fn conditional_update(
+        &self,
+        key: &[u8],
+        new: Option<&[u8]>,
+        expected: Option<&[u8]>,
+    ) -> Result<CasOutcome<Self::Bytes>, Self::Error> {
+        let outcome = self.store.conditional_update(key, new, expected)?;
+        self.written(outcome)
+    }
+}
+
+impl<BS> byte_store::Ordered for Flushing<BS>
+where
+    BS: byte_store::Ordered + Flushable<Error = <BS as ByteStore>::Error> + Clone + Send + 'static,
+{
+    type Range<'a>
+        = BS::Range<'a>
+    where
+        Self: 'a;
+
+    fn get_lt(&self, key: &[u8]) -> Result<Option<KeyValue<Self>>, Self::Error> {
+        self.store.get_lt(key)
+    }
+
+    fn get_gt(&self, key: &[u8]) -> Result<Option<KeyValue<Self>>, Self::Error> {
+        self.store.get_gt(key)
+    }
+
+    fn range(&self, start: Bound<&[u8]>, end: Bound<&[u8]>) -> Self::Range<'_> {
+        self.store.range(start, end)
+    }
+}
+
+impl<BS> byte_store::Watch for Flushing<BS>
+where
+    BS: byte_store::Watch + Flushable<Error = <BS as ByteStore>::Error> + Clone + Send + 'static,
+{
+    type Subscriber = BS::Subscriber;
+
+    fn watch_prefix(&self, prefix: &[u8]) -> Result<Self::Subscriber, Self::Error> {
+        self.store.watch_prefix(prefix)
+    }
+}
+
+impl<BS: Flushable> Flushable for Flushing<BS> {
+    type Error = BS::Error;
+
+    fn flush(&self) -> Result<(), Self::Error> {
+        self.store.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::stores::BTreeMap;
+
+    /// counts the flushes of the store it wraps
+    #[derive(Clone, Default)]
+    struct Counting {
+        store: BTreeMap,
+        flushes: Arc<AtomicUsize>,
+    }
+
+    impl ByteStore for Counting {
+        type Error = crate::stores::BTreeMapError;
+        type Bytes = Vec<u8>;
+        type Codec = crate::codec::Bincode;
+
+        fn get(&self, key: &[u8]) -> Result<Option<Self::Bytes>, Self::Error> {
+            self.store.get(key)
+        }
+
+        fn remove(&self, key: &[u8]) -> Result<Option<Self::Bytes>, Self::Error> {
+            self.store.remove(key)
+        }
+
+        fn insert(&self, key: &[u8], val: &[u8]) -> Result<Option<Self::Bytes>, Self::Error> {
+            self.store.insert(key, val)
+        }
+    }
+
+    impl Flushable for Counting {
+        type Error = crate::stores::BTreeMapError;
+
+        fn flush(&self) -> Result<(), Self::Error> {
+            self.flushes.fetch_add(1, Ordering::Relaxed);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn every_write() {
+        let inner = Counting::default();
+        let store = Flushing::new(inner.clone(), FlushPolicy::every_write());
+        store.insert(&[1], &[2]).unwrap();
+        store.get(&[1]).unwrap();
+        store.remove(&[1]).unwrap();
+        assert_eq!(inner.flushes.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn interval() {
+        let inner = Counting::default();
+        let policy = FlushPolicy::interval(Duration::from_millis(20));
+        let store = Flushing::new(inner.clone(), policy.clone());
+        store.insert(&[1], &[2]).unwrap();
+        // shares the scheduled flush
+        let other = Flushing::new(inner.clone(), policy);
+        other.insert(&[1], &[3]).unwrap();
+        assert_eq!(inner.flushes.load(Ordering::Relaxed), 0);
+
+        std::thread::sleep(Duration::from_millis(200));
+        assert_eq!(inner.flushes.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn interval_flushes_when_dropped() {
+        let inner = Counting::default();
+        let policy = FlushPolicy::interval(Duration::from_secs(3600));
+        let store = Flushing::new(inner.clone(), policy);
+        store.insert(&[1], &[2]).unwrap();
+        drop(store);
+        assert_eq!(inner.flushes.load(Ordering::Relaxed), 1);
+    }
+}
@@ -1,7 +1,8 @@
 use std::collections;
 use std::sync::{Arc, RwLock};
 
-use crate::traits::ByteStore;
+use super::broadcast::{Broadcast, BroadcastSubscriber};
+use crate::traits::{self, byte_store, ByteStore, CasOutcome};
 
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
@@ -15,8 +16,12 @@ pub enum Error {
 /// ### ALL CHANGES ARE LOST WHEN THE OBJECT IS DROPPED
 /// again: use for testing the api only
 ///
+/// Changes can be watched using an in-process [`Broadcast`].
 #[derive(Default, Clone)]
-pub struct HashMap(Arc<RwLock<collections::HashMap<Vec<u8>, Vec<u8>>>>);
+pub struct HashMap {
+    map: Arc<RwLock<collections::HashMap<Vec<u8>, Vec<u8>>>>,
+    watchers: Broadcast,
+}
 
 impl HashMap {
     pub fn new() -> Self {
@@ -27,27 +32,107 @@ impl HashMap {
 impl ByteStore for HashMap {
     type Error = Error;
     type Bytes = Vec<u8>;
+    type Codec = crate::codec::Bincode;
 
     fn get(&self, key: &[u8]) -> Result<Option<Self::Bytes>, Self::Error> {
-        let map = self.0.read().map_err(|_| Self::Error::Poisoned)?;
+        let map = self.map.read().map_err(|_| Self::Error::Poisoned)?;
         Ok(map.get(key).cloned())
     }
 
     fn remove(&self, key: &[u8]) -> Result<Option<Self::Bytes>, Self::Error> {
-        let mut map = self.0.write().map_err(|_| Self::Error::Poisoned)?;
-        Ok(map.remove(key))
+        let mut map = self.map.write().map_err(|_| Self::Error::Poisoned)?;
+        let existing = map.remove(key);
+        if existing.is_some() {
+            self.watchers.publish(key, None);
+        }
+        Ok(existing)
     }
 
     fn insert(&self, key: &[u8], val: &[u8]) -> Result<Option<Self::Bytes>, Self::Error> {
-        let mut map = self.0.write().map_err(|_| Self::Error::Poisoned)?;
+        let mut map = self.map.write().map_err(|_| Self::Error::Poisoned)?;
+        self.watchers.publish(key, Some(val));
         Ok(map.insert(key.to_vec(), val.to_vec()))
     }
+
+    fn write_batch(&self, batch: &[(&[u8], Option<&[u8]>)]) -> Result<(), Self::Error> {
+        let mut map = self.map.write().map_err(|_| Self::Error::Poisoned)?;
+        for (key, val) in batch {
+            let existing = match val {
+                Some(val) => map.insert(key.to_vec(), val.to_vec()),
+                None => map.remove(*key),
+            };
+            if val.is_some() || existing.is_some() {
+                self.watchers.publish(key, *val);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Holds the write lock for the whole update
+impl byte_store::Atomic for HashMap {
+    fn atomic_update(
+        &self,
+        key: &[u8],
+        mut op: impl FnMut(Option<&[u8]>) -> Option<Vec<u8>>,
+    ) -> Result<(), Self::Error> {
+        let mut map = self.map.write().map_err(|_| Self::Error::Poisoned)?;
+        let old = map.get(key).map(Vec::as_slice);
+        let new = op(old);
+        if old != new.as_deref() {
+            self.watchers.publish(key, new.as_deref());
+        }
+        match new {
+            Some(new) => map.insert(key.to_vec(), new),
+            None => map.remove(key),
+        };
+        Ok(())
+    }
+
+    fn conditional_update(
+        &self,
+        key: &[u8],
+        new: Option<&[u8]>,
+        expected: Option<&[u8]>,
+    ) -> Result<CasOutcome<Self::Bytes>, Self::Error> {
+        let mut map = self.map.write().map_err(|_| Self::Error::Poisoned)?;
+        let current = map.get(key);
+        if current.map(Vec::as_slice) != expected {
+            let current = current.cloned();
+            return Ok(CasOutcome::Mismatch { current });
+        }
+        if new != expected {
+            self.watchers.publish(key, new);
+        }
+        match new {
+            Some(new) => map.insert(key.to_vec(), new.to_vec()),
+            None => map.remove(key),
+        };
+        Ok(CasOutcome::Swapped)
+    }
+}
+
+impl byte_store::Watch for HashMap {
+    type Subscriber = BroadcastSubscriber;
+
+    fn watch_prefix(&self, prefix: &[u8]) -> Result<Self::Subscriber, Self::Error> {
+        Ok(self.watchers.subscribe(prefix))
+    }
+}
+
+/// Nothing is persisted, flushing does nothing
+impl traits::Flushable for HashMap {
+    type Error = Error;
+
+    fn flush(&self) -> Result<(), Self::Error> {
+        Ok(())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::HashMap;
-    use crate::traits::DataStore;
+    use crate::traits::{CasOutcome, DataStore};
 
     #[test]
     fn get_then_insert() {
@@ -57,4 +142,19 @@ mod tests {
         let val: u8 = ds.remove(&1).unwrap().unwrap();
         assert_eq!(val, 2);
     }
+
+    #[test]
+    fn atomic_update() {
+        use crate::traits::data_store::Atomic;
+        let ds = HashMap::new();
+        ds.atomic_update(&1u8, |v: u8| v + 1).unwrap();
+        assert_eq!(ds.get::<u8, u8>(&1).unwrap(), None);
+        ds.insert(&1u8, &2u8).unwrap();
+        ds.atomic_update(&1u8, |v: u8| v + 1).unwrap();
+        let outcome = ds.conditional_update(&1u8, Some(&9u8), Some(&2)).unwrap();
+        assert_eq!(outcome, CasOutcome::Mismatch { current: Some(3) });
+        let outcome = ds.conditional_update(&1u8, Some(&9u8), Some(&3)).unwrap();
+        assert_eq!(outcome, CasOutcome::Swapped);
+        assert_eq!(ds.get::<u8, u8>(&1).unwrap(), Some(9));
+    }
 }
@@ -1,5 +1,13 @@
-use crate::ByteStore;
-use rocksdb::{ThreadMode, TransactionDB};
+use core::cell::RefCell;
+use core::ops::Bound;
+use std::sync::Arc;
+
+use crate::traits::byte_store::{self, KeyValue};
+use crate::traits::{self, ByteStore, CasOutcome};
+use rocksdb::{
+    DBIteratorWithThreadMode, Direction, IteratorMode, MergeOperands, ReadOptions, ThreadMode,
+    Transaction, TransactionDB, WriteOptions,
+};
 
 // default off, needs libclang-dev package on ubuntu
 
@@ -11,6 +19,7 @@ DataStore directly and use pinnend in that implementation
 impl<TH: ThreadMode> ByteStore for TransactionDB<TH> {
     type Error = rocksdb::Error;
     type Bytes = Vec<u8>;
+    type Codec = crate::codec::Bincode;
 
     fn get(&self, key: &[u8]) -> Result<Option<Self::Bytes>, Self::Error> {
         self.get(key)
@@ -54,4 +63,379 @@ impl<TH: ThreadMode> ByteStore for TransactionDB<TH> {
             Ok(Some(val)) => Ok(Some(val)),
         }
     }
+
+    fn write_batch(&self, batch: &[(&[u8], Option<&[u8]>)]) -> Result<(), Self::Error> {
+        let txn = self.transaction();
+        for (key, val) in batch {
+            match val {
+                Some(val) => txn.put(key, val)?,
+                None => txn.delete(key)?,
+            }
+        }
+        txn.commit()
+    }
+}
+
+/// Rocksdb returns these if another transaction holds or changed the key,
+/// trying again can succeed.
+fn is_conflict(e: &rocksdb::Error) -> bool {
+    use rocksdb::ErrorKind::*;
+    matches!(e.kind(), Busy | TryAgain | TimedOut)
+}
+
+/// Options used by the `new` of `db=rocksdb` structs. Creates the database
+/// if it is missing and installs [`counter_merge`](super::counter_merge)
+/// as merge operator.
+pub fn options() -> rocksdb::Options {
+    let mut options = rocksdb::Options::default();
+    options.create_if_missing(true);
+    options.set_merge_operator_associative("dbstruct_counter", merge_counters);
+    options
+}
+
+/// Applies every delta in `operands`, with `old` set to `None` this
+/// combines deltas into one
+fn merge_counters(key: &[u8], old: Option<&[u8]>, operands: &MergeOperands) -> Option<Vec<u8>> {
+    let mut value = old.map(<[u8]>::to_vec);
+    for delta in operands {
+        value = super::counter_merge(key, value.as_deref(), delta);
+    }
+    value
+}
+
+/// Implemented using a transaction per update. The update is retried if it
+/// conflicts with another transaction.
+impl<TH: ThreadMode> byte_store::Atomic for TransactionDB<TH> {
+    fn atomic_update(
+        &self,
+        key: &[u8],
+        mut op: impl FnMut(Option<&[u8]>) -> Option<Vec<u8>>,
+    ) -> Result<(), Self::Error> {
+        let mut update = || {
+            let txn = self.transaction();
+            let old = txn.get_for_update(key, true)?;
+            match op(old.as_deref()) {
+                Some(new) => txn.put(key, new)?,
+                None => txn.delete(key)?,
+            }
+            txn.commit()
+        };
+
+        loop {
+            match update() {
+                Err(e) if is_conflict(&e) => continue,
+                res => return res,
+            }
+        }
+    }
+
+    /// Needs the merge operator installed by [`options`]. Without it the
+    /// merge succeeds but reading the counter fails.
+    fn counter_add(&self, key: &[u8], delta: &[u8]) -> Result<(), Self::Error> {
+        self.merge(key, delta)
+    }
+
+    fn conditional_update(
+        &self,
+        key: &[u8],
+        new: Option<&[u8]>,
+        expected: Option<&[u8]>,
+    ) -> Result<CasOutcome<Self::Bytes>, Self::Error> {
+        let txn = self.transaction();
+        let current = txn.get_for_update(key, true)?;
+        if current.as_deref() != expected {
+            txn.rollback()?;
+            return Ok(CasOutcome::Mismatch { current });
+        }
+        match new {
+            Some(new) => txn.put(key, new)?,
+            None => txn.delete(key)?,
+        }
+        txn.commit()?;
+        Ok(CasOutcome::Swapped)
+    }
+}
+
+/// Iterator over a range of a rocksdb [`TransactionDB`]
+pub struct Range<'a, TH: ThreadMode> {
+    iter: DBIteratorWithThreadMode<'a, TransactionDB<TH>>,
+    /// rocksdb can only seek to a key, if the start is excluded we
+    /// need to skip it
+    skip: Option<Vec<u8>>,
+    end: Bound<Vec<u8>>,
+}
+
+impl<TH: ThreadMode> Iterator for Range<'_, TH> {
+    type Item = Result<KeyValue<TransactionDB<TH>>, rocksdb::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (key, val) = match self.iter.next()? {
+            Ok(pair) => pair,
+            Err(e) => return Some(Err(e)),
+        };
+        if let Some(skip) = self.skip.take() {
+            if skip == *key {
+                return self.next();
+            }
+        }
+        let past_end = match &self.end {
+            Bound::Included(end) => *key > **end,
+            Bound::Excluded(end) => *key >= **end,
+            Bound::Unbounded => false,
+        };
+        if past_end {
+            return None;
+        }
+        Some(Ok((key.into_vec(), val.into_vec())))
+    }
+}
+
+impl<TH: ThreadMode> byte_store::Ordered for TransactionDB<TH> {
+    type Range<'a> = Range<'a, TH> where Self: 'a;
+
+    fn get_lt(&self, key: &[u8]) -> Result<Option<KeyValue<Self>>, Self::Error> {
+        // seeks to the last key less then or equal to key
+        let mode = IteratorMode::From(key, Direction::Reverse);
+        for res in self.iterator_opt(mode, ReadOptions::default()) {
+            let (found, val) = res?;
+            if *found != *key {
+                return Ok(Some((found.into_vec(), val.into_vec())));
+            }
+        }
+        Ok(None)
+    }
+    fn get_gt(&self, key: &[u8]) -> Result<Option<KeyValue<Self>>, Self::Error> {
+        self.range(Bound::Excluded(key), Bound::Unbounded)
+            .next()
+            .transpose()
+    }
+    fn range(&self, start: Bound<&[u8]>, end: Bound<&[u8]>) -> Self::Range<'_> {
+        let (mode, skip) = match start {
+            Bound::Included(key) => (IteratorMode::From(key, Direction::Forward), None),
+            Bound::Excluded(key) => (
+                IteratorMode::From(key, Direction::Forward),
+                Some(key.to_vec()),
+            ),
+            Bound::Unbounded => (IteratorMode::Start, None),
+        };
+        let end = match end {
+            Bound::Included(key) => Bound::Included(key.to_vec()),
+            Bound::Excluded(key) => Bound::Excluded(key.to_vec()),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+        Range {
+            iter: self.iterator_opt(mode, ReadOptions::default()),
+            skip,
+            end,
+        }
+    }
+}
+
+/// A rocksdb transaction, see [`Transactional`](traits::Transactional).
+pub struct RocksTransaction<'tx, TH: ThreadMode> {
+    txn: &'tx Transaction<'tx, TransactionDB<TH>>,
+    /// the first error, if there is one the transaction is not committed
+    failed: &'tx RefCell<Option<rocksdb::Error>>,
+}
+
+// derive would require TH: Clone
+impl<TH: ThreadMode> Clone for RocksTransaction<'_, TH> {
+    fn clone(&self) -> Self {
+        Self {
+            txn: self.txn,
+            failed: self.failed,
+        }
+    }
+}
+
+impl<TH: ThreadMode> RocksTransaction<'_, TH> {
+    fn record<T>(&self, res: Result<T, rocksdb::Error>) -> Result<T, rocksdb::Error> {
+        if let Err(e) = &res {
+            self.failed.borrow_mut().get_or_insert_with(|| e.clone());
+        }
+        res
+    }
+}
+
+impl<TH: ThreadMode> ByteStore for RocksTransaction<'_, TH> {
+    type Error = rocksdb::Error;
+    type Bytes = Vec<u8>;
+    type Codec = crate::codec::Bincode;
+
+    fn get(&self, key: &[u8]) -> Result<Option<Self::Bytes>, Self::Error> {
+        self.record(self.txn.get(key))
+    }
+
+    fn remove(&self, key: &[u8]) -> Result<Option<Self::Bytes>, Self::Error> {
+        let existing = self.record(self.txn.get_for_update(key, true))?;
+        self.record(self.txn.delete(key))?;
+        Ok(existing)
+    }
+
+    fn insert(&self, key: &[u8], val: &[u8]) -> Result<Option<Self::Bytes>, Self::Error> {
+        let existing = self.record(self.txn.get_for_update(key, true))?;
+        self.record(self.txn.put(key, val))?;
+        Ok(existing)
+    }
+}
+
+/// Everything in a transaction is atomic
+impl<TH: ThreadMode> byte_store::Atomic for RocksTransaction<'_, TH> {
+    fn atomic_update(
+        &self,
+        key: &[u8],
+        mut op: impl FnMut(Option<&[u8]>) -> Option<Vec<u8>>,
+    ) -> Result<(), Self::Error> {
+        let old = self.record(self.txn.get_for_update(key, true))?;
+        match op(old.as_deref()) {
+            Some(new) => self.record(self.txn.put(key, new)),
+            None => self.record(self.txn.delete(key)),
+        }
+    }
+
+    fn conditional_update(
+        &self,
+        key: &[u8],
+        new: Option<&[u8]>,
+        expected: Option<&[u8]>,
+    ) -> Result<CasOutcome<Self::Bytes>, Self::Error> {
+        let current = self.record(self.txn.get_for_update(key, true))?;
+        if current.as_deref() != expected {
+            return Ok(CasOutcome::Mismatch { current });
+        }
+        match new {
+            Some(new) => self.record(self.txn.put(key, new))?,
+            None => self.record(self.txn.delete(key))?,
+        }
+        Ok(CasOutcome::Swapped)
+    }
+}
+
+/// The transaction is retried if it conflicts with another transaction
+impl<TH: ThreadMode> traits::Transactional for TransactionDB<TH> {
+    type Tx<'tx> = RocksTransaction<'tx, TH> where Self: 'tx;
+    type Error = rocksdb::Error;
+
+    fn transaction<T, E>(
+        &self,
+        f: impl Fn(&Self::Tx<'_>) -> Result<T, E>,
+    ) -> Result<T, traits::TransactionError<E, Self::Error>> {
+        loop {
+            let txn = self.transaction();
+            let failed = RefCell::new(None);
+            let tx = RocksTransaction {
+                txn: &txn,
+                failed: &failed,
+            };
+            let res = f(&tx);
+            // dropping txn without committing rolls it back
+            match failed.into_inner() {
+                Some(e) if is_conflict(&e) => continue,
+                Some(e) => return Err(traits::TransactionError::Database(e)),
+                None => (),
+            }
+            let value = res.map_err(traits::TransactionError::Abort)?;
+            match txn.commit() {
+                Err(e) if is_conflict(&e) => continue,
+                Err(e) => return Err(traits::TransactionError::Database(e)),
+                Ok(()) => return Ok(value),
+            }
+        }
+    }
+}
+
+// The struct generated for `db=rocksdb` hands every field a clone of the
+// database. A TransactionDB can not be cloned so it is shared using an Arc.
+
+/// The rocksdb crate can not flush a TransactionDB or sync its write ahead
+/// log. Every write goes to the log first, a synced write has the log
+/// written to disk up to and including itself. That write deletes the empty
+/// key, no field is stored there.
+impl<TH: ThreadMode> traits::Flushable for TransactionDB<TH> {
+    type Error = rocksdb::Error;
+
+    fn flush(&self) -> Result<(), Self::Error> {
+        let mut options = WriteOptions::default();
+        options.set_sync(true);
+        self.delete_opt(b"", &options)
+    }
+}
+
+impl<TH: ThreadMode> traits::Flushable for Arc<TransactionDB<TH>> {
+    type Error = rocksdb::Error;
+
+    fn flush(&self) -> Result<(), Self::Error> {
+        traits::Flushable::flush(self.as_ref())
+    }
+}
+
+impl<TH: ThreadMode> ByteStore for Arc<TransactionDB<TH>> {
+    type Error = rocksdb::Error;
+    type Bytes = Vec<u8>;
+    type Codec = crate::codec::Bincode;
+
+    fn get(&self, key: &[u8]) -> Result<Option<Self::Bytes>, Self::Error> {
+        ByteStore::get(self.as_ref(), key)
+    }
+
+    fn remove(&self, key: &[u8]) -> Result<Option<Self::Bytes>, Self::Error> {
+        ByteStore::remove(self.as_ref(), key)
+    }
+
+    fn insert(&self, key: &[u8], val: &[u8]) -> Result<Option<Self::Bytes>, Self::Error> {
+        ByteStore::insert(self.as_ref(), key, val)
+    }
+
+    fn write_batch(&self, batch: &[(&[u8], Option<&[u8]>)]) -> Result<(), Self::Error> {
+        ByteStore::write_batch(self.as_ref(), batch)
+    }
+}
+
+impl<TH: ThreadMode> byte_store::Atomic for Arc<TransactionDB<TH>> {
+    fn atomic_update(
+        &self,
+        key: &[u8],
+        op: impl FnMut(Option<&[u8]>) -> Option<Vec<u8>>,
+    ) -> Result<(), Self::Error> {
+        byte_store::Atomic::atomic_update(self.as_ref(), key, op)
+    }
+
+    fn counter_add(&self, key: &[u8], delta: &[u8]) -> Result<(), Self::Error> {
+        byte_store::Atomic::counter_add(self.as_ref(), key, delta)
+    }
+
+    fn conditional_update(
+        &self,
+        key: &[u8],
+        new: Option<&[u8]>,
+        expected: Option<&[u8]>,
+    ) -> Result<CasOutcome<Self::Bytes>, Self::Error> {
+        byte_store::Atomic::conditional_update(self.as_ref(), key, new, expected)
+    }
+}
+
+impl<TH: ThreadMode> byte_store::Ordered for Arc<TransactionDB<TH>> {
+    type Range<'a> = Range<'a, TH> where Self: 'a;
+
+    fn get_lt(&self, key: &[u8]) -> Result<Option<KeyValue<Self>>, Self::Error> {
+        byte_store::Ordered::get_lt(self.as_ref(), key)
+    }
+    fn get_gt(&self, key: &[u8]) -> Result<Option<KeyValue<Self>>, Self::Error> {
+        byte_store::Ordered::get_gt(self.as_ref(), key)
+    }
+    fn range(&self, start: Bound<&[u8]>, end: Bound<&[u8]>) -> Self::Range<'_> {
+        byte_store::Ordered::range(self.as_ref(), start, end)
+    }
+}
+
+impl<TH: ThreadMode> traits::Transactional for Arc<TransactionDB<TH>> {
+    type Tx<'tx> = RocksTransaction<'tx, TH> where Self: 'tx;
+    type Error = rocksdb::Error;
+
+    fn transaction<T, E>(
+        &self,
+        f: impl Fn(&Self::Tx<'_>) -> Result<T, E>,
+    ) -> Result<T, traits::TransactionError<E, Self::Error>> {
+        traits::Transactional::transaction(self.as_ref(), f)
+    }
 }
@@ -1,8 +1,22 @@
-use crate::traits::{byte_store, ByteStore};
+use core::cell::RefCell;
+use core::future::Future;
+use core::ops::Bound;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
+use sled::transaction::{
+    ConflictableTransactionError, TransactionError, TransactionalTree,
+    UnabortableTransactionError,
+};
+
+use crate::traits::byte_store::{self, KeyValue};
+use crate::traits::data_store::Change;
+use crate::traits::{self, ByteStore, CasOutcome};
 
 impl ByteStore for sled::Tree {
     type Error = sled::Error;
     type Bytes = sled::IVec;
+    type Codec = crate::codec::Bincode;
 
     fn get(&self, key: &[u8]) -> Result<Option<Self::Bytes>, Self::Error> {
         self.get(key)
@@ -15,6 +29,17 @@ impl ByteStore for sled::Tree {
     fn insert(&self, key: &[u8], val: &[u8]) -> Result<Option<Self::Bytes>, Self::Error> {
         self.insert(key, val)
     }
+
+    fn write_batch(&self, batch: &[(&[u8], Option<&[u8]>)]) -> Result<(), Self::Error> {
+        let mut sled_batch = sled::Batch::default();
+        for (key, val) in batch {
+            match val {
+                Some(val) => sled_batch.insert(*key, *val),
+                None => sled_batch.remove(*key),
+            }
+        }
+        self.apply_batch(sled_batch)
+    }
 }
 
 impl byte_store::Atomic for sled::Tree {
@@ -26,22 +51,199 @@ impl byte_store::Atomic for sled::Tree {
         self.fetch_and_update(key, op).map(|_| ())
     }
 
+    /// Merges using [`counter_merge`](crate::stores::counter_merge), the
+    /// generated structs set it as merge operator when opening the tree.
+    /// Without merge operator this falls back to an atomic update.
+    fn counter_add(&self, key: &[u8], delta: &[u8]) -> Result<(), Self::Error> {
+        match self.merge(key, delta) {
+            // sled checks for the merge operator before changing anything
+            Err(sled::Error::Unsupported(_)) => self
+                .fetch_and_update(key, |old| crate::stores::counter_merge(key, old, delta))
+                .map(|_| ()),
+            res => res.map(|_| ()),
+        }
+    }
+
     fn conditional_update(
         &self,
         key: &[u8],
-        new: &[u8],
-        expected: &[u8],
-    ) -> Result<(), Self::Error> {
-        let _ignore_compare_and_swap_res = self.compare_and_swap(key, Some(expected), Some(new))?;
-        Ok(())
+        new: Option<&[u8]>,
+        expected: Option<&[u8]>,
+    ) -> Result<CasOutcome<Self::Bytes>, Self::Error> {
+        Ok(match self.compare_and_swap(key, expected, new)? {
+            Ok(()) => CasOutcome::Swapped,
+            Err(sled::CompareAndSwapError { current, .. }) => CasOutcome::Mismatch { current },
+        })
     }
 }
 
 impl byte_store::Ordered for sled::Tree {
-    fn get_lt(&self, key: &[u8]) -> Result<Option<(Self::Bytes, Self::Bytes)>, Self::Error> {
+    type Range<'a> = sled::Iter;
+
+    fn get_lt(&self, key: &[u8]) -> Result<Option<KeyValue<Self>>, Self::Error> {
         self.get_lt(key)
     }
-    fn get_gt(&self, key: &[u8]) -> Result<Option<(Self::Bytes, Self::Bytes)>, Self::Error> {
+    fn get_gt(&self, key: &[u8]) -> Result<Option<KeyValue<Self>>, Self::Error> {
         self.get_gt(key)
     }
+    fn range(&self, start: Bound<&[u8]>, end: Bound<&[u8]>) -> Self::Range<'_> {
+        sled::Tree::range::<&[u8], _>(self, (start, end))
+    }
+    fn scan_prefix(&self, prefix: &[u8]) -> Self::Range<'_> {
+        sled::Tree::scan_prefix(self, prefix)
+    }
+}
+
+impl byte_store::Watch for sled::Tree {
+    type Subscriber = SledSubscriber;
+
+    fn watch_prefix(&self, prefix: &[u8]) -> Result<Self::Subscriber, Self::Error> {
+        Ok(SledSubscriber(sled::Tree::watch_prefix(self, prefix)))
+    }
+}
+
+impl traits::Flushable for sled::Tree {
+    type Error = sled::Error;
+
+    /// Flushes the whole database, not only this tree
+    fn flush(&self) -> Result<(), Self::Error> {
+        sled::Tree::flush(self).map(|_| ())
+    }
+}
+
+/// Sled's own [`Subscriber`](sled::Subscriber), returning [`Change`]s
+pub struct SledSubscriber(sled::Subscriber);
+
+impl From<sled::Event> for Change {
+    fn from(event: sled::Event) -> Self {
+        match event {
+            sled::Event::Insert { key, value } => Change::Insert {
+                key: key.to_vec(),
+                value: value.to_vec(),
+            },
+            sled::Event::Remove { key } => Change::Remove { key: key.to_vec() },
+        }
+    }
+}
+
+impl Iterator for SledSubscriber {
+    type Item = Change;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(Change::from)
+    }
+}
+
+impl Future for SledSubscriber {
+    type Output = Option<Change>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.0)
+            .poll(cx)
+            .map(|event| event.map(Change::from))
+    }
+}
+
+/// A sled tree inside a transaction, see [`Transactional`](traits::Transactional).
+#[derive(Clone)]
+pub struct SledTransaction<'tx> {
+    tree: &'tx TransactionalTree,
+    /// sled needs to know about conflicts to retry the transaction, this
+    /// remembers them even if the closure replaces the error
+    failed: &'tx RefCell<Option<UnabortableTransactionError>>,
+}
+
+impl SledTransaction<'_> {
+    fn record<T>(
+        &self,
+        res: Result<T, UnabortableTransactionError>,
+    ) -> Result<T, UnabortableTransactionError> {
+        if let Err(e) = &res {
+            self.failed.borrow_mut().get_or_insert_with(|| e.clone());
+        }
+        res
+    }
+}
+
+impl ByteStore for SledTransaction<'_> {
+    type Error = UnabortableTransactionError;
+    type Bytes = sled::IVec;
+    type Codec = crate::codec::Bincode;
+
+    fn get(&self, key: &[u8]) -> Result<Option<Self::Bytes>, Self::Error> {
+        self.record(self.tree.get(key))
+    }
+
+    fn remove(&self, key: &[u8]) -> Result<Option<Self::Bytes>, Self::Error> {
+        self.record(self.tree.remove(key))
+    }
+
+    fn insert(&self, key: &[u8], val: &[u8]) -> Result<Option<Self::Bytes>, Self::Error> {
+        self.record(self.tree.insert(key, val))
+    }
+}
+
+/// Everything in a transaction is atomic
+impl byte_store::Atomic for SledTransaction<'_> {
+    fn atomic_update(
+        &self,
+        key: &[u8],
+        mut op: impl FnMut(Option<&[u8]>) -> Option<Vec<u8>>,
+    ) -> Result<(), Self::Error> {
+        let old = ByteStore::get(self, key)?;
+        match op(old.as_deref()) {
+            Some(new) => ByteStore::insert(self, key, &new)?,
+            None => ByteStore::remove(self, key)?,
+        };
+        Ok(())
+    }
+
+    fn conditional_update(
+        &self,
+        key: &[u8],
+        new: Option<&[u8]>,
+        expected: Option<&[u8]>,
+    ) -> Result<CasOutcome<Self::Bytes>, Self::Error> {
+        let current = ByteStore::get(self, key)?;
+        if current.as_deref() != expected {
+            return Ok(CasOutcome::Mismatch { current });
+        }
+        match new {
+            Some(new) => ByteStore::insert(self, key, new)?,
+            None => ByteStore::remove(self, key)?,
+        };
+        Ok(CasOutcome::Swapped)
+    }
+}
+
+impl traits::Transactional for sled::Tree {
+    type Tx<'tx> = SledTransaction<'tx>;
+    type Error = sled::Error;
+
+    fn transaction<T, E>(
+        &self,
+        f: impl Fn(&Self::Tx<'_>) -> Result<T, E>,
+    ) -> Result<T, traits::TransactionError<E, Self::Error>> {
+        let res = sled::Transactional::transaction(self, |tree| {
+            let failed = RefCell::new(None);
+            let tx = SledTransaction {
+                tree,
+                failed: &failed,
+            };
+            let res = f(&tx);
+            match failed.into_inner() {
+                Some(UnabortableTransactionError::Conflict) => {
+                    Err(ConflictableTransactionError::Conflict)
+                }
+                Some(UnabortableTransactionError::Storage(e)) => {
+                    Err(ConflictableTransactionError::Storage(e))
+                }
+                None => res.map_err(ConflictableTransactionError::Abort),
+            }
+        });
+        res.map_err(|e| match e {
+            TransactionError::Abort(e) => traits::TransactionError::Abort(e),
+            TransactionError::Storage(e) => traits::TransactionError::Database(e),
+        })
+    }
 }
@@ -0,0 +1,337 @@
+use core::cell::RefCell;
+use core::ops::Bound;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use super::broadcast::{Broadcast, BroadcastSubscriber};
+use crate::traits::byte_store::{self, KeyValue};
+use crate::traits::{ByteStore, CasOutcome, Flushable, TransactionError, Transactional};
+
+/// Adds [`Watch`](byte_store::Watch) to a store that can not watch keys
+/// itself by publishing every write to a [`Broadcast`]. The `db=rocksdb`
+/// structs wrap their `TransactionDB` in it, use it with `db=trait` for
+/// other stores.
+///
+/// Only writes made through (clones of) this `Watched` are seen, not those
+/// made through the inner store directly or by another process. The writes
+/// of a transaction are published once it commits.
+#[derive(Clone)]
+pub struct Watched<BS> {
+    store: BS,
+    watchers: Broadcast,
+    /// held while writing so changes are published in the order they
+    /// are applied
+    writing: Arc<Mutex<()>>,
+}
+
+impl<BS> Watched<BS> {
+    pub fn new(store: BS) -> Self {
+        Self {
+            store,
+            watchers: Broadcast::new(),
+            writing: Arc::default(),
+        }
+    }
+
+    pub fn into_inner(self) -> BS {
+        self.store
+    }
+
+    fn lock(&self) -> MutexGuard<'_, ()> {
+        self.writing.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<BS: ByteStore> ByteStore for Watched<BS> {
+    type Error = BS::Error;
+    type Bytes = BS::Bytes;
+    type Codec = BS::Codec;
+
+    fn get(&self, key: &[u8]) -> Result<Option<Self::Bytes>, Self::Error> {
+        self.store.get(key)
+    }
+
+    fn remove(&self, key: &[u8]) -> Result<Option<Self::Bytes>, Self::Error> {
+        let _writing = self.lock();
+        let existing = self.store.remove(key)?;
+        if existing.is_some() {
+            self.watchers.publish(key, None);
+        }
+        Ok(existing)
+    }
+
+    fn insert(&self, key: &[u8], val: &[u8]) -> Result<Option<Self::Bytes>, Self::Error> {
+        let _writing = self.lock();
+        let existing = self.store.insert(key, val)?;
+        self.watchers.publish(key, Some(val));
+        Ok(existing)
+    }
+
+    fn write_batch(&self, batch: &[(&[u8], Option<&[u8]>)]) -> Result<(), Self::Error> {
+        let _writing = self.lock();
+        self.store.write_batch(batch)?;
+        for (key, val) in batch {
+            self.watchers.publish(key, *val);
+        }
+        Ok(())
+    }
+}
+
+impl<BS: byte_store::Atomic> byte_store::Atomic for Watched<BS> {
+    fn atomic_update(
+        &self,
+        key: &[u8],
+        mut op: impl FnMut(Option<&[u8]>) -> Option<Vec<u8>>,
+    ) -> Result<(), Self::Error> {
+        let _writing = self.lock();
+        // op may run more then once, only the last run is applied
+        let mut change = None;
+        self.store.atomic_update(key, |old| {
+            let new = op(old);
+            change = (old != new.as_deref()).then(|| new.clone());
+            new
+        })?;
+        if let Some(new) = change {
+            self.watchers.publish(key, new.as_deref());
+        }
+        Ok(())
+    }
+
+    /// Adds using the inner store, which may merge without reading. The
+    /// new value is only read if someone watches the key.
+    fn counter_add(&self, key: &[u8], delta: &[u8]) -> Result<(), Self::Error> {
+        let _writing = self.lock();
+        self.store.counter_add(key, delta)?;
+        if self.watchers.is_watched(key) {
+            let new = self.store.get(key)?;
+            self.watchers.publish(key, new.as_ref().map(AsRef::as_ref));
+        }
+        Ok(())
+    }
+
+    fn conditional_update(
+        &self,
+        key: &[u8],
+        new: Option<&[u8]>,
+        expected: Option<&[u8]>,
+    ) -> Result<CasOutcome<Self::Bytes>, Self::Error> {
+        let _writing = self.lock();
+        let outcome = self.store.conditional_update(key, new, expected)?;
+        if outcome.is_swapped() && new != expected {
+            self.watchers.publish(key, new);
+        }
+        Ok(outcome)
+    }
+}
+
+impl<BS: byte_store::Ordered> byte_store::Ordered for Watched<BS> {
+    type Range<'a>
+        = BS::Range<'a>
+    where
+        Self: 'a;
+
+    fn get_lt(&self, key: &[u8]) -> Result<Option<KeyValue<Self>>, Self::Error> {
+        self.store.get_lt(key)
+    }
+
+    fn get_gt(&self, key: &[u8]) -> Result<Option<KeyValue<Self>>, Self::Error> {
+        self.store.get_gt(key)
+    }
+
+    fn range(&self, start: Bound<&[u8]>, end: Bound<&[u8]>) -> Self::Range<'_> {
+        self.store.range(start, end)
+    }
+}
+
+impl<BS: ByteStore> byte_store::Watch for Watched<BS> {
+    type Subscriber = BroadcastSubscriber;
+
+    fn watch_prefix(&self, prefix: &[u8]) -> Result<Self::Subscriber, Self::Error> {
+        Ok(self.watchers.subscribe(prefix))
+    }
+}
+
+impl<BS: Flushable> Flushable for Watched<BS> {
+    type Error = BS::Error;
+
+    fn flush(&self) -> Result<(), Self::Error> {
+        self.store.flush()
+    }
+}
+
+/// The writes made in a transaction on a [`Watched`] store, published once
+/// the transaction commits
+type Writes = Rc<RefCell<Vec<(Vec<u8>, Option<Vec<u8>>)>>>;
+
+/// A transaction on a [`Watched`] store, records its writes
+#[derive(Clone)]
+pub struct WatchedTransaction<Tx> {
+    tx: Tx,
+    writes: Writes,
+}
+
+impl<Tx> WatchedTransaction<Tx> {
+    fn record(&self, key: &[u8], value: Option<&[u8]>) {
+        let write = (key.to_vec(), value.map(<[u8]>::to_vec));
+        self.writes.borrow_mut().push(write);
+    }
+}
+
+impl<Tx: ByteStore> ByteStore for WatchedTransaction<Tx> {
+    type Error = Tx::Error;
+    type Bytes = Tx::Bytes;
+    type Codec = Tx::Codec;
+
+    fn get(&self, key: &[u8]) -> Result<Option<Self::Bytes>, Self::Error> {
+        self.tx.get(key)
+    }
+
+    fn remove(&self, key: &[u8]) -> Result<Option<Self::Bytes>, Self::Error> {
+        let existing = self.tx.remove(key)?;
+        if existing.is_some() {
+            self.record(key, None);
+        }
+        Ok(existing)
+    }
+
+    fn insert(&self, key: &[u8], val: &[u8]) -> Result<Option<Self::Bytes>, Self::Error> {
+        let existing = self.tx.insert(key, val)?;
+        self.record(key, Some(val));
+        Ok(existing)
+    }
+
+    fn write_batch(&self, batch: &[(&[u8], Option<&[u8]>)]) -> Result<(), Self::Error> {
+        self.tx.write_batch(batch)?;
+        for (key, val) in batch {
+            self.record(key, *val);
+        }
+        Ok(())
+    }
+}
+
+impl<Tx: byte_store::Atomic> byte_store::Atomic for WatchedTransaction<Tx> {
+    fn atomic_update(
+        &self,
+        key: &[u8],
+        mut op: impl FnMut(Option<&[u8]>) -> Option<Vec<u8>>,
+    ) -> Result<(), Self::Error> {
+        let mut change = None;
+        self.tx.atomic_update(key, |old| {
+            let new = op(old);
+            change = (old != new.as_deref()).then(|| new.clone());
+            new
+        })?;
+        if let Some(new) = change {
+            self.record(key, new.as_deref());
+        }
+        Ok(())
+    }
+
+    fn conditional_update(
+        &self,
+        key: &[u8],
+        new: Option<&[u8]>,
+        expected: Option<&[u8]>,
+    ) -> Result<CasOutcome<Self::Bytes>, Self::Error> {
+        let outcome = self.tx.conditional_update(key, new, expected)?;
+        if outcome.is_swapped() && new != expected {
+            self.record(key, new);
+        }
+        Ok(outcome)
+    }
+}
+
+impl<BS> Transactional for Watched<BS>
+where
+    BS: Transactional + 'static,
+    for<'tx> BS::Tx<'tx>: ByteStore,
+{
+    type Tx<'tx> = WatchedTransaction<BS::Tx<'tx>> where Self: 'tx;
+    type Error = BS::Error;
+
+    fn transaction<T, E>(
+        &self,
+        f: impl Fn(&Self::Tx<'_>) -> Result<T, E>,
+    ) -> Result<T, TransactionError<E, Self::Error>> {
+        let writes = Writes::default();
+        let value = self.store.transaction(|tx| {
+            // a retried transaction starts over
+            writes.borrow_mut().clear();
+            f(&WatchedTransaction {
+                tx: tx.clone(),
+                writes: Rc::clone(&writes),
+            })
+        })?;
+
+        let _writing = self.lock();
+        for (key, value) in writes.take() {
+            self.watchers.publish(&key, value.as_deref());
+        }
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stores::BTreeMap;
+    use crate::traits::byte_store::Watch;
+    use crate::traits::data_store::Change;
+
+    #[test]
+    fn publishes_writes() {
+        let store = Watched::new(BTreeMap::new());
+        let mut changes = store.watch_prefix(&[1]).unwrap();
+        store.insert(&[1, 0], &[5]).unwrap();
+        store.insert(&[2], &[5]).unwrap();
+        store
+            .write_batch(&[(&[1, 1], Some(&[7])), (&[1, 0], None)])
+            .unwrap();
+        drop(store);
+
+        let changes: Vec<_> = changes.by_ref().collect();
+        assert_eq!(
+            changes,
+            vec![
+                Change::Insert {
+                    key: vec![1, 0],
+                    value: vec![5]
+                },
+                Change::Insert {
+                    key: vec![1, 1],
+                    value: vec![7]
+                },
+                Change::Remove { key: vec![1, 0] },
+            ]
+        );
+    }
+
+    #[cfg(feature = "sled")]
+    #[test]
+    fn publishes_committed_transactions() {
+        let db = sled::Config::default().temporary(true).open().unwrap();
+        let store = Watched::new(db.open_tree("watched").unwrap());
+        let mut changes = store.watch_prefix(&[]).unwrap();
+        let aborted = store.transaction(|tx| {
+            tx.insert(&[1], &[1]).unwrap();
+            Err::<(), _>("abort")
+        });
+        assert!(aborted.is_err());
+        store
+            .transaction(|tx| {
+                tx.insert(&[2], &[2]).unwrap();
+                Ok::<_, ()>(())
+            })
+            .unwrap();
+        drop(store);
+
+        let changes: Vec<_> = changes.by_ref().collect();
+        assert_eq!(
+            changes,
+            vec![Change::Insert {
+                key: vec![2],
+                value: vec![2]
+            }]
+        );
+    }
+}
@@ -2,9 +2,17 @@
 
 pub mod byte_store;
 pub mod data_store;
+#[cfg(feature = "async")]
+pub mod async_data_store;
+
+use core::fmt;
 
 pub use byte_store::ByteStore;
 pub use data_store::DataStore;
+#[cfg(feature = "async")]
+pub use async_data_store::{
+    AsyncAtomic, AsyncDataStore, AsyncFlushable, AsyncOrdered, AsyncWatch,
+};
 
 /// Error returned by [`TryExtend::try_extend`].
 #[derive(Debug, thiserror::Error)]
@@ -31,3 +39,67 @@ pub trait TryExtend<T> {
     where
         I: IntoIterator<Item = T>;
 }
+
+/// The result of a `conditional_update`, a compare-and-swap of a single value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CasOutcome<T> {
+    /// The value was the expected value and has been replaced.
+    Swapped,
+    /// The value was not the expected value and nothing changed. `current`
+    /// is the value found instead, `None` if there was none.
+    Mismatch { current: Option<T> },
+}
+
+impl<T> CasOutcome<T> {
+    pub fn is_swapped(&self) -> bool {
+        matches!(self, CasOutcome::Swapped)
+    }
+
+    /// Converts the current value of a mismatch using `f`
+    pub fn try_map<U, E>(self, f: impl FnOnce(T) -> Result<U, E>) -> Result<CasOutcome<U>, E> {
+        Ok(match self {
+            CasOutcome::Swapped => CasOutcome::Swapped,
+            CasOutcome::Mismatch { current } => CasOutcome::Mismatch {
+                current: current.map(f).transpose()?,
+            },
+        })
+    }
+}
+
+/// Error returned by [`Transactional::transaction`].
+#[derive(Debug, thiserror::Error)]
+pub enum TransactionError<E, DbError> {
+    /// The closure returned an error, none of the changes are applied.
+    #[error("the transaction was aborted")]
+    Abort(E),
+    #[error("the database returned an error")]
+    Database(DbError),
+}
+
+/// Enables `transaction` on the generated struct which changes multiple
+/// fields atomically. Implement this to use transactions with `db=trait`.
+pub trait Transactional {
+    /// The store passed to the closure, all changes made to it are part of
+    /// the transaction.
+    type Tx<'tx>: DataStore + Clone
+    where
+        Self: 'tx;
+    type Error: fmt::Debug;
+
+    /// Runs `f` in a transaction. If `f` returns an error the transaction is
+    /// aborted and none of its changes are applied. The store can call `f`
+    /// multiple times if the transaction conflicts with another.
+    fn transaction<T, E>(
+        &self,
+        f: impl Fn(&Self::Tx<'_>) -> Result<T, E>,
+    ) -> Result<T, TransactionError<E, Self::Error>>;
+}
+
+/// Enables `flush` on the generated struct. Implement this to flush a store
+/// used with `db=trait`.
+pub trait Flushable {
+    type Error: fmt::Debug;
+
+    /// Returns once every write made before the call is on disk
+    fn flush(&self) -> Result<(), Self::Error>;
+}
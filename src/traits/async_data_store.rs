@@ -0,0 +1,143 @@
+//! Async counterparts of the [`data_store`](super::data_store) traits, used
+//! by the struct generated with the `async` option.
+//!
+//! Implement these to use a natively async store with `db=trait, async`. To
+//! use a blocking store from async code wrap it in
+//! [`Blocking`](crate::stores::Blocking).
+use core::fmt;
+use core::future::Future;
+
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use super::data_store::{Change, Write};
+use super::CasOutcome;
+
+/// Base trait needed by every async wrapper, the async version of
+/// [`DataStore`](super::DataStore).
+#[async_trait]
+pub trait AsyncDataStore: Send + Sync {
+    type Error: fmt::Debug + Send;
+    async fn get<K, V>(&self, key: &K) -> Result<Option<V>, Self::Error>
+    where
+        K: Serialize + Sync,
+        V: DeserializeOwned + Send;
+    async fn remove<K, V>(&self, key: &K) -> Result<Option<V>, Self::Error>
+    where
+        K: Serialize + Sync,
+        V: DeserializeOwned + Send;
+    async fn insert<K, V>(&self, key: &K, val: &V) -> Result<Option<V>, Self::Error>
+    where
+        K: Serialize + Sync,
+        V: Serialize + DeserializeOwned + Send + Sync;
+    /// Applies both writes, if the store supports it as a single atomic
+    /// write. The default implementation applies `first` then `second`.
+    async fn write_pair<K1, V1, K2, V2>(
+        &self,
+        first: Write<'_, K1, V1>,
+        second: Write<'_, K2, V2>,
+    ) -> Result<(), Self::Error>
+    where
+        K1: Serialize + Sync,
+        V1: Serialize + DeserializeOwned + Send + Sync,
+        K2: Serialize + Sync,
+        V2: Serialize + DeserializeOwned + Send + Sync,
+    {
+        apply(self, first).await?;
+        apply(self, second).await
+    }
+}
+
+async fn apply<DS, K, V>(ds: &DS, write: Write<'_, K, V>) -> Result<(), DS::Error>
+where
+    DS: AsyncDataStore + ?Sized,
+    K: Serialize + Sync,
+    V: Serialize + DeserializeOwned + Send + Sync,
+{
+    match write {
+        Write::Insert(key, val) => ds.insert(key, val).await.map(|_| ()),
+        Write::Remove(key, _) => ds.remove::<K, V>(key).await.map(|_| ()),
+    }
+}
+
+/// Enables `update` and `conditional_update` on the async wrappers, the
+/// async version of [`data_store::Atomic`](super::data_store::Atomic).
+#[async_trait]
+pub trait AsyncAtomic: AsyncDataStore {
+    async fn atomic_update<K, V, F>(&self, key: &K, op: F) -> Result<(), Self::Error>
+    where
+        K: Serialize + Sync,
+        V: Serialize + DeserializeOwned + Send,
+        F: FnMut(V) -> V + Clone + Send + 'static;
+    /// Atomically replaces the value with the result of `op`, `None`
+    /// standing for no value. Returns the value before and after the update.
+    async fn atomic_fetch_update<K, V, F>(
+        &self,
+        key: &K,
+        op: F,
+    ) -> Result<(Option<V>, Option<V>), Self::Error>
+    where
+        K: Serialize + Sync,
+        V: Serialize + DeserializeOwned + Send,
+        F: FnMut(Option<V>) -> Option<V> + Clone + Send + 'static;
+    /// Sets the value to `new` if it is `expected`, `None` stands for no
+    /// value. On error the update is aborted.
+    async fn conditional_update<K, V>(
+        &self,
+        key: &K,
+        new: Option<&V>,
+        expected: Option<&V>,
+    ) -> Result<CasOutcome<V>, Self::Error>
+    where
+        K: Serialize + Sync,
+        V: Serialize + DeserializeOwned + Sync;
+}
+
+/// Enables iterating over a map on the async wrappers, the async version of
+/// [`byte_store::Ordered`](super::byte_store::Ordered).
+#[async_trait]
+pub trait AsyncOrdered: AsyncDataStore {
+    /// The format the returned values are stored in
+    type Codec: crate::codec::Codec;
+    /// Returns the first key value pair with a key starting with `prefix`
+    /// that sorts after `after`, or the first such pair if `after` is `None`.
+    /// Keys and values are passed and returned serialized.
+    async fn next_in_prefix(
+        &self,
+        prefix: &[u8],
+        after: Option<&[u8]>,
+    ) -> Result<Option<(Vec<u8>, Vec<u8>)>, Self::Error>;
+    /// The part after `prefix` of the last key starting with `prefix`, see
+    /// [`data_store::Ordered::last_key_in_prefix`](super::data_store::Ordered::last_key_in_prefix).
+    async fn last_key_in_prefix<Prefix, Suffix>(
+        &self,
+        prefix: &Prefix,
+    ) -> Result<Option<Suffix>, Self::Error>
+    where
+        Prefix: Serialize + Sync,
+        Suffix: DeserializeOwned + Send;
+}
+
+/// Enables `watch` on the async wrappers, the async version of
+/// [`data_store::Watch`](super::data_store::Watch). Subscribing does not
+/// block, waiting for changes happens by polling the subscriber.
+pub trait AsyncWatch: AsyncDataStore {
+    /// The format the values in a [`Change`] are stored in
+    type Codec: crate::codec::Codec;
+    /// Completes with the next change every time it is polled to
+    /// completion, `None` once the store is gone.
+    type Subscriber: Future<Output = Option<Change>> + Unpin + Send;
+    /// Subscribes to the changes to every key starting with `prefix`
+    fn watch_prefix<K: Serialize>(&self, prefix: &K) -> Result<Self::Subscriber, Self::Error>;
+}
+
+/// Enables `flush_async` on the struct generated with the `async` option,
+/// the async version of [`Flushable`](super::Flushable).
+#[async_trait]
+pub trait AsyncFlushable: Send + Sync {
+    type Error: fmt::Debug + Send;
+
+    /// Completes once every write made before the call is on disk
+    async fn flush(&self) -> Result<(), Self::Error>;
+}
@@ -2,12 +2,16 @@
 //! [`data_store`].
 
 use core::fmt;
+use core::future::Future;
+use core::ops::Bound;
 use serde::{de::DeserializeOwned, Serialize};
 use tracing::{instrument, trace};
 
 use super::byte_store;
 use super::data_store;
-use super::data_store::DataStore;
+use super::data_store::{Change, DataStore, Write};
+use super::CasOutcome;
+use crate::codec::{self, Codec};
 use crate::Error;
 
 /// A helper trait, implementing this automatically implements
@@ -15,9 +19,24 @@ use crate::Error;
 pub trait ByteStore {
     type Error: fmt::Debug;
     type Bytes: AsRef<[u8]>;
+    /// The format values are stored in, use [`codec::Bincode`] if you have
+    /// no preference.
+    type Codec: Codec;
     fn get(&self, key: &[u8]) -> Result<Option<Self::Bytes>, Self::Error>;
     fn remove(&self, key: &[u8]) -> Result<Option<Self::Bytes>, Self::Error>;
     fn insert(&self, key: &[u8], val: &[u8]) -> Result<Option<Self::Bytes>, Self::Error>;
+    /// Inserts (`Some`) or removes (`None`) the values for the keys in order. Override
+    /// this if the store can apply all the writes atomically, the default
+    /// implementation applies them one by one.
+    fn write_batch(&self, batch: &[(&[u8], Option<&[u8]>)]) -> Result<(), Self::Error> {
+        for (key, val) in batch {
+            match val {
+                Some(val) => self.insert(key, val)?,
+                None => self.remove(key)?,
+            };
+        }
+        Ok(())
+    }
 }
 
 /// A helper trait, implementing this automatically implements
@@ -28,21 +47,76 @@ pub trait Atomic: ByteStore {
         key: &[u8],
         op: impl FnMut(Option<&[u8]>) -> Option<Vec<u8>>,
     ) -> Result<(), Self::Error>;
+    /// Adds `delta` to the counter at `key`, see
+    /// [`counter_merge`](crate::stores::counter_merge) for how. Used by the
+    /// [`Counter`](crate::wrapper::Counter) wrapper. Stores with a merge
+    /// operator can override this to add without reading the value first.
+    fn counter_add(&self, key: &[u8], delta: &[u8]) -> Result<(), Self::Error> {
+        self.atomic_update(key, |old| crate::stores::counter_merge(key, old, delta))
+    }
+    /// Sets the value at `key` to `new` if it is `expected`. `None` stands
+    /// for no value: expect the key to be absent or remove it.
     fn conditional_update(
         &self,
         key: &[u8],
-        new: &[u8],
-        expected: &[u8],
-    ) -> Result<(), Self::Error>;
+        new: Option<&[u8]>,
+        expected: Option<&[u8]>,
+    ) -> Result<CasOutcome<Self::Bytes>, Self::Error>;
+}
+
+/// A helper trait, implementing this automatically implements
+/// [`data_store::Watch`][super::data_store::Watch]. Stores that can not
+/// watch keys themselves can use [`Broadcast`](crate::stores::Broadcast).
+pub trait Watch: ByteStore {
+    /// see [`data_store::Watch::Subscriber`]
+    type Subscriber: Iterator<Item = Change> + Future<Output = Option<Change>> + Unpin + Send;
+    /// Subscribes to the changes to every key starting with `prefix`
+    fn watch_prefix(&self, prefix: &[u8]) -> Result<Self::Subscriber, Self::Error>;
 }
 
+/// A key and value as stored in the database
+pub type KeyValue<BS> = (<BS as ByteStore>::Bytes, <BS as ByteStore>::Bytes);
+
 /// A helper trait, implementing this automatically implements
 /// [`data_store::Ordered`][super::data_store::Ordered]
 pub trait Ordered: ByteStore {
+    /// iterator over the key value pairs in a range, in ascending key order
+    type Range<'a>: Iterator<Item = Result<KeyValue<Self>, Self::Error>>
+    where
+        Self: 'a;
+
     /// returns the previous key value pair before key
-    fn get_lt(&self, key: &[u8]) -> Result<Option<(Self::Bytes, Self::Bytes)>, Self::Error>;
+    fn get_lt(&self, key: &[u8]) -> Result<Option<KeyValue<Self>>, Self::Error>;
     /// returns the next key value pair after key
-    fn get_gt(&self, key: &[u8]) -> Result<Option<(Self::Bytes, Self::Bytes)>, Self::Error>;
+    fn get_gt(&self, key: &[u8]) -> Result<Option<KeyValue<Self>>, Self::Error>;
+    /// iterates over the key value pairs with a key between start and end
+    fn range(&self, start: Bound<&[u8]>, end: Bound<&[u8]>) -> Self::Range<'_>;
+    /// iterates over the key value pairs with a key starting with prefix
+    fn scan_prefix(&self, prefix: &[u8]) -> Self::Range<'_> {
+        let end = prefix_end(prefix);
+        let end = match &end {
+            Some(end) => Bound::Excluded(end.as_slice()),
+            None => Bound::Unbounded,
+        };
+        self.range(Bound::Included(prefix), end)
+    }
+    /// returns the last key value pair with a key starting with prefix
+    fn last_in_prefix(&self, prefix: &[u8]) -> Result<Option<KeyValue<Self>>, Self::Error> {
+        let last = match prefix_end(prefix) {
+            Some(end) => self.get_lt(&end)?,
+            None => self.scan_prefix(prefix).last().transpose()?,
+        };
+        Ok(last.filter(|(key, _)| key.as_ref().starts_with(prefix)))
+    }
+}
+
+/// The smallest key larger then every key starting with prefix. There is
+/// no such key if the prefix is empty or only consists of `u8::MAX`.
+pub(crate) fn prefix_end(prefix: &[u8]) -> Option<Vec<u8>> {
+    let last = prefix.iter().rposition(|byte| *byte != u8::MAX)?;
+    let mut end = prefix[..=last].to_vec();
+    end[last] += 1;
+    Some(end)
 }
 
 impl<E, B, BS> DataStore for BS
@@ -59,13 +133,13 @@ where
         K: Serialize,
         V: DeserializeOwned,
     {
-        let key = bincode::serialize(key).map_err(Error::SerializingKey)?;
+        let key = codec::serialize_key(key).map_err(Error::SerializingKey)?;
         trace!("getting value for key: {key:?}");
         let val = BS::get(self, &key)?;
         Ok(match val {
             Some(bytes) => {
                 trace!("bytes of value: {:?}", bytes.as_ref());
-                let val = bincode::deserialize(bytes.as_ref()).map_err(Error::DeSerializingVal)?;
+                let val = BS::Codec::deserialize(bytes.as_ref()).map_err(Error::DeSerializingVal)?;
                 Some(val)
             }
             None => None,
@@ -78,13 +152,13 @@ where
         K: Serialize,
         V: DeserializeOwned,
     {
-        let key = bincode::serialize(key).map_err(Error::SerializingKey)?;
+        let key = codec::serialize_key(key).map_err(Error::SerializingKey)?;
         trace!("removing at key: {key:?}");
         let val = BS::remove(self, &key)?;
         Ok(match val {
             Some(bytes) => {
                 trace!("bytes of current value: {:?}", bytes.as_ref());
-                let val = bincode::deserialize(bytes.as_ref()).map_err(Error::DeSerializingVal)?;
+                let val = BS::Codec::deserialize(bytes.as_ref()).map_err(Error::DeSerializingVal)?;
                 Some(val)
             }
             None => None,
@@ -97,19 +171,160 @@ where
         K: Serialize,
         V: Serialize + DeserializeOwned,
     {
-        let key = bincode::serialize(key).map_err(Error::SerializingKey)?;
-        let val = bincode::serialize(val).map_err(Error::SerializingValue)?;
+        let key = codec::serialize_key(key).map_err(Error::SerializingKey)?;
+        let val = BS::Codec::serialize(val).map_err(Error::SerializingValue)?;
         trace!("inserting key: {key:?}, val: {val:?}");
         let existing = BS::insert(self, &key, &val)?;
         Ok(match existing {
             Some(bytes) => {
                 trace!("bytes of previous value: {:?}", bytes.as_ref());
                 trace!("deserializing to: {}", std::any::type_name::<V>());
-                Some(bincode::deserialize(bytes.as_ref()).map_err(Error::DeSerializingVal)?)
+                Some(BS::Codec::deserialize(bytes.as_ref()).map_err(Error::DeSerializingVal)?)
             }
             None => None,
         })
     }
+
+    #[instrument(skip_all, level = "trace", err)]
+    fn write_pair<K1, V1, K2, V2>(
+        &self,
+        first: Write<'_, K1, V1>,
+        second: Write<'_, K2, V2>,
+    ) -> Result<(), Self::Error>
+    where
+        K1: Serialize,
+        V1: Serialize + DeserializeOwned,
+        K2: Serialize,
+        V2: Serialize + DeserializeOwned,
+    {
+        let first = serialize_write::<BS::Codec, _, _, _>(first)?;
+        let second = serialize_write::<BS::Codec, _, _, _>(second)?;
+        trace!("writing batch: {first:?}, {second:?}");
+        let batch = [
+            (first.0.as_slice(), first.1.as_deref()),
+            (second.0.as_slice(), second.1.as_deref()),
+        ];
+        BS::write_batch(self, &batch)?;
+        Ok(())
+    }
+}
+
+pub(crate) type SerializedWrite = (Vec<u8>, Option<Vec<u8>>);
+
+pub(crate) fn serialize_write<C, K, V, E>(
+    write: Write<'_, K, V>,
+) -> Result<SerializedWrite, Error<E>>
+where
+    C: Codec,
+    K: Serialize,
+    V: Serialize + DeserializeOwned,
+    E: fmt::Debug,
+{
+    let key = codec::serialize_key(write.key()).map_err(Error::SerializingKey)?;
+    let val = match write {
+        Write::Insert(_, val) => Some(C::serialize(val).map_err(Error::SerializingValue)?),
+        Write::Remove(..) => None,
+    };
+    Ok((key, val))
+}
+
+/// Serializes the new or expected value of a conditional update, `None`
+/// stands for no value
+pub(crate) fn serialize_optional<C: Codec, V: Serialize>(
+    val: Option<&V>,
+) -> Result<Option<Vec<u8>>, codec::Error> {
+    val.map(C::serialize).transpose()
+}
+
+/// Atomically applies op to the deserialized value at the serialized key
+pub(crate) fn atomic_update_bytes<BS, V>(
+    bs: &BS,
+    key: &[u8],
+    mut op: impl FnMut(V) -> V,
+) -> Result<(), Error<BS::Error>>
+where
+    BS: Atomic,
+    V: Serialize + DeserializeOwned,
+{
+    let mut res = Ok(());
+    let bytes_op = |old: Option<&[u8]>| -> Option<Vec<u8>> {
+        if let Some(old) = old {
+            trace!("bytes of current value: {old:?}");
+            match BS::Codec::deserialize(old) {
+                Err(e) => {
+                    res = Err(Error::DeSerializingVal(e));
+                    Some(old.to_vec())
+                }
+                Ok(val) => {
+                    let new = op(val);
+                    match BS::Codec::serialize(&new) {
+                        Err(e) => {
+                            res = Err(Error::DeSerializingVal(e));
+                            Some(old.to_vec())
+                        }
+                        Ok(new_bytes) => Some(new_bytes),
+                    }
+                }
+            }
+        } else {
+            None
+        }
+    };
+    BS::atomic_update(bs, key, bytes_op)?;
+    res
+}
+
+/// Atomically applies op to the deserialized value at the serialized key,
+/// `None` standing for no value. Returns the bytes of the value before and
+/// after the update.
+pub(crate) fn fetch_update_bytes<BS, V>(
+    bs: &BS,
+    key: &[u8],
+    mut op: impl FnMut(Option<V>) -> Option<V>,
+) -> Result<FetchedBytes, Error<BS::Error>>
+where
+    BS: Atomic,
+    V: Serialize + DeserializeOwned,
+{
+    let mut res = Ok((None, None));
+    let bytes_op = |old: Option<&[u8]>| -> Option<Vec<u8>> {
+        trace!("bytes of current value: {old:?}");
+        let new = match old.map(BS::Codec::deserialize).transpose() {
+            Ok(val) => op(val),
+            Err(e) => {
+                res = Err(Error::DeSerializingVal(e));
+                return old.map(<[u8]>::to_vec);
+            }
+        };
+        match new.as_ref().map(BS::Codec::serialize).transpose() {
+            Ok(new_bytes) => {
+                res = Ok((old.map(<[u8]>::to_vec), new_bytes.clone()));
+                new_bytes
+            }
+            Err(e) => {
+                res = Err(Error::SerializingValue(e));
+                old.map(<[u8]>::to_vec)
+            }
+        }
+    };
+    BS::atomic_update(bs, key, bytes_op)?;
+    res
+}
+
+/// The bytes of a value before and after [`fetch_update_bytes`]
+pub(crate) type FetchedBytes = (Option<Vec<u8>>, Option<Vec<u8>>);
+
+/// Deserializes the values returned by [`fetch_update_bytes`]
+pub(crate) fn deserialize_fetched<C: Codec, V: DeserializeOwned, E: fmt::Debug>(
+    (old, new): FetchedBytes,
+) -> Result<(Option<V>, Option<V>), Error<E>> {
+    let deserialize = |bytes: Option<Vec<u8>>| {
+        bytes
+            .map(|bytes| C::deserialize(&bytes))
+            .transpose()
+            .map_err(Error::DeSerializingVal)
+    };
+    Ok((deserialize(old)?, deserialize(new)?))
 }
 
 impl<E, B, BS> data_store::Atomic for BS
@@ -122,52 +337,49 @@ where
     fn atomic_update<K, V>(
         &self,
         key: &K,
-        mut op: impl FnMut(V) -> V + Clone,
+        op: impl FnMut(V) -> V + Clone,
     ) -> Result<(), Self::Error>
     where
         K: Serialize,
         V: Serialize + DeserializeOwned,
     {
-        let key = bincode::serialize(key).map_err(Error::SerializingKey)?;
-        let mut res = Ok(());
-        let bytes_op = |old: Option<&[u8]>| -> Option<Vec<u8>> {
-            if let Some(old) = old {
-                trace!("bytes of current value: {old:?}");
-                match bincode::deserialize(old) {
-                    Err(e) => {
-                        res = Err(Error::DeSerializingVal(e));
-                        Some(old.to_vec())
-                    }
-                    Ok(val) => {
-                        let new = op(val);
-                        match bincode::serialize(&new) {
-                            Err(e) => {
-                                res = Err(Error::DeSerializingVal(e));
-                                Some(old.to_vec())
-                            }
-                            Ok(new_bytes) => Some(new_bytes),
-                        }
-                    }
-                }
-            } else {
-                None
-            }
-        };
-        BS::atomic_update(self, &key, bytes_op)?;
-        res
+        let key = codec::serialize_key(key).map_err(Error::SerializingKey)?;
+        atomic_update_bytes(self, &key, op)
     }
 
     #[instrument(skip_all, level = "trace", err)]
-    fn conditional_update<K, V>(&self, key: &K, new: &V, expected: &V) -> Result<(), Self::Error>
+    fn atomic_fetch_update<K, V>(
+        &self,
+        key: &K,
+        op: impl FnMut(Option<V>) -> Option<V> + Clone,
+    ) -> Result<(Option<V>, Option<V>), Self::Error>
     where
         K: Serialize,
         V: Serialize + DeserializeOwned,
     {
-        let key = bincode::serialize(key).map_err(Error::SerializingKey)?;
-        let new = bincode::serialize(new).map_err(Error::SerializingValue)?;
-        let expected = bincode::serialize(expected).map_err(Error::SerializingValue)?;
-        BS::conditional_update(self, &key, &new, &expected)?;
-        Ok(())
+        let key = codec::serialize_key(key).map_err(Error::SerializingKey)?;
+        let fetched = fetch_update_bytes(self, &key, op)?;
+        deserialize_fetched::<BS::Codec, _, _>(fetched)
+    }
+
+    #[instrument(skip_all, level = "trace", err)]
+    fn conditional_update<K, V>(
+        &self,
+        key: &K,
+        new: Option<&V>,
+        expected: Option<&V>,
+    ) -> Result<CasOutcome<V>, Self::Error>
+    where
+        K: Serialize,
+        V: Serialize + DeserializeOwned,
+    {
+        let key = codec::serialize_key(key).map_err(Error::SerializingKey)?;
+        let new = serialize_optional::<BS::Codec, _>(new).map_err(Error::SerializingValue)?;
+        let expected =
+            serialize_optional::<BS::Codec, _>(expected).map_err(Error::SerializingValue)?;
+        BS::conditional_update(self, &key, new.as_deref(), expected.as_deref())?
+            .try_map(|current| BS::Codec::deserialize(current.as_ref()))
+            .map_err(Error::DeSerializingVal)
     }
 }
 
@@ -187,7 +399,7 @@ where
         OutKey: Serialize + DeserializeOwned,
         Value: Serialize + DeserializeOwned,
     {
-        let key = bincode::serialize(key).map_err(Error::SerializingKey)?;
+        let key = codec::serialize_key(key).map_err(Error::SerializingKey)?;
         trace!("getting less then key: {key:?}");
         Ok(match byte_store::Ordered::get_lt(self, &key)? {
             None => None,
@@ -199,8 +411,8 @@ where
                     std::any::type_name::<dyn Value>(),
                     val.as_ref()
                 );
-                let key = bincode::deserialize(key.as_ref()).map_err(Error::DeSerializingKey)?;
-                let val = bincode::deserialize(val.as_ref()).map_err(Error::DeSerializingVal)?;
+                let key = codec::deserialize_key(key.as_ref()).map_err(Error::DeSerializingKey)?;
+                let val = BS::Codec::deserialize(val.as_ref()).map_err(Error::DeSerializingVal)?;
                 Some((key, val))
             }
         })
@@ -216,7 +428,7 @@ where
         OutKey: Serialize + DeserializeOwned,
         Value: Serialize + DeserializeOwned,
     {
-        let key = bincode::serialize(key).map_err(Error::SerializingKey)?;
+        let key = codec::serialize_key(key).map_err(Error::SerializingKey)?;
         trace!("getting greater then key: {key:?}");
         Ok(match byte_store::Ordered::get_gt(self, &key)? {
             None => None,
@@ -228,10 +440,60 @@ where
                     std::any::type_name::<dyn Value>(),
                     val.as_ref()
                 );
-                let key = bincode::deserialize(key.as_ref()).map_err(Error::DeSerializingKey)?;
-                let val = bincode::deserialize(val.as_ref()).map_err(Error::DeSerializingVal)?;
+                let key = codec::deserialize_key(key.as_ref()).map_err(Error::DeSerializingKey)?;
+                let val = BS::Codec::deserialize(val.as_ref()).map_err(Error::DeSerializingVal)?;
                 Some((key, val))
             }
         })
     }
+
+    #[instrument(skip_all, level = "trace", err)]
+    fn last_key_in_prefix<Prefix, Suffix>(
+        &self,
+        prefix: &Prefix,
+    ) -> Result<Option<Suffix>, Self::Error>
+    where
+        Prefix: Serialize,
+        Suffix: DeserializeOwned,
+    {
+        let prefix = codec::serialize_key(prefix).map_err(Error::SerializingKey)?;
+        trace!("getting last key with prefix: {prefix:?}");
+        Ok(match byte_store::Ordered::last_in_prefix(self, &prefix)? {
+            None => None,
+            Some((key, _)) => {
+                let suffix = &key.as_ref()[prefix.len()..];
+                Some(codec::deserialize_key(suffix).map_err(Error::DeSerializingKey)?)
+            }
+        })
+    }
+}
+
+impl<E, B, BS> data_store::Watch for BS
+where
+    E: fmt::Debug,
+    B: AsRef<[u8]>,
+    BS: Watch<Error = E, Bytes = B>,
+{
+    type Codec = BS::Codec;
+    type Subscriber = BS::Subscriber;
+
+    #[instrument(skip_all, level = "trace", err)]
+    fn watch_prefix<K: Serialize>(&self, prefix: &K) -> Result<Self::Subscriber, Self::Error> {
+        let prefix = codec::serialize_key(prefix).map_err(Error::SerializingKey)?;
+        trace!("watching prefix: {prefix:?}");
+        Ok(Watch::watch_prefix(self, &prefix)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::prefix_end;
+
+    #[test]
+    fn end_of_prefix() {
+        assert_eq!(prefix_end(&[1]), Some(vec![2]));
+        assert_eq!(prefix_end(&[1, 255]), Some(vec![2]));
+        assert_eq!(prefix_end(&[255, 255]), None);
+        assert_eq!(prefix_end(&[]), None);
+    }
 }
@@ -1,9 +1,13 @@
 //! The traits used by the wrapper to operate on the database.
 use core::fmt;
+use core::future::Future;
+use core::marker::PhantomData;
 
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
+use super::CasOutcome;
+
 /// Base trait needed by every wrapper. It is usually more convenient to implement
 /// [`ByteStore`][super::byte_store::ByteStore] instead.
 pub trait DataStore {
@@ -20,6 +24,52 @@ pub trait DataStore {
     where
         K: Serialize,
         V: Serialize + DeserializeOwned;
+    /// Applies both writes, if the store supports it as a single atomic
+    /// write. The default implementation applies `first` then `second`.
+    fn write_pair<K1, V1, K2, V2>(
+        &self,
+        first: Write<'_, K1, V1>,
+        second: Write<'_, K2, V2>,
+    ) -> Result<(), Self::Error>
+    where
+        K1: Serialize,
+        V1: Serialize + DeserializeOwned,
+        K2: Serialize,
+        V2: Serialize + DeserializeOwned,
+    {
+        first.apply(self)?;
+        second.apply(self)
+    }
+}
+
+/// A single change to the database, see [`DataStore::write_pair`]
+pub enum Write<'a, K, V> {
+    Insert(&'a K, &'a V),
+    /// The value type is needed by stores that deserialize the removed value
+    Remove(&'a K, PhantomData<V>),
+}
+
+impl<'a, K, V> Write<'a, K, V>
+where
+    K: Serialize,
+    V: Serialize + DeserializeOwned,
+{
+    pub fn remove(key: &'a K) -> Self {
+        Self::Remove(key, PhantomData)
+    }
+
+    pub fn key(&self) -> &'a K {
+        match self {
+            Write::Insert(key, _) | Write::Remove(key, _) => key,
+        }
+    }
+
+    fn apply<DS: DataStore + ?Sized>(self, ds: &DS) -> Result<(), DS::Error> {
+        match self {
+            Write::Insert(key, val) => ds.insert(key, val).map(|_| ()),
+            Write::Remove(key, _) => ds.remove::<K, V>(key).map(|_| ()),
+        }
+    }
 }
 
 /// This trait enables wrapper to provide `update` and `conditional` update.
@@ -34,11 +84,57 @@ pub trait Atomic: DataStore {
     where
         K: Serialize,
         V: Serialize + DeserializeOwned;
-    /// on error the update is aborted
-    fn conditional_update<K, V>(&self, key: &K, new: &V, expected: &V) -> Result<(), Self::Error>
+    /// Atomically replaces the value with the result of `op`, `None`
+    /// standing for no value. `op` can run more than once, only the last
+    /// run is applied. Returns the value before and after the update.
+    fn atomic_fetch_update<K, V>(
+        &self,
+        key: &K,
+        op: impl FnMut(Option<V>) -> Option<V> + Clone,
+    ) -> Result<(Option<V>, Option<V>), Self::Error>
     where
         K: Serialize,
         V: Serialize + DeserializeOwned;
+    /// Sets the value to `new` if it is `expected`, `None` stands for no
+    /// value. On error the update is aborted.
+    fn conditional_update<K, V>(
+        &self,
+        key: &K,
+        new: Option<&V>,
+        expected: Option<&V>,
+    ) -> Result<CasOutcome<V>, Self::Error>
+    where
+        K: Serialize,
+        V: Serialize + DeserializeOwned;
+}
+
+/// A change to a single key as it is stored in the database, see [`Watch`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    Insert { key: Vec<u8>, value: Vec<u8> },
+    Remove { key: Vec<u8> },
+}
+
+impl Change {
+    pub fn key(&self) -> &[u8] {
+        match self {
+            Change::Insert { key, .. } | Change::Remove { key } => key,
+        }
+    }
+}
+
+/// This trait enables `watch` on the wrappers. It is usually more convenient
+/// to implement [`byte_store::Watch`][super::byte_store::Watch] instead.
+pub trait Watch: DataStore {
+    /// The format the values in a [`Change`] are stored in
+    type Codec: crate::codec::Codec;
+    /// Returns the changes in the order they are made. As an iterator it
+    /// blocks until the next change, as a future it completes with the next
+    /// change every time it is polled to completion. Both return `None` once
+    /// the store is gone.
+    type Subscriber: Iterator<Item = Change> + Future<Output = Option<Change>> + Unpin + Send;
+    /// Subscribes to the changes to every key starting with `prefix`
+    fn watch_prefix<K: Serialize>(&self, prefix: &K) -> Result<Self::Subscriber, Self::Error>;
 }
 
 /// This trait needed for the Vec wrapper. It is usually more convenient to implement
@@ -64,4 +160,13 @@ pub trait Ordered: DataStore {
         InKey: Serialize,
         OutKey: Serialize + DeserializeOwned,
         Value: Serialize + DeserializeOwned;
+    /// The part after `prefix` of the last key starting with `prefix`. Only
+    /// the key is deserialized, the value may be of any type.
+    fn last_key_in_prefix<Prefix, Suffix>(
+        &self,
+        prefix: &Prefix,
+    ) -> Result<Option<Suffix>, Self::Error>
+    where
+        Prefix: Serialize,
+        Suffix: DeserializeOwned;
 }
@@ -1,25 +1,53 @@
 //! Wrapper are how *dbstruct* reads and writes to the database. They handle serializing and
 //! deserializing, provide the API and determine how to handle missing data. 
 //!
-//! There are two types of wrapper: 
+//! The wrappers fall into these groups: 
 //! - Those describing how to handle missing values. These at the minimum offer you `get` and
-//! `set`. Depending on the [`traits`][crate::traits::data_store] the database you chose
-//! implements they may also support `update` and `conditional_update`.
+//!   `set`. Depending on the [`traits`][crate::traits::data_store] the database you chose
+//!   implements they may also support `update` and `conditional_update`. Updates start from the
+//!   default for missing values.
 //! - Wrapper that mimic the api of a standard library type.
+//! - [`Counter`], a number changed by adding to it.
 
 mod option;
 mod default_val;
 mod default_trait;
+mod counter;
 mod vec;
+mod vec_deque;
 mod map;
+mod btreemap;
+mod set;
+mod prefix;
+mod watch;
+#[cfg(feature = "async")]
+pub mod asynchronous;
 
 pub use option::OptionValue;
 pub use default_val::DefaultValue;
 pub use default_trait::DefaultTrait;
-pub use map::Map;
+pub use counter::{Counter, Integer};
+pub use map::{Entry, Map, OccupiedEntry, VacantEntry};
+pub use btreemap::BTreeMap;
+pub use set::Set;
 pub use vec::Vec;
+pub use vec_deque::VecDeque;
+pub use watch::{MapEvent, MapWatcher, SetEvent, ValueEvent, VecDequeEvent, VecEvent, Watcher};
 
 // we need to expose prefixed for the generated 
 // code to be able to access it
 #[doc(hidden)]
-pub use vec::Prefixed;
+pub use vec::{stored_len, Prefixed};
+#[doc(hidden)]
+pub use prefix::Prefix;
+#[doc(hidden)]
+pub use vec_deque::{stored_ends, Ends};
+
+/// Turns an update of a value into one of a possibly missing value, a
+/// missing value is replaced by `default` before `op` is applied
+fn seeded<T>(
+    default: impl Fn() -> T + Clone,
+    mut op: impl FnMut(T) -> T + Clone,
+) -> impl FnMut(Option<T>) -> Option<T> + Clone {
+    move |old| Some(op(old.unwrap_or_else(&default)))
+}
@@ -0,0 +1,56 @@
+//! Async versions of the wrappers, returned by the struct generated with the
+//! `async` option. They work on an [`AsyncDataStore`][crate::traits::AsyncDataStore]
+//! and mirror the API of the blocking wrappers with every method being an
+//! `async fn`.
+//!
+//! ```rust
+//! #[dbstruct::dbstruct(db=sled, async)]
+//! pub struct Test {
+//!     #[dbstruct(Default)]
+//!     the_awnser: u8,
+//!     primes: Vec<u32>,
+//! }
+//!
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() {
+//! # let dir = tempdir::TempDir::new("dbstruct_examples").unwrap();
+//! # let path = dir.path().join("async");
+//! let db = Test::new(&path).await.unwrap();
+//! db.the_awnser().set(&42).await.unwrap();
+//! assert_eq!(42u8, db.the_awnser().get().await.unwrap());
+//!
+//! db.primes().push(&2).await.unwrap();
+//! assert_eq!(Some(2), db.primes().pop().await.unwrap());
+//! # }
+//! ```
+//!
+//! The [`Watcher`](crate::wrapper::Watcher) returned by `watch` is used as a
+//! [`Stream`](futures_core::Stream) of changes.
+//!
+//! A [`Map`] is iterated as a [`Stream`](futures_core::Stream), which
+//! together with `len`, `retain` and `clear` needs a store implementing
+//! [`AsyncOrdered`](crate::traits::AsyncOrdered). Iterating over a
+//! [`BTreeMap`] or [`Set`] is not available on the async wrappers yet.
+
+mod btreemap;
+mod default_trait;
+mod default_val;
+mod map;
+mod option;
+mod set;
+mod vec;
+mod vec_deque;
+
+pub use btreemap::BTreeMap;
+pub use default_trait::DefaultTrait;
+pub use default_val::DefaultValue;
+pub use map::{Entry, Map, OccupiedEntry, VacantEntry};
+pub use option::OptionValue;
+pub use set::Set;
+pub use vec::Vec;
+pub use vec_deque::VecDeque;
+
+#[doc(hidden)]
+pub use vec::{stored_len, Len};
+#[doc(hidden)]
+pub use vec_deque::stored_ends;
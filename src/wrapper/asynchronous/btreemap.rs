@@ -0,0 +1,111 @@
+use core::fmt;
+use std::marker::PhantomData;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tracing::instrument;
+
+use crate::traits::{AsyncDataStore, AsyncWatch};
+use crate::wrapper::map::Prefixed;
+use crate::wrapper::Prefix;
+use crate::wrapper::watch::{MapDecoder, MapWatcher, SetDecoder, SetEvent, Watcher};
+use crate::Error;
+
+/// mimics the API of [`BTreeMap`][std::collections::BTreeMap]
+pub struct BTreeMap<Key, Value, DS>
+where
+    Key: Serialize,
+    Value: Serialize + DeserializeOwned,
+    DS: AsyncDataStore,
+{
+    phantom_key: PhantomData<Key>,
+    phantom_val: PhantomData<Value>,
+    tree: DS,
+    prefix: Prefix,
+}
+
+impl<Key, Value, E, DS> BTreeMap<Key, Value, DS>
+where
+    E: fmt::Debug,
+    Key: Serialize + DeserializeOwned + Sync,
+    Value: Serialize + DeserializeOwned + Send + Sync,
+    DS: AsyncDataStore<Error = E>,
+{
+    #[doc(hidden)]
+    #[instrument(skip(tree), level = "debug")]
+    pub fn new(tree: DS, prefix: Prefix) -> Self {
+        Self {
+            phantom_key: PhantomData,
+            phantom_val: PhantomData,
+            tree,
+            prefix,
+        }
+    }
+
+    /// returns existing value if any was set
+    #[instrument(skip_all, level = "debug")]
+    pub async fn insert(&self, key: &Key, value: &Value) -> Result<Option<Value>, Error<E>> {
+        let key = Prefixed::new(self.prefix.clone(), key);
+        let existing = self.tree.insert(&key, value).await?;
+        Ok(existing)
+    }
+
+    #[instrument(skip_all, level = "debug")]
+    pub async fn get(&self, key: &Key) -> Result<Option<Value>, Error<E>> {
+        let key = Prefixed::new(self.prefix.clone(), key);
+        let value = self.tree.get(&key).await?;
+        Ok(value)
+    }
+
+    /// returns the value at the key if it was in the map
+    #[instrument(skip_all, level = "debug")]
+    pub async fn remove(&self, key: &Key) -> Result<Option<Value>, Error<E>> {
+        let key = Prefixed::new(self.prefix.clone(), key);
+        let existing = self.tree.remove(&key).await?;
+        Ok(existing)
+    }
+
+    #[instrument(skip_all, level = "debug")]
+    pub async fn contains_key(&self, key: &Key) -> Result<bool, Error<E>> {
+        Ok(self.get(key).await?.is_some())
+    }
+
+    /// returns the value at the key, if there is none inserts and
+    /// returns the value returned by `f`.
+    #[instrument(skip_all, level = "debug")]
+    pub async fn get_or_insert_with(
+        &self,
+        key: &Key,
+        f: impl FnOnce() -> Value,
+    ) -> Result<Value, Error<E>> {
+        if let Some(value) = self.get(key).await? {
+            return Ok(value);
+        }
+        let value = f();
+        self.insert(key, &value).await?;
+        Ok(value)
+    }
+}
+
+impl<Key, Value, E, DS> BTreeMap<Key, Value, DS>
+where
+    E: fmt::Debug,
+    Key: Serialize + DeserializeOwned + Sync + 'static,
+    Value: Serialize + DeserializeOwned + Send + Sync + 'static,
+    DS: AsyncWatch<Error = E>,
+{
+    /// subscribes to the changes made to the map from here on, see
+    /// [`Watcher`]
+    #[instrument(skip_all, level = "debug")]
+    pub async fn watch(&self) -> Result<MapWatcher<Key, Value, DS::Subscriber, E>, Error<E>> {
+        let subscriber = self.tree.watch_prefix(&self.prefix)?;
+        let decoder = MapDecoder::new::<DS::Codec>(&self.prefix);
+        Ok(Watcher::new(subscriber, decoder))
+    }
+
+    /// like [`watch`](Self::watch) without the values, for [`Set`](super::Set)
+    pub(crate) fn watch_keys(&self) -> Result<Watcher<SetEvent<Key>, DS::Subscriber, E>, Error<E>> {
+        let subscriber = self.tree.watch_prefix(&self.prefix)?;
+        Ok(Watcher::new(subscriber, SetDecoder::new(&self.prefix)))
+    }
+}
@@ -0,0 +1,118 @@
+use core::fmt;
+use std::marker::PhantomData;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::traits::{AsyncAtomic, AsyncDataStore, AsyncWatch};
+use crate::wrapper::Prefix;
+use crate::wrapper::watch::{ValueDecoder, ValueEvent, Watcher};
+use crate::Error;
+
+/// handles missing values by generating a replacement using the types [`Default`] implementation
+pub struct DefaultTrait<T, DS>
+where
+    T: Serialize + DeserializeOwned + Default,
+    DS: AsyncDataStore,
+{
+    phantom: PhantomData<T>,
+    ds: DS,
+    key: Prefix,
+}
+
+impl<T, E, DS> DefaultTrait<T, DS>
+where
+    E: fmt::Debug,
+    T: Serialize + DeserializeOwned + Default + Send + Sync,
+    DS: AsyncDataStore<Error = E>,
+{
+    #[doc(hidden)]
+    pub fn new(ds: DS, key: Prefix) -> Self {
+        Self {
+            phantom: PhantomData,
+            ds,
+            key,
+        }
+    }
+
+    pub async fn set(&mut self, value: &T) -> Result<(), Error<E>> {
+        self.ds.insert(&self.key, value).await?;
+        Ok(())
+    }
+
+    pub async fn get(&self) -> Result<T, Error<E>> {
+        Ok(self.ds.get(&self.key).await?.unwrap_or_default())
+    }
+
+    /// Removes the value returning it, the default if there is none
+    pub async fn take(&mut self) -> Result<T, Error<E>> {
+        let old = self.ds.remove(&self.key).await?;
+        Ok(old.unwrap_or_default())
+    }
+
+    /// Removes the value so `get` falls back to the default again
+    pub async fn clear(&mut self) -> Result<(), Error<E>> {
+        self.ds.remove::<_, T>(&self.key).await?;
+        Ok(())
+    }
+
+    /// Sets the value returning the previous one, the default if there is none
+    pub async fn replace(&mut self, value: &T) -> Result<T, Error<E>> {
+        let old = self.ds.insert(&self.key, value).await?;
+        Ok(old.unwrap_or_default())
+    }
+
+    /// Whether a value is stored
+    pub async fn is_set(&self) -> Result<bool, Error<E>> {
+        Ok(self.ds.get::<_, T>(&self.key).await?.is_some())
+    }
+}
+
+impl<T, E, DS> DefaultTrait<T, DS>
+where
+    E: fmt::Debug,
+    T: Serialize + DeserializeOwned + Default + Send + Sync + 'static,
+    DS: AsyncAtomic<Error = E>,
+{
+    /// Atomically applies `op` to the value, a missing value is replaced
+    /// by [`Default::default`] first. `op` can run more than once.
+    pub async fn update(
+        &self,
+        op: impl FnMut(T) -> T + Clone + Send + 'static,
+    ) -> Result<(), Error<E>> {
+        self.update_and_fetch(op).await.map(|_| ())
+    }
+    /// Like [`update`](Self::update), returns the value before the update
+    pub async fn fetch_update(
+        &self,
+        op: impl FnMut(T) -> T + Clone + Send + 'static,
+    ) -> Result<T, Error<E>> {
+        let op = crate::wrapper::seeded(T::default, op);
+        let (old, _) = self.ds.atomic_fetch_update(&self.key, op).await?;
+        Ok(old.unwrap_or_default())
+    }
+    /// Like [`update`](Self::update), returns the value after the update
+    pub async fn update_and_fetch(
+        &self,
+        op: impl FnMut(T) -> T + Clone + Send + 'static,
+    ) -> Result<T, Error<E>> {
+        let op = crate::wrapper::seeded(T::default, op);
+        let (_, new) = self.ds.atomic_fetch_update(&self.key, op).await?;
+        Ok(new.expect("seeded op always returns a value"))
+    }
+}
+
+impl<T, E, DS> DefaultTrait<T, DS>
+where
+    E: fmt::Debug,
+    T: Serialize + DeserializeOwned + Default + Send + Sync + 'static,
+    DS: AsyncWatch<Error = E>,
+{
+    /// subscribes to the changes made to the value from here on, see
+    /// [`Watcher`]
+    pub async fn watch(&self) -> Result<Watcher<ValueEvent<T>, DS::Subscriber, E>, Error<E>> {
+        let subscriber = self.ds.watch_prefix(&self.key)?;
+        let decoder = ValueDecoder::new::<DS::Codec>(&self.key);
+        Ok(Watcher::new(subscriber, decoder))
+    }
+}
@@ -0,0 +1,141 @@
+use core::fmt;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::traits::{AsyncAtomic, AsyncDataStore, AsyncWatch, CasOutcome};
+use crate::wrapper::Prefix;
+use crate::wrapper::watch::{ValueDecoder, ValueEvent, Watcher};
+use crate::Error;
+
+/// handles missing values by generating a replacement from an expression.
+pub struct DefaultValue<T, DS>
+where
+    T: Serialize + DeserializeOwned + Clone,
+    DS: AsyncDataStore,
+{
+    default_value: T,
+    ds: DS,
+    key: Prefix,
+}
+
+impl<T, E, DS> DefaultValue<T, DS>
+where
+    E: fmt::Debug,
+    T: Serialize + DeserializeOwned + Clone + Send + Sync,
+    DS: AsyncDataStore<Error = E>,
+{
+    #[doc(hidden)]
+    pub fn new(ds: DS, key: Prefix, default_value: T) -> Self {
+        Self {
+            default_value,
+            ds,
+            key,
+        }
+    }
+
+    pub async fn set(&mut self, value: &T) -> Result<(), Error<E>> {
+        self.ds.insert(&self.key, value).await?;
+        Ok(())
+    }
+
+    pub async fn get(&self) -> Result<T, Error<E>> {
+        Ok(self
+            .ds
+            .get(&self.key)
+            .await?
+            .unwrap_or_else(|| self.default_value.clone()))
+    }
+
+    /// Removes the value returning it, the default if there is none
+    pub async fn take(&mut self) -> Result<T, Error<E>> {
+        let old = self.ds.remove(&self.key).await?;
+        Ok(old.unwrap_or_else(|| self.default_value.clone()))
+    }
+
+    /// Removes the value so `get` falls back to the default again
+    pub async fn clear(&mut self) -> Result<(), Error<E>> {
+        self.ds.remove::<_, T>(&self.key).await?;
+        Ok(())
+    }
+
+    /// Sets the value returning the previous one, the default if there is none
+    pub async fn replace(&mut self, value: &T) -> Result<T, Error<E>> {
+        let old = self.ds.insert(&self.key, value).await?;
+        Ok(old.unwrap_or_else(|| self.default_value.clone()))
+    }
+
+    /// Whether a value is stored
+    pub async fn is_set(&self) -> Result<bool, Error<E>> {
+        Ok(self.ds.get::<_, T>(&self.key).await?.is_some())
+    }
+}
+
+impl<T, E, DS> DefaultValue<T, DS>
+where
+    E: fmt::Debug,
+    T: Serialize + DeserializeOwned + Clone + Send + Sync + 'static,
+    DS: AsyncAtomic<Error = E>,
+{
+    /// Atomically applies `op` to the value, a missing value is replaced
+    /// by the default first. `op` can run more than once.
+    pub async fn update(
+        &self,
+        op: impl FnMut(T) -> T + Clone + Send + 'static,
+    ) -> Result<(), Error<E>> {
+        self.update_and_fetch(op).await.map(|_| ())
+    }
+    /// Like [`update`](Self::update), returns the value before the update
+    pub async fn fetch_update(
+        &self,
+        op: impl FnMut(T) -> T + Clone + Send + 'static,
+    ) -> Result<T, Error<E>> {
+        let (old, _) = self
+            .ds
+            .atomic_fetch_update(&self.key, self.seeded(op))
+            .await?;
+        Ok(old.unwrap_or_else(|| self.default_value.clone()))
+    }
+    /// Like [`update`](Self::update), returns the value after the update
+    pub async fn update_and_fetch(
+        &self,
+        op: impl FnMut(T) -> T + Clone + Send + 'static,
+    ) -> Result<T, Error<E>> {
+        let (_, new) = self
+            .ds
+            .atomic_fetch_update(&self.key, self.seeded(op))
+            .await?;
+        Ok(new.expect("seeded op always returns a value"))
+    }
+    fn seeded(
+        &self,
+        op: impl FnMut(T) -> T + Clone + Send + 'static,
+    ) -> impl FnMut(Option<T>) -> Option<T> + Clone + Send + 'static {
+        let default = self.default_value.clone();
+        crate::wrapper::seeded(move || default.clone(), op)
+    }
+    /// Sets the value to `new` if the stored value is `old`. A value that
+    /// was never set is not compared to the default, the outcome is then
+    /// a mismatch with `current: None`.
+    pub async fn conditional_update(&self, old: T, new: T) -> Result<CasOutcome<T>, Error<E>> {
+        Ok(self
+            .ds
+            .conditional_update(&self.key, Some(&new), Some(&old))
+            .await?)
+    }
+}
+
+impl<T, E, DS> DefaultValue<T, DS>
+where
+    E: fmt::Debug,
+    T: Serialize + DeserializeOwned + Clone + Send + Sync + 'static,
+    DS: AsyncWatch<Error = E>,
+{
+    /// subscribes to the changes made to the value from here on, see
+    /// [`Watcher`]
+    pub async fn watch(&self) -> Result<Watcher<ValueEvent<T>, DS::Subscriber, E>, Error<E>> {
+        let subscriber = self.ds.watch_prefix(&self.key)?;
+        let decoder = ValueDecoder::new::<DS::Codec>(&self.key);
+        Ok(Watcher::new(subscriber, decoder))
+    }
+}
@@ -0,0 +1,292 @@
+use core::borrow::Borrow;
+use core::fmt;
+use std::marker::PhantomData;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tracing::instrument;
+
+use crate::codec::Codec;
+use crate::traits::{AsyncDataStore, AsyncOrdered, AsyncWatch, ExtendError};
+use crate::wrapper::map::Prefixed;
+use crate::wrapper::Prefix;
+use crate::wrapper::watch::{MapDecoder, MapWatcher, Watcher};
+use crate::Error;
+
+mod entry;
+mod iterator;
+
+pub use entry::{Entry, OccupiedEntry, VacantEntry};
+
+/// mimics the API of [`HashMap`][std::collections::HashMap]
+pub struct Map<'a, Key, Value, DS>
+where
+    Key: Serialize,
+    Value: Serialize + DeserializeOwned,
+    DS: AsyncDataStore,
+{
+    phantom_key: PhantomData<&'a Key>,
+    phantom_val: PhantomData<Value>,
+    tree: DS,
+    prefix: Prefix,
+}
+
+/// Returned by [`Map::try_extend`], on error holds the pair that was not
+/// inserted and the rest of the iterator
+type ExtendResult<K, V, I, E> = Result<(), ExtendError<(K, V), I, Error<E>>>;
+
+impl<'a, Key, Value, E, DS> Map<'a, Key, Value, DS>
+where
+    E: fmt::Debug,
+    Key: Serialize + DeserializeOwned + Sync,
+    Value: Serialize + DeserializeOwned + Send + Sync,
+    DS: AsyncDataStore<Error = E>,
+{
+    #[doc(hidden)]
+    #[instrument(skip(tree), level = "debug")]
+    pub fn new(tree: DS, prefix: Prefix) -> Self {
+        Self {
+            phantom_key: PhantomData,
+            phantom_val: PhantomData,
+            tree,
+            prefix,
+        }
+    }
+
+    /// returns existing value if any was set
+    #[instrument(skip_all, level = "debug")]
+    pub async fn insert(&self, key: &'a Key, value: &'a Value) -> Result<Option<Value>, Error<E>> {
+        let key = Prefixed::new(self.prefix.clone(), key);
+        let existing = self.tree.insert(&key, value).await?;
+        Ok(existing)
+    }
+
+    #[instrument(skip_all, level = "debug")]
+    pub async fn get(&self, key: &'a Key) -> Result<Option<Value>, Error<E>> {
+        let key = Prefixed::new(self.prefix.clone(), key);
+        let value = self.tree.get(&key).await?;
+        Ok(value)
+    }
+
+    /// returns the value at the key if it was in the map
+    #[instrument(skip_all, level = "debug")]
+    pub async fn remove(&self, key: &'a Key) -> Result<Option<Value>, Error<E>> {
+        let key = Prefixed::new(self.prefix.clone(), key);
+        let existing = self.tree.remove(&key).await?;
+        Ok(existing)
+    }
+
+    #[instrument(skip_all, level = "debug")]
+    pub async fn contains_key(&self, key: &'a Key) -> Result<bool, Error<E>> {
+        Ok(self.get(key).await?.is_some())
+    }
+
+    /// returns the value at the key, if there is none inserts and
+    /// returns the value returned by `f`.
+    #[instrument(skip_all, level = "debug")]
+    pub async fn get_or_insert_with(
+        &self,
+        key: &'a Key,
+        f: impl FnOnce() -> Value,
+    ) -> Result<Value, Error<E>> {
+        if let Some(value) = self.get(key).await? {
+            return Ok(value);
+        }
+        let value = f();
+        self.insert(key, &value).await?;
+        Ok(value)
+    }
+
+    /// Inserts all key-values from the iterator, replacing the values of
+    /// existing keys. Takes owned pairs or references.
+    #[instrument(skip_all, level = "debug")]
+    pub async fn try_extend<I, K, V>(&self, iter: I) -> ExtendResult<K, V, I::IntoIter, E>
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Borrow<Key>,
+        V: Borrow<Value>,
+    {
+        let mut iter = iter.into_iter();
+        while let Some((key, value)) = iter.next() {
+            let prefixed = Prefixed::new(self.prefix.clone(), key.borrow());
+            if let Err(error) = self.tree.insert(&prefixed, value.borrow()).await {
+                return Err(ExtendError {
+                    unadded: (key, value),
+                    iter,
+                    error: Error::Database(error),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// These need to scan over the map in the database which requires
+/// an ordered store
+impl<'a, Key, Value, E, DS> Map<'a, Key, Value, DS>
+where
+    E: fmt::Debug,
+    Key: Serialize + DeserializeOwned + Sync,
+    Value: Serialize + DeserializeOwned + Send + Sync,
+    DS: AsyncOrdered<Error = E>,
+{
+    /// the number of elements in the map, this has to visit every element
+    #[instrument(skip_all, level = "debug")]
+    pub async fn len(&self) -> Result<usize, Error<E>> {
+        let mut len = 0;
+        let mut last_key = None;
+        while let Some((key, _)) = self
+            .tree
+            .next_in_prefix(self.prefix.as_bytes(), last_key.as_deref())
+            .await?
+        {
+            len += 1;
+            last_key = Some(key);
+        }
+        Ok(len)
+    }
+
+    #[instrument(skip_all, level = "debug")]
+    pub async fn is_empty(&self) -> Result<bool, Error<E>> {
+        let first = self
+            .tree
+            .next_in_prefix(self.prefix.as_bytes(), None)
+            .await?;
+        Ok(first.is_none())
+    }
+
+    /// removes all elements from the map
+    #[instrument(skip_all, level = "debug")]
+    pub async fn clear(&self) -> Result<(), Error<E>> {
+        self.retain(|_, _| false).await
+    }
+
+    /// Retains only the elements for which `f` returns true. Changes `f` makes
+    /// to the values that are kept are written to the database.
+    #[instrument(skip_all, level = "debug")]
+    pub async fn retain(
+        &self,
+        mut f: impl FnMut(&Key, &mut Value) -> bool,
+    ) -> Result<(), Error<E>> {
+        let prefix_len = self.prefix.as_bytes().len();
+        let mut last_key = None;
+        while let Some((key_bytes, val_bytes)) = self
+            .tree
+            .next_in_prefix(self.prefix.as_bytes(), last_key.as_deref())
+            .await?
+        {
+            let (key, mut value): (Key, Value) =
+                iterator::decode::<_, _, DS::Codec, E>(&key_bytes, &val_bytes, prefix_len)?;
+            let prefixed = Prefixed::new(self.prefix.clone(), &key);
+
+            if !f(&key, &mut value) {
+                self.tree.remove::<_, Value>(&prefixed).await?;
+            } else {
+                let new_bytes = DS::Codec::serialize(&value).map_err(Error::SerializingValue)?;
+                if new_bytes != val_bytes {
+                    self.tree.insert(&prefixed, &value).await?;
+                }
+            }
+            last_key = Some(key_bytes);
+        }
+        Ok(())
+    }
+}
+
+impl<'a, Key, Value, E, DS> Map<'a, Key, Value, DS>
+where
+    E: fmt::Debug,
+    Key: Serialize + DeserializeOwned + Sync + 'static,
+    Value: Serialize + DeserializeOwned + Send + Sync + 'static,
+    DS: AsyncWatch<Error = E>,
+{
+    /// subscribes to the changes made to the map from here on, see
+    /// [`Watcher`]
+    #[instrument(skip_all, level = "debug")]
+    pub async fn watch(&self) -> Result<MapWatcher<Key, Value, DS::Subscriber, E>, Error<E>> {
+        let subscriber = self.tree.watch_prefix(&self.prefix)?;
+        let decoder = MapDecoder::new::<DS::Codec>(&self.prefix);
+        Ok(Watcher::new(subscriber, decoder))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_core::Stream;
+
+    use super::*;
+    use crate::stores::{self, Blocking};
+
+    type TestMap<'a> = Map<'a, u8, u8, Blocking<stores::BTreeMap>>;
+
+    /// collects a stream without pulling in a futures utility crate
+    async fn collect<S: Stream + Unpin>(mut stream: S) -> std::vec::Vec<S::Item> {
+        let mut items = std::vec::Vec::new();
+        while let Some(item) =
+            core::future::poll_fn(|cx| core::pin::Pin::new(&mut stream).poll_next(cx)).await
+        {
+            items.push(item);
+        }
+        items
+    }
+
+    #[tokio::test]
+    async fn iter_len_and_retain() {
+        let ds = Blocking::new(stores::BTreeMap::new());
+        let map: TestMap = Map::new(ds.clone(), Prefix::from(1));
+        let other: TestMap = Map::new(ds, Prefix::from(2));
+        other.insert(&1, &1).await.unwrap();
+        assert!(map.is_empty().await.unwrap());
+
+        map.try_extend((0u8..10).map(|i| (i, i))).await.unwrap();
+        assert_eq!(map.len().await.unwrap(), 10);
+        let keys: Result<std::vec::Vec<u8>, _> = collect(map.keys()).await.into_iter().collect();
+        assert_eq!(keys.unwrap(), (0..10).collect::<std::vec::Vec<_>>());
+
+        map.retain(|key, value| {
+            *value *= 2;
+            key % 2 == 0
+        })
+        .await
+        .unwrap();
+        let pairs: Result<std::vec::Vec<(u8, u8)>, _> =
+            collect(map.iter()).await.into_iter().collect();
+        assert_eq!(
+            pairs.unwrap(),
+            vec![(0, 0), (2, 4), (4, 8), (6, 12), (8, 16)]
+        );
+
+        map.clear().await.unwrap();
+        assert!(map.is_empty().await.unwrap());
+        assert_eq!(other.len().await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn entry() {
+        let map: TestMap = Map::new(Blocking::new(stores::BTreeMap::new()), Prefix::from(1));
+        let value = map
+            .entry(&1)
+            .await
+            .unwrap()
+            .and_modify(|v| *v += 1)
+            .await
+            .unwrap()
+            .or_insert(10)
+            .await
+            .unwrap();
+        assert_eq!(value, 10);
+
+        let value = map
+            .entry(&1)
+            .await
+            .unwrap()
+            .and_modify(|v| *v += 1)
+            .await
+            .unwrap()
+            .or_insert(10)
+            .await
+            .unwrap();
+        assert_eq!(value, 11);
+        assert_eq!(map.get(&1).await.unwrap(), Some(11));
+    }
+}
@@ -0,0 +1,213 @@
+use core::fmt;
+use std::sync::{Arc, Mutex};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tracing::instrument;
+
+use crate::traits::{AsyncAtomic, AsyncDataStore, CasOutcome};
+use crate::wrapper::map::Prefixed;
+use crate::Error;
+
+use super::Map;
+
+/// A view into a single entry of a [`Map`], returned by [`Map::entry`].
+///
+/// Changes made through the entry use an atomic read-modify-write, an update
+/// racing with another task is never lost.
+pub enum Entry<'m, 'a, Key, Value, DS>
+where
+    Key: Serialize,
+    Value: Serialize + DeserializeOwned,
+    DS: AsyncDataStore,
+{
+    Occupied(OccupiedEntry<'m, 'a, Key, Value, DS>),
+    Vacant(VacantEntry<'m, 'a, Key, Value, DS>),
+}
+
+/// An entry that had a value when it was looked up
+pub struct OccupiedEntry<'m, 'a, Key, Value, DS>
+where
+    Key: Serialize,
+    Value: Serialize + DeserializeOwned,
+    DS: AsyncDataStore,
+{
+    map: &'m Map<'a, Key, Value, DS>,
+    key: &'a Key,
+    value: Value,
+}
+
+/// Changes passed to [`Entry::and_modify`], the store may run them on
+/// another thread
+type Modify<Value> = Arc<Mutex<dyn FnMut(&mut Value) + Send>>;
+
+/// An entry that had no value when it was looked up
+pub struct VacantEntry<'m, 'a, Key, Value, DS>
+where
+    Key: Serialize,
+    Value: Serialize + DeserializeOwned,
+    DS: AsyncDataStore,
+{
+    map: &'m Map<'a, Key, Value, DS>,
+    key: &'a Key,
+    /// applied instead of inserting if another task inserts a value
+    /// before we do
+    modify: Option<Modify<Value>>,
+}
+
+impl<'a, Key, Value, E, DS> Map<'a, Key, Value, DS>
+where
+    E: fmt::Debug,
+    Key: Serialize + DeserializeOwned + Sync,
+    Value: Serialize + DeserializeOwned + Send + Sync + 'static,
+    DS: AsyncAtomic<Error = E>,
+{
+    /// Gets the entry for the key for in-place manipulation
+    #[instrument(skip_all, level = "debug")]
+    pub async fn entry(&self, key: &'a Key) -> Result<Entry<'_, 'a, Key, Value, DS>, Error<E>> {
+        Ok(match self.get(key).await? {
+            Some(value) => Entry::Occupied(OccupiedEntry {
+                map: self,
+                key,
+                value,
+            }),
+            None => Entry::Vacant(VacantEntry {
+                map: self,
+                key,
+                modify: None,
+            }),
+        })
+    }
+
+    /// Atomically applies `f` to the value at key, returns the new value or
+    /// `None` if there is no value. `f` can be called multiple times.
+    async fn modify(&self, key: &'a Key, f: Modify<Value>) -> Result<Option<Value>, Error<E>> {
+        let op = move |value: Option<Value>| {
+            value.map(|mut value| {
+                (f.lock().expect("a modify closure panicked"))(&mut value);
+                value
+            })
+        };
+        let key = Prefixed::new(self.prefix.clone(), key);
+        let (_, new) = self.tree.atomic_fetch_update(&key, op).await?;
+        Ok(new)
+    }
+
+    /// Inserts `value` if there is no value at key. If there is one it is
+    /// changed using `modify` instead, or returned if there is nothing to
+    /// modify. Returns the value at key afterwards.
+    async fn modify_or_insert(
+        &self,
+        key: &'a Key,
+        value: Value,
+        modify: Option<Modify<Value>>,
+    ) -> Result<Value, Error<E>> {
+        let prefixed = Prefixed::new(self.prefix.clone(), key);
+        loop {
+            let outcome = self
+                .tree
+                .conditional_update(&prefixed, Some(&value), None)
+                .await?;
+            let current = match outcome {
+                CasOutcome::Swapped => return Ok(value),
+                CasOutcome::Mismatch { current } => current,
+            };
+            match &modify {
+                Some(modify) => {
+                    if let Some(new) = self.modify(key, modify.clone()).await? {
+                        return Ok(new);
+                    }
+                }
+                None => {
+                    if let Some(current) = current {
+                        return Ok(current);
+                    }
+                }
+            }
+            // the value got removed again, try inserting once more
+        }
+    }
+}
+
+impl<'m, 'a, Key, Value, E, DS> Entry<'m, 'a, Key, Value, DS>
+where
+    E: fmt::Debug,
+    Key: Serialize + DeserializeOwned + Sync,
+    Value: Serialize + DeserializeOwned + Send + Sync + 'static,
+    DS: AsyncAtomic<Error = E>,
+{
+    pub fn key(&self) -> &Key {
+        match self {
+            Entry::Occupied(entry) => entry.key,
+            Entry::Vacant(entry) => entry.key,
+        }
+    }
+
+    /// Ensures a value is in the entry by inserting the default if empty,
+    /// returns the value in the entry.
+    pub async fn or_insert(self, default: Value) -> Result<Value, Error<E>> {
+        self.or_insert_with(|| default).await
+    }
+
+    /// Ensures a value is in the entry by inserting the result of the
+    /// default function if empty, returns the value in the entry.
+    ///
+    /// If another task inserted a value since the entry was looked up the
+    /// changes passed to [`and_modify`](Self::and_modify) are applied to
+    /// that value instead.
+    pub async fn or_insert_with(self, default: impl FnOnce() -> Value) -> Result<Value, Error<E>> {
+        match self {
+            Entry::Occupied(entry) => Ok(entry.value),
+            Entry::Vacant(entry) => {
+                entry
+                    .map
+                    .modify_or_insert(entry.key, default(), entry.modify)
+                    .await
+            }
+        }
+    }
+
+    /// Provides in-place mutable access to an occupied entry before any
+    /// potential inserts into the map. The function can be called more then
+    /// once if another task changes the value at the same time.
+    ///
+    /// For a vacant entry the function is kept, it is applied if another
+    /// task inserts a value before [`or_insert`](Self::or_insert) does.
+    pub async fn and_modify(
+        self,
+        f: impl FnMut(&mut Value) + Send + 'static,
+    ) -> Result<Self, Error<E>> {
+        let (map, key, modify) = match self {
+            Entry::Occupied(entry) => {
+                let modify: Modify<Value> = Arc::new(Mutex::new(f));
+                match entry.map.modify(entry.key, modify.clone()).await? {
+                    Some(value) => return Ok(Entry::Occupied(OccupiedEntry { value, ..entry })),
+                    // removed by someone else since we looked up the entry
+                    None => (entry.map, entry.key, modify),
+                }
+            }
+            Entry::Vacant(VacantEntry {
+                map,
+                key,
+                modify: Some(earlier),
+            }) => {
+                let mut f = f;
+                let modify = move |value: &mut Value| {
+                    (earlier.lock().expect("a modify closure panicked"))(value);
+                    f(value);
+                };
+                (map, key, Arc::new(Mutex::new(modify)) as Modify<Value>)
+            }
+            Entry::Vacant(VacantEntry {
+                map,
+                key,
+                modify: None,
+            }) => (map, key, Arc::new(Mutex::new(f)) as Modify<Value>),
+        };
+        Ok(Entry::Vacant(VacantEntry {
+            map,
+            key,
+            modify: Some(modify),
+        }))
+    }
+}
@@ -0,0 +1,165 @@
+use core::future::Future;
+use core::marker::PhantomData;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use std::fmt;
+
+use futures_core::Stream;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::codec::{self, Codec};
+use crate::traits::AsyncOrdered;
+use crate::Error;
+
+use super::Map;
+
+type NextPair<'a, E> =
+    Pin<Box<dyn Future<Output = Result<Option<(Vec<u8>, Vec<u8>)>, E>> + Send + 'a>>;
+
+/// A [`Stream`] over the key value pairs in a [`Map`], returned by
+/// [`Map::iter`]. Every pair is read from the database when it is polled.
+pub struct Iter<'a, K, V, DS>
+where
+    DS: AsyncOrdered,
+{
+    tree: &'a DS,
+    prefix: Vec<u8>,
+    last_key: Option<Vec<u8>>,
+    next: Option<NextPair<'a, DS::Error>>,
+    done: bool,
+    phantom: PhantomData<fn() -> (K, V)>,
+}
+
+impl<'a, K, V, E, DS> Stream for Iter<'a, K, V, DS>
+where
+    E: fmt::Debug,
+    K: DeserializeOwned,
+    V: DeserializeOwned,
+    DS: AsyncOrdered<Error = E>,
+{
+    type Item = Result<(K, V), Error<E>>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if this.done {
+            return Poll::Ready(None);
+        }
+
+        let next = this.next.get_or_insert_with(|| {
+            let tree = this.tree;
+            let prefix = this.prefix.clone();
+            let after = this.last_key.clone();
+            Box::pin(async move { tree.next_in_prefix(&prefix, after.as_deref()).await })
+        });
+        let next = match next.as_mut().poll(cx) {
+            Poll::Ready(next) => next,
+            Poll::Pending => return Poll::Pending,
+        };
+        this.next = None;
+
+        let (key, val) = match next {
+            Ok(Some(pair)) => pair,
+            Ok(None) => {
+                this.done = true;
+                return Poll::Ready(None);
+            }
+            Err(e) => return Poll::Ready(Some(Err(Error::Database(e)))),
+        };
+
+        let pair = decode::<K, V, DS::Codec, E>(&key, &val, this.prefix.len());
+        this.last_key = Some(key);
+        Poll::Ready(Some(pair))
+    }
+}
+
+pub(super) fn decode<K, V, C, E>(
+    key: &[u8],
+    val: &[u8],
+    prefix_len: usize,
+) -> Result<(K, V), Error<E>>
+where
+    K: DeserializeOwned,
+    V: DeserializeOwned,
+    C: Codec,
+    E: fmt::Debug,
+{
+    let key = codec::deserialize_key(&key[prefix_len..]).map_err(Error::DeSerializingKey)?;
+    let val = C::deserialize(val).map_err(Error::DeSerializingVal)?;
+    Ok((key, val))
+}
+
+/// A [`Stream`] over the values in a [`Map`], returned by [`Map::values`]
+pub struct Values<'a, K, V, DS>(Iter<'a, K, V, DS>)
+where
+    DS: AsyncOrdered;
+
+impl<'a, K, V, E, DS> Stream for Values<'a, K, V, DS>
+where
+    E: fmt::Debug,
+    K: DeserializeOwned,
+    V: DeserializeOwned,
+    DS: AsyncOrdered<Error = E>,
+{
+    type Item = Result<V, Error<E>>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.get_mut().0)
+            .poll_next(cx)
+            .map(|next| next.map(|res| res.map(|(_, val)| val)))
+    }
+}
+
+/// A [`Stream`] over the keys in a [`Map`], returned by [`Map::keys`]
+pub struct Keys<'a, K, V, DS>(Iter<'a, K, V, DS>)
+where
+    DS: AsyncOrdered;
+
+impl<'a, K, V, E, DS> Stream for Keys<'a, K, V, DS>
+where
+    E: fmt::Debug,
+    K: DeserializeOwned,
+    V: DeserializeOwned,
+    DS: AsyncOrdered<Error = E>,
+{
+    type Item = Result<K, Error<E>>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.get_mut().0)
+            .poll_next(cx)
+            .map(|next| next.map(|res| res.map(|(key, _)| key)))
+    }
+}
+
+impl<'a, Key, Value, E, DS> Map<'a, Key, Value, DS>
+where
+    E: fmt::Debug,
+    Key: Serialize + DeserializeOwned + Sync,
+    Value: Serialize + DeserializeOwned + Send + Sync,
+    DS: AsyncOrdered<Error = E>,
+{
+    fn range(&self) -> Iter<'_, Key, Value, DS> {
+        Iter {
+            tree: &self.tree,
+            prefix: self.prefix.as_bytes().to_vec(),
+            last_key: None,
+            next: None,
+            done: false,
+            phantom: PhantomData,
+        }
+    }
+
+    /// A [`Stream`] over the key value pairs in the map, in the order of
+    /// the serialized keys
+    pub fn iter(&self) -> Iter<'_, Key, Value, DS> {
+        self.range()
+    }
+
+    pub fn values(&self) -> Values<'_, Key, Value, DS> {
+        Values(self.range())
+    }
+
+    pub fn keys(&self) -> Keys<'_, Key, Value, DS> {
+        Keys(self.range())
+    }
+}
@@ -0,0 +1,127 @@
+use core::fmt;
+use std::marker::PhantomData;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::traits::{AsyncAtomic, AsyncDataStore, AsyncWatch, CasOutcome};
+use crate::wrapper::Prefix;
+use crate::wrapper::watch::{ValueDecoder, ValueEvent, Watcher};
+use crate::Error;
+
+/// here missing values are represented by [`Option::None`].
+pub struct OptionValue<T, DS>
+where
+    DS: AsyncDataStore,
+{
+    phantom: PhantomData<T>,
+    ds: DS,
+    key: Prefix,
+}
+
+impl<T, E, DS> OptionValue<T, DS>
+where
+    E: fmt::Debug,
+    T: Serialize + DeserializeOwned + Send + Sync,
+    DS: AsyncDataStore<Error = E>,
+{
+    #[doc(hidden)]
+    pub fn new(ds: DS, key: Prefix) -> Self {
+        Self {
+            phantom: PhantomData,
+            ds,
+            key,
+        }
+    }
+
+    pub async fn set(&mut self, value: &T) -> Result<(), Error<E>> {
+        self.ds.insert(&self.key, value).await?;
+        Ok(())
+    }
+
+    pub async fn get(&self) -> Result<Option<T>, Error<E>> {
+        Ok(self.ds.get(&self.key).await?)
+    }
+
+    /// Removes the value returning it, `None` if there is none
+    pub async fn take(&mut self) -> Result<Option<T>, Error<E>> {
+        Ok(self.ds.remove(&self.key).await?)
+    }
+
+    /// Removes the value, `get` then returns `None`
+    pub async fn clear(&mut self) -> Result<(), Error<E>> {
+        self.ds.remove::<_, T>(&self.key).await?;
+        Ok(())
+    }
+
+    /// Sets the value returning the previous one, `None` if there is none
+    pub async fn replace(&mut self, value: &T) -> Result<Option<T>, Error<E>> {
+        Ok(self.ds.insert(&self.key, value).await?)
+    }
+
+    /// Whether a value is stored
+    pub async fn is_set(&self) -> Result<bool, Error<E>> {
+        Ok(self.ds.get::<_, T>(&self.key).await?.is_some())
+    }
+}
+
+impl<T, E, DS> OptionValue<T, DS>
+where
+    E: fmt::Debug,
+    T: Serialize + DeserializeOwned + Send + Sync,
+    DS: AsyncAtomic<Error = E>,
+{
+    /// Atomically replaces the value with the result of `op`, which gets
+    /// `None` if there is no value and removes the value by returning
+    /// `None`. `op` can run more than once.
+    pub async fn update(
+        &self,
+        op: impl FnMut(Option<T>) -> Option<T> + Clone + Send + 'static,
+    ) -> Result<(), Error<E>> {
+        self.ds.atomic_fetch_update(&self.key, op).await?;
+        Ok(())
+    }
+    /// Like [`update`](Self::update), returns the value before the update
+    pub async fn fetch_update(
+        &self,
+        op: impl FnMut(Option<T>) -> Option<T> + Clone + Send + 'static,
+    ) -> Result<Option<T>, Error<E>> {
+        let (old, _) = self.ds.atomic_fetch_update(&self.key, op).await?;
+        Ok(old)
+    }
+    /// Like [`update`](Self::update), returns the value after the update
+    pub async fn update_and_fetch(
+        &self,
+        op: impl FnMut(Option<T>) -> Option<T> + Clone + Send + 'static,
+    ) -> Result<Option<T>, Error<E>> {
+        let (_, new) = self.ds.atomic_fetch_update(&self.key, op).await?;
+        Ok(new)
+    }
+    /// Sets the value to `new` if it currently is `old`, `None` meaning
+    /// no value. Returns the value found instead if it was not `old`.
+    pub async fn conditional_update(
+        &self,
+        old: Option<T>,
+        new: Option<T>,
+    ) -> Result<CasOutcome<T>, Error<E>> {
+        Ok(self
+            .ds
+            .conditional_update(&self.key, new.as_ref(), old.as_ref())
+            .await?)
+    }
+}
+
+impl<T, E, DS> OptionValue<T, DS>
+where
+    E: fmt::Debug,
+    T: Serialize + DeserializeOwned + Send + Sync + 'static,
+    DS: AsyncWatch<Error = E>,
+{
+    /// subscribes to the changes made to the value from here on, see
+    /// [`Watcher`]
+    pub async fn watch(&self) -> Result<Watcher<ValueEvent<T>, DS::Subscriber, E>, Error<E>> {
+        let subscriber = self.ds.watch_prefix(&self.key)?;
+        let decoder = ValueDecoder::new::<DS::Codec>(&self.key);
+        Ok(Watcher::new(subscriber, decoder))
+    }
+}
@@ -0,0 +1,68 @@
+use core::fmt;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tracing::instrument;
+
+use crate::traits::{AsyncDataStore, AsyncWatch};
+use crate::wrapper::Prefix;
+use crate::wrapper::watch::{SetEvent, Watcher};
+use crate::Error;
+
+use super::BTreeMap;
+
+/// mimics the API of [`HashSet`][std::collections::HashSet] and
+/// [`BTreeSet`][std::collections::BTreeSet]
+pub struct Set<T, DS>
+where
+    T: Serialize,
+    DS: AsyncDataStore,
+{
+    map: BTreeMap<T, (), DS>,
+}
+
+impl<T, E, DS> Set<T, DS>
+where
+    E: fmt::Debug,
+    T: Serialize + DeserializeOwned + Sync,
+    DS: AsyncDataStore<Error = E>,
+{
+    #[doc(hidden)]
+    #[instrument(skip(tree), level = "debug")]
+    pub fn new(tree: DS, prefix: Prefix) -> Self {
+        Self {
+            map: BTreeMap::new(tree, prefix),
+        }
+    }
+
+    /// returns whether the value was newly inserted
+    #[instrument(skip_all, level = "debug")]
+    pub async fn insert(&self, value: &T) -> Result<bool, Error<E>> {
+        Ok(self.map.insert(value, &()).await?.is_none())
+    }
+
+    /// returns whether the value was in the set
+    #[instrument(skip_all, level = "debug")]
+    pub async fn remove(&self, value: &T) -> Result<bool, Error<E>> {
+        Ok(self.map.remove(value).await?.is_some())
+    }
+
+    #[instrument(skip_all, level = "debug")]
+    pub async fn contains(&self, value: &T) -> Result<bool, Error<E>> {
+        self.map.contains_key(value).await
+    }
+}
+
+impl<T, E, DS> Set<T, DS>
+where
+    E: fmt::Debug,
+    T: Serialize + DeserializeOwned + Send + Sync + 'static,
+    DS: AsyncWatch<Error = E>,
+{
+    /// subscribes to the changes made to the set from here on, see
+    /// [`Watcher`]
+    #[instrument(skip_all, level = "debug")]
+    pub async fn watch(&self) -> Result<Watcher<SetEvent<T>, DS::Subscriber, E>, Error<E>> {
+        self.map.watch_keys()
+    }
+}
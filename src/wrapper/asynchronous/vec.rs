@@ -0,0 +1,304 @@
+use core::fmt;
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tokio::sync::MutexGuard;
+use tracing::{debug, warn};
+
+use crate::traits::{AsyncAtomic, AsyncDataStore, AsyncOrdered, AsyncWatch};
+use crate::wrapper::vec::{check_index, write_element, Keys, LenChange};
+use crate::wrapper::Prefix;
+use crate::wrapper::watch::{VecDecoder, VecEvent, Watcher};
+use crate::Error;
+
+/// The cached length of a vector, the lock is held while changing it. A
+/// [`VecDeque`](super::VecDeque) caches its ends in the same way.
+#[doc(hidden)]
+pub type Len<T = usize> = tokio::sync::Mutex<T>;
+
+/// mimics the API of [`Vec`], see the blocking [`Vec`](crate::wrapper::Vec)
+/// for how the length is stored.
+pub struct Vec<T, DS>
+where
+    DS: AsyncDataStore,
+{
+    phantom: PhantomData<T>,
+    ds: DS,
+    keys: Keys,
+    len: Arc<Len>,
+}
+
+/// Reads the length of the vector at prefix when opening the database, see
+/// the blocking [`stored_len`](crate::wrapper::stored_len).
+#[doc(hidden)]
+pub async fn stored_len<T, DS>(ds: &DS, prefix: Prefix) -> Result<usize, DS::Error>
+where
+    T: Serialize + DeserializeOwned + Send,
+    DS: AsyncOrdered,
+{
+    let keys = Keys::new(prefix);
+    if let Some(len) = ds.get(&keys.len()).await? {
+        return Ok(len);
+    }
+
+    let last: Option<usize> = ds.last_key_in_prefix(keys.prefix()).await?;
+    Ok(last.map_or(0, |index| index + 1))
+}
+
+impl<T, E, DS> Vec<T, DS>
+where
+    E: fmt::Debug,
+    T: Serialize + DeserializeOwned + Send + Sync,
+    DS: AsyncDataStore<Error = E>,
+{
+    #[doc(hidden)]
+    pub fn new(ds: DS, prefix: Prefix, len: Arc<Len>) -> Self {
+        Self {
+            phantom: PhantomData,
+            ds,
+            keys: Keys::new(prefix),
+            len,
+        }
+    }
+
+    async fn lock(&self) -> MutexGuard<'_, usize> {
+        self.len.lock().await
+    }
+
+    /// Applies the change to the database, then to the cached length
+    async fn change_len(&self, len: &mut usize, change: LenChange<'_, T>) -> Result<(), Error<E>> {
+        if let Err(e) = change.write_async(&self.ds).await {
+            self.resync(len).await;
+            return Err(e.into());
+        }
+        *len = change.new_len();
+        Ok(())
+    }
+
+    /// If a write failed part of it might still have been applied,
+    /// the length in the database is leading.
+    async fn resync(&self, len: &mut usize) {
+        match self.ds.get(&self.keys.len()).await {
+            Ok(Some(stored)) => *len = stored,
+            Ok(None) => (),
+            Err(e) => warn!("could not resync vector length: {e:?}"),
+        }
+    }
+
+    pub async fn get(&self, index: usize) -> Result<Option<T>, Error<E>> {
+        let len = self.len().await;
+        if index >= len {
+            return Ok(None);
+        }
+        Ok(self.ds.get(&self.keys.element(index)).await?)
+    }
+
+    pub async fn push(&self, value: &T) -> Result<(), Error<E>> {
+        let mut len = self.lock().await;
+        debug!("pushing onto vector (index: {})", *len);
+        let change = LenChange::push(&self.keys, *len, value);
+        self.change_len(&mut len, change).await
+    }
+
+    pub async fn pop(&self) -> Result<Option<T>, Error<E>> {
+        let mut len = self.lock().await;
+        let Some(index) = len.checked_sub(1) else {
+            return Ok(None);
+        };
+
+        debug!("popping from vector (index: {index})");
+        let value = self.ds.get(&self.keys.element(index)).await?;
+        self.change_len(&mut len, LenChange::pop(&self.keys, index))
+            .await?;
+        Ok(value)
+    }
+
+    pub async fn len(&self) -> usize {
+        *self.lock().await
+    }
+
+    pub async fn is_empty(&self) -> bool {
+        self.len().await == 0
+    }
+
+    pub async fn first(&self) -> Result<Option<T>, Error<E>> {
+        self.get(0).await
+    }
+
+    pub async fn last(&self) -> Result<Option<T>, Error<E>> {
+        match self.len().await.checked_sub(1) {
+            Some(index) => self.get(index).await,
+            None => Ok(None),
+        }
+    }
+
+    /// replaces the element at index, returns an error if the index is
+    /// out of range.
+    pub async fn set(&self, index: usize, value: &T) -> Result<(), Error<E>> {
+        let len = self.lock().await;
+        check_index(index, *len)?;
+        self.ds.insert(&self.keys.element(index), value).await?;
+        Ok(())
+    }
+
+    /// swaps two elements, returns an error if either index is out
+    /// of range. A hole is swapped like any other element.
+    pub async fn swap(&self, a: usize, b: usize) -> Result<(), Error<E>> {
+        let len = self.lock().await;
+        check_index(a, *len)?;
+        check_index(b, *len)?;
+        if a == b {
+            return Ok(());
+        }
+
+        let (key_a, key_b) = (self.keys.element(a), self.keys.element(b));
+        let val_a: Option<T> = self.ds.get(&key_a).await?;
+        let val_b: Option<T> = self.ds.get(&key_b).await?;
+        if val_a.is_none() && val_b.is_none() {
+            return Ok(());
+        }
+        self.ds
+            .write_pair(
+                write_element(&key_a, val_b.as_ref()),
+                write_element(&key_b, val_a.as_ref()),
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Shortens the vector keeping the first `new_len` elements. Does
+    /// nothing if the vector is already shorter.
+    pub async fn truncate(&self, new_len: usize) -> Result<(), Error<E>> {
+        let mut len = self.lock().await;
+        if new_len >= *len {
+            return Ok(());
+        }
+
+        debug!("truncating vector from {} to {new_len}", *len);
+        // the elements past the new length are ignored from here on
+        if let Err(e) = self.ds.insert(&self.keys.len(), &new_len).await {
+            self.resync(&mut len).await;
+            return Err(e.into());
+        }
+        let old_len = std::mem::replace(&mut *len, new_len);
+        for index in (new_len..old_len).rev() {
+            let _: Option<T> = self.ds.remove(&self.keys.element(index)).await?;
+        }
+        Ok(())
+    }
+
+    /// removes all elements
+    pub async fn clear(&self) -> Result<(), Error<E>> {
+        self.truncate(0).await
+    }
+
+    /// Removes the element at index and returns it, `None` if it was a hole.
+    /// The last element takes its place. Returns an error if the index is
+    /// out of range.
+    pub async fn swap_remove(&self, index: usize) -> Result<Option<T>, Error<E>> {
+        let mut len = self.lock().await;
+        check_index(index, *len)?;
+        let last_index = *len - 1;
+
+        let removed = self.ds.get(&self.keys.element(index)).await?;
+        let last: Option<T> = if index == last_index {
+            None
+        } else {
+            self.ds.get(&self.keys.element(last_index)).await?
+        };
+        let change = LenChange::swap_remove(&self.keys, index, last_index, last.as_ref());
+        self.change_len(&mut len, change).await?;
+        Ok(removed)
+    }
+}
+
+impl<T, E, DS> Vec<T, DS>
+where
+    E: fmt::Debug,
+    T: Serialize + DeserializeOwned + Send + Sync,
+    DS: AsyncAtomic<Error = E>,
+{
+    /// changes the element at index using op, returns an error if the
+    /// index is out of range.
+    pub async fn update(
+        &self,
+        index: usize,
+        op: impl FnMut(T) -> T + Clone + Send + 'static,
+    ) -> Result<(), Error<E>> {
+        let len = self.lock().await;
+        check_index(index, *len)?;
+        self.ds.atomic_update(&self.keys.element(index), op).await?;
+        Ok(())
+    }
+}
+
+impl<T, E, DS> Vec<T, DS>
+where
+    E: fmt::Debug,
+    T: Serialize + DeserializeOwned + Send + Sync + 'static,
+    DS: AsyncWatch<Error = E>,
+{
+    /// subscribes to the changes made to the vector from here on, see
+    /// [`Watcher`]
+    pub async fn watch(&self) -> Result<Watcher<VecEvent<T>, DS::Subscriber, E>, Error<E>> {
+        // no changes can happen between reading the length and subscribing
+        let len = self.lock().await;
+        let subscriber = self.ds.watch_prefix(self.keys.prefix())?;
+        let decoder = VecDecoder::new::<DS::Codec>(self.keys.prefix(), *len);
+        Ok(Watcher::new(subscriber, decoder))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stores::{self, Blocking};
+
+    fn empty_vec() -> Vec<u32, Blocking<stores::BTreeMap>> {
+        let ds = Blocking::new(stores::BTreeMap::new());
+        Vec::new(ds, Prefix::from(1), Arc::new(Len::new(0)))
+    }
+
+    #[tokio::test]
+    async fn push_pop() {
+        let vec = empty_vec();
+        vec.push(&1).await.unwrap();
+        vec.push(&2).await.unwrap();
+        assert_eq!(vec.len().await, 2);
+        assert_eq!(vec.last().await.unwrap(), Some(2));
+        assert_eq!(vec.pop().await.unwrap(), Some(2));
+        assert_eq!(vec.pop().await.unwrap(), Some(1));
+        assert_eq!(vec.pop().await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn length_is_stored() {
+        let vec = empty_vec();
+        vec.push(&1).await.unwrap();
+        vec.push(&2).await.unwrap();
+        vec.swap_remove(0).await.unwrap();
+        assert_eq!(
+            stored_len::<u32, _>(&vec.ds, Prefix::from(1))
+                .await
+                .unwrap(),
+            1
+        );
+        assert_eq!(vec.first().await.unwrap(), Some(2));
+    }
+
+    #[tokio::test]
+    async fn len_without_stored_length() {
+        let ds = Blocking::new(stores::BTreeMap::new());
+        let keys = Keys::new(Prefix::from(1));
+        // an element is missing, the length is one past the last element
+        for index in [0, 1, 3, 4] {
+            ds.insert(&keys.element(index), &(index as u32))
+                .await
+                .unwrap();
+        }
+        assert_eq!(stored_len::<u32, _>(&ds, Prefix::from(1)).await.unwrap(), 5);
+        assert_eq!(stored_len::<u32, _>(&ds, Prefix::from(2)).await.unwrap(), 0);
+    }
+}
@@ -0,0 +1,259 @@
+use core::fmt;
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tokio::sync::MutexGuard;
+use tracing::{debug, warn};
+
+use super::Len;
+use crate::traits::data_store::Write;
+use crate::traits::{AsyncDataStore, AsyncWatch};
+use crate::wrapper::vec_deque::Prefixed;
+use crate::wrapper::{Ends, Prefix};
+use crate::wrapper::watch::{VecDequeDecoder, VecDequeEvent, Watcher};
+use crate::Error;
+
+/// mimics the API of [`VecDeque`](std::collections::VecDeque), see the
+/// blocking [`VecDeque`](crate::wrapper::VecDeque) for how the ends are
+/// stored.
+pub struct VecDeque<T, DS>
+where
+    DS: AsyncDataStore,
+{
+    phantom: PhantomData<T>,
+    ds: DS,
+    prefix: Prefix,
+    ends: Arc<Len<Ends>>,
+}
+
+/// Reads the ends of the deque at prefix when opening the database.
+#[doc(hidden)]
+pub async fn stored_ends<DS>(ds: &DS, prefix: Prefix) -> Result<Ends, DS::Error>
+where
+    DS: AsyncDataStore,
+{
+    Ok(ds.get(&Prefixed::ends(prefix)).await?.unwrap_or_default())
+}
+
+impl<T, E, DS> VecDeque<T, DS>
+where
+    E: fmt::Debug,
+    T: Serialize + DeserializeOwned + Send + Sync,
+    DS: AsyncDataStore<Error = E>,
+{
+    #[doc(hidden)]
+    pub fn new(ds: DS, prefix: Prefix, ends: Arc<Len<Ends>>) -> Self {
+        Self {
+            phantom: PhantomData,
+            ds,
+            prefix,
+            ends,
+        }
+    }
+
+    async fn lock(&self) -> MutexGuard<'_, Ends> {
+        self.ends.lock().await
+    }
+
+    fn key(&self, index: i64) -> Prefixed {
+        Prefixed::new(self.prefix.clone(), index)
+    }
+
+    fn ends_key(&self) -> Prefixed {
+        Prefixed::ends(self.prefix.clone())
+    }
+
+    /// If a write failed part of it might still have been applied,
+    /// the ends in the database are leading.
+    async fn resync(&self, ends: &mut Ends) {
+        match self.ds.get(&self.ends_key()).await {
+            Ok(Some(stored)) => *ends = stored,
+            Ok(None) => (),
+            Err(e) => warn!("could not resync deque ends: {e:?}"),
+        }
+    }
+
+    /// Adds the element at `index` and stores `new` as the ends. The element
+    /// goes first, if only that makes it to disk it is ignored.
+    async fn add(&self, ends: &mut Ends, new: Ends, index: i64, value: &T) -> Result<(), Error<E>> {
+        let res = self
+            .ds
+            .write_pair(
+                Write::Insert(&self.key(index), value),
+                Write::Insert(&self.ends_key(), &new),
+            )
+            .await;
+        if let Err(e) = res {
+            self.resync(ends).await;
+            return Err(e.into());
+        }
+        *ends = new;
+        Ok(())
+    }
+
+    /// Removes the element at `index` and stores `new` as the ends. The ends
+    /// go first, if only they make it to disk the element is ignored.
+    async fn take(&self, ends: &mut Ends, new: Ends, index: i64) -> Result<Option<T>, Error<E>> {
+        let value = self.ds.get(&self.key(index)).await?;
+        let res = self
+            .ds
+            .write_pair(
+                Write::Insert(&self.ends_key(), &new),
+                Write::<_, T>::remove(&self.key(index)),
+            )
+            .await;
+        if let Err(e) = res {
+            self.resync(ends).await;
+            return Err(e.into());
+        }
+        *ends = new;
+        Ok(value)
+    }
+
+    pub async fn push_back(&self, value: &T) -> Result<(), Error<E>> {
+        let mut ends = self.lock().await;
+        debug!("pushing onto back of deque (index: {})", ends.tail);
+        let new = Ends {
+            tail: ends.tail + 1,
+            ..*ends
+        };
+        let index = ends.tail;
+        self.add(&mut ends, new, index, value).await
+    }
+
+    pub async fn push_front(&self, value: &T) -> Result<(), Error<E>> {
+        let mut ends = self.lock().await;
+        let index = ends.head - 1;
+        debug!("pushing onto front of deque (index: {index})");
+        let new = Ends {
+            head: index,
+            ..*ends
+        };
+        self.add(&mut ends, new, index, value).await
+    }
+
+    pub async fn pop_back(&self) -> Result<Option<T>, Error<E>> {
+        let mut ends = self.lock().await;
+        if ends.len() == 0 {
+            return Ok(None);
+        }
+        let index = ends.tail - 1;
+        debug!("popping from back of deque (index: {index})");
+        let new = Ends {
+            tail: index,
+            ..*ends
+        };
+        self.take(&mut ends, new, index).await
+    }
+
+    pub async fn pop_front(&self) -> Result<Option<T>, Error<E>> {
+        let mut ends = self.lock().await;
+        if ends.len() == 0 {
+            return Ok(None);
+        }
+        let index = ends.head;
+        debug!("popping from front of deque (index: {index})");
+        let new = Ends {
+            head: index + 1,
+            ..*ends
+        };
+        self.take(&mut ends, new, index).await
+    }
+
+    /// the element `index` places from the front
+    pub async fn get(&self, index: usize) -> Result<Option<T>, Error<E>> {
+        let ends = *self.lock().await;
+        if index >= ends.len() {
+            return Ok(None);
+        }
+        Ok(self.ds.get(&self.key(ends.index(index))).await?)
+    }
+
+    pub async fn front(&self) -> Result<Option<T>, Error<E>> {
+        self.get(0).await
+    }
+
+    pub async fn back(&self) -> Result<Option<T>, Error<E>> {
+        match self.len().await.checked_sub(1) {
+            Some(index) => self.get(index).await,
+            None => Ok(None),
+        }
+    }
+
+    pub async fn len(&self) -> usize {
+        self.lock().await.len()
+    }
+
+    pub async fn is_empty(&self) -> bool {
+        self.len().await == 0
+    }
+
+    /// removes all elements
+    pub async fn clear(&self) -> Result<(), Error<E>> {
+        let mut ends = self.lock().await;
+        // the elements are ignored from here on
+        if let Err(e) = self.ds.insert(&self.ends_key(), &Ends::default()).await {
+            self.resync(&mut ends).await;
+            return Err(e.into());
+        }
+        let old = std::mem::take(&mut *ends);
+        for index in old.head..old.tail {
+            let _: Option<T> = self.ds.remove(&self.key(index)).await?;
+        }
+        Ok(())
+    }
+}
+
+impl<T, E, DS> VecDeque<T, DS>
+where
+    E: fmt::Debug,
+    T: Serialize + DeserializeOwned + Send + Sync + 'static,
+    DS: AsyncWatch<Error = E>,
+{
+    /// subscribes to the changes made to the deque from here on, see
+    /// [`Watcher`]
+    pub async fn watch(&self) -> Result<Watcher<VecDequeEvent<T>, DS::Subscriber, E>, Error<E>> {
+        // no changes can happen between reading the ends and subscribing
+        let ends = self.lock().await;
+        let subscriber = self.ds.watch_prefix(&self.prefix)?;
+        let decoder = VecDequeDecoder::new::<DS::Codec>(&self.prefix, *ends);
+        Ok(Watcher::new(subscriber, decoder))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stores::{self, Blocking};
+
+    fn empty_deque() -> VecDeque<u32, Blocking<stores::BTreeMap>> {
+        let ds = Blocking::new(stores::BTreeMap::new());
+        VecDeque::new(ds, Prefix::from(1), Arc::new(Len::new(Ends::default())))
+    }
+
+    #[tokio::test]
+    async fn push_pop() {
+        let deque = empty_deque();
+        deque.push_back(&2).await.unwrap();
+        deque.push_front(&1).await.unwrap();
+        assert_eq!(deque.len().await, 2);
+        assert_eq!(deque.front().await.unwrap(), Some(1));
+        assert_eq!(deque.back().await.unwrap(), Some(2));
+        assert_eq!(deque.pop_back().await.unwrap(), Some(2));
+        assert_eq!(deque.pop_back().await.unwrap(), Some(1));
+        assert_eq!(deque.pop_front().await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn ends_are_stored() {
+        let deque = empty_deque();
+        deque.push_front(&1).await.unwrap();
+        deque.push_front(&0).await.unwrap();
+        deque.pop_back().await.unwrap();
+        let ends = stored_ends(&deque.ds, Prefix::from(1)).await.unwrap();
+        assert_eq!(ends.len(), 1);
+        assert_eq!(deque.front().await.unwrap(), Some(0));
+    }
+}
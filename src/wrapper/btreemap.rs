@@ -0,0 +1,291 @@
+use core::fmt;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::marker::PhantomData;
+use tracing::instrument;
+
+use crate::traits::{byte_store, data_store, ByteStore, DataStore};
+use crate::wrapper::Prefix;
+use crate::wrapper::watch::{MapDecoder, MapWatcher, SetDecoder, SetEvent, Watcher};
+use crate::Error;
+
+use super::map::Prefixed;
+
+mod iterator;
+
+pub(crate) use iterator::Keys;
+
+/// mimics the API of [`BTreeMap`][std::collections::BTreeMap]. The elements
+/// are kept in key order by the database, range queries and iterating only
+/// visit the part of the map that is needed.
+pub struct BTreeMap<Key, Value, DS>
+where
+    Key: Serialize,
+    Value: Serialize + DeserializeOwned,
+    DS: DataStore,
+{
+    phantom_key: PhantomData<Key>,
+    phantom_val: PhantomData<Value>,
+    tree: DS,
+    prefix: Prefix,
+}
+
+impl<Key, Value, E, DS> BTreeMap<Key, Value, DS>
+where
+    E: fmt::Debug,
+    Key: Serialize + DeserializeOwned,
+    Value: Serialize + DeserializeOwned,
+    DS: DataStore<Error = E>,
+{
+    #[doc(hidden)]
+    #[instrument(skip(tree), level = "debug")]
+    pub fn new(tree: DS, prefix: Prefix) -> Self {
+        Self {
+            phantom_key: PhantomData,
+            phantom_val: PhantomData,
+            tree,
+            prefix,
+        }
+    }
+
+    /// returns existing value if any was set
+    #[instrument(skip_all, level = "debug")]
+    pub fn insert(&self, key: &Key, value: &Value) -> Result<Option<Value>, Error<E>> {
+        let key = Prefixed::new(self.prefix.clone(), key);
+        let existing = self.tree.insert(&key, value)?;
+        Ok(existing)
+    }
+
+    #[instrument(skip_all, level = "debug")]
+    pub fn get(&self, key: &Key) -> Result<Option<Value>, Error<E>> {
+        let key = Prefixed::new(self.prefix.clone(), key);
+        let value = self.tree.get(&key)?;
+        Ok(value)
+    }
+
+    /// returns the value at the key if it was in the map
+    #[instrument(skip_all, level = "debug")]
+    pub fn remove(&self, key: &Key) -> Result<Option<Value>, Error<E>> {
+        let key = Prefixed::new(self.prefix.clone(), key);
+        let existing = self.tree.remove(&key)?;
+        Ok(existing)
+    }
+
+    #[instrument(skip_all, level = "debug")]
+    pub fn contains_key(&self, key: &Key) -> Result<bool, Error<E>> {
+        Ok(self.get(key)?.is_some())
+    }
+
+    /// returns the value at the key, if there is none inserts and
+    /// returns the value returned by `f`.
+    #[instrument(skip_all, level = "debug")]
+    pub fn get_or_insert_with(
+        &self,
+        key: &Key,
+        f: impl FnOnce() -> Value,
+    ) -> Result<Value, Error<E>> {
+        if let Some(value) = self.get(key)? {
+            return Ok(value);
+        }
+        let value = f();
+        self.insert(key, &value)?;
+        Ok(value)
+    }
+}
+
+/// These need to scan over the map in the database which requires
+/// an ordered store
+impl<Key, Value, E, DS> BTreeMap<Key, Value, DS>
+where
+    E: fmt::Debug + From<<DS as ByteStore>::Error>,
+    Key: Serialize + DeserializeOwned,
+    Value: Serialize + DeserializeOwned,
+    DS: DataStore<Error = E> + byte_store::Ordered,
+{
+    /// returns the element with the smallest key
+    #[instrument(skip_all, level = "debug")]
+    pub fn first_key_value(&self) -> Result<Option<(Key, Value)>, Error<E>> {
+        self.iter().next().transpose()
+    }
+
+    /// returns the element with the largest key
+    #[instrument(skip_all, level = "debug")]
+    pub fn last_key_value(&self) -> Result<Option<(Key, Value)>, Error<E>> {
+        self.iter().next_back().transpose()
+    }
+
+    /// removes and returns the element with the smallest key
+    #[instrument(skip_all, level = "debug")]
+    pub fn pop_first(&self) -> Result<Option<(Key, Value)>, Error<E>> {
+        loop {
+            let Some(res) = self.raw().next() else {
+                return Ok(None);
+            };
+            // someone else removed the element first, try the next one
+            if let Some(pair) = self.take(res)? {
+                return Ok(Some(pair));
+            }
+        }
+    }
+
+    /// removes and returns the element with the largest key
+    #[instrument(skip_all, level = "debug")]
+    pub fn pop_last(&self) -> Result<Option<(Key, Value)>, Error<E>> {
+        loop {
+            let Some(res) = self.raw().next_back() else {
+                return Ok(None);
+            };
+            // someone else removed the element first, try the next one
+            if let Some(pair) = self.take(res)? {
+                return Ok(Some(pair));
+            }
+        }
+    }
+
+    /// the number of elements in the map, this has to visit every element
+    #[instrument(skip_all, level = "debug")]
+    pub fn len(&self) -> Result<usize, Error<E>> {
+        let mut len = 0;
+        for res in self.raw() {
+            res.map_err(|e| Error::Database(E::from(e)))?;
+            len += 1;
+        }
+        Ok(len)
+    }
+
+    #[instrument(skip_all, level = "debug")]
+    pub fn is_empty(&self) -> Result<bool, Error<E>> {
+        match self.raw().next() {
+            None => Ok(true),
+            Some(Ok(_)) => Ok(false),
+            Some(Err(e)) => Err(Error::Database(E::from(e))),
+        }
+    }
+
+    /// removes all elements from the map
+    #[instrument(skip_all, level = "debug")]
+    pub fn clear(&self) -> Result<(), Error<E>> {
+        let db_err = |e| Error::Database(E::from(e));
+        for res in self.raw() {
+            let (key, _) = res.map_err(db_err)?;
+            ByteStore::remove(&self.tree, key.as_ref()).map_err(db_err)?;
+        }
+        Ok(())
+    }
+
+    /// removes the element, returns `None` if it is already gone
+    fn take(
+        &self,
+        res: Result<byte_store::KeyValue<DS>, <DS as ByteStore>::Error>,
+    ) -> Result<Option<(Key, Value)>, Error<E>> {
+        let db_err = |e| Error::Database(E::from(e));
+        let (key, _) = res.map_err(db_err)?;
+        match ByteStore::remove(&self.tree, key.as_ref()).map_err(db_err)? {
+            Some(value) => {
+                let prefix_len = self.prefix.as_bytes().len();
+                iterator::decode::<_, _, E, DS>(Ok((key, value)), prefix_len).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+impl<Key, Value, E, DS> BTreeMap<Key, Value, DS>
+where
+    E: fmt::Debug,
+    Key: Serialize + DeserializeOwned + 'static,
+    Value: Serialize + DeserializeOwned + 'static,
+    DS: data_store::Watch<Error = E>,
+{
+    /// subscribes to the changes made to the map from here on, see
+    /// [`Watcher`]
+    #[instrument(skip_all, level = "debug")]
+    pub fn watch(&self) -> Result<MapWatcher<Key, Value, DS::Subscriber, E>, Error<E>> {
+        let subscriber = self.tree.watch_prefix(&self.prefix)?;
+        let decoder = MapDecoder::new::<DS::Codec>(&self.prefix);
+        Ok(Watcher::new(subscriber, decoder))
+    }
+
+    /// like [`watch`](Self::watch) without the values, for [`Set`](super::Set)
+    pub(crate) fn watch_keys(&self) -> Result<Watcher<SetEvent<Key>, DS::Subscriber, E>, Error<E>> {
+        let subscriber = self.tree.watch_prefix(&self.prefix)?;
+        Ok(Watcher::new(subscriber, SetDecoder::new(&self.prefix)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stores;
+
+    pub(crate) type TestMap<K, V> = BTreeMap<K, V, stores::BTreeMap>;
+    pub(crate) fn filled(keys: &[u16]) -> TestMap<u16, u16> {
+        let ds = stores::BTreeMap::new();
+        let neighbour: TestMap<u16, u16> = BTreeMap::new(ds.clone(), Prefix::from(0));
+        neighbour.insert(&0, &0).unwrap();
+        let neighbour: TestMap<u16, u16> = BTreeMap::new(ds.clone(), Prefix::from(2));
+        neighbour.insert(&0, &0).unwrap();
+
+        let map = BTreeMap::new(ds, Prefix::from(1));
+        for key in keys {
+            map.insert(key, &(key * 10)).unwrap();
+        }
+        map
+    }
+
+    #[test]
+    fn first_and_last() {
+        let map = filled(&[300, 7, 42]);
+        assert_eq!(map.first_key_value().unwrap(), Some((7, 70)));
+        assert_eq!(map.last_key_value().unwrap(), Some((300, 3000)));
+
+        let empty = filled(&[]);
+        assert_eq!(empty.first_key_value().unwrap(), None);
+        assert_eq!(empty.last_key_value().unwrap(), None);
+    }
+
+    #[test]
+    fn pop_first_and_last() {
+        let map = filled(&[300, 7, 42]);
+        assert_eq!(map.pop_first().unwrap(), Some((7, 70)));
+        assert_eq!(map.pop_last().unwrap(), Some((300, 3000)));
+        assert_eq!(map.pop_last().unwrap(), Some((42, 420)));
+        assert_eq!(map.pop_first().unwrap(), None);
+        assert_eq!(map.pop_last().unwrap(), None);
+    }
+
+    #[test]
+    fn len_and_clear() {
+        let map = filled(&[1, 2, 3]);
+        assert_eq!(map.len().unwrap(), 3);
+        assert!(!map.is_empty().unwrap());
+
+        map.clear().unwrap();
+        assert!(map.is_empty().unwrap());
+        let neighbour: TestMap<u16, u16> = BTreeMap::new(map.tree.clone(), Prefix::from(2));
+        assert_eq!(neighbour.len().unwrap(), 1);
+    }
+
+    #[test]
+    fn nested_prefix() {
+        let ds = stores::BTreeMap::new();
+        let map: TestMap<u16, u16> = BTreeMap::new(ds.clone(), Prefix::from(1).field(0));
+        let neighbour: TestMap<u16, u16> = BTreeMap::new(ds, Prefix::from(1).field(1));
+        neighbour.insert(&0, &0).unwrap();
+        map.insert(&300, &1).unwrap();
+        map.insert(&2, &2).unwrap();
+
+        let keys: std::vec::Vec<u16> = map.keys().map(Result::unwrap).collect();
+        assert_eq!(keys, vec![2, 300]);
+        assert_eq!(map.pop_last().unwrap(), Some((300, 1)));
+        assert_eq!(neighbour.len().unwrap(), 1);
+    }
+
+    #[test]
+    fn database_error_is_returned() {
+        let map = filled(&[1]);
+        map.tree.force_error();
+        assert!(matches!(map.pop_first(), Err(Error::Database(_))));
+        assert!(matches!(map.last_key_value(), Err(Error::Database(_))));
+    }
+}
@@ -0,0 +1,329 @@
+use core::fmt;
+use core::marker::PhantomData;
+use core::ops::{Bound, RangeBounds};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::codec::{self, Codec};
+use crate::traits::byte_store::{self, KeyValue};
+use crate::traits::{ByteStore, DataStore};
+use crate::Error;
+
+use super::{BTreeMap, Prefixed};
+
+/// The keys between `front` (inclusive) and `back` (exclusive), walked from
+/// either end. Every step is a separate bounded lookup in the store, changes
+/// made while iterating are seen.
+pub(super) struct RawRange<'a, DS> {
+    tree: &'a DS,
+    prefix_len: usize,
+    front: Vec<u8>,
+    back: Vec<u8>,
+    done: bool,
+}
+
+/// the smallest key larger then `key`
+fn successor(key: &[u8]) -> Vec<u8> {
+    let mut next = key.to_vec();
+    next.push(0);
+    next
+}
+
+impl<'a, DS: byte_store::Ordered> RawRange<'a, DS> {
+    fn exhausted(&mut self) -> bool {
+        self.done |= self.front >= self.back;
+        self.done
+    }
+}
+
+impl<'a, DS: byte_store::Ordered> Iterator for RawRange<'a, DS> {
+    type Item = Result<KeyValue<DS>, DS::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.exhausted() {
+            return None;
+        }
+        let start = Bound::Included(self.front.as_slice());
+        let end = Bound::Excluded(self.back.as_slice());
+        match byte_store::Ordered::range(self.tree, start, end).next() {
+            Some(Ok((key, val))) => {
+                self.front = successor(key.as_ref());
+                Some(Ok((key, val)))
+            }
+            Some(Err(e)) => {
+                self.done = true;
+                Some(Err(e))
+            }
+            None => {
+                self.done = true;
+                None
+            }
+        }
+    }
+}
+
+impl<'a, DS: byte_store::Ordered> DoubleEndedIterator for RawRange<'a, DS> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.exhausted() {
+            return None;
+        }
+        match byte_store::Ordered::get_lt(self.tree, &self.back) {
+            Ok(Some((key, val))) if key.as_ref() >= self.front.as_slice() => {
+                self.back = key.as_ref().to_vec();
+                Some(Ok((key, val)))
+            }
+            Ok(_) => {
+                self.done = true;
+                None
+            }
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+pub(super) fn decode<K, V, E, DS>(
+    res: Result<KeyValue<DS>, <DS as ByteStore>::Error>,
+    prefix_len: usize,
+) -> Result<(K, V), Error<E>>
+where
+    E: fmt::Debug + From<<DS as ByteStore>::Error>,
+    K: DeserializeOwned,
+    V: DeserializeOwned,
+    DS: ByteStore,
+{
+    let (key, val) = res.map_err(|e| Error::Database(E::from(e)))?;
+    let key = &key.as_ref()[prefix_len..];
+    let key = codec::deserialize_key(key).map_err(Error::DeSerializingKey)?;
+    let val = DS::Codec::deserialize(val.as_ref()).map_err(Error::DeSerializingVal)?;
+    Ok((key, val))
+}
+
+pub struct Iter<'a, K, V, E, DS>
+where
+    K: Serialize + DeserializeOwned,
+    V: Serialize + DeserializeOwned,
+    E: fmt::Debug,
+    DS: DataStore<Error = E> + byte_store::Ordered + 'a,
+{
+    raw: RawRange<'a, DS>,
+    phantom_val: PhantomData<V>,
+    phantom_key: PhantomData<K>,
+}
+
+impl<'a, K, V, E, DS> Iterator for Iter<'a, K, V, E, DS>
+where
+    E: fmt::Debug + From<<DS as ByteStore>::Error>,
+    K: Serialize + DeserializeOwned,
+    V: Serialize + DeserializeOwned,
+    DS: DataStore<Error = E> + byte_store::Ordered + 'a,
+{
+    type Item = Result<(K, V), Error<E>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let prefix_len = self.raw.prefix_len;
+        self.raw.next().map(|res| decode::<K, V, E, DS>(res, prefix_len))
+    }
+}
+
+impl<'a, K, V, E, DS> DoubleEndedIterator for Iter<'a, K, V, E, DS>
+where
+    E: fmt::Debug + From<<DS as ByteStore>::Error>,
+    K: Serialize + DeserializeOwned,
+    V: Serialize + DeserializeOwned,
+    DS: DataStore<Error = E> + byte_store::Ordered + 'a,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let prefix_len = self.raw.prefix_len;
+        self.raw.next_back().map(|res| decode::<K, V, E, DS>(res, prefix_len))
+    }
+}
+
+pub struct Values<'a, K, V, E, DS>(Iter<'a, K, V, E, DS>)
+where
+    K: Serialize + DeserializeOwned,
+    V: Serialize + DeserializeOwned,
+    E: fmt::Debug,
+    DS: DataStore<Error = E> + byte_store::Ordered + 'a;
+
+impl<'a, K, V, E, DS> Iterator for Values<'a, K, V, E, DS>
+where
+    K: Serialize + DeserializeOwned,
+    V: Serialize + DeserializeOwned,
+    E: fmt::Debug + From<<DS as ByteStore>::Error>,
+    DS: DataStore<Error = E> + byte_store::Ordered + 'a,
+{
+    type Item = Result<V, Error<E>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|res| res.map(|(_, val)| val))
+    }
+}
+
+impl<'a, K, V, E, DS> DoubleEndedIterator for Values<'a, K, V, E, DS>
+where
+    K: Serialize + DeserializeOwned,
+    V: Serialize + DeserializeOwned,
+    E: fmt::Debug + From<<DS as ByteStore>::Error>,
+    DS: DataStore<Error = E> + byte_store::Ordered + 'a,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back().map(|res| res.map(|(_, val)| val))
+    }
+}
+
+pub struct Keys<'a, K, V, E, DS>(pub(crate) Iter<'a, K, V, E, DS>)
+where
+    K: Serialize + DeserializeOwned,
+    V: Serialize + DeserializeOwned,
+    E: fmt::Debug,
+    DS: DataStore<Error = E> + byte_store::Ordered + 'a;
+
+impl<'a, K, V, E, DS> Iterator for Keys<'a, K, V, E, DS>
+where
+    K: Serialize + DeserializeOwned,
+    V: Serialize + DeserializeOwned,
+    E: fmt::Debug + From<<DS as ByteStore>::Error>,
+    DS: DataStore<Error = E> + byte_store::Ordered + 'a,
+{
+    type Item = Result<K, Error<E>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|res| res.map(|(key, _)| key))
+    }
+}
+
+impl<'a, K, V, E, DS> DoubleEndedIterator for Keys<'a, K, V, E, DS>
+where
+    K: Serialize + DeserializeOwned,
+    V: Serialize + DeserializeOwned,
+    E: fmt::Debug + From<<DS as ByteStore>::Error>,
+    DS: DataStore<Error = E> + byte_store::Ordered + 'a,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back().map(|res| res.map(|(key, _)| key))
+    }
+}
+
+impl<Key, Value, E, DS> BTreeMap<Key, Value, DS>
+where
+    E: fmt::Debug,
+    Key: Serialize + DeserializeOwned,
+    Value: Serialize + DeserializeOwned,
+    DS: DataStore<Error = E> + byte_store::Ordered,
+{
+    fn key_bytes(&self, key: &Key) -> Result<Vec<u8>, Error<E>> {
+        codec::serialize_key(&Prefixed::new(self.prefix.clone(), key)).map_err(Error::SerializingKey)
+    }
+
+    /// all the keys of the map
+    pub(super) fn raw(&self) -> RawRange<'_, DS> {
+        RawRange {
+            tree: &self.tree,
+            prefix_len: self.prefix.as_bytes().len(),
+            front: self.prefix.as_bytes().to_vec(),
+            back: byte_store::prefix_end(self.prefix.as_bytes())
+                .expect("the key of a field is never u8::MAX"),
+            done: false,
+        }
+    }
+
+    fn typed(raw: RawRange<'_, DS>) -> Iter<'_, Key, Value, E, DS> {
+        Iter {
+            raw,
+            phantom_val: PhantomData,
+            phantom_key: PhantomData,
+        }
+    }
+
+    /// iterate over the elements in ascending key order
+    pub fn iter(&self) -> Iter<'_, Key, Value, E, DS> {
+        Self::typed(self.raw())
+    }
+
+    pub fn values(&self) -> Values<'_, Key, Value, E, DS> {
+        Values(self.iter())
+    }
+
+    pub fn keys(&self) -> Keys<'_, Key, Value, E, DS> {
+        Keys(self.iter())
+    }
+
+    /// iterate over the elements with a key in `range` in ascending key
+    /// order. Errors if a bound of the range can not be serialized.
+    pub fn range(
+        &self,
+        range: impl RangeBounds<Key>,
+    ) -> Result<Iter<'_, Key, Value, E, DS>, Error<E>> {
+        let mut raw = self.raw();
+        match range.start_bound() {
+            Bound::Included(key) => raw.front = self.key_bytes(key)?,
+            Bound::Excluded(key) => raw.front = successor(&self.key_bytes(key)?),
+            Bound::Unbounded => (),
+        }
+        match range.end_bound() {
+            Bound::Included(key) => raw.back = successor(&self.key_bytes(key)?),
+            Bound::Excluded(key) => raw.back = self.key_bytes(key)?,
+            Bound::Unbounded => (),
+        }
+        Ok(Self::typed(raw))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::*;
+    use core::ops::Bound::{self, *};
+
+    fn keys(map: &TestMap<u16, u16>, range: (Bound<u16>, Bound<u16>)) -> Vec<u16> {
+        map.range(range)
+            .unwrap()
+            .map(|res| res.unwrap().0)
+            .collect()
+    }
+
+    #[test]
+    fn iterates_in_key_order() {
+        let map = filled(&[300, 7, 42, 1000]);
+        let keys: Vec<u16> = map.keys().map(Result::unwrap).collect();
+        assert_eq!(keys, vec![7, 42, 300, 1000]);
+        let values: Vec<u16> = map.values().rev().map(Result::unwrap).collect();
+        assert_eq!(values, vec![10000, 3000, 420, 70]);
+    }
+
+    #[test]
+    fn both_ends_meet() {
+        let map = filled(&[1, 2, 3, 4, 5]);
+        let mut iter = map.keys();
+        assert_eq!(iter.next().unwrap().unwrap(), 1);
+        assert_eq!(iter.next_back().unwrap().unwrap(), 5);
+        assert_eq!(iter.next_back().unwrap().unwrap(), 4);
+        assert_eq!(iter.next().unwrap().unwrap(), 2);
+        assert_eq!(iter.next().unwrap().unwrap(), 3);
+        assert!(iter.next().is_none());
+        assert!(iter.next_back().is_none());
+    }
+
+    #[test]
+    fn range() {
+        let map = filled(&[1, 2, 3, 256, 257]);
+        assert_eq!(keys(&map, (Included(2), Excluded(256))), vec![2, 3]);
+        assert_eq!(keys(&map, (Excluded(2), Included(256))), vec![3, 256]);
+        assert_eq!(keys(&map, (Unbounded, Included(2))), vec![1, 2]);
+        assert_eq!(keys(&map, (Included(256), Unbounded)), vec![256, 257]);
+        assert!(keys(&map, (Included(5), Excluded(5))).is_empty());
+        assert!(keys(&map, (Included(300), Excluded(2))).is_empty());
+
+        let last_two: Vec<u16> = map
+            .range(2..=256)
+            .unwrap()
+            .rev()
+            .take(2)
+            .map(|res| res.unwrap().0)
+            .collect();
+        assert_eq!(last_two, vec![256, 3]);
+    }
+}
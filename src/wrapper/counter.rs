@@ -0,0 +1,138 @@
+use core::fmt;
+use std::marker::PhantomData;
+
+use crate::codec;
+use crate::traits::{byte_store, ByteStore, DataStore};
+use crate::wrapper::Prefix;
+use crate::Error;
+
+/// An integer a [`Counter`] can count with, implemented for the fixed width
+/// integer types. It is stored as little endian bytes.
+pub trait Integer: Copy {
+    const ZERO: Self;
+    const ONE: Self;
+    fn wrapping_neg(self) -> Self;
+    fn to_le_vec(self) -> Vec<u8>;
+    /// `None` if `bytes` is not as wide as the integer
+    fn from_le_slice(bytes: &[u8]) -> Option<Self>;
+}
+
+macro_rules! integer {
+    ($($ty:ty),*) => {$(
+        impl Integer for $ty {
+            const ZERO: Self = 0;
+            const ONE: Self = 1;
+
+            fn wrapping_neg(self) -> Self {
+                <$ty>::wrapping_neg(self)
+            }
+
+            fn to_le_vec(self) -> Vec<u8> {
+                self.to_le_bytes().to_vec()
+            }
+
+            fn from_le_slice(bytes: &[u8]) -> Option<Self> {
+                bytes.try_into().ok().map(<$ty>::from_le_bytes)
+            }
+        }
+    )*};
+}
+
+integer!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128);
+
+/// A number that is changed by adding to it. On sled and RocksDB additions
+/// are merged into the stored value without reading it, other stores use an
+/// atomic update. A missing value is zero and values wrap around on
+/// overflow.
+///
+/// The value is always stored as a little endian integer, the codec is not
+/// used.
+pub struct Counter<T, DS>
+where
+    DS: DataStore,
+{
+    phantom: PhantomData<T>,
+    ds: DS,
+    key: Prefix,
+}
+
+impl<T, DS: DataStore> Counter<T, DS> {
+    #[doc(hidden)]
+    pub fn new(ds: DS, key: Prefix) -> Self {
+        Self {
+            phantom: PhantomData,
+            ds,
+            key,
+        }
+    }
+}
+
+/// The store needs to implement [`byte_store::Atomic`], with `db=trait` that
+/// is not required to open the struct
+impl<T, E, DS> Counter<T, DS>
+where
+    E: fmt::Debug + From<<DS as ByteStore>::Error>,
+    T: Integer,
+    DS: DataStore<Error = E> + byte_store::Atomic,
+{
+    fn key(&self) -> Result<Vec<u8>, Error<E>> {
+        codec::serialize_key(&self.key).map_err(Error::SerializingKey)
+    }
+
+    fn decode(bytes: &[u8]) -> Result<T, Error<E>> {
+        T::from_le_slice(bytes).ok_or_else(|| {
+            let len = core::mem::size_of::<T>();
+            let err = format!("counter is {} bytes wide, expected {len}", bytes.len());
+            Error::DeSerializingVal(err.into())
+        })
+    }
+
+    pub fn get(&self) -> Result<T, Error<E>> {
+        let bytes = ByteStore::get(&self.ds, &self.key()?).map_err(db_err)?;
+        match bytes {
+            Some(bytes) => Self::decode(bytes.as_ref()),
+            None => Ok(T::ZERO),
+        }
+    }
+
+    pub fn increment(&self) -> Result<(), Error<E>> {
+        self.add(T::ONE)
+    }
+
+    /// Adds without reading the value. A stored value of another width
+    /// than `T` is left unchanged, reading it fails.
+    pub fn add(&self, n: T) -> Result<(), Error<E>> {
+        byte_store::Atomic::counter_add(&self.ds, &self.key()?, &n.to_le_vec()).map_err(db_err)
+    }
+
+    pub fn sub(&self, n: T) -> Result<(), Error<E>> {
+        self.add(n.wrapping_neg())
+    }
+
+    /// Adds `n` and returns the value before. Always reads the value using
+    /// an atomic update.
+    pub fn fetch_add(&self, n: T) -> Result<T, Error<E>> {
+        let key = self.key()?;
+        let delta = n.to_le_vec();
+        let mut old = None;
+        byte_store::Atomic::atomic_update(&self.ds, &key, |current| {
+            old = current.map(<[u8]>::to_vec);
+            crate::stores::counter_merge(&key, current, &delta)
+        })
+        .map_err(db_err)?;
+        match old {
+            Some(bytes) => Self::decode(&bytes),
+            None => Ok(T::ZERO),
+        }
+    }
+
+    /// Sets the counter back to zero
+    pub fn reset(&self) -> Result<(), Error<E>> {
+        ByteStore::remove(&self.ds, &self.key()?).map_err(db_err)?;
+        Ok(())
+    }
+}
+
+fn db_err<E: fmt::Debug>(e: impl Into<E>) -> Error<E> {
+    Error::Database(e.into())
+}
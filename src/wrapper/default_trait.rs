@@ -4,8 +4,10 @@ use std::marker::PhantomData;
 use serde::Serialize;
 use serde::de::DeserializeOwned;
 
+use crate::wrapper::Prefix;
+use crate::wrapper::watch::{ValueDecoder, ValueEvent, Watcher};
 use crate::Error;
-use crate::traits::DataStore;
+use crate::traits::{data_store, DataStore};
 
 /// handles missing values by generating a replacement using the types [`Default`] implementation
 pub struct DefaultTrait<T, DS> 
@@ -15,7 +17,7 @@ where
 {
     phantom: PhantomData<T>,
     ds: DS,
-    key: u8,
+    key: Prefix,
 }
 
 impl<T, E, DS> DefaultTrait<T, DS>
@@ -26,9 +28,9 @@ where
 
 {
     #[doc(hidden)]
-    pub fn new(ds: DS, key: u8) -> Self {
+    pub fn new(ds: DS, key: Prefix) -> Self {
         Self {
-            phantom: PhantomData::default(),
+            phantom: PhantomData,
             ds,
             key,
         }
@@ -42,4 +44,67 @@ where
     pub fn get(&self) -> Result<T, Error<E>> {
         Ok(self.ds.get(&self.key)?.unwrap_or_default())
     }
+
+    /// Removes the value returning it, the default if there is none
+    pub fn take(&mut self) -> Result<T, Error<E>> {
+        let old = self.ds.remove(&self.key)?;
+        Ok(old.unwrap_or_default())
+    }
+
+    /// Removes the value so `get` falls back to the default again
+    pub fn clear(&mut self) -> Result<(), Error<E>> {
+        self.ds.remove::<_, T>(&self.key)?;
+        Ok(())
+    }
+
+    /// Sets the value returning the previous one, the default if there is none
+    pub fn replace(&mut self, value: &T) -> Result<T, Error<E>> {
+        let old = self.ds.insert(&self.key, value)?;
+        Ok(old.unwrap_or_default())
+    }
+
+    /// Whether a value is stored
+    pub fn is_set(&self) -> Result<bool, Error<E>> {
+        Ok(self.ds.get::<_, T>(&self.key)?.is_some())
+    }
+}
+
+impl<T, E, DS> DefaultTrait<T, DS>
+where
+    E: fmt::Debug,
+    T: Serialize + DeserializeOwned + Default,
+    DS: data_store::Atomic<Error = E>,
+{
+    /// Atomically applies `op` to the value, a missing value is replaced
+    /// by [`Default::default`] first. `op` can run more than once.
+    pub fn update(&self, op: impl FnMut(T) -> T + Clone) -> Result<(), Error<E>> {
+        self.update_and_fetch(op).map(|_| ())
+    }
+    /// Like [`update`](Self::update), returns the value before the update
+    pub fn fetch_update(&self, op: impl FnMut(T) -> T + Clone) -> Result<T, Error<E>> {
+        let op = super::seeded(T::default, op);
+        let (old, _) = self.ds.atomic_fetch_update(&self.key, op)?;
+        Ok(old.unwrap_or_default())
+    }
+    /// Like [`update`](Self::update), returns the value after the update
+    pub fn update_and_fetch(&self, op: impl FnMut(T) -> T + Clone) -> Result<T, Error<E>> {
+        let op = super::seeded(T::default, op);
+        let (_, new) = self.ds.atomic_fetch_update(&self.key, op)?;
+        Ok(new.expect("seeded op always returns a value"))
+    }
+}
+
+impl<T, E, DS> DefaultTrait<T, DS>
+where
+    E: fmt::Debug,
+    T: Serialize + DeserializeOwned + Default + Send + 'static,
+    DS: data_store::Watch<Error = E>,
+{
+    /// subscribes to the changes made to the value from here on, see
+    /// [`Watcher`]
+    pub fn watch(&self) -> Result<Watcher<ValueEvent<T>, DS::Subscriber, E>, Error<E>> {
+        let subscriber = self.ds.watch_prefix(&self.key)?;
+        let decoder = ValueDecoder::new::<DS::Codec>(&self.key);
+        Ok(Watcher::new(subscriber, decoder))
+    }
 }
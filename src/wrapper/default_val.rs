@@ -3,7 +3,9 @@ use std::fmt;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
-use crate::traits::{data_store, DataStore};
+use crate::traits::{data_store, CasOutcome, DataStore};
+use crate::wrapper::Prefix;
+use crate::wrapper::watch::{ValueDecoder, ValueEvent, Watcher};
 use crate::Error;
 
 /// handles missing values by generating a replacement from an expression. 
@@ -14,7 +16,7 @@ where
 {
     default_value: T,
     ds: DS,
-    key: u8,
+    key: Prefix,
 }
 
 impl<T, E, DS> DefaultValue<T, DS>
@@ -24,7 +26,7 @@ where
     DS: DataStore<Error = E>,
 {
     #[doc(hidden)]
-    pub fn new(ds: DS, key: u8, default_value: T) -> Self {
+    pub fn new(ds: DS, key: Prefix, default_value: T) -> Self {
         Self {
             default_value,
             ds,
@@ -43,6 +45,29 @@ where
             .get(&self.key)?
             .unwrap_or_else(|| self.default_value.clone()))
     }
+
+    /// Removes the value returning it, the default if there is none
+    pub fn take(&mut self) -> Result<T, Error<E>> {
+        let old = self.ds.remove(&self.key)?;
+        Ok(old.unwrap_or_else(|| self.default_value.clone()))
+    }
+
+    /// Removes the value so `get` falls back to the default again
+    pub fn clear(&mut self) -> Result<(), Error<E>> {
+        self.ds.remove::<_, T>(&self.key)?;
+        Ok(())
+    }
+
+    /// Sets the value returning the previous one, the default if there is none
+    pub fn replace(&mut self, value: &T) -> Result<T, Error<E>> {
+        let old = self.ds.insert(&self.key, value)?;
+        Ok(old.unwrap_or_else(|| self.default_value.clone()))
+    }
+
+    /// Whether a value is stored
+    pub fn is_set(&self) -> Result<bool, Error<E>> {
+        Ok(self.ds.get::<_, T>(&self.key)?.is_some())
+    }
 }
 
 impl<T, E, DS> DefaultValue<T, DS>
@@ -51,11 +76,49 @@ where
     T: Serialize + DeserializeOwned + Clone,
     DS: data_store::Atomic<Error = E>,
 {
+    /// Atomically applies `op` to the value, a missing value is replaced
+    /// by the default first. `op` can run more than once.
     pub fn update(&self, op: impl FnMut(T) -> T + Clone) -> Result<(), Error<E>> {
-        self.ds.atomic_update(&self.key, op)?;
-        Ok(())
+        self.update_and_fetch(op).map(|_| ())
+    }
+    /// Like [`update`](Self::update), returns the value before the update
+    pub fn fetch_update(&self, op: impl FnMut(T) -> T + Clone) -> Result<T, Error<E>> {
+        let (old, _) = self.ds.atomic_fetch_update(&self.key, self.seeded(op))?;
+        Ok(old.unwrap_or_else(|| self.default_value.clone()))
     }
-    pub fn conditional_update(&self, old: T, new: T) -> Result<(), Error<E>> {
-        Ok(self.ds.conditional_update(&self.key, &new, &old)?)
+    /// Like [`update`](Self::update), returns the value after the update
+    pub fn update_and_fetch(&self, op: impl FnMut(T) -> T + Clone) -> Result<T, Error<E>> {
+        let (_, new) = self.ds.atomic_fetch_update(&self.key, self.seeded(op))?;
+        Ok(new.expect("seeded op always returns a value"))
+    }
+    fn seeded(
+        &self,
+        op: impl FnMut(T) -> T + Clone,
+    ) -> impl FnMut(Option<T>) -> Option<T> + Clone {
+        let default = self.default_value.clone();
+        super::seeded(move || default.clone(), op)
+    }
+    /// Sets the value to `new` if the stored value is `old`. A value that
+    /// was never set is not compared to the default, the outcome is then
+    /// a mismatch with `current: None`.
+    pub fn conditional_update(&self, old: T, new: T) -> Result<CasOutcome<T>, Error<E>> {
+        Ok(self
+            .ds
+            .conditional_update(&self.key, Some(&new), Some(&old))?)
+    }
+}
+
+impl<T, E, DS> DefaultValue<T, DS>
+where
+    E: fmt::Debug,
+    T: Serialize + DeserializeOwned + Clone + Send + 'static,
+    DS: data_store::Watch<Error = E>,
+{
+    /// subscribes to the changes made to the value from here on, see
+    /// [`Watcher`]
+    pub fn watch(&self) -> Result<Watcher<ValueEvent<T>, DS::Subscriber, E>, Error<E>> {
+        let subscriber = self.ds.watch_prefix(&self.key)?;
+        let decoder = ValueDecoder::new::<DS::Codec>(&self.key);
+        Ok(Watcher::new(subscriber, decoder))
     }
 }
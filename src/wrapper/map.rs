@@ -4,12 +4,18 @@ use serde::Serialize;
 use std::marker::PhantomData;
 use tracing::{instrument, trace};
 
-use crate::traits::DataStore;
+use crate::codec::{self, Codec};
+use crate::traits::{byte_store, data_store, ByteStore, DataStore};
+use crate::wrapper::Prefix;
+use crate::wrapper::watch::{MapDecoder, MapWatcher, Watcher};
 use crate::Error;
 
+mod entry;
 mod extend;
 mod iterator;
 
+pub use entry::{Entry, OccupiedEntry, VacantEntry};
+
 /// mimics the API of [`HashMap`][std::collections::HashMap]
 pub struct Map<'a, Key, Value, DS>
 where
@@ -20,15 +26,21 @@ where
     phantom_key: PhantomData<&'a Key>,
     phantom_val: PhantomData<Value>,
     tree: DS,
-    prefix: u8,
+    prefix: Prefix,
 }
 
 #[derive(Serialize)]
 pub struct Prefixed<'a, K> {
-    prefix: u8,
+    prefix: Prefix,
     key: &'a K,
 }
 
+impl<'a, K> Prefixed<'a, K> {
+    pub(crate) fn new(prefix: Prefix, key: &'a K) -> Self {
+        Self { prefix, key }
+    }
+}
+
 impl<'a, Key, Value, E, DS> Map<'a, Key, Value, DS>
 where
     E: fmt::Debug,
@@ -38,7 +50,7 @@ where
 {
     #[doc(hidden)]
     #[instrument(skip(tree), level = "debug")]
-    pub fn new(tree: DS, prefix: u8) -> Self {
+    pub fn new(tree: DS, prefix: Prefix) -> Self {
         Self {
             phantom_key: PhantomData,
             phantom_val: PhantomData,
@@ -48,11 +60,8 @@ where
     }
 
     fn prefix(&self, key: &'a Key) -> Prefixed<'a, Key> {
-        trace!("prefixing key with: {}", self.prefix);
-        Prefixed {
-            prefix: self.prefix,
-            key,
-        }
+        trace!("prefixing key with: {:?}", self.prefix);
+        Prefixed::new(self.prefix.clone(), key)
     }
 
     /// returns existing value if any was set
@@ -69,6 +78,114 @@ where
         let value = self.tree.get(&key)?;
         Ok(value)
     }
+
+    /// returns the value at the key if it was in the map
+    #[instrument(skip_all, level = "debug")]
+    pub fn remove(&self, key: &'a Key) -> Result<Option<Value>, Error<E>> {
+        let key = self.prefix(key);
+        let existing = self.tree.remove(&key)?;
+        Ok(existing)
+    }
+
+    #[instrument(skip_all, level = "debug")]
+    pub fn contains_key(&self, key: &'a Key) -> Result<bool, Error<E>> {
+        Ok(self.get(key)?.is_some())
+    }
+
+    /// returns the value at the key, if there is none inserts and
+    /// returns the value returned by `f`.
+    #[instrument(skip_all, level = "debug")]
+    pub fn get_or_insert_with(
+        &self,
+        key: &'a Key,
+        f: impl FnOnce() -> Value,
+    ) -> Result<Value, Error<E>> {
+        if let Some(value) = self.get(key)? {
+            return Ok(value);
+        }
+        let value = f();
+        self.insert(key, &value)?;
+        Ok(value)
+    }
+}
+
+/// These need to scan over the map in the database which requires
+/// an ordered store
+impl<'a, Key, Value, E, DS> Map<'a, Key, Value, DS>
+where
+    E: fmt::Debug + From<<DS as ByteStore>::Error>,
+    Key: Serialize + DeserializeOwned,
+    Value: Serialize + DeserializeOwned,
+    DS: DataStore<Error = E> + byte_store::Ordered,
+{
+    /// the number of elements in the map, this has to visit every element
+    #[instrument(skip_all, level = "debug")]
+    pub fn len(&self) -> Result<usize, Error<E>> {
+        let mut len = 0;
+        for res in byte_store::Ordered::scan_prefix(&self.tree, self.prefix.as_bytes()) {
+            res.map_err(|e| Error::Database(E::from(e)))?;
+            len += 1;
+        }
+        Ok(len)
+    }
+
+    #[instrument(skip_all, level = "debug")]
+    pub fn is_empty(&self) -> Result<bool, Error<E>> {
+        match byte_store::Ordered::scan_prefix(&self.tree, self.prefix.as_bytes()).next() {
+            None => Ok(true),
+            Some(Ok(_)) => Ok(false),
+            Some(Err(e)) => Err(Error::Database(E::from(e))),
+        }
+    }
+
+    /// removes all elements from the map
+    #[instrument(skip_all, level = "debug")]
+    pub fn clear(&self) -> Result<(), Error<E>> {
+        self.retain(|_, _| false)
+    }
+
+    /// Retains only the elements for which `f` returns true. Changes `f` makes
+    /// to the values that are kept are written to the database.
+    #[instrument(skip_all, level = "debug")]
+    pub fn retain(&self, mut f: impl FnMut(&Key, &mut Value) -> bool) -> Result<(), Error<E>> {
+        let db_err = |e| Error::Database(E::from(e));
+        for res in byte_store::Ordered::scan_prefix(&self.tree, self.prefix.as_bytes()) {
+            let (key_bytes, val_bytes) = res.map_err(db_err)?;
+            let key_bytes = key_bytes.as_ref();
+            let key = codec::deserialize_key(&key_bytes[self.prefix.as_bytes().len()..]).map_err(Error::DeSerializingKey)?;
+            let mut value = <DS as ByteStore>::Codec::deserialize(val_bytes.as_ref())
+                .map_err(Error::DeSerializingVal)?;
+
+            if !f(&key, &mut value) {
+                ByteStore::remove(&self.tree, key_bytes).map_err(db_err)?;
+                continue;
+            }
+
+            let new_bytes =
+                <DS as ByteStore>::Codec::serialize(&value).map_err(Error::SerializingValue)?;
+            if new_bytes != val_bytes.as_ref() {
+                ByteStore::insert(&self.tree, key_bytes, &new_bytes).map_err(db_err)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'a, Key, Value, E, DS> Map<'a, Key, Value, DS>
+where
+    E: fmt::Debug,
+    Key: Serialize + DeserializeOwned + 'static,
+    Value: Serialize + DeserializeOwned + 'static,
+    DS: data_store::Watch<Error = E>,
+{
+    /// subscribes to the changes made to the map from here on, see
+    /// [`Watcher`]
+    #[instrument(skip_all, level = "debug")]
+    pub fn watch(&self) -> Result<MapWatcher<Key, Value, DS::Subscriber, E>, Error<E>> {
+        let subscriber = self.tree.watch_prefix(&self.prefix)?;
+        let decoder = MapDecoder::new::<DS::Codec>(&self.prefix);
+        Ok(Watcher::new(subscriber, decoder))
+    }
 }
 
 #[cfg(test)]
@@ -83,7 +200,58 @@ mod tests {
         V: Clone + Serialize + DeserializeOwned,
     {
         let ds = stores::BTreeMap::new();
-        let map = Map::new(ds, 1);
-        map
+        Map::new(ds, Prefix::from(1))
+    }
+
+    #[test]
+    fn remove_and_contains() {
+        let map = empty();
+        map.insert(&1u8, &11u8).unwrap();
+        assert!(map.contains_key(&1).unwrap());
+        assert_eq!(map.remove(&1).unwrap(), Some(11));
+        assert!(!map.contains_key(&1).unwrap());
+        assert_eq!(map.remove(&1).unwrap(), None);
+    }
+
+    #[test]
+    fn get_or_insert_with() {
+        let map = empty();
+        assert_eq!(map.get_or_insert_with(&1u8, || 11u8).unwrap(), 11);
+        assert_eq!(map.get_or_insert_with(&1, || 12).unwrap(), 11);
+        assert_eq!(map.get(&1).unwrap(), Some(11));
+    }
+
+    #[test]
+    fn len_and_clear() {
+        let ds = stores::BTreeMap::new();
+        let map: TestMap<u8, u8> = Map::new(ds.clone(), Prefix::from(1));
+        let other: TestMap<u8, u8> = Map::new(ds, Prefix::from(2));
+        other.insert(&1, &1).unwrap();
+        assert!(map.is_empty().unwrap());
+
+        map.insert(&1, &11).unwrap();
+        map.insert(&2, &12).unwrap();
+        assert_eq!(map.len().unwrap(), 2);
+        assert!(!map.is_empty().unwrap());
+
+        map.clear().unwrap();
+        assert_eq!(map.len().unwrap(), 0);
+        assert_eq!(other.len().unwrap(), 1);
+    }
+
+    #[test]
+    fn retain() {
+        let map = empty();
+        for i in 0u8..10 {
+            map.insert(&i, &i).unwrap();
+        }
+        map.retain(|key, value| {
+            *value *= 2;
+            key % 2 == 0
+        })
+        .unwrap();
+
+        let pairs: std::vec::Vec<(u8, u8)> = map.iter().map(Result::unwrap).collect();
+        assert_eq!(pairs, vec![(0, 0), (2, 4), (4, 8), (6, 12), (8, 16)]);
     }
 }
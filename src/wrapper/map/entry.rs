@@ -0,0 +1,441 @@
+use core::cell::RefCell;
+use core::fmt;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tracing::instrument;
+
+use crate::traits::{data_store, CasOutcome, DataStore};
+use crate::Error;
+
+use super::Map;
+
+/// A view into a single entry of a [`Map`], returned by [`Map::entry`].
+///
+/// Changes made through the entry use an atomic read-modify-write, an update
+/// racing with another thread is never lost.
+pub enum Entry<'m, 'a, Key, Value, DS>
+where
+    Key: Serialize,
+    Value: Serialize + DeserializeOwned,
+    DS: DataStore,
+{
+    Occupied(OccupiedEntry<'m, 'a, Key, Value, DS>),
+    Vacant(VacantEntry<'m, 'a, Key, Value, DS>),
+}
+
+/// An entry that had a value when it was looked up
+pub struct OccupiedEntry<'m, 'a, Key, Value, DS>
+where
+    Key: Serialize,
+    Value: Serialize + DeserializeOwned,
+    DS: DataStore,
+{
+    map: &'m Map<'a, Key, Value, DS>,
+    key: &'a Key,
+    value: Value,
+}
+
+/// Changes passed to [`Entry::and_modify`] while the entry had no value
+type Modify<'m, Value> = Box<dyn FnMut(&mut Value) + 'm>;
+
+/// An entry that had no value when it was looked up
+pub struct VacantEntry<'m, 'a, Key, Value, DS>
+where
+    Key: Serialize,
+    Value: Serialize + DeserializeOwned,
+    DS: DataStore,
+{
+    map: &'m Map<'a, Key, Value, DS>,
+    key: &'a Key,
+    /// applied instead of inserting if another thread inserts a value
+    /// before we do
+    modify: Option<Modify<'m, Value>>,
+}
+
+impl<'a, Key, Value, E, DS> Map<'a, Key, Value, DS>
+where
+    E: fmt::Debug,
+    Key: Serialize + DeserializeOwned,
+    Value: Serialize + DeserializeOwned,
+    DS: data_store::Atomic<Error = E>,
+{
+    /// Gets the entry for the key for in-place manipulation
+    #[instrument(skip_all, level = "debug")]
+    pub fn entry(&self, key: &'a Key) -> Result<Entry<'_, 'a, Key, Value, DS>, Error<E>> {
+        Ok(match self.get(key)? {
+            Some(value) => Entry::Occupied(OccupiedEntry {
+                map: self,
+                key,
+                value,
+            }),
+            None => Entry::Vacant(VacantEntry {
+                map: self,
+                key,
+                modify: None,
+            }),
+        })
+    }
+
+    /// Atomically applies `f` to the value at key, returns the new value or
+    /// `None` if there is no value. `f` can be called multiple times.
+    fn modify(&self, key: &'a Key, f: impl FnMut(&mut Value)) -> Result<Option<Value>, Error<E>> {
+        // the store may clone the operation, they share `f`
+        let f = RefCell::new(f);
+        let op = |value: Option<Value>| {
+            value.map(|mut value| {
+                (f.borrow_mut())(&mut value);
+                value
+            })
+        };
+        let (_, new) = self.tree.atomic_fetch_update(&self.prefix(key), op)?;
+        Ok(new)
+    }
+
+    /// Inserts `value` if there is no value at key. If there is one it is
+    /// changed using `modify` instead, or returned if there is nothing to
+    /// modify. Returns the value at key afterwards.
+    fn modify_or_insert(
+        &self,
+        key: &'a Key,
+        value: Value,
+        mut modify: Option<Modify<'_, Value>>,
+    ) -> Result<Value, Error<E>> {
+        loop {
+            let outcome = self
+                .tree
+                .conditional_update(&self.prefix(key), Some(&value), None)?;
+            let current = match outcome {
+                CasOutcome::Swapped => return Ok(value),
+                CasOutcome::Mismatch { current } => current,
+            };
+            match modify.as_mut() {
+                Some(modify) => {
+                    if let Some(new) = self.modify(key, modify)? {
+                        return Ok(new);
+                    }
+                }
+                None => {
+                    if let Some(current) = current {
+                        return Ok(current);
+                    }
+                }
+            }
+            // the value got removed again, try inserting once more
+        }
+    }
+}
+
+impl<'m, 'a, Key, Value, E, DS> Entry<'m, 'a, Key, Value, DS>
+where
+    E: fmt::Debug,
+    Key: Serialize + DeserializeOwned,
+    Value: Serialize + DeserializeOwned,
+    DS: data_store::Atomic<Error = E>,
+{
+    pub fn key(&self) -> &Key {
+        match self {
+            Entry::Occupied(entry) => entry.key,
+            Entry::Vacant(entry) => entry.key,
+        }
+    }
+
+    /// Ensures a value is in the entry by inserting the default if empty,
+    /// returns the value in the entry.
+    pub fn or_insert(self, default: Value) -> Result<Value, Error<E>> {
+        self.or_insert_with(|| default)
+    }
+
+    /// Ensures a value is in the entry by inserting the result of the
+    /// default function if empty, returns the value in the entry.
+    ///
+    /// If another thread inserted a value since the entry was looked up the
+    /// changes passed to [`and_modify`](Self::and_modify) are applied to
+    /// that value instead.
+    pub fn or_insert_with(self, default: impl FnOnce() -> Value) -> Result<Value, Error<E>> {
+        match self {
+            Entry::Occupied(entry) => Ok(entry.value),
+            Entry::Vacant(entry) => entry
+                .map
+                .modify_or_insert(entry.key, default(), entry.modify),
+        }
+    }
+
+    /// Provides in-place mutable access to an occupied entry before any
+    /// potential inserts into the map. The function can be called more then
+    /// once if another thread changes the value at the same time.
+    ///
+    /// For a vacant entry the function is kept, it is applied if another
+    /// thread inserts a value before [`or_insert`](Self::or_insert) does.
+    pub fn and_modify(self, mut f: impl FnMut(&mut Value) + 'm) -> Result<Self, Error<E>> {
+        let (map, key, modify) = match self {
+            Entry::Occupied(entry) => match entry.map.modify(entry.key, &mut f)? {
+                Some(value) => return Ok(Entry::Occupied(OccupiedEntry { value, ..entry })),
+                // removed by someone else since we looked up the entry
+                None => (entry.map, entry.key, Box::new(f) as Modify<'m, Value>),
+            },
+            Entry::Vacant(VacantEntry {
+                map,
+                key,
+                modify: Some(mut earlier),
+            }) => {
+                let modify = move |value: &mut Value| {
+                    earlier(value);
+                    f(value);
+                };
+                (map, key, Box::new(modify) as Modify<'m, Value>)
+            }
+            Entry::Vacant(VacantEntry {
+                map,
+                key,
+                modify: None,
+            }) => (map, key, Box::new(f) as Modify<'m, Value>),
+        };
+        Ok(Entry::Vacant(VacantEntry {
+            map,
+            key,
+            modify: Some(modify),
+        }))
+    }
+}
+
+impl<'m, 'a, Key, Value, E, DS> Entry<'m, 'a, Key, Value, DS>
+where
+    E: fmt::Debug,
+    Key: Serialize + DeserializeOwned,
+    Value: Serialize + DeserializeOwned + Default,
+    DS: data_store::Atomic<Error = E>,
+{
+    /// Ensures a value is in the entry by inserting the default value if
+    /// empty, returns the value in the entry.
+    pub fn or_default(self) -> Result<Value, Error<E>> {
+        self.or_insert_with(Value::default)
+    }
+}
+
+impl<'m, 'a, Key, Value, E, DS> OccupiedEntry<'m, 'a, Key, Value, DS>
+where
+    E: fmt::Debug,
+    Key: Serialize + DeserializeOwned,
+    Value: Serialize + DeserializeOwned,
+    DS: DataStore<Error = E>,
+{
+    pub fn key(&self) -> &Key {
+        self.key
+    }
+
+    /// the value at the time the entry was looked up
+    pub fn get(&self) -> &Value {
+        &self.value
+    }
+
+    pub fn into_value(self) -> Value {
+        self.value
+    }
+
+    /// Sets the value of the entry, returns the previous value
+    pub fn insert(&mut self, value: Value) -> Result<Option<Value>, Error<E>> {
+        let existing = self.map.insert(self.key, &value)?;
+        self.value = value;
+        Ok(existing)
+    }
+
+    /// Takes the value out of the map
+    pub fn remove(self) -> Result<Option<Value>, Error<E>> {
+        self.map.remove(self.key)
+    }
+}
+
+impl<'m, 'a, Key, Value, E, DS> VacantEntry<'m, 'a, Key, Value, DS>
+where
+    E: fmt::Debug,
+    Key: Serialize + DeserializeOwned,
+    Value: Serialize + DeserializeOwned,
+    DS: DataStore<Error = E>,
+{
+    pub fn key(&self) -> &Key {
+        self.key
+    }
+
+    /// Sets the value of the entry, returns it
+    pub fn insert(self, value: Value) -> Result<Value, Error<E>> {
+        self.map.insert(self.key, &value)?;
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stores;
+    use crate::traits::{byte_store, ByteStore};
+    use crate::wrapper::Prefix;
+
+    type Hook = Box<dyn FnOnce(&Map<u8, u16, stores::BTreeMap>)>;
+
+    /// Runs a hook right before the first atomic operation, that way the
+    /// hook can change the map between `entry` and `or_insert` as another
+    /// thread could.
+    struct Interleaved {
+        store: stores::BTreeMap,
+        hook: RefCell<Option<Hook>>,
+    }
+
+    impl Interleaved {
+        fn new(hook: impl FnOnce(&Map<u8, u16, stores::BTreeMap>) + 'static) -> Self {
+            Self {
+                store: stores::BTreeMap::new(),
+                hook: RefCell::new(Some(Box::new(hook))),
+            }
+        }
+
+        fn run_hook(&self) {
+            if let Some(hook) = self.hook.borrow_mut().take() {
+                hook(&Map::new(self.store.clone(), Prefix::from(1)));
+            }
+        }
+    }
+
+    impl ByteStore for Interleaved {
+        type Error = stores::BTreeMapError;
+        type Bytes = std::vec::Vec<u8>;
+        type Codec = crate::codec::Bincode;
+
+        fn get(&self, key: &[u8]) -> Result<Option<Self::Bytes>, Self::Error> {
+            ByteStore::get(&self.store, key)
+        }
+
+        fn remove(&self, key: &[u8]) -> Result<Option<Self::Bytes>, Self::Error> {
+            ByteStore::remove(&self.store, key)
+        }
+
+        fn insert(&self, key: &[u8], val: &[u8]) -> Result<Option<Self::Bytes>, Self::Error> {
+            ByteStore::insert(&self.store, key, val)
+        }
+    }
+
+    impl byte_store::Atomic for Interleaved {
+        fn atomic_update(
+            &self,
+            key: &[u8],
+            op: impl FnMut(Option<&[u8]>) -> Option<std::vec::Vec<u8>>,
+        ) -> Result<(), Self::Error> {
+            self.run_hook();
+            self.store.atomic_update(key, op)
+        }
+
+        fn conditional_update(
+            &self,
+            key: &[u8],
+            new: Option<&[u8]>,
+            expected: Option<&[u8]>,
+        ) -> Result<CasOutcome<Self::Bytes>, Self::Error> {
+            self.run_hook();
+            self.store.conditional_update(key, new, expected)
+        }
+    }
+
+    #[test]
+    fn or_insert() {
+        let map: Map<u8, u16, _> = Map::new(stores::BTreeMap::new(), Prefix::from(1));
+        assert!(matches!(map.entry(&1).unwrap(), Entry::Vacant(_)));
+        assert_eq!(map.entry(&1).unwrap().or_insert(5).unwrap(), 5);
+        assert!(matches!(map.entry(&1).unwrap(), Entry::Occupied(_)));
+        assert_eq!(map.entry(&1).unwrap().or_insert(6).unwrap(), 5);
+        assert_eq!(map.entry(&2).unwrap().or_default().unwrap(), 0);
+    }
+
+    #[test]
+    fn and_modify() {
+        let map: Map<u8, u16, _> = Map::new(stores::BTreeMap::new(), Prefix::from(1));
+        let count = |map: &Map<u8, u16, _>| {
+            map.entry(&1)
+                .unwrap()
+                .and_modify(|v| *v += 1)
+                .unwrap()
+                .or_insert(1)
+                .unwrap()
+        };
+        assert_eq!(count(&map), 1);
+        assert_eq!(count(&map), 2);
+        assert_eq!(map.get(&1).unwrap(), Some(2));
+    }
+
+    #[test]
+    fn inserted_after_lookup_is_modified() {
+        let map: Map<u8, u16, _> = Map::new(
+            Interleaved::new(|other| {
+                other.insert(&1, &5).unwrap();
+            }),
+            Prefix::from(1),
+        );
+        let entry = map.entry(&1).unwrap();
+        assert!(matches!(entry, Entry::Vacant(_)));
+        let value = entry.and_modify(|v| *v += 1).unwrap().or_insert(1).unwrap();
+        assert_eq!(value, 6);
+        assert_eq!(map.get(&1).unwrap(), Some(6));
+    }
+
+    #[test]
+    fn inserted_after_lookup_is_kept() {
+        let map: Map<u8, u16, _> = Map::new(
+            Interleaved::new(|other| {
+                other.insert(&1, &5).unwrap();
+            }),
+            Prefix::from(1),
+        );
+        let entry = map.entry(&1).unwrap();
+        assert!(matches!(entry, Entry::Vacant(_)));
+        assert_eq!(entry.or_insert(1).unwrap(), 5);
+        assert_eq!(map.get(&1).unwrap(), Some(5));
+    }
+
+    #[test]
+    fn removed_after_lookup_is_inserted() {
+        let map: Map<u8, u16, _> = Map::new(
+            Interleaved::new(|other| {
+                other.remove(&1).unwrap();
+            }),
+            Prefix::from(1),
+        );
+        map.insert(&1, &5).unwrap();
+        let entry = map.entry(&1).unwrap();
+        assert!(matches!(entry, Entry::Occupied(_)));
+        let entry = entry.and_modify(|v| *v += 1).unwrap();
+        assert!(matches!(entry, Entry::Vacant(_)));
+        assert_eq!(entry.or_insert(1).unwrap(), 1);
+    }
+
+    #[cfg(feature = "sled")]
+    #[test]
+    fn concurrent_updates_are_not_lost() {
+        let tree = sled::Config::default()
+            .temporary(true)
+            .open()
+            .unwrap()
+            .open_tree("test")
+            .unwrap();
+        let threads: std::vec::Vec<_> = (0..4)
+            .map(|_| {
+                let tree = tree.clone();
+                std::thread::spawn(move || {
+                    let map: Map<u8, u32, _> = Map::new(tree, Prefix::from(1));
+                    for _ in 0..100 {
+                        map.entry(&1)
+                            .unwrap()
+                            .and_modify(|v| *v += 1)
+                            .unwrap()
+                            .or_insert(1)
+                            .unwrap();
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        let map: Map<u8, u32, _> = Map::new(tree, Prefix::from(1));
+        assert_eq!(map.get(&1).unwrap(), Some(400));
+    }
+}
@@ -63,7 +63,7 @@ where
                 return Ok(());
             };
 
-            if let Err(error) = self.insert(&key, &value) {
+            if let Err(error) = self.insert(key, value) {
                 return Err(ExtendError {
                     unadded: (key, value),
                     iter,
@@ -78,6 +78,7 @@ where
 mod tests {
     use super::*;
     use crate::stores;
+    use crate::wrapper::Prefix;
 
     mod iter_one_item {
         use super::*;
@@ -85,7 +86,7 @@ mod tests {
         #[test]
         fn while_db_errors() {
             let ds = stores::BTreeMap::new();
-            let mut map = Map::new(ds.clone(), 1);
+            let mut map = Map::new(ds.clone(), Prefix::from(1));
 
             let iter = [("a".to_string(), 1)];
             ds.force_error();
@@ -99,7 +100,7 @@ mod tests {
         #[test]
         fn without_error() {
             let ds = stores::BTreeMap::new();
-            let mut map: Map<String, usize, _> = Map::new(ds.clone(), 1);
+            let mut map: Map<String, usize, _> = Map::new(ds.clone(), Prefix::from(1));
 
             let iter = [(&"a".to_string(), &1)];
             map.try_extend(iter).unwrap();
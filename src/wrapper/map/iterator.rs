@@ -2,7 +2,8 @@ use crate::Error;
 use core::marker::PhantomData;
 use std::fmt;
 
-use crate::traits::{byte_store, DataStore};
+use crate::codec::{self, Codec};
+use crate::traits::{byte_store, ByteStore, DataStore};
 
 use serde::de::DeserializeOwned;
 use serde::Serialize;
@@ -14,38 +15,36 @@ where
     K: Serialize + DeserializeOwned,
     V: Serialize + DeserializeOwned,
     E: fmt::Debug,
-    DS: DataStore<Error = E> + byte_store::Ordered,
+    DS: DataStore<Error = E> + byte_store::Ordered + 'a,
 {
-    prev_key_bytes: Vec<u8>,
+    range: <DS as byte_store::Ordered>::Range<'a>,
+    prefix_len: usize,
     phantom_val: PhantomData<V>,
     phantom_key: PhantomData<K>,
-    ds: &'a DS,
 }
 
 impl<'a, K, V, E, DS> Iterator for Iter<'a, K, V, E, DS>
 where
-    E: fmt::Debug,
+    E: fmt::Debug + From<<DS as ByteStore>::Error>,
     K: Serialize + DeserializeOwned,
     V: Serialize + DeserializeOwned,
-    DS: DataStore<Error = E> + byte_store::Ordered,
+    DS: DataStore<Error = E> + byte_store::Ordered + 'a,
 {
     type Item = Result<(K, V), Error<E>>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let Some((key, val)) = byte_store::Ordered::get_gt(self.ds, &self.prev_key_bytes).unwrap() else {
-            return None;
+        let (key, val) = match self.range.next()? {
+            Ok(pair) => pair,
+            Err(e) => return Some(Err(Error::Database(E::from(e)))),
         };
 
-        let key = key.as_ref();
-        self.prev_key_bytes.clear();
-        self.prev_key_bytes.extend_from_slice(key);
-
-        let key = &key[1..]; // strip prefix
-        let key = match bincode::deserialize(key).map_err(Error::DeSerializingKey) {
+        let key = &key.as_ref()[self.prefix_len..];
+        let key = match codec::deserialize_key(key).map_err(Error::DeSerializingKey) {
             Ok(key) => key,
             Err(e) => return Some(Err(e)),
         };
-        let val = match bincode::deserialize(val.as_ref()).map_err(Error::DeSerializingVal) {
+        let val = <DS as ByteStore>::Codec::deserialize(val.as_ref());
+        let val = match val.map_err(Error::DeSerializingVal) {
             Ok(val) => val,
             Err(e) => return Some(Err(e)),
         };
@@ -58,14 +57,14 @@ where
     K: Serialize + DeserializeOwned,
     V: Serialize + DeserializeOwned,
     E: fmt::Debug,
-    DS: DataStore<Error = E> + byte_store::Ordered;
+    DS: DataStore<Error = E> + byte_store::Ordered + 'a;
 
 impl<'a, K, V, E, DS> Iterator for Values<'a, K, V, E, DS>
 where
     K: Serialize + DeserializeOwned,
     V: Serialize + DeserializeOwned,
-    E: fmt::Debug,
-    DS: DataStore<Error = E> + byte_store::Ordered,
+    E: fmt::Debug + From<<DS as ByteStore>::Error>,
+    DS: DataStore<Error = E> + byte_store::Ordered + 'a,
 {
     type Item = Result<V, Error<E>>;
 
@@ -79,14 +78,14 @@ where
     K: Serialize + DeserializeOwned,
     V: Serialize + DeserializeOwned,
     E: fmt::Debug,
-    DS: DataStore<Error = E> + byte_store::Ordered;
+    DS: DataStore<Error = E> + byte_store::Ordered + 'a;
 
 impl<'a, K, V, E, DS> Iterator for Keys<'a, K, V, E, DS>
 where
     K: Serialize + DeserializeOwned,
     V: Serialize + DeserializeOwned,
-    E: fmt::Debug,
-    DS: DataStore<Error = E> + byte_store::Ordered,
+    E: fmt::Debug + From<<DS as ByteStore>::Error>,
+    DS: DataStore<Error = E> + byte_store::Ordered + 'a,
 {
     type Item = Result<K, Error<E>>;
 
@@ -102,31 +101,25 @@ where
     Value: Serialize + DeserializeOwned,
     DS: DataStore<Error = E> + byte_store::Ordered,
 {
-    pub fn iter(&self) -> Iter<Key, Value, E, DS> {
+    fn range(&self) -> Iter<'_, Key, Value, E, DS> {
         Iter {
-            prev_key_bytes: vec![self.prefix],
+            range: byte_store::Ordered::scan_prefix(&self.tree, self.prefix.as_bytes()),
+            prefix_len: self.prefix.as_bytes().len(),
             phantom_val: PhantomData,
             phantom_key: PhantomData,
-            ds: &self.tree,
         }
     }
 
-    pub fn values(&self) -> Values<Key, Value, E, DS> {
-        Values(Iter {
-            prev_key_bytes: vec![self.prefix],
-            phantom_val: PhantomData,
-            phantom_key: PhantomData,
-            ds: &self.tree,
-        })
+    pub fn iter(&self) -> Iter<'_, Key, Value, E, DS> {
+        self.range()
     }
 
-    pub fn keys(&self) -> Keys<Key, Value, E, DS> {
-        Keys(Iter {
-            prev_key_bytes: vec![self.prefix],
-            phantom_val: PhantomData,
-            phantom_key: PhantomData,
-            ds: &self.tree,
-        })
+    pub fn values(&self) -> Values<'_, Key, Value, E, DS> {
+        Values(self.range())
+    }
+
+    pub fn keys(&self) -> Keys<'_, Key, Value, E, DS> {
+        Keys(self.range())
     }
 }
 
@@ -145,4 +138,46 @@ mod tests {
         assert!(pairs.contains(&(2,12)));
         assert!(pairs.contains(&(3,13)));
     }
+
+    #[test]
+    fn iterator_stops_at_end_of_map() {
+        use crate::stores;
+        use crate::wrapper::{Map, Prefix};
+        let ds = stores::BTreeMap::new();
+        let map: Map<u8, u8, _> = Map::new(ds.clone(), Prefix::from(1));
+        let next_map: Map<u16, u16, _> = Map::new(ds, Prefix::from(2));
+        map.insert(&1, &11).unwrap();
+        next_map.insert(&1000, &2000).unwrap();
+
+        let pairs: Vec<(u8, u8)> = map.iter().map(Result::unwrap).collect();
+        assert_eq!(pairs, vec![(1, 11)]);
+        let pairs: Vec<(u16, u16)> = next_map.iter().map(Result::unwrap).collect();
+        assert_eq!(pairs, vec![(1000, 2000)]);
+    }
+
+    #[test]
+    fn iterator_follows_key_order() {
+        use super::super::tests::*;
+        let map = empty();
+        for key in [256u32, 2, 70_000, 1] {
+            map.insert(&key, &0u8).unwrap();
+        }
+
+        let keys: Vec<u32> = map.keys().map(Result::unwrap).collect();
+        assert_eq!(keys, vec![1, 2, 256, 70_000]);
+    }
+
+    #[test]
+    fn database_error_is_returned() {
+        use crate::stores;
+        use crate::wrapper::{Map, Prefix};
+        let ds = stores::BTreeMap::new();
+        let map: Map<u8, u8, _> = Map::new(ds.clone(), Prefix::from(1));
+        map.insert(&1, &11).unwrap();
+        ds.force_error();
+
+        let mut iter = map.iter();
+        assert!(matches!(iter.next(), Some(Err(crate::Error::Database(_)))));
+        assert!(iter.next().is_none());
+    }
 }
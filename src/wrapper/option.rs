@@ -4,7 +4,9 @@ use std::marker::PhantomData;
 use serde::Serialize;
 use serde::de::DeserializeOwned;
 
-use crate::traits::{data_store, DataStore};
+use crate::traits::{data_store, CasOutcome, DataStore};
+use crate::wrapper::Prefix;
+use crate::wrapper::watch::{ValueDecoder, ValueEvent, Watcher};
 use crate::Error;
 
 /// here missing values are represented by [`Option::None`]. 
@@ -14,7 +16,7 @@ where
 {
     phantom: PhantomData<T>,
     ds: DS,
-    key: u8,
+    key: Prefix,
 }
 
 impl<T, E, DS> OptionValue<T, DS>
@@ -24,9 +26,9 @@ where
     DS: DataStore<Error = E>,
 {
     #[doc(hidden)]
-    pub fn new(ds: DS, key: u8) -> Self {
+    pub fn new(ds: DS, key: Prefix) -> Self {
         Self {
-            phantom: PhantomData::default(),
+            phantom: PhantomData,
             ds,
             key,
         }
@@ -40,6 +42,27 @@ where
     pub fn get(&self) -> Result<Option<T>, Error<E>> {
         Ok(self.ds.get(&self.key)?)
     }
+
+    /// Removes the value returning it, `None` if there is none
+    pub fn take(&mut self) -> Result<Option<T>, Error<E>> {
+        Ok(self.ds.remove(&self.key)?)
+    }
+
+    /// Removes the value, `get` then returns `None`
+    pub fn clear(&mut self) -> Result<(), Error<E>> {
+        self.ds.remove::<_, T>(&self.key)?;
+        Ok(())
+    }
+
+    /// Sets the value returning the previous one, `None` if there is none
+    pub fn replace(&mut self, value: &T) -> Result<Option<T>, Error<E>> {
+        Ok(self.ds.insert(&self.key, value)?)
+    }
+
+    /// Whether a value is stored
+    pub fn is_set(&self) -> Result<bool, Error<E>> {
+        Ok(self.ds.get::<_, T>(&self.key)?.is_some())
+    }
 }
 
 impl<T, E, DS> OptionValue<T, DS>
@@ -48,12 +71,56 @@ where
     T: Serialize + DeserializeOwned,
     DS: data_store::Atomic<Error = E>,
 {
-    pub fn update(&self, op: impl FnMut(T) -> T + Clone) -> Result<(), Error<E>> {
-        self.ds.atomic_update(&self.key, op)?;
+    /// Atomically replaces the value with the result of `op`, which gets
+    /// `None` if there is no value and removes the value by returning
+    /// `None`. `op` can run more than once.
+    pub fn update(
+        &self,
+        op: impl FnMut(Option<T>) -> Option<T> + Clone,
+    ) -> Result<(), Error<E>> {
+        self.ds.atomic_fetch_update(&self.key, op)?;
         Ok(())
     }
-    /// if the value is None then no update is performed
-    pub fn conditional_update(&self, old: T, new: T) -> Result<(), Error<E>> {
-        Ok(self.ds.conditional_update(&self.key, &new, &old)?)
+    /// Like [`update`](Self::update), returns the value before the update
+    pub fn fetch_update(
+        &self,
+        op: impl FnMut(Option<T>) -> Option<T> + Clone,
+    ) -> Result<Option<T>, Error<E>> {
+        let (old, _) = self.ds.atomic_fetch_update(&self.key, op)?;
+        Ok(old)
+    }
+    /// Like [`update`](Self::update), returns the value after the update
+    pub fn update_and_fetch(
+        &self,
+        op: impl FnMut(Option<T>) -> Option<T> + Clone,
+    ) -> Result<Option<T>, Error<E>> {
+        let (_, new) = self.ds.atomic_fetch_update(&self.key, op)?;
+        Ok(new)
+    }
+    /// Sets the value to `new` if it currently is `old`, `None` meaning
+    /// no value. Returns the value found instead if it was not `old`.
+    pub fn conditional_update(
+        &self,
+        old: Option<T>,
+        new: Option<T>,
+    ) -> Result<CasOutcome<T>, Error<E>> {
+        Ok(self
+            .ds
+            .conditional_update(&self.key, new.as_ref(), old.as_ref())?)
+    }
+}
+
+impl<T, E, DS> OptionValue<T, DS>
+where
+    E: fmt::Debug,
+    T: Serialize + DeserializeOwned + Send + 'static,
+    DS: data_store::Watch<Error = E>,
+{
+    /// subscribes to the changes made to the value from here on, see
+    /// [`Watcher`]
+    pub fn watch(&self) -> Result<Watcher<ValueEvent<T>, DS::Subscriber, E>, Error<E>> {
+        let subscriber = self.ds.watch_prefix(&self.key)?;
+        let decoder = ValueDecoder::new::<DS::Codec>(&self.key);
+        Ok(Watcher::new(subscriber, decoder))
     }
 }
@@ -0,0 +1,90 @@
+use std::sync::Arc;
+
+use serde::ser::SerializeTuple;
+use serde::{Serialize, Serializer};
+
+/// The bytes every key of a field starts with. The fields of a struct add
+/// their key to the prefix of the struct, that is empty unless the struct
+/// is nested in another.
+///
+/// Serializes as just its bytes, a field of a top level struct is stored
+/// exactly like it was when prefixes were a single `u8`.
+#[doc(hidden)]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Prefix(Arc<[u8]>);
+
+impl Prefix {
+    /// the prefix of a struct that is not nested
+    pub fn root() -> Self {
+        Self::default()
+    }
+
+    /// the prefix of the field with `key` in the struct at this prefix
+    pub fn field(&self, key: u8) -> Self {
+        let mut bytes = self.0.to_vec();
+        bytes.push(key);
+        Self(bytes.into())
+    }
+
+    /// the prefix of the field with `key` in a struct using `wide_keys`, the
+    /// key is stored big endian so fields stay in key order
+    pub fn wide_field(&self, key: u16) -> Self {
+        let mut bytes = self.0.to_vec();
+        bytes.extend_from_slice(&key.to_be_bytes());
+        Self(bytes.into())
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<u8> for Prefix {
+    fn from(key: u8) -> Self {
+        Self::root().field(key)
+    }
+}
+
+impl Serialize for Prefix {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut tuple = serializer.serialize_tuple(self.0.len())?;
+        for byte in self.0.iter() {
+            tuple.serialize_element(byte)?;
+        }
+        tuple.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec;
+
+    #[test]
+    fn one_byte_prefix_is_stored_like_u8() {
+        let prefix = Prefix::from(7);
+        assert_eq!(
+            codec::serialize_key(&prefix).unwrap(),
+            codec::serialize_key(&7u8).unwrap()
+        );
+    }
+
+    #[test]
+    fn nested_prefix_extends_parent() {
+        let parent = Prefix::from(3);
+        let nested = parent.field(1).field(4);
+        assert_eq!(nested.as_bytes(), &[3, 1, 4]);
+        assert_eq!(codec::serialize_key(&nested).unwrap(), vec![3, 1, 4]);
+    }
+
+    #[test]
+    fn wide_keys_sort_in_order() {
+        let root = Prefix::root();
+        let keys: Vec<_> = [0, 1, 255, 256, 1000]
+            .into_iter()
+            .map(|key| root.wide_field(key))
+            .collect();
+        assert_eq!(keys[3].as_bytes(), &[1, 0]);
+        assert!(keys.windows(2).all(|w| w[0].as_bytes() < w[1].as_bytes()));
+    }
+}
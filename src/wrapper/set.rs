@@ -0,0 +1,159 @@
+use core::fmt;
+use core::ops::RangeBounds;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tracing::instrument;
+
+use crate::traits::{byte_store, data_store, ByteStore, DataStore};
+use crate::wrapper::Prefix;
+use crate::wrapper::watch::{SetEvent, Watcher};
+use crate::Error;
+
+use super::btreemap::{BTreeMap, Keys};
+
+mod extend;
+
+/// mimics the API of [`HashSet`][std::collections::HashSet] and
+/// [`BTreeSet`][std::collections::BTreeSet]. Every element is stored as a
+/// key under the prefix of the field, just like the keys of a
+/// [`Map`][super::Map]. Iterating visits the elements in order.
+pub struct Set<T, DS>
+where
+    T: Serialize,
+    DS: DataStore,
+{
+    map: BTreeMap<T, (), DS>,
+}
+
+impl<T, E, DS> Set<T, DS>
+where
+    E: fmt::Debug,
+    T: Serialize + DeserializeOwned,
+    DS: DataStore<Error = E>,
+{
+    #[doc(hidden)]
+    #[instrument(skip(tree), level = "debug")]
+    pub fn new(tree: DS, prefix: Prefix) -> Self {
+        Self {
+            map: BTreeMap::new(tree, prefix),
+        }
+    }
+
+    /// returns whether the value was newly inserted
+    #[instrument(skip_all, level = "debug")]
+    pub fn insert(&self, value: &T) -> Result<bool, Error<E>> {
+        Ok(self.map.insert(value, &())?.is_none())
+    }
+
+    /// returns whether the value was in the set
+    #[instrument(skip_all, level = "debug")]
+    pub fn remove(&self, value: &T) -> Result<bool, Error<E>> {
+        Ok(self.map.remove(value)?.is_some())
+    }
+
+    #[instrument(skip_all, level = "debug")]
+    pub fn contains(&self, value: &T) -> Result<bool, Error<E>> {
+        self.map.contains_key(value)
+    }
+}
+
+/// These need to scan over the set in the database which requires
+/// an ordered store
+impl<T, E, DS> Set<T, DS>
+where
+    E: fmt::Debug + From<<DS as ByteStore>::Error>,
+    T: Serialize + DeserializeOwned,
+    DS: DataStore<Error = E> + byte_store::Ordered,
+{
+    /// the number of elements in the set, this has to visit every element
+    #[instrument(skip_all, level = "debug")]
+    pub fn len(&self) -> Result<usize, Error<E>> {
+        self.map.len()
+    }
+
+    #[instrument(skip_all, level = "debug")]
+    pub fn is_empty(&self) -> Result<bool, Error<E>> {
+        self.map.is_empty()
+    }
+
+    /// removes all elements from the set
+    #[instrument(skip_all, level = "debug")]
+    pub fn clear(&self) -> Result<(), Error<E>> {
+        self.map.clear()
+    }
+
+    /// iterate over the elements in ascending order
+    pub fn iter(&self) -> Keys<'_, T, (), E, DS> {
+        self.map.keys()
+    }
+
+    /// iterate over the elements in `range` in ascending order. Errors if a
+    /// bound of the range can not be serialized.
+    pub fn range(&self, range: impl RangeBounds<T>) -> Result<Keys<'_, T, (), E, DS>, Error<E>> {
+        self.map.range(range).map(Keys)
+    }
+}
+
+impl<T, E, DS> Set<T, DS>
+where
+    E: fmt::Debug,
+    T: Serialize + DeserializeOwned + 'static,
+    DS: data_store::Watch<Error = E>,
+{
+    /// subscribes to the changes made to the set from here on, see
+    /// [`Watcher`]
+    #[instrument(skip_all, level = "debug")]
+    pub fn watch(&self) -> Result<Watcher<SetEvent<T>, DS::Subscriber, E>, Error<E>> {
+        self.map.watch_keys()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stores;
+
+    pub(crate) type TestSet<T> = Set<T, stores::BTreeMap>;
+
+    #[test]
+    fn insert_remove_contains() {
+        let set: TestSet<String> = Set::new(stores::BTreeMap::new(), Prefix::from(1));
+        assert!(set.insert(&"a".to_owned()).unwrap());
+        assert!(!set.insert(&"a".to_owned()).unwrap());
+        assert!(set.contains(&"a".to_owned()).unwrap());
+
+        assert!(set.remove(&"a".to_owned()).unwrap());
+        assert!(!set.remove(&"a".to_owned()).unwrap());
+        assert!(!set.contains(&"a".to_owned()).unwrap());
+    }
+
+    #[test]
+    fn iter_and_range() {
+        let ds = stores::BTreeMap::new();
+        let set: TestSet<u32> = Set::new(ds.clone(), Prefix::from(1));
+        let other: TestSet<u32> = Set::new(ds, Prefix::from(2));
+        other.insert(&0).unwrap();
+        for value in [500, 3, 70] {
+            set.insert(&value).unwrap();
+        }
+
+        assert_eq!(set.len().unwrap(), 3);
+        let all: std::vec::Vec<u32> = set.iter().map(Result::unwrap).collect();
+        assert_eq!(all, vec![3, 70, 500]);
+        let some: std::vec::Vec<u32> = set.range(4..).unwrap().map(Result::unwrap).collect();
+        assert_eq!(some, vec![70, 500]);
+
+        set.clear().unwrap();
+        assert!(set.is_empty().unwrap());
+        assert_eq!(other.len().unwrap(), 1);
+    }
+
+    #[test]
+    fn same_layout_as_map() {
+        let ds = stores::BTreeMap::new();
+        let map: super::super::Map<u8, (), _> = super::super::Map::new(ds.clone(), Prefix::from(1));
+        map.insert(&7, &()).unwrap();
+        let set: TestSet<u8> = Set::new(ds, Prefix::from(1));
+        assert!(set.contains(&7).unwrap());
+    }
+}
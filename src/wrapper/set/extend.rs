@@ -0,0 +1,91 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::traits::ExtendError;
+use crate::{DataStore, TryExtend};
+
+use super::Set;
+
+/// Inserts all values from the iterator, values already in the set are
+/// left as they are.
+impl<T, DS> TryExtend<T> for Set<T, DS>
+where
+    DS: DataStore,
+    T: Serialize + DeserializeOwned,
+{
+    type Error = crate::Error<DS::Error>;
+
+    fn try_extend<I>(&mut self, iter: I) -> Result<(), ExtendError<T, I::IntoIter, Self::Error>>
+    where
+        I: IntoIterator<Item = T>,
+    {
+        let mut iter = iter.into_iter();
+        loop {
+            let Some(item) = iter.next() else {
+                return Ok(());
+            };
+
+            if let Err(error) = self.insert(&item) {
+                return Err(ExtendError {
+                    unadded: item,
+                    iter,
+                    error,
+                });
+            }
+        }
+    }
+}
+
+/// Inserts all values from the iterator, values already in the set are
+/// left as they are.
+impl<'a, T, DS> TryExtend<&'a T> for Set<T, DS>
+where
+    DS: DataStore,
+    T: Serialize + DeserializeOwned,
+{
+    type Error = crate::Error<DS::Error>;
+
+    fn try_extend<I>(
+        &mut self,
+        iter: I,
+    ) -> Result<(), ExtendError<I::Item, I::IntoIter, Self::Error>>
+    where
+        I: IntoIterator<Item = &'a T>,
+    {
+        let mut iter = iter.into_iter();
+        loop {
+            let Some(item) = iter.next() else {
+                return Ok(());
+            };
+
+            if let Err(error) = self.insert(item) {
+                return Err(ExtendError {
+                    unadded: item,
+                    iter,
+                    error,
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stores;
+    use crate::wrapper::Prefix;
+
+    #[test]
+    fn stops_at_db_error() {
+        let ds = stores::BTreeMap::new();
+        let mut set: Set<u8, _> = Set::new(ds.clone(), Prefix::from(1));
+        set.try_extend([1, 2]).unwrap();
+
+        ds.force_error();
+        let err = set
+            .try_extend([3, 4])
+            .expect_err("we forced the datastore to crash on access");
+        assert_eq!(err.unadded, 3);
+        assert_eq!(err.iter.collect::<std::vec::Vec<_>>(), vec![4]);
+    }
+}
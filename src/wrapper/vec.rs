@@ -1,46 +1,87 @@
 use core::fmt;
 use std::marker::PhantomData;
-use std::sync::atomic::{AtomicUsize, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
 
 use serde::de::DeserializeOwned;
-use serde::{Deserialize, Serialize};
-use tracing::debug;
+use serde::Serialize;
+use tracing::{debug, warn};
 
+use crate::traits::data_store;
 use crate::traits::DataStore;
+use crate::wrapper::Prefix;
+use crate::wrapper::watch::{VecDecoder, VecEvent, Watcher};
 use crate::Error;
 
 mod iterator;
 mod extend;
+mod layout;
+
+pub(crate) use layout::{check_index, write_element, Keys, LenChange};
 
 /// mimics the API of [`Vec`]
+///
+/// The length is stored in the database next to the elements. Changes to
+/// the length are written together with the element, atomically if the
+/// database supports it (see [`DataStore::write_pair`]). Otherwise the writes
+/// are ordered such that the vector stays consistent if we crash in between.
 pub struct Vec<T, DS>
 where
     DS: DataStore,
 {
     phantom: PhantomData<T>,
     ds: DS,
-    prefix: u8,
-    len: Arc<AtomicUsize>,
+    keys: Keys,
+    /// Cached length, the lock is held while changing the vector
+    len: Arc<Mutex<usize>>,
 }
 
-#[derive(Serialize, Deserialize, Hash, Eq, PartialEq, Clone, Debug)]
+#[derive(Serialize, Hash, Eq, PartialEq, Clone, Debug)]
 pub struct Prefixed {
-    prefix: u8,
+    prefix: Prefix,
     index: usize,
 }
 
 impl Prefixed {
+    pub(crate) fn new(prefix: Prefix, index: usize) -> Self {
+        Self { prefix, index }
+    }
+
     pub fn index(&self) -> usize {
         self.index
     }
 
-    pub fn max(prefix: u8) -> Self {
+    pub fn max(prefix: Prefix) -> Self {
         Self {
             prefix,
-            index: usize::max_value(),
+            index: usize::MAX,
         }
     }
+
+    /// The length of the vector is stored at the highest index, an element
+    /// can never end up there.
+    pub fn len(prefix: Prefix) -> Self {
+        Self::max(prefix)
+    }
+}
+
+/// Reads the length of the vector at prefix when opening the database.
+///
+/// Databases written by versions of dbstruct that did not store the length
+/// fall back to one past the index of the last element. Holes before the
+/// last element are counted like any other element.
+#[doc(hidden)]
+pub fn stored_len<T, DS>(ds: &DS, prefix: Prefix) -> Result<usize, DS::Error>
+where
+    T: Serialize + DeserializeOwned,
+    DS: data_store::Ordered,
+{
+    let keys = Keys::new(prefix);
+    if let Some(len) = ds.get(&keys.len())? {
+        return Ok(len);
+    }
+
+    let last: Option<usize> = ds.last_key_in_prefix(keys.prefix())?;
+    Ok(last.map_or(0, |index| index + 1))
 }
 
 impl<T, E, DS> Vec<T, DS>
@@ -50,66 +91,196 @@ where
     DS: DataStore<Error = E>,
 {
     #[doc(hidden)]
-    pub fn new(ds: DS, prefix: u8, len: Arc<AtomicUsize>) -> Self {
+    pub fn new(ds: DS, prefix: Prefix, len: Arc<Mutex<usize>>) -> Self {
         Self {
             phantom: PhantomData,
             ds,
-            prefix,
+            keys: Keys::new(prefix),
             len,
         }
     }
 
+    /// The lock is only poisoned if we panicked while holding it, the
+    /// cached length is only changed after the database is updated.
+    fn lock(&self) -> MutexGuard<'_, usize> {
+        self.len.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Applies the change to the database, then to the cached length
+    fn change_len(&self, len: &mut usize, change: LenChange<'_, T>) -> Result<(), Error<E>> {
+        if let Err(e) = change.write(&self.ds) {
+            self.resync(len);
+            return Err(e.into());
+        }
+        *len = change.new_len();
+        Ok(())
+    }
+
+    /// If a write failed part of it might still have been applied,
+    /// the length in the database is leading.
+    fn resync(&self, len: &mut usize) {
+        match self.ds.get(&self.keys.len()) {
+            Ok(Some(stored)) => *len = stored,
+            Ok(None) => (),
+            Err(e) => warn!("could not resync vector length: {e:?}"),
+        }
+    }
+
     pub fn get(&self, index: usize) -> Result<Option<T>, Error<E>> {
         let len = self.len();
         if index >= len {
             return Ok(None);
         }
-
-        let key = Prefixed {
-            prefix: self.prefix,
-            index,
-        };
-        Ok(self.ds.get(&key)?)
+        Ok(self.ds.get(&self.keys.element(index))?)
     }
 
     pub fn push(&self, value: &T) -> Result<(), Error<E>> {
-        let prev_len = self.len.fetch_add(1, Ordering::SeqCst);
-        let key = Prefixed {
-            prefix: self.prefix,
-            index: prev_len,
-        };
-        debug!("pushing onto vector (index: {prev_len})");
-        self.ds.insert(&key, value)?;
-        Ok(())
+        let mut len = self.lock();
+        debug!("pushing onto vector (index: {})", *len);
+        let change = LenChange::push(&self.keys, *len, value);
+        self.change_len(&mut len, change)
     }
 
     pub fn pop(&self) -> Result<Option<T>, Error<E>> {
-        let old_len = self
-            .len
-            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |len| {
-                Some(len.saturating_sub(1))
-            }).expect("closure never returns None");
-
-        let index = match old_len.checked_sub(1) {
-            Some(idx) => idx,
-            None => return Ok(None),
-        };
-        let key = Prefixed {
-            prefix: self.prefix,
-            index,
+        let mut len = self.lock();
+        let Some(index) = len.checked_sub(1) else {
+            return Ok(None);
         };
 
         debug!("popping from vector (index: {index})");
-        Ok(self.ds.remove(&key)?)
+        let value = self.ds.get(&self.keys.element(index))?;
+        self.change_len(&mut len, LenChange::pop(&self.keys, index))?;
+        Ok(value)
     }
 
     pub fn len(&self) -> usize {
-        self.len.load(Ordering::SeqCst)
+        *self.lock()
     }
 
     pub fn is_empty(&self) -> bool {
         self.len() == 0
     }
+
+    pub fn first(&self) -> Result<Option<T>, Error<E>> {
+        self.get(0)
+    }
+
+    pub fn last(&self) -> Result<Option<T>, Error<E>> {
+        match self.len().checked_sub(1) {
+            Some(index) => self.get(index),
+            None => Ok(None),
+        }
+    }
+
+    /// replaces the element at index, returns an error if the index is
+    /// out of range.
+    pub fn set(&self, index: usize, value: &T) -> Result<(), Error<E>> {
+        let len = self.lock();
+        check_index(index, *len)?;
+        self.ds.insert(&self.keys.element(index), value)?;
+        Ok(())
+    }
+
+    /// swaps two elements, returns an error if either index is out
+    /// of range. A hole is swapped like any other element.
+    pub fn swap(&self, a: usize, b: usize) -> Result<(), Error<E>> {
+        let len = self.lock();
+        check_index(a, *len)?;
+        check_index(b, *len)?;
+        if a == b {
+            return Ok(());
+        }
+
+        let (key_a, key_b) = (self.keys.element(a), self.keys.element(b));
+        let val_a: Option<T> = self.ds.get(&key_a)?;
+        let val_b: Option<T> = self.ds.get(&key_b)?;
+        if val_a.is_none() && val_b.is_none() {
+            return Ok(());
+        }
+        self.ds.write_pair(
+            write_element(&key_a, val_b.as_ref()),
+            write_element(&key_b, val_a.as_ref()),
+        )?;
+        Ok(())
+    }
+
+    /// Shortens the vector keeping the first `new_len` elements. Does
+    /// nothing if the vector is already shorter.
+    pub fn truncate(&self, new_len: usize) -> Result<(), Error<E>> {
+        let mut len = self.lock();
+        if new_len >= *len {
+            return Ok(());
+        }
+
+        debug!("truncating vector from {} to {new_len}", *len);
+        // the elements past the new length are ignored from here on
+        if let Err(e) = self.ds.insert(&self.keys.len(), &new_len) {
+            self.resync(&mut len);
+            return Err(e.into());
+        }
+        let old_len = std::mem::replace(&mut *len, new_len);
+        for index in (new_len..old_len).rev() {
+            let _: Option<T> = self.ds.remove(&self.keys.element(index))?;
+        }
+        Ok(())
+    }
+
+    /// removes all elements
+    pub fn clear(&self) -> Result<(), Error<E>> {
+        self.truncate(0)
+    }
+
+    /// Removes the element at index and returns it, `None` if it was a hole.
+    /// The last element takes its place. Returns an error if the index is
+    /// out of range.
+    pub fn swap_remove(&self, index: usize) -> Result<Option<T>, Error<E>> {
+        let mut len = self.lock();
+        check_index(index, *len)?;
+        let last_index = *len - 1;
+
+        let removed = self.ds.get(&self.keys.element(index))?;
+        let last: Option<T> = if index == last_index {
+            None
+        } else {
+            self.ds.get(&self.keys.element(last_index))?
+        };
+        let change = LenChange::swap_remove(&self.keys, index, last_index, last.as_ref());
+        self.change_len(&mut len, change)?;
+        Ok(removed)
+    }
+}
+
+impl<T, E, DS> Vec<T, DS>
+where
+    E: fmt::Debug,
+    T: Serialize + DeserializeOwned,
+    DS: data_store::Atomic<Error = E>,
+{
+    /// changes the element at index using op, returns an error if the
+    /// index is out of range.
+    pub fn update(&self, index: usize, op: impl FnMut(T) -> T + Clone) -> Result<(), Error<E>> {
+        let len = self.lock();
+        check_index(index, *len)?;
+        self.ds.atomic_update(&self.keys.element(index), op)?;
+        Ok(())
+    }
+}
+
+impl<T, E, DS> Vec<T, DS>
+where
+    E: fmt::Debug,
+    T: Serialize + DeserializeOwned + Send + 'static,
+    DS: data_store::Watch<Error = E>,
+{
+    /// subscribes to the changes made to the vector from here on, see
+    /// [`Watcher`]
+    pub fn watch(&self) -> Result<Watcher<VecEvent<T>, DS::Subscriber, E>, Error<E>> {
+        // no changes can happen between reading the length and subscribing
+        let len = self.lock();
+        let subscriber = self.ds.watch_prefix(self.keys.prefix())?;
+        let decoder = VecDecoder::new::<DS::Codec>(self.keys.prefix(), *len);
+        Ok(Watcher::new(subscriber, decoder))
+    }
 }
 
 #[cfg(test)]
@@ -120,9 +291,8 @@ mod tests {
     pub(crate) type TestVec<T> = Vec<T, stores::BTreeMap>;
     pub(crate) fn empty<T: Clone + Serialize + DeserializeOwned>() -> TestVec<T> {
         let ds = stores::BTreeMap::new();
-        let len = Arc::new(AtomicUsize::new(0));
-        let vec = Vec::new(ds, 1, len);
-        vec
+        let len = Arc::new(Mutex::new(0));
+        Vec::new(ds, Prefix::from(1), len)
     }
 
     mod given_empty_vec {
@@ -149,6 +319,102 @@ mod tests {
         }
     }
 
+    mod given_3_long_vec {
+        use super::*;
+
+        fn vec() -> TestVec<u8> {
+            let vec = empty();
+            vec.push(&1).unwrap();
+            vec.push(&2).unwrap();
+            vec.push(&3).unwrap();
+            vec
+        }
+
+        fn elems(vec: &TestVec<u8>) -> std::vec::Vec<u8> {
+            vec.into_iter().map(Result::unwrap).collect()
+        }
+
+        #[test]
+        fn first_and_last() {
+            let vec = vec();
+            assert_eq!(vec.first().unwrap(), Some(1));
+            assert_eq!(vec.last().unwrap(), Some(3));
+        }
+
+        #[test]
+        fn set_replaces_element() {
+            let vec = vec();
+            vec.set(1, &20).unwrap();
+            assert_eq!(elems(&vec), [1, 20, 3]);
+        }
+
+        #[test]
+        fn set_out_of_range_errors() {
+            let vec = vec();
+            let err = vec.set(3, &4).unwrap_err();
+            assert!(matches!(err, Error::IndexOutOfRange { index: 3, len: 3 }));
+            assert_eq!(vec.len(), 3);
+        }
+
+        #[test]
+        fn swap_elements() {
+            let vec = vec();
+            vec.swap(0, 2).unwrap();
+            assert_eq!(elems(&vec), [3, 2, 1]);
+            assert!(vec.swap(0, 3).is_err());
+        }
+
+        #[test]
+        fn swap_moves_holes() {
+            let vec = vec();
+            let _: Option<u8> = vec.ds.remove(&vec.keys.element(0)).unwrap();
+            vec.swap(0, 2).unwrap();
+            assert_eq!(vec.get(0).unwrap(), Some(3));
+            assert_eq!(vec.get(2).unwrap(), None);
+            vec.swap(1, 2).unwrap();
+            assert_eq!(vec.get(1).unwrap(), None);
+            assert_eq!(vec.get(2).unwrap(), Some(2));
+        }
+
+        #[test]
+        fn truncate_and_clear() {
+            let vec = vec();
+            vec.truncate(5).unwrap();
+            assert_eq!(vec.len(), 3);
+            vec.truncate(1).unwrap();
+            assert_eq!(elems(&vec), [1]);
+            vec.clear().unwrap();
+            assert!(vec.is_empty());
+            assert_eq!(vec.pop().unwrap(), None);
+        }
+
+        #[test]
+        fn swap_remove() {
+            let vec = vec();
+            assert_eq!(vec.swap_remove(0).unwrap(), Some(1));
+            assert_eq!(elems(&vec), [3, 2]);
+            assert_eq!(vec.swap_remove(1).unwrap(), Some(2));
+            assert_eq!(elems(&vec), [3]);
+            assert!(matches!(
+                vec.swap_remove(1),
+                Err(Error::IndexOutOfRange { index: 1, len: 1 })
+            ));
+        }
+
+        #[test]
+        fn swap_remove_holes() {
+            let vec = vec();
+            let _: Option<u8> = vec.ds.remove(&vec.keys.element(0)).unwrap();
+            assert_eq!(vec.swap_remove(0).unwrap(), None);
+            assert_eq!(elems(&vec), [3, 2]);
+
+            let _: Option<u8> = vec.ds.remove(&vec.keys.element(1)).unwrap();
+            assert_eq!(vec.swap_remove(0).unwrap(), Some(3));
+            assert_eq!(vec.len(), 1);
+            assert_eq!(vec.get(0).unwrap(), None);
+        }
+    }
+
     mod given_2_long_vec {
         use super::*;
 
@@ -174,4 +440,93 @@ mod tests {
             assert_eq!(elem, None)
         }
     }
+
+    mod reopening {
+        use super::*;
+
+        fn reopen(ds: &stores::BTreeMap) -> TestVec<u8> {
+            let len = stored_len::<u8, _>(ds, Prefix::from(1)).unwrap();
+            Vec::new(ds.clone(), Prefix::from(1), Arc::new(Mutex::new(len)))
+        }
+
+        #[test]
+        fn len_is_stored() {
+            let ds = stores::BTreeMap::new();
+            let vec = reopen(&ds);
+            vec.push(&1).unwrap();
+            vec.push(&2).unwrap();
+            vec.push(&3).unwrap();
+            vec.pop().unwrap();
+            assert_eq!(reopen(&ds).len(), 2);
+        }
+
+        #[test]
+        fn len_survives_holes() {
+            let ds = stores::BTreeMap::new();
+            let vec = reopen(&ds);
+            vec.push(&1).unwrap();
+            vec.push(&2).unwrap();
+            vec.push(&3).unwrap();
+            let _: Option<u8> = ds.remove(&vec.keys.element(2)).unwrap();
+            assert_eq!(reopen(&ds).len(), 3);
+        }
+
+        #[test]
+        fn element_without_len_is_ignored() {
+            let ds = stores::BTreeMap::new();
+            let vec = reopen(&ds);
+            vec.push(&1).unwrap();
+            // crashed after writing the element but before the length
+            ds.insert(&vec.keys.element(1), &2u8).unwrap();
+
+            let vec = reopen(&ds);
+            assert_eq!(vec.len(), 1);
+            vec.push(&3).unwrap();
+            assert_eq!(vec.pop().unwrap(), Some(3));
+        }
+
+        #[test]
+        fn len_without_len_key_past_255() {
+            let ds = stores::BTreeMap::new();
+            let vec = reopen(&ds);
+            for i in 0..300 {
+                vec.push(&(i as u8)).unwrap();
+            }
+            let _: Option<usize> = ds.remove(&Prefixed::len(Prefix::from(1))).unwrap();
+            assert_eq!(reopen(&ds).len(), 300);
+        }
+
+        #[test]
+        fn len_without_len_key_counts_holes() {
+            let ds = stores::BTreeMap::new();
+            let vec = reopen(&ds);
+            for i in 0..5 {
+                vec.push(&i).unwrap();
+            }
+            let _: Option<u8> = ds.remove(&vec.keys.element(1)).unwrap();
+            let _: Option<usize> = ds.remove(&Prefixed::len(Prefix::from(1))).unwrap();
+            let vec = reopen(&ds);
+            assert_eq!(vec.len(), 5);
+            assert_eq!(vec.get(1).unwrap(), None);
+            assert_eq!(vec.last().unwrap(), Some(4));
+        }
+
+        #[test]
+        fn len_without_len_key_next_to_other_field() {
+            let ds = stores::BTreeMap::new();
+            ds.insert(&0u8, &5u8).unwrap();
+            ds.insert(&2u8, &5u8).unwrap();
+            assert_eq!(reopen(&ds).len(), 0);
+        }
+
+        #[test]
+        fn failed_pop_keeps_len() {
+            let ds = stores::BTreeMap::new();
+            let vec = reopen(&ds);
+            vec.push(&1).unwrap();
+            ds.force_error();
+            assert!(vec.pop().is_err());
+            assert_eq!(vec.len(), 1);
+        }
+    }
 }
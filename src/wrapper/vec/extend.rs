@@ -61,7 +61,7 @@ where
                 return Ok(());
             };
 
-            if let Err(error) = self.push(&item) {
+            if let Err(error) = self.push(item) {
                 return Err(ExtendError {
                     unadded: item,
                     iter,
@@ -72,18 +72,18 @@ where
     }
 }
 
-
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::stores;
-    use std::sync::{atomic::AtomicUsize, Arc};
+    use crate::wrapper::Prefix;
+    use std::sync::{Arc, Mutex};
 
     #[test]
     fn error() {
         let ds = stores::BTreeMap::new();
-        let len = Arc::new(AtomicUsize::new(0));
-        let mut vec = Vec::new(ds.clone(), 1, len);
+        let len = Arc::new(Mutex::new(0));
+        let mut vec = Vec::new(ds.clone(), Prefix::from(1), len);
 
         let iter = [1, 2, 3, 4];
         ds.force_error();
@@ -0,0 +1,173 @@
+//! How a vector is stored, shared by the blocking and the async `Vec`. Every
+//! element is stored at its index and the length at the highest index, see
+//! [`Prefixed::len`].
+
+use core::fmt;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::traits::data_store::Write;
+#[cfg(feature = "async")]
+use crate::traits::AsyncDataStore;
+use crate::traits::DataStore;
+use crate::wrapper::Prefix;
+use crate::Error;
+
+use super::Prefixed;
+
+/// The keys of the vector at a prefix
+#[derive(Debug, Clone)]
+pub(crate) struct Keys {
+    prefix: Prefix,
+}
+
+impl Keys {
+    pub(crate) fn new(prefix: Prefix) -> Self {
+        Self { prefix }
+    }
+
+    pub(crate) fn prefix(&self) -> &Prefix {
+        &self.prefix
+    }
+
+    pub(crate) fn element(&self, index: usize) -> Prefixed {
+        Prefixed::new(self.prefix.clone(), index)
+    }
+
+    pub(crate) fn len(&self) -> Prefixed {
+        Prefixed::len(self.prefix.clone())
+    }
+}
+
+pub(crate) fn check_index<E: fmt::Debug>(index: usize, len: usize) -> Result<(), Error<E>> {
+    if index >= len {
+        return Err(Error::IndexOutOfRange { index, len });
+    }
+    Ok(())
+}
+
+/// Writes `value` to `key` or removes it if the element is a hole
+pub(crate) fn write_element<'a, T>(
+    key: &'a Prefixed,
+    value: Option<&'a T>,
+) -> Write<'a, Prefixed, T>
+where
+    T: Serialize + DeserializeOwned,
+{
+    match value {
+        Some(value) => Write::Insert(key, value),
+        None => Write::remove(key),
+    }
+}
+
+/// Which write of a [`LenChange`] is applied first if the store can not
+/// apply both at once
+enum Order {
+    ElementFirst,
+    LenFirst,
+}
+
+/// A change to the length of a vector written together with one element.
+/// The writes are ordered such that the vector stays consistent if only
+/// the first makes it to disk.
+pub(crate) struct LenChange<'a, T> {
+    element: Prefixed,
+    value: Option<&'a T>,
+    len: Prefixed,
+    new_len: usize,
+    order: Order,
+    /// left behind past the new length, removed once the pair is written
+    stale: Option<Prefixed>,
+}
+
+impl<'a, T> LenChange<'a, T>
+where
+    T: Serialize + DeserializeOwned,
+{
+    /// Appends `value` to a vector of length `len`. If only the element
+    /// makes it to disk it is ignored.
+    pub(crate) fn push(keys: &Keys, len: usize, value: &'a T) -> Self {
+        Self {
+            element: keys.element(len),
+            value: Some(value),
+            len: keys.len(),
+            new_len: len + 1,
+            order: Order::ElementFirst,
+            stale: None,
+        }
+    }
+
+    /// Removes the last element. If only the length makes it to disk the
+    /// element is ignored.
+    pub(crate) fn pop(keys: &Keys, last_index: usize) -> Self {
+        Self {
+            element: keys.element(last_index),
+            value: None,
+            len: keys.len(),
+            new_len: last_index,
+            order: Order::LenFirst,
+            stale: None,
+        }
+    }
+
+    /// Removes the element at `index` putting `last`, the element at
+    /// `last_index`, in its place.
+    pub(crate) fn swap_remove(
+        keys: &Keys,
+        index: usize,
+        last_index: usize,
+        last: Option<&'a T>,
+    ) -> Self {
+        match last {
+            Some(last) => Self {
+                element: keys.element(index),
+                value: Some(last),
+                len: keys.len(),
+                new_len: last_index,
+                order: Order::ElementFirst,
+                stale: Some(keys.element(last_index)),
+            },
+            // removing the last element or a hole takes its place, either
+            // way only the length changes
+            None => Self {
+                element: keys.element(index),
+                ..Self::pop(keys, last_index)
+            },
+        }
+    }
+
+    pub(crate) fn new_len(&self) -> usize {
+        self.new_len
+    }
+
+    pub(crate) fn write<DS: DataStore>(&self, ds: &DS) -> Result<(), DS::Error> {
+        let element = write_element(&self.element, self.value);
+        let len = Write::<_, usize>::Insert(&self.len, &self.new_len);
+        match self.order {
+            Order::ElementFirst => ds.write_pair(element, len)?,
+            Order::LenFirst => ds.write_pair(len, element)?,
+        }
+        if let Some(stale) = &self.stale {
+            ds.remove::<_, T>(stale)?;
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "async")]
+    pub(crate) async fn write_async<DS: AsyncDataStore>(&self, ds: &DS) -> Result<(), DS::Error>
+    where
+        T: Send + Sync,
+    {
+        let element = write_element(&self.element, self.value);
+        let len = Write::<_, usize>::Insert(&self.len, &self.new_len);
+        match self.order {
+            Order::ElementFirst => ds.write_pair(element, len).await?,
+            Order::LenFirst => ds.write_pair(len, element).await?,
+        }
+        if let Some(stale) = &self.stale {
+            ds.remove::<_, T>(stale).await?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,331 @@
+use core::fmt;
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+use crate::traits::data_store::{self, Write};
+use crate::traits::DataStore;
+use crate::wrapper::Prefix;
+use crate::wrapper::watch::{VecDequeDecoder, VecDequeEvent, Watcher};
+use crate::Error;
+
+mod iterator;
+
+/// mimics the API of [`VecDeque`](std::collections::VecDeque)
+///
+/// The elements are stored between a head and a tail counter, pushing or
+/// popping at either end moves one of them. Like the [`Vec`](super::Vec)
+/// wrapper the counters are stored in the database and written together
+/// with the element.
+pub struct VecDeque<T, DS>
+where
+    DS: DataStore,
+{
+    phantom: PhantomData<T>,
+    ds: DS,
+    prefix: Prefix,
+    /// Cached ends, the lock is held while changing the deque
+    ends: Arc<Mutex<Ends>>,
+}
+
+/// The index of the first element and one past the last element of a
+/// [`VecDeque`]. Both start at zero, pushing to the front lowers the head
+/// below it.
+#[doc(hidden)]
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Ends {
+    pub(crate) head: i64,
+    pub(crate) tail: i64,
+}
+
+impl Ends {
+    pub(crate) fn len(&self) -> usize {
+        (self.tail - self.head) as usize
+    }
+
+    pub(crate) fn index(&self, index: usize) -> i64 {
+        self.head + index as i64
+    }
+}
+
+#[derive(Serialize, Hash, Eq, PartialEq, Clone, Debug)]
+pub(crate) struct Prefixed {
+    prefix: Prefix,
+    index: i64,
+}
+
+impl Prefixed {
+    pub(crate) fn new(prefix: Prefix, index: i64) -> Self {
+        Self { prefix, index }
+    }
+
+    /// The ends are stored at the highest index, the tail would have to
+    /// pass `i64::MAX` for an element to end up there.
+    pub(crate) fn ends(prefix: Prefix) -> Self {
+        Self::new(prefix, i64::MAX)
+    }
+}
+
+/// Reads the ends of the deque at prefix when opening the database.
+#[doc(hidden)]
+pub fn stored_ends<DS: DataStore>(ds: &DS, prefix: Prefix) -> Result<Ends, DS::Error> {
+    Ok(ds.get(&Prefixed::ends(prefix))?.unwrap_or_default())
+}
+
+impl<T, E, DS> VecDeque<T, DS>
+where
+    E: fmt::Debug,
+    T: Serialize + DeserializeOwned,
+    DS: DataStore<Error = E>,
+{
+    #[doc(hidden)]
+    pub fn new(ds: DS, prefix: Prefix, ends: Arc<Mutex<Ends>>) -> Self {
+        Self {
+            phantom: PhantomData,
+            ds,
+            prefix,
+            ends,
+        }
+    }
+
+    /// The lock is only poisoned if we panicked while holding it, the
+    /// cached ends are only changed after the database is updated.
+    fn lock(&self) -> MutexGuard<'_, Ends> {
+        self.ends.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn key(&self, index: i64) -> Prefixed {
+        Prefixed::new(self.prefix.clone(), index)
+    }
+
+    fn ends_key(&self) -> Prefixed {
+        Prefixed::ends(self.prefix.clone())
+    }
+
+    /// If a write failed part of it might still have been applied,
+    /// the ends in the database are leading.
+    fn resync(&self, ends: &mut Ends) {
+        match self.ds.get(&self.ends_key()) {
+            Ok(Some(stored)) => *ends = stored,
+            Ok(None) => (),
+            Err(e) => warn!("could not resync deque ends: {e:?}"),
+        }
+    }
+
+    /// Adds the element at `index` and stores `new` as the ends. The element
+    /// goes first, if only that makes it to disk it is ignored.
+    fn add(&self, ends: &mut Ends, new: Ends, index: i64, value: &T) -> Result<(), Error<E>> {
+        let res = self.ds.write_pair(
+            Write::Insert(&self.key(index), value),
+            Write::Insert(&self.ends_key(), &new),
+        );
+        if let Err(e) = res {
+            self.resync(ends);
+            return Err(e.into());
+        }
+        *ends = new;
+        Ok(())
+    }
+
+    /// Removes the element at `index` and stores `new` as the ends. The ends
+    /// go first, if only they make it to disk the element is ignored.
+    fn take(&self, ends: &mut Ends, new: Ends, index: i64) -> Result<Option<T>, Error<E>> {
+        let value = self.ds.get(&self.key(index))?;
+        let res = self.ds.write_pair(
+            Write::Insert(&self.ends_key(), &new),
+            Write::<_, T>::remove(&self.key(index)),
+        );
+        if let Err(e) = res {
+            self.resync(ends);
+            return Err(e.into());
+        }
+        *ends = new;
+        Ok(value)
+    }
+
+    pub fn push_back(&self, value: &T) -> Result<(), Error<E>> {
+        let mut ends = self.lock();
+        debug!("pushing onto back of deque (index: {})", ends.tail);
+        let new = Ends {
+            tail: ends.tail + 1,
+            ..*ends
+        };
+        let index = ends.tail;
+        self.add(&mut ends, new, index, value)
+    }
+
+    pub fn push_front(&self, value: &T) -> Result<(), Error<E>> {
+        let mut ends = self.lock();
+        let index = ends.head - 1;
+        debug!("pushing onto front of deque (index: {index})");
+        let new = Ends {
+            head: index,
+            ..*ends
+        };
+        self.add(&mut ends, new, index, value)
+    }
+
+    pub fn pop_back(&self) -> Result<Option<T>, Error<E>> {
+        let mut ends = self.lock();
+        if ends.len() == 0 {
+            return Ok(None);
+        }
+        let index = ends.tail - 1;
+        debug!("popping from back of deque (index: {index})");
+        let new = Ends {
+            tail: index,
+            ..*ends
+        };
+        self.take(&mut ends, new, index)
+    }
+
+    pub fn pop_front(&self) -> Result<Option<T>, Error<E>> {
+        let mut ends = self.lock();
+        if ends.len() == 0 {
+            return Ok(None);
+        }
+        let index = ends.head;
+        debug!("popping from front of deque (index: {index})");
+        let new = Ends {
+            head: index + 1,
+            ..*ends
+        };
+        self.take(&mut ends, new, index)
+    }
+
+    /// the element `index` places from the front
+    pub fn get(&self, index: usize) -> Result<Option<T>, Error<E>> {
+        let ends = *self.lock();
+        if index >= ends.len() {
+            return Ok(None);
+        }
+        Ok(self.ds.get(&self.key(ends.index(index)))?)
+    }
+
+    pub fn front(&self) -> Result<Option<T>, Error<E>> {
+        self.get(0)
+    }
+
+    pub fn back(&self) -> Result<Option<T>, Error<E>> {
+        match self.len().checked_sub(1) {
+            Some(index) => self.get(index),
+            None => Ok(None),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// removes all elements
+    pub fn clear(&self) -> Result<(), Error<E>> {
+        let mut ends = self.lock();
+        // the elements are ignored from here on
+        if let Err(e) = self.ds.insert(&self.ends_key(), &Ends::default()) {
+            self.resync(&mut ends);
+            return Err(e.into());
+        }
+        let old = std::mem::take(&mut *ends);
+        for index in old.head..old.tail {
+            let _: Option<T> = self.ds.remove(&self.key(index))?;
+        }
+        Ok(())
+    }
+}
+
+impl<T, E, DS> VecDeque<T, DS>
+where
+    E: fmt::Debug,
+    T: Serialize + DeserializeOwned + Send + 'static,
+    DS: data_store::Watch<Error = E>,
+{
+    /// subscribes to the changes made to the deque from here on, see
+    /// [`Watcher`]
+    pub fn watch(&self) -> Result<Watcher<VecDequeEvent<T>, DS::Subscriber, E>, Error<E>> {
+        // no changes can happen between reading the ends and subscribing
+        let ends = self.lock();
+        let subscriber = self.ds.watch_prefix(&self.prefix)?;
+        let decoder = VecDequeDecoder::new::<DS::Codec>(&self.prefix, *ends);
+        Ok(Watcher::new(subscriber, decoder))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stores;
+
+    pub(crate) type TestDeque<T> = VecDeque<T, stores::BTreeMap>;
+    pub(crate) fn empty<T: Serialize + DeserializeOwned>() -> TestDeque<T> {
+        let ds = stores::BTreeMap::new();
+        VecDeque::new(ds, Prefix::from(1), Arc::new(Mutex::new(Ends::default())))
+    }
+
+    fn reopen<T: Serialize + DeserializeOwned>(deque: &TestDeque<T>) -> TestDeque<T> {
+        let ends = stored_ends(&deque.ds, Prefix::from(1)).unwrap();
+        VecDeque::new(deque.ds.clone(), Prefix::from(1), Arc::new(Mutex::new(ends)))
+    }
+
+    #[test]
+    fn push_and_pop_at_both_ends() {
+        let deque = empty();
+        deque.push_back(&2).unwrap();
+        deque.push_front(&1).unwrap();
+        deque.push_back(&3).unwrap();
+        deque.push_front(&0).unwrap();
+        assert_eq!(deque.len(), 4);
+        assert_eq!(deque.front().unwrap(), Some(0));
+        assert_eq!(deque.back().unwrap(), Some(3));
+        assert_eq!(deque.get(1).unwrap(), Some(1));
+
+        assert_eq!(deque.pop_front().unwrap(), Some(0));
+        assert_eq!(deque.pop_back().unwrap(), Some(3));
+        assert_eq!(deque.pop_back().unwrap(), Some(2));
+        assert_eq!(deque.pop_back().unwrap(), Some(1));
+        assert_eq!(deque.pop_front().unwrap(), None);
+        assert_eq!(deque.pop_back().unwrap(), None);
+        assert!(deque.is_empty());
+    }
+
+    #[test]
+    fn ends_are_stored() {
+        let deque = empty();
+        deque.push_front(&1u8).unwrap();
+        deque.push_front(&0).unwrap();
+        deque.push_back(&2).unwrap();
+        deque.pop_back().unwrap();
+
+        let deque = reopen(&deque);
+        assert_eq!(deque.len(), 2);
+        assert_eq!(deque.pop_front().unwrap(), Some(0));
+        assert_eq!(deque.pop_front().unwrap(), Some(1));
+    }
+
+    #[test]
+    fn clear_removes_elements() {
+        let deque = empty();
+        deque.push_front(&1u8).unwrap();
+        deque.push_back(&2).unwrap();
+        deque.clear().unwrap();
+        assert!(deque.is_empty());
+        assert_eq!(reopen(&deque).len(), 0);
+        let left: Option<u8> = deque.ds.get(&Prefixed::new(Prefix::from(1), 0)).unwrap();
+        assert_eq!(left, None);
+    }
+
+    #[test]
+    fn failed_pop_keeps_ends() {
+        let deque = empty();
+        deque.push_back(&1u8).unwrap();
+        deque.ds.force_error();
+        assert!(deque.pop_front().is_err());
+        assert_eq!(deque.len(), 1);
+    }
+}
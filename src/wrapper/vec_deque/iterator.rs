@@ -0,0 +1,111 @@
+use super::VecDeque;
+use crate::Error;
+use std::fmt;
+
+use crate::traits::DataStore;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Iterates over the elements from either end, the positions are relative
+/// to the front of the deque. Popping from the front while iterating shifts
+/// the elements that are visited.
+pub struct VecDequeIter<'a, T, E, DS>
+where
+    E: fmt::Debug,
+    T: Serialize + DeserializeOwned,
+    DS: DataStore<Error = E>,
+{
+    front: usize,
+    back: usize,
+    deque: &'a VecDeque<T, DS>,
+}
+
+impl<'a, T, E, DS> Iterator for VecDequeIter<'a, T, E, DS>
+where
+    E: fmt::Debug,
+    T: Serialize + DeserializeOwned,
+    DS: DataStore<Error = E>,
+{
+    type Item = Result<T, Error<E>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.front >= self.back {
+            return None;
+        }
+        let elem = self.deque.get(self.front);
+        self.front += 1;
+        elem.transpose()
+    }
+}
+
+impl<'a, T, E, DS> DoubleEndedIterator for VecDequeIter<'a, T, E, DS>
+where
+    E: fmt::Debug,
+    T: Serialize + DeserializeOwned,
+    DS: DataStore<Error = E>,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.front >= self.back {
+            return None;
+        }
+        self.back -= 1;
+        self.deque.get(self.back).transpose()
+    }
+}
+
+impl<T, E, DS> VecDeque<T, DS>
+where
+    E: fmt::Debug,
+    T: Serialize + DeserializeOwned,
+    DS: DataStore<Error = E>,
+{
+    /// This can be quite slow as it gets each element from the db
+    /// individually.
+    pub fn iter(&self) -> VecDequeIter<'_, T, E, DS> {
+        VecDequeIter {
+            front: 0,
+            back: self.len(),
+            deque: self,
+        }
+    }
+}
+
+impl<'a, T, E, DS> IntoIterator for &'a VecDeque<T, DS>
+where
+    E: fmt::Debug,
+    T: Serialize + DeserializeOwned,
+    DS: DataStore<Error = E>,
+{
+    type IntoIter = VecDequeIter<'a, T, E, DS>;
+    type Item = Result<T, Error<E>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::*;
+
+    #[test]
+    fn iterates_from_both_ends() {
+        let deque = empty();
+        for i in 1..=3 {
+            deque.push_back(&i).unwrap();
+            deque.push_front(&-i).unwrap();
+        }
+
+        let all: Vec<i32> = deque.iter().map(Result::unwrap).collect();
+        assert_eq!(all, vec![-3, -2, -1, 1, 2, 3]);
+        let reversed: Vec<i32> = deque.iter().rev().map(Result::unwrap).collect();
+        assert_eq!(reversed, vec![3, 2, 1, -1, -2, -3]);
+
+        let mut iter = deque.iter();
+        assert_eq!(iter.next_back().unwrap().unwrap(), 3);
+        assert_eq!(iter.next().unwrap().unwrap(), -3);
+        assert_eq!(iter.by_ref().count(), 4);
+        assert!(iter.next_back().is_none());
+    }
+}
@@ -0,0 +1,522 @@
+//! Typed change events returned by the `watch` method of the wrappers.
+//!
+//! A [`Watcher`] turns the raw [`Change`]s to the keys of a field into
+//! events. It is an [`Iterator`] that blocks until the next event and, with
+//! the `async` feature, a [`Stream`](futures_core::Stream). Only changes
+//! made after calling `watch` are returned.
+
+use core::fmt;
+use core::marker::PhantomData;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+use serde::de::DeserializeOwned;
+
+use crate::codec::{self, Codec};
+use crate::traits::data_store::Change;
+use crate::wrapper::{Ends, Prefix};
+use crate::Error;
+
+/// A change to a single value, a removed value reads as missing (or as its
+/// default) again.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValueEvent<T> {
+    Set(T),
+    Removed,
+}
+
+/// A change to a [`Vec`](super::Vec). Clearing or truncating the vector pops
+/// every removed element, last one first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VecEvent<T> {
+    Pushed(T),
+    Popped,
+    /// The element at `index` was replaced (by `set`, `swap` or `update`)
+    Set {
+        index: usize,
+        value: T,
+    },
+}
+
+/// A change to a [`VecDeque`](super::VecDeque). Clearing the deque pops
+/// every element from the back.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VecDequeEvent<T> {
+    PushedFront(T),
+    PushedBack(T),
+    PoppedFront,
+    PoppedBack,
+}
+
+/// A change to a [`Map`](super::Map) or [`BTreeMap`](super::BTreeMap)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MapEvent<K, V> {
+    /// The key was inserted or its value replaced
+    Inserted(K, V),
+    Removed(K),
+}
+
+/// A change to a [`Set`](super::Set)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SetEvent<T> {
+    Inserted(T),
+    Removed(T),
+}
+
+/// A change that could not be turned into an event
+pub(crate) enum Invalid {
+    Key(codec::Error),
+    Value(codec::Error),
+}
+
+impl<E: fmt::Debug> From<Invalid> for Error<E> {
+    fn from(invalid: Invalid) -> Self {
+        match invalid {
+            Invalid::Key(e) => Error::DeSerializingKey(e),
+            Invalid::Value(e) => Error::DeSerializingVal(e),
+        }
+    }
+}
+
+/// Turns the changes to the keys of a field into events
+pub(crate) trait Decode<Event>: Send {
+    fn decode(&mut self, change: Change, events: &mut VecDeque<Event>) -> Result<(), Invalid>;
+}
+
+/// The events for a field, returned by the `watch` method of a wrapper.
+///
+/// Ends once the database is closed. An item is an error if a change could
+/// not be deserialized, the watcher can be used after that.
+pub struct Watcher<Event, S, E> {
+    subscriber: S,
+    decoder: Box<dyn Decode<Event>>,
+    events: VecDeque<Event>,
+    phantom: PhantomData<fn() -> E>,
+}
+
+impl<Event, S, E> Watcher<Event, S, E> {
+    pub(crate) fn new(subscriber: S, decoder: impl Decode<Event> + 'static) -> Self {
+        Self {
+            subscriber,
+            decoder: Box::new(decoder),
+            events: VecDeque::new(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+/// The [`Watcher`] returned by [`Map::watch`](super::Map::watch) and
+/// [`BTreeMap::watch`](super::BTreeMap::watch)
+pub type MapWatcher<K, V, S, E> = Watcher<MapEvent<K, V>, S, E>;
+
+/// The events are never pinned, only the subscriber is polled
+impl<Event, S: Unpin, E> Unpin for Watcher<Event, S, E> {}
+
+impl<Event, S, E> fmt::Debug for Watcher<Event, S, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Watcher")
+            .field("queued_events", &self.events.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<Event, S, E> Iterator for Watcher<Event, S, E>
+where
+    S: Iterator<Item = Change>,
+    E: fmt::Debug,
+{
+    type Item = Result<Event, Error<E>>;
+
+    /// Blocks until the next event
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(event) = self.events.pop_front() {
+                return Some(Ok(event));
+            }
+            let change = self.subscriber.next()?;
+            if let Err(invalid) = self.decoder.decode(change, &mut self.events) {
+                return Some(Err(invalid.into()));
+            }
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+impl<Event, S, E> futures_core::Stream for Watcher<Event, S, E>
+where
+    S: core::future::Future<Output = Option<Change>> + Unpin,
+    E: fmt::Debug,
+{
+    type Item = Result<Event, Error<E>>;
+
+    fn poll_next(
+        self: core::pin::Pin<&mut Self>,
+        cx: &mut core::task::Context<'_>,
+    ) -> core::task::Poll<Option<Self::Item>> {
+        use core::task::Poll;
+
+        let this = self.get_mut();
+        loop {
+            if let Some(event) = this.events.pop_front() {
+                return Poll::Ready(Some(Ok(event)));
+            }
+            let change = match core::pin::Pin::new(&mut this.subscriber).poll(cx) {
+                Poll::Ready(Some(change)) => change,
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            };
+            if let Err(invalid) = this.decoder.decode(change, &mut this.events) {
+                return Poll::Ready(Some(Err(invalid.into())));
+            }
+        }
+    }
+}
+
+/// The bytes every key of the field starts with, the key encoding of
+/// a prefix is its bytes.
+fn prefix_bytes(prefix: &Prefix) -> Vec<u8> {
+    prefix.as_bytes().to_vec()
+}
+
+/// The part of a key after the prefix of the field
+fn key_after<K: DeserializeOwned>(key: &[u8], prefix: &[u8]) -> Result<K, Invalid> {
+    codec::deserialize_key(&key[prefix.len()..]).map_err(Invalid::Key)
+}
+
+/// Deserializes a value using the codec of the store, as a function pointer
+/// the decoders do not depend on the lifetime of the codec type.
+type Deserialize<T> = fn(&[u8]) -> Result<T, codec::Error>;
+
+pub(crate) struct ValueDecoder<T> {
+    key: Vec<u8>,
+    value: Deserialize<T>,
+}
+
+impl<T: DeserializeOwned> ValueDecoder<T> {
+    pub(crate) fn new<C: Codec>(key: &Prefix) -> Self {
+        Self {
+            key: prefix_bytes(key),
+            value: C::deserialize,
+        }
+    }
+}
+
+impl<T: DeserializeOwned> Decode<ValueEvent<T>> for ValueDecoder<T> {
+    fn decode(
+        &mut self,
+        change: Change,
+        events: &mut VecDeque<ValueEvent<T>>,
+    ) -> Result<(), Invalid> {
+        // fields nested under this key are not part of the value
+        if change.key() != self.key {
+            return Ok(());
+        }
+        events.push_back(match change {
+            Change::Insert { value, .. } => {
+                ValueEvent::Set((self.value)(&value).map_err(Invalid::Value)?)
+            }
+            Change::Remove { .. } => ValueEvent::Removed,
+        });
+        Ok(())
+    }
+}
+
+pub(crate) struct MapDecoder<K, V> {
+    prefix: Vec<u8>,
+    value: Deserialize<V>,
+    phantom: PhantomData<fn() -> K>,
+}
+
+impl<K, V: DeserializeOwned> MapDecoder<K, V> {
+    pub(crate) fn new<C: Codec>(prefix: &Prefix) -> Self {
+        Self {
+            prefix: prefix_bytes(prefix),
+            value: C::deserialize,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<K, V> Decode<MapEvent<K, V>> for MapDecoder<K, V>
+where
+    K: DeserializeOwned,
+    V: DeserializeOwned,
+{
+    fn decode(
+        &mut self,
+        change: Change,
+        events: &mut VecDeque<MapEvent<K, V>>,
+    ) -> Result<(), Invalid> {
+        events.push_back(match change {
+            Change::Insert { key, value } => MapEvent::Inserted(
+                key_after(&key, &self.prefix)?,
+                (self.value)(&value).map_err(Invalid::Value)?,
+            ),
+            Change::Remove { key } => MapEvent::Removed(key_after(&key, &self.prefix)?),
+        });
+        Ok(())
+    }
+}
+
+pub(crate) struct SetDecoder<T> {
+    prefix: Vec<u8>,
+    phantom: PhantomData<fn() -> T>,
+}
+
+impl<T> SetDecoder<T> {
+    pub(crate) fn new(prefix: &Prefix) -> Self {
+        Self {
+            prefix: prefix_bytes(prefix),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<T: DeserializeOwned> Decode<SetEvent<T>> for SetDecoder<T> {
+    fn decode(
+        &mut self,
+        change: Change,
+        events: &mut VecDeque<SetEvent<T>>,
+    ) -> Result<(), Invalid> {
+        events.push_back(match change {
+            Change::Insert { key, .. } => SetEvent::Inserted(key_after(&key, &self.prefix)?),
+            Change::Remove { key } => SetEvent::Removed(key_after(&key, &self.prefix)?),
+        });
+        Ok(())
+    }
+}
+
+/// A push writes the element and the new length together, a store may
+/// apply such a pair in any order. Whichever arrives first is kept until the
+/// other one does. Removed elements are ignored, the length shrinking
+/// already tells us they were popped.
+pub(crate) struct VecDecoder<T> {
+    prefix: Vec<u8>,
+    len: usize,
+    /// elements past the end, waiting for the length to grow
+    unpushed: BTreeMap<usize, T>,
+    /// indices the length grew over, waiting for their element
+    pending: BTreeSet<usize>,
+    element: Deserialize<T>,
+    stored_len: Deserialize<usize>,
+}
+
+impl<T: DeserializeOwned> VecDecoder<T> {
+    /// `len` must be read while holding the lock the vector is changed under
+    pub(crate) fn new<C: Codec>(prefix: &Prefix, len: usize) -> Self {
+        Self {
+            prefix: prefix_bytes(prefix),
+            len,
+            unpushed: BTreeMap::new(),
+            pending: BTreeSet::new(),
+            element: C::deserialize,
+            stored_len: C::deserialize,
+        }
+    }
+}
+
+impl<T: DeserializeOwned + Send> Decode<VecEvent<T>> for VecDecoder<T> {
+    fn decode(
+        &mut self,
+        change: Change,
+        events: &mut VecDeque<VecEvent<T>>,
+    ) -> Result<(), Invalid> {
+        let Change::Insert { key, value } = change else {
+            return Ok(());
+        };
+        let index: usize = key_after(&key, &self.prefix)?;
+        if index == usize::MAX {
+            let new_len = (self.stored_len)(&value).map_err(Invalid::Value)?;
+            // an element that never arrived was not written, skip it
+            self.pending.clear();
+            for _ in new_len..self.len {
+                events.push_back(VecEvent::Popped);
+            }
+            for index in self.len..new_len {
+                match self.unpushed.remove(&index) {
+                    Some(value) => events.push_back(VecEvent::Pushed(value)),
+                    None => {
+                        self.pending.insert(index);
+                    }
+                }
+            }
+            self.unpushed.clear();
+            self.len = new_len;
+            return Ok(());
+        }
+
+        let value = (self.element)(&value).map_err(Invalid::Value)?;
+        if self.pending.remove(&index) {
+            events.push_back(VecEvent::Pushed(value));
+        } else if index < self.len {
+            events.push_back(VecEvent::Set { index, value });
+        } else {
+            self.unpushed.insert(index, value);
+        }
+        Ok(())
+    }
+}
+
+type PushEvent<T> = fn(T) -> VecDequeEvent<T>;
+
+/// Like [`VecDecoder`], pushes are matched up with the ends moving past them
+pub(crate) struct VecDequeDecoder<T> {
+    prefix: Vec<u8>,
+    ends: Ends,
+    unpushed: BTreeMap<i64, T>,
+    pending: BTreeMap<i64, PushEvent<T>>,
+    element: Deserialize<T>,
+    stored_ends: Deserialize<Ends>,
+}
+
+impl<T: DeserializeOwned> VecDequeDecoder<T> {
+    /// `ends` must be read while holding the lock the deque is changed under
+    pub(crate) fn new<C: Codec>(prefix: &Prefix, ends: Ends) -> Self {
+        Self {
+            prefix: prefix_bytes(prefix),
+            ends,
+            unpushed: BTreeMap::new(),
+            pending: BTreeMap::new(),
+            element: C::deserialize,
+            stored_ends: C::deserialize,
+        }
+    }
+
+    fn push(&mut self, index: i64, event: PushEvent<T>, events: &mut VecDeque<VecDequeEvent<T>>) {
+        match self.unpushed.remove(&index) {
+            Some(value) => events.push_back(event(value)),
+            None => {
+                self.pending.insert(index, event);
+            }
+        }
+    }
+}
+
+impl<T: DeserializeOwned + Send> Decode<VecDequeEvent<T>> for VecDequeDecoder<T> {
+    fn decode(
+        &mut self,
+        change: Change,
+        events: &mut VecDeque<VecDequeEvent<T>>,
+    ) -> Result<(), Invalid> {
+        let Change::Insert { key, value } = change else {
+            return Ok(());
+        };
+        let index: i64 = key_after(&key, &self.prefix)?;
+        if index != i64::MAX {
+            let value = (self.element)(&value).map_err(Invalid::Value)?;
+            if let Some(event) = self.pending.remove(&index) {
+                events.push_back(event(value));
+            } else if !(self.ends.head..self.ends.tail).contains(&index) {
+                self.unpushed.insert(index, value);
+            }
+            return Ok(());
+        }
+
+        let old = self.ends;
+        let new = (self.stored_ends)(&value).map_err(Invalid::Value)?;
+        self.pending.clear();
+        // the part of the old deque that is still there
+        let kept_head = old.head.max(new.head).min(old.tail);
+        let kept_tail = old.tail.min(new.tail).max(kept_head);
+        for _ in old.head..kept_head {
+            events.push_back(VecDequeEvent::PoppedFront);
+        }
+        for _ in kept_tail..old.tail {
+            events.push_back(VecDequeEvent::PoppedBack);
+        }
+        for index in (new.head..old.head.min(new.tail)).rev() {
+            self.push(index, VecDequeEvent::PushedFront, events);
+        }
+        for index in old.tail.max(new.head)..new.tail {
+            self.push(index, VecDequeEvent::PushedBack, events);
+        }
+        self.unpushed.clear();
+        self.ends = new;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::Bincode;
+    use crate::wrapper::vec::Prefixed;
+
+    fn insert<K: serde::Serialize, V: serde::Serialize>(key: &K, value: &V) -> Change {
+        Change::Insert {
+            key: codec::serialize_key(key).unwrap(),
+            value: Bincode::serialize(value).unwrap(),
+        }
+    }
+
+    fn remove<K: serde::Serialize>(key: &K) -> Change {
+        Change::Remove {
+            key: codec::serialize_key(key).unwrap(),
+        }
+    }
+
+    fn decode<Event>(decoder: &mut impl Decode<Event>, changes: Vec<Change>) -> Vec<Event> {
+        let mut events = VecDeque::new();
+        for change in changes {
+            decoder.decode(change, &mut events).ok().unwrap();
+        }
+        events.into()
+    }
+
+    #[test]
+    fn vec_push_pop_and_set() {
+        let prefix = Prefix::from(1);
+        let element = |index| Prefixed::new(prefix.clone(), index);
+        let len = Prefixed::len(prefix.clone());
+        let mut decoder = VecDecoder::<u8>::new::<Bincode>(&prefix, 1);
+
+        let events = decode(
+            &mut decoder,
+            vec![
+                insert(&element(1), &7u8),
+                insert(&len, &2usize),
+                insert(&element(0), &3u8),
+                insert(&len, &1usize),
+                remove(&element(1)),
+                // the store may apply the length first
+                insert(&len, &2usize),
+                insert(&element(1), &8u8),
+            ],
+        );
+        assert_eq!(
+            events,
+            vec![
+                VecEvent::Pushed(7),
+                VecEvent::Set { index: 0, value: 3 },
+                VecEvent::Popped,
+                VecEvent::Pushed(8),
+            ]
+        );
+    }
+
+    #[test]
+    fn clearing_deque_pops_every_element() {
+        use crate::wrapper::vec_deque::Prefixed;
+
+        let prefix = Prefix::from(1);
+        let ends = Prefixed::ends(prefix.clone());
+        let mut decoder = VecDequeDecoder::<u8>::new::<Bincode>(&prefix, Ends { head: 5, tail: 8 });
+
+        let events = decode(
+            &mut decoder,
+            vec![
+                insert(&ends, &Ends::default()),
+                insert(&Prefixed::new(prefix.clone(), -1), &9u8),
+                insert(&ends, &Ends { head: -1, tail: 0 }),
+            ],
+        );
+        assert_eq!(
+            events,
+            vec![
+                VecDequeEvent::PoppedBack,
+                VecDequeEvent::PoppedBack,
+                VecDequeEvent::PoppedBack,
+                VecDequeEvent::PushedFront(9),
+            ]
+        );
+    }
+}
@@ -0,0 +1,107 @@
+#![cfg(feature = "async")]
+
+use dbstruct::stores::{self, Blocking};
+
+#[dbstruct::dbstruct(db=sled, async)]
+pub struct SledTest {
+    #[dbstruct(Default)]
+    the_field: u8,
+    #[dbstruct(Default = "3")]
+    counter: u32,
+    queue: Vec<u32>,
+    names: HashMap<u8, String>,
+    ranked: BTreeMap<u16, String>,
+    seen: HashSet<u32>,
+    jobs: VecDeque<u8>,
+}
+
+#[tokio::test]
+async fn sled_backend() {
+    let dir = tempdir::TempDir::new("dbstruct_tests").unwrap();
+    let path = dir.path().join("async_db");
+
+    let db = SledTest::new(&path).await.unwrap();
+
+    db.the_field().set(&8).await.unwrap();
+    assert_eq!(8u8, db.the_field().get().await.unwrap());
+
+    db.counter().update(|n| n + 1).await.unwrap();
+    assert_eq!(4, db.counter().get().await.unwrap());
+    db.counter().set(&5).await.unwrap();
+    db.counter().update(|n| n + 1).await.unwrap();
+    assert_eq!(6, db.counter().get().await.unwrap());
+
+    db.queue().push(&1).await.unwrap();
+    db.queue().push(&2).await.unwrap();
+    assert_eq!(db.queue().len().await, 2);
+    assert_eq!(Some(2), db.queue().pop().await.unwrap());
+
+    db.names().insert(&1, &"one".to_owned()).await.unwrap();
+    assert_eq!(Some("one".to_owned()), db.names().get(&1).await.unwrap());
+    assert!(!db.names().contains_key(&2).await.unwrap());
+
+    db.ranked().insert(&300, &"third".to_owned()).await.unwrap();
+    assert!(db.ranked().contains_key(&300).await.unwrap());
+    assert_eq!(None, db.ranked().remove(&1).await.unwrap());
+
+    assert!(db.seen().insert(&5).await.unwrap());
+    assert!(db.seen().contains(&5).await.unwrap());
+    assert!(db.seen().remove(&5).await.unwrap());
+
+    db.jobs().push_back(&2).await.unwrap();
+    db.jobs().push_front(&1).await.unwrap();
+    assert_eq!(Some(2), db.jobs().back().await.unwrap());
+    assert_eq!(Some(1), db.jobs().pop_front().await.unwrap());
+}
+
+#[dbstruct::dbstruct(db=btreemap, async)]
+pub struct BTreeMapTest {
+    maybe: Option<u8>,
+}
+
+#[tokio::test]
+async fn btreemap_backend() {
+    let db = BTreeMapTest::new().await.unwrap();
+    assert_eq!(None, db.maybe().get().await.unwrap());
+    db.maybe().set(&4).await.unwrap();
+    assert_eq!(Some(4), db.maybe().get().await.unwrap());
+}
+
+mod generic {
+    #[dbstruct::dbstruct(db=trait, async)]
+    pub struct Test {
+        pub queue: Vec<u32>,
+    }
+}
+
+#[tokio::test]
+async fn async_store_keeps_vec_len() {
+    let store = Blocking::new(stores::BTreeMap::new());
+
+    let db = generic::Test::new(store.clone()).await.unwrap();
+    db.queue().push(&1).await.unwrap();
+    db.queue().push(&2).await.unwrap();
+    drop(db);
+
+    let db = generic::Test::new(store).await.unwrap();
+    assert_eq!(db.queue().len().await, 2);
+    assert_eq!(Some(2), db.queue().last().await.unwrap());
+}
+
+#[cfg(feature = "json")]
+mod codec {
+    #[dbstruct::dbstruct(db=sled, async)]
+    pub struct Test {
+        #[dbstruct(codec=json)]
+        pub queue: Vec<u32>,
+    }
+
+    #[tokio::test]
+    async fn field_codec() {
+        let dir = tempdir::TempDir::new("dbstruct_tests").unwrap();
+        let db = Test::new(dir.path().join("db")).await.unwrap();
+        db.queue().push(&1).await.unwrap();
+        assert_eq!(db.queue().len().await, 1);
+        assert_eq!(Some(1), db.queue().pop().await.unwrap());
+    }
+}
@@ -0,0 +1,48 @@
+#[dbstruct::dbstruct(db=sled)]
+pub struct Test {
+    scores: BTreeMap<i32, String>,
+    #[dbstruct(Default)]
+    the_field: u8,
+}
+
+#[test]
+fn range_queries() {
+    let dir = tempdir::TempDir::new("dbstruct_test").unwrap();
+    let db = Test::new(dir.path().join("db")).unwrap();
+    db.the_field().set(&1).unwrap();
+
+    for score in [300, -5, 12, 0, 1000] {
+        db.scores().insert(&score, &score.to_string()).unwrap();
+    }
+
+    let keys: Vec<i32> = db.scores().keys().map(Result::unwrap).collect();
+    assert_eq!(keys, vec![-5, 0, 12, 300, 1000]);
+
+    let in_range: Vec<i32> = db
+        .scores()
+        .range(0..300)
+        .unwrap()
+        .map(|res| res.unwrap().0)
+        .collect();
+    assert_eq!(in_range, vec![0, 12]);
+
+    let highest: Vec<String> = db
+        .scores()
+        .values()
+        .rev()
+        .take(2)
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(highest, vec!["1000".to_owned(), "300".to_owned()]);
+
+    assert_eq!(
+        db.scores().pop_first().unwrap(),
+        Some((-5, "-5".to_owned()))
+    );
+    assert_eq!(
+        db.scores().last_key_value().unwrap(),
+        Some((1000, "1000".to_owned()))
+    );
+    assert_eq!(db.scores().len().unwrap(), 4);
+    assert_eq!(db.the_field().get().unwrap(), 1);
+}
@@ -0,0 +1,62 @@
+#[dbstruct::dbstruct(db=sled)]
+pub struct Settings {
+    #[dbstruct(Default)]
+    volume: u8,
+}
+
+#[dbstruct::dbstruct(db=sled)]
+pub struct Library {
+    songs: Vec<String>,
+}
+
+#[test]
+fn configured_database() {
+    let db = Settings::builder()
+        .temporary(true)
+        .cache_capacity(1024 * 1024)
+        .flush_every_ms(None)
+        .tree_name("settings")
+        .open()
+        .unwrap();
+    db.volume().set(&4).unwrap();
+    assert_eq!(db.volume().get().unwrap(), 4);
+}
+
+#[test]
+fn structs_share_a_database() {
+    let dir = tempdir::TempDir::new("dbstruct_test").unwrap();
+    let sled = dbstruct::sled::open(dir.path().join("db")).unwrap();
+
+    let settings = SettingsBuilder::from_db(&sled)
+        .tree_name("settings")
+        .open()
+        .unwrap();
+    let library = LibraryBuilder::from_db(&sled)
+        .tree_name("library")
+        .open()
+        .unwrap();
+    settings.volume().set(&9).unwrap();
+    library.songs().push(&"song".to_owned()).unwrap();
+
+    let names: Vec<_> = sled.tree_names().into_iter().collect();
+    assert!(names.contains(&"settings".into()), "{names:?}");
+    assert!(names.contains(&"library".into()), "{names:?}");
+    assert_eq!(settings.volume().get().unwrap(), 9);
+    assert_eq!(library.songs().len(), 1);
+}
+
+#[cfg(feature = "async")]
+mod asynchronous {
+    #[dbstruct::dbstruct(db=sled, async)]
+    pub struct Settings {
+        #[dbstruct(Default)]
+        volume: u8,
+    }
+
+    #[tokio::test]
+    async fn builder_opens_async() {
+        let db = Settings::builder().temporary(true).open().await.unwrap();
+        db.volume().set(&2).await.unwrap();
+        assert_eq!(db.volume().get().await.unwrap(), 2);
+    }
+}
@@ -0,0 +1,68 @@
+#![cfg(all(feature = "json", feature = "postcard"))]
+
+use dbstruct::codec::{Json, WithCodec};
+use dbstruct::DataStore;
+
+#[dbstruct::dbstruct(db=sled, codec=postcard)]
+pub struct Test {
+    queue: Vec<u32>,
+    names: HashMap<u8, String>,
+    #[dbstruct(Default, codec=json, key=200)]
+    counter: u64,
+}
+
+#[test]
+fn field_codec_overrides_struct_codec() {
+    let dir = tempdir::TempDir::new("dbstruct_test").unwrap();
+    let path = dir.path().join("db");
+
+    let db = Test::new(&path).unwrap();
+    db.queue().push(&1).unwrap();
+    db.queue().push(&2).unwrap();
+    db.names().insert(&1, &"one".to_owned()).unwrap();
+    db.counter().set(&42).unwrap();
+    drop(db);
+
+    let db = Test::new(&path).unwrap();
+    assert_eq!(db.queue().len(), 2);
+    assert_eq!(db.queue().pop().unwrap(), Some(2));
+    let names: Vec<_> = db.names().iter().map(Result::unwrap).collect();
+    assert_eq!(names, vec![(1, "one".to_owned())]);
+    assert_eq!(db.counter().get().unwrap(), 42);
+}
+
+#[test]
+fn json_is_readable_by_others() {
+    let tree = dbstruct::sled::Config::default()
+        .temporary(true)
+        .open()
+        .unwrap()
+        .open_tree("test")
+        .unwrap();
+    let json: WithCodec<_, Json> = WithCodec::new(tree.clone());
+    json.insert(&200u8, &42u64).unwrap();
+
+    let key = 200u8.to_le_bytes();
+    assert_eq!(tree.get(key).unwrap().unwrap().as_ref(), b"42");
+}
+
+type Error =
+    dbstruct::Error<dbstruct::Error<dbstruct::sled::transaction::UnabortableTransactionError>>;
+
+#[test]
+fn transaction_uses_field_codec() {
+    let dir = tempdir::TempDir::new("dbstruct_test").unwrap();
+    let db = Test::new(dir.path().join("db")).unwrap();
+    db.counter().set(&1).unwrap();
+
+    db.transaction(|tx| {
+        let counter = tx.counter().get()?;
+        tx.counter().set(&(counter + 1))?;
+        tx.queue().push(&7)?;
+        Ok::<_, Error>(())
+    })
+    .unwrap();
+
+    assert_eq!(db.counter().get().unwrap(), 2);
+    assert_eq!(db.queue().last().unwrap(), Some(7));
+}
@@ -0,0 +1,131 @@
+#[dbstruct::dbstruct(db=sled)]
+pub struct Stats {
+    #[dbstruct(Counter)]
+    visits: u64,
+    #[dbstruct(Counter)]
+    balance: i32,
+    #[dbstruct(Default)]
+    name: String,
+}
+
+#[test]
+fn merges_on_sled() {
+    let db = Stats::builder().temporary(true).open().unwrap();
+    assert_eq!(db.visits().get().unwrap(), 0);
+    db.visits().increment().unwrap();
+    db.visits().add(10).unwrap();
+    assert_eq!(db.visits().fetch_add(1).unwrap(), 11);
+    assert_eq!(db.visits().get().unwrap(), 12);
+
+    db.balance().sub(5).unwrap();
+    assert_eq!(db.balance().get().unwrap(), -5);
+    db.balance().reset().unwrap();
+    assert_eq!(db.balance().get().unwrap(), 0);
+}
+
+#[cfg(feature = "rocksdb")]
+mod rocksdb {
+    #[dbstruct::dbstruct(db=rocksdb)]
+    pub struct Stats {
+        #[dbstruct(Counter)]
+        visits: u64,
+    }
+
+    #[test]
+    fn merges_on_rocksdb() {
+        let dir = tempdir::TempDir::new("dbstruct_tests").unwrap();
+        let path = dir.path().join("counter_db");
+        {
+            let db = Stats::new(&path).unwrap();
+            db.visits().increment().unwrap();
+            db.visits().add(10).unwrap();
+            assert_eq!(db.visits().fetch_add(1).unwrap(), 11);
+            db.visits().sub(2).unwrap();
+        }
+
+        let db = Stats::new(&path).unwrap();
+        assert_eq!(db.visits().get().unwrap(), 10);
+    }
+}
+
+#[test]
+fn concurrent_increments() {
+    let db = Stats::builder().temporary(true).open().unwrap();
+    std::thread::scope(|s| {
+        for _ in 0..4 {
+            s.spawn(|| {
+                for _ in 0..100 {
+                    db.visits().increment().unwrap();
+                }
+            });
+        }
+    });
+    assert_eq!(db.visits().get().unwrap(), 400);
+}
+
+#[test]
+fn in_transaction() {
+    let db = Stats::builder().temporary(true).open().unwrap();
+    db.transaction(|tx| {
+        tx.visits().add(2)?;
+        tx.name().set(&"home".to_owned())
+    })
+    .unwrap();
+    assert_eq!(db.visits().get().unwrap(), 2);
+}
+
+mod in_memory {
+    #[dbstruct::dbstruct(db=btreemap)]
+    pub struct Stats {
+        #[dbstruct(Counter)]
+        visits: u8,
+    }
+
+    #[test]
+    fn falls_back_to_atomic_update() {
+        let db = Stats::new().unwrap();
+        db.visits().sub(1).unwrap();
+        assert_eq!(db.visits().get().unwrap(), u8::MAX);
+        db.visits().increment().unwrap();
+        assert_eq!(db.visits().fetch_add(3).unwrap(), 0);
+        assert_eq!(db.visits().get().unwrap(), 3);
+    }
+}
+
+mod generic {
+    use dbstruct::traits::ByteStore;
+
+    #[dbstruct::dbstruct(db=trait)]
+    pub struct Stats {
+        #[dbstruct(Counter)]
+        visits: u32,
+    }
+
+    #[test]
+    fn sled_without_merge_operator() {
+        let db = dbstruct::sled::Config::default()
+            .temporary(true)
+            .open()
+            .unwrap();
+        let tree = db.open_tree("stats").unwrap();
+        let stats = Stats::new(tree).unwrap();
+        stats.visits().add(2).unwrap();
+        stats.visits().increment().unwrap();
+        assert_eq!(stats.visits().get().unwrap(), 3);
+    }
+
+    #[test]
+    fn other_width_is_an_error() {
+        let ds = dbstruct::stores::BTreeMap::new();
+        // written as an u16 counter
+        ByteStore::insert(&ds, &[0], &5u16.to_le_bytes()).unwrap();
+
+        let stats = Stats::new(ds.clone()).unwrap();
+        // adding does not read the value, it is left unchanged
+        stats.visits().add(1).unwrap();
+        assert!(stats.visits().get().is_err());
+        assert!(stats.visits().fetch_add(1).is_err());
+        let stored = ByteStore::get(&ds, &[0]).unwrap().unwrap();
+        assert_eq!(stored, 5u16.to_le_bytes());
+    }
+}
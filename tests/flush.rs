@@ -0,0 +1,80 @@
+#[dbstruct::dbstruct(db=sled, flush = "interval_ms=500")]
+pub struct Test {
+    #[dbstruct(Default = "0", flush = "every_write")]
+    counter: u32,
+    queue: Vec<u32>,
+    #[dbstruct(flush = "never")]
+    names: HashMap<u8, String>,
+    jobs: VecDeque<u8>,
+}
+
+#[test]
+fn policies_keep_fields_working() {
+    let dir = tempdir::TempDir::new("dbstruct_test").unwrap();
+    let path = dir.path().join("db");
+    {
+        let db = Test::new(&path).unwrap();
+        db.counter().set(&1).unwrap();
+        db.counter().update(|c| c + 1).unwrap();
+        db.queue().push(&7).unwrap();
+        db.names().insert(&1, &"one".to_owned()).unwrap();
+        db.jobs().push_back(&3).unwrap();
+        db.flush().unwrap();
+    }
+
+    let db = Test::new(&path).unwrap();
+    assert_eq!(db.counter().get().unwrap(), 2);
+    assert_eq!(db.queue().pop().unwrap(), Some(7));
+    assert_eq!(db.names().get(&1).unwrap(), Some("one".to_owned()));
+    assert_eq!(db.jobs().pop_front().unwrap(), Some(3));
+}
+
+#[test]
+fn transaction_on_flushed_fields() {
+    let dir = tempdir::TempDir::new("dbstruct_test").unwrap();
+    let db = Test::new(dir.path().join("db")).unwrap();
+
+    db.transaction(|tx| {
+        tx.queue().push(&1)?;
+        tx.counter().set(&2)
+    })
+    .unwrap();
+    assert_eq!(db.queue().len(), 1);
+    assert_eq!(db.counter().get().unwrap(), 2);
+}
+
+mod generic {
+    #[dbstruct::dbstruct(db=trait)]
+    pub struct Test {
+        pub queue: Vec<u32>,
+    }
+
+    #[test]
+    fn flushable_store() {
+        let db = Test::new(dbstruct::stores::BTreeMap::new()).unwrap();
+        db.queue().push(&1).unwrap();
+        db.flush().unwrap();
+    }
+}
+
+#[cfg(feature = "async")]
+mod asynchronous {
+    #[dbstruct::dbstruct(db=sled, async)]
+    pub struct Test {
+        #[dbstruct(Default, flush = "every_write")]
+        counter: u32,
+        queue: Vec<u32>,
+    }
+
+    #[tokio::test]
+    async fn flush_async() {
+        let dir = tempdir::TempDir::new("dbstruct_test").unwrap();
+        let db = Test::new(dir.path().join("db")).await.unwrap();
+        db.counter().set(&3).await.unwrap();
+        db.queue().push(&4).await.unwrap();
+        db.flush_async().await.unwrap();
+
+        assert_eq!(db.counter().get().await.unwrap(), 3);
+        assert_eq!(db.queue().len().await, 1);
+    }
+}
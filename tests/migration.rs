@@ -0,0 +1,110 @@
+use dbstruct::{schema, stores};
+
+mod v0 {
+    #[dbstruct::dbstruct(db=trait)]
+    pub struct Test {
+        #[dbstruct(Default)]
+        pub counter: u8,
+    }
+}
+
+mod v0_changed {
+    #[dbstruct::dbstruct(db=trait)]
+    pub struct Test {
+        #[dbstruct(Default)]
+        pub counter: u64,
+    }
+}
+
+mod v1 {
+    use dbstruct::DataStore;
+
+    #[dbstruct::dbstruct(db=trait, version=1, migrate(from=0, counter_to_u64))]
+    pub struct Test {
+        #[dbstruct(Default)]
+        pub counter: u64,
+    }
+
+    fn counter_to_u64<DS: DataStore>(ds: &DS) -> Result<(), DS::Error> {
+        // insert returns the previous value, remove it first as
+        // it can not be deserialized as an u64
+        if let Some(counter) = ds.remove::<u8, u8>(&0)? {
+            ds.insert::<u8, u64>(&0, &(counter as u64 * 1000))?;
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn type_change_without_version() {
+    let ds = stores::BTreeMap::new();
+
+    let db = v0::Test::new(ds.clone()).unwrap();
+    db.counter().set(&5).unwrap();
+
+    let Err(err) = v0_changed::Test::new(ds) else {
+        panic!("opening should fail as the type of counter changed");
+    };
+    assert!(matches!(
+        err,
+        dbstruct::Error::Schema(schema::Error::FieldChanged { field: "counter" })
+    ));
+}
+
+#[test]
+fn migrate_field_type() {
+    let ds = stores::BTreeMap::new();
+
+    let db = v0::Test::new(ds.clone()).unwrap();
+    db.counter().set(&5).unwrap();
+
+    let db = v1::Test::new(ds.clone()).unwrap();
+    assert_eq!(db.counter().get().unwrap(), 5000);
+
+    let Err(err) = v0::Test::new(ds) else {
+        panic!("opening with an older version should fail");
+    };
+    assert!(matches!(
+        err,
+        dbstruct::Error::Schema(schema::Error::NewerVersion {
+            stored: 1,
+            current: 0
+        })
+    ));
+}
+
+#[dbstruct::dbstruct(db=sled)]
+pub struct Legacy {
+    queue: Vec<u8>,
+    names: HashMap<String, u8>,
+}
+
+#[test]
+fn rewrite_keys_written_by_0_4() {
+    let db = dbstruct::sled::Config::default()
+        .temporary(true)
+        .open()
+        .unwrap();
+    // how dbstruct 0.4 stored the fields: bincode encoded keys, the index
+    // of a vec element as `u64` and the map key as string with its length.
+    // The fields get their keys in alphabetical order.
+    let tree = db.open_tree("DbStruct").unwrap();
+    for index in 0..3u8 {
+        tree.insert([1, index, 0, 0, 0, 0, 0, 0, 0], &[index * 10])
+            .unwrap();
+    }
+    tree.insert([0, 3, 0, 0, 0, 0, 0, 0, 0, b'o', b'n', b'e'], &[1])
+        .unwrap();
+
+    let legacy = LegacyBuilder::from_db(&db).open().unwrap();
+    assert_eq!(legacy.queue().len(), 3);
+    assert_eq!(legacy.queue().get(2).unwrap(), Some(20));
+    assert_eq!(legacy.names().get(&"one".to_owned()).unwrap(), Some(1));
+
+    // rewritten once, opening again leaves the keys alone
+    legacy.queue().push(&30).unwrap();
+    drop(legacy);
+    let legacy = LegacyBuilder::from_db(&db).open().unwrap();
+    let queue: Vec<_> = legacy.queue().into_iter().map(Result::unwrap).collect();
+    assert_eq!(queue, [0, 10, 20, 30]);
+}
@@ -0,0 +1,75 @@
+#[dbstruct::dbstruct(db=sled)]
+pub struct Settings {
+    #[dbstruct(Default)]
+    volume: u8,
+    recent: Vec<String>,
+    shortcuts: HashMap<char, String>,
+}
+
+#[dbstruct::dbstruct(db=sled)]
+pub struct Player {
+    #[dbstruct(Default)]
+    volume: u8,
+    queue: Vec<String>,
+    #[dbstruct(nested)]
+    settings: Settings,
+    #[dbstruct(nested)]
+    defaults: Settings,
+}
+
+#[test]
+fn fields_do_not_collide() {
+    let dir = tempdir::TempDir::new("dbstruct_test").unwrap();
+    let db = Player::new(dir.path().join("db")).unwrap();
+
+    db.volume().set(&1).unwrap();
+    db.settings().volume().set(&2).unwrap();
+    db.defaults().volume().set(&3).unwrap();
+    db.queue().push(&"parent".to_owned()).unwrap();
+    db.settings().recent().push(&"settings".to_owned()).unwrap();
+    db.settings()
+        .shortcuts()
+        .insert(&'q', &"quit".to_owned())
+        .unwrap();
+
+    assert_eq!(db.volume().get().unwrap(), 1);
+    assert_eq!(db.settings().volume().get().unwrap(), 2);
+    assert_eq!(db.defaults().volume().get().unwrap(), 3);
+    assert_eq!(db.queue().len(), 1);
+    assert_eq!(db.settings().recent().len(), 1);
+    assert_eq!(db.defaults().recent().len(), 0);
+    assert_eq!(db.defaults().shortcuts().len().unwrap(), 0);
+    let shortcuts: Vec<_> = db
+        .settings()
+        .shortcuts()
+        .iter()
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(shortcuts, vec![('q', "quit".to_owned())]);
+}
+
+#[test]
+fn persists() {
+    let dir = tempdir::TempDir::new("dbstruct_test").unwrap();
+    let path = dir.path().join("db");
+    {
+        let db = Player::new(&path).unwrap();
+        db.settings().volume().set(&7).unwrap();
+        db.settings().recent().push(&"a".to_owned()).unwrap();
+        db.settings().recent().push(&"b".to_owned()).unwrap();
+        db.queue().push(&"c".to_owned()).unwrap();
+    }
+
+    let db = Player::new(&path).unwrap();
+    assert_eq!(db.settings().volume().get().unwrap(), 7);
+    assert_eq!(db.volume().get().unwrap(), 0);
+    let recent: Vec<String> = db
+        .settings()
+        .recent()
+        .into_iter()
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(recent, vec!["a", "b"]);
+    assert_eq!(db.queue().pop().unwrap(), Some("c".to_owned()));
+    assert_eq!(db.defaults().recent().len(), 0);
+}
@@ -0,0 +1,54 @@
+mod v1 {
+    #[dbstruct::dbstruct(db=sled)]
+    pub struct Test {
+        #[dbstruct(key = 0)]
+        pub name: Option<String>,
+        #[dbstruct(Default, key = 1)]
+        pub counter: u32,
+    }
+}
+
+mod v2 {
+    #[dbstruct::dbstruct(db=sled)]
+    pub struct Test {
+        // sorts before `name` and `counter`, without the pinned
+        // keys it would have taken over `name`'s prefix
+        #[dbstruct(Default)]
+        pub aaa: u64,
+        #[dbstruct(key = 0)]
+        pub name: Option<String>,
+        #[dbstruct(Default, key = 1)]
+        pub counter: u32,
+    }
+}
+
+#[test]
+fn adding_a_field_keeps_pinned_data() {
+    let dir = tempdir::TempDir::new("dbstruct_test").unwrap();
+    let path = dir.path().join("db");
+
+    let db = v1::Test::new(&path).unwrap();
+    db.name().set(&"dbstruct".to_owned()).unwrap();
+    std::mem::drop(db);
+
+    let db = v2::Test::new(&path).unwrap();
+    assert_eq!(db.name().get().unwrap(), Some("dbstruct".to_owned()));
+    assert_eq!(db.aaa().get().unwrap(), 0);
+}
+
+#[dbstruct::dbstruct(db=btreemap, explicit_keys)]
+pub struct Explicit {
+    #[dbstruct(Default, key = 7)]
+    counter: u32,
+    #[dbstruct(key = 3)]
+    name: Option<String>,
+}
+
+#[test]
+fn explicit_keys() {
+    let db = Explicit::new().unwrap();
+    db.counter().set(&42).unwrap();
+    db.name().set(&"dbstruct".to_owned()).unwrap();
+    assert_eq!(db.counter().get().unwrap(), 42);
+    assert_eq!(db.name().get().unwrap(), Some("dbstruct".to_owned()));
+}
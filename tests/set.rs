@@ -0,0 +1,51 @@
+mod before {
+    #[dbstruct::dbstruct(db=sled)]
+    pub struct Test {
+        pub seen: HashMap<u32, ()>,
+    }
+}
+
+#[dbstruct::dbstruct(db=sled)]
+pub struct Test {
+    seen: HashSet<u32>,
+    tags: BTreeSet<String>,
+}
+
+#[test]
+fn sets() {
+    let dir = tempdir::TempDir::new("dbstruct_test").unwrap();
+    let db = Test::new(dir.path().join("db")).unwrap();
+
+    assert!(db.seen().insert(&12).unwrap());
+    assert!(!db.seen().insert(&12).unwrap());
+    assert!(db.seen().contains(&12).unwrap());
+
+    for tag in ["rust", "db", "embedded"] {
+        db.tags().insert(&tag.to_owned()).unwrap();
+    }
+    let tags: Vec<String> = db.tags().iter().map(Result::unwrap).collect();
+    assert_eq!(tags, vec!["db", "embedded", "rust"]);
+    let tags: Vec<String> = db
+        .tags()
+        .range("e".to_owned()..)
+        .unwrap()
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(tags, vec!["embedded", "rust"]);
+    assert_eq!(db.seen().len().unwrap(), 1);
+}
+
+#[test]
+fn set_reads_map_of_units() {
+    let dir = tempdir::TempDir::new("dbstruct_test").unwrap();
+    let path = dir.path().join("db");
+
+    let db = before::Test::new(&path).unwrap();
+    db.seen().insert(&1, &()).unwrap();
+    db.seen().insert(&2, &()).unwrap();
+    drop(db);
+
+    let db = Test::new(&path).unwrap();
+    let seen: Vec<u32> = db.seen().iter().map(Result::unwrap).collect();
+    assert_eq!(seen, vec![1, 2]);
+}
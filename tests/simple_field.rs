@@ -19,6 +19,10 @@ fn sled_backend() {
 pub struct BtreeMapTest {
     #[dbstruct(Default)]
     the_field: u8,
+    #[dbstruct(Default = "8")]
+    counter: u8,
+    list: Vec<u8>,
+    scores: BTreeMap<u8, u16>,
 }
 
 
@@ -28,4 +32,54 @@ fn btreemap_backend() {
 
     db.the_field().set(&8).unwrap();
     assert_eq!(8u8, db.the_field().get().unwrap());
+
+    db.counter().set(&8).unwrap();
+    db.counter().update(|v| v + 1).unwrap();
+    let outcome = db.counter().conditional_update(9, 10).unwrap();
+    assert_eq!(outcome, dbstruct::CasOutcome::Swapped);
+    let outcome = db.counter().conditional_update(9, 11).unwrap();
+    assert_eq!(outcome, dbstruct::CasOutcome::Mismatch { current: Some(10) });
+    assert_eq!(10u8, db.counter().get().unwrap());
+
+    db.list().push(&1).unwrap();
+    db.list().push(&2).unwrap();
+    assert_eq!(Some(2), db.list().pop().unwrap());
+    assert_eq!(1, db.list().len());
+
+    db.scores().insert(&2, &20).unwrap();
+    db.scores().insert(&1, &10).unwrap();
+    let keys: Vec<_> = db.scores().keys().map(Result::unwrap).collect();
+    assert_eq!(keys, vec![1, 2]);
+}
+
+#[cfg(feature = "rocksdb")]
+mod rocksdb {
+    #[dbstruct::dbstruct(db=rocksdb)]
+    pub struct RocksTest {
+        #[dbstruct(Default)]
+        the_field: u8,
+        list: Vec<u8>,
+        map: HashMap<u8, u16>,
+    }
+
+    #[test]
+    fn rocksdb_backend() {
+        let dir = tempdir::TempDir::new("dbstruct_tests").unwrap();
+        let path = dir.path().join("simple_field_db");
+
+        let db = RocksTest::new(path).unwrap();
+
+        db.the_field().set(&8).unwrap();
+        assert_eq!(8u8, db.the_field().get().unwrap());
+
+        db.list().push(&1).unwrap();
+        db.list().push(&2).unwrap();
+        assert_eq!(Some(2), db.list().pop().unwrap());
+        assert_eq!(1, db.list().len());
+
+        db.map().insert(&1, &10).unwrap();
+        db.map().insert(&2, &20).unwrap();
+        let pairs: Vec<_> = db.map().iter().map(Result::unwrap).collect();
+        assert_eq!(pairs, vec![(1, 10), (2, 20)]);
+    }
 }
@@ -0,0 +1,75 @@
+#[dbstruct::dbstruct(db=btreemap)]
+pub struct Test {
+    #[dbstruct(Default = "10")]
+    credits: u32,
+    #[dbstruct(Default)]
+    visits: u32,
+    last_seen: Option<u64>,
+}
+
+#[test]
+fn clear_falls_back_to_default() {
+    let db = Test::new().unwrap();
+    assert!(!db.credits().is_set().unwrap());
+    db.credits().set(&3).unwrap();
+    assert!(db.credits().is_set().unwrap());
+    db.credits().clear().unwrap();
+    assert!(!db.credits().is_set().unwrap());
+    assert_eq!(db.credits().get().unwrap(), 10);
+
+    db.visits().set(&4).unwrap();
+    db.visits().clear().unwrap();
+    assert_eq!(db.visits().get().unwrap(), 0);
+
+    db.last_seen().set(&5).unwrap();
+    db.last_seen().clear().unwrap();
+    assert_eq!(db.last_seen().get().unwrap(), None);
+}
+
+#[test]
+fn take_and_replace_return_previous() {
+    let db = Test::new().unwrap();
+    assert_eq!(db.credits().take().unwrap(), 10);
+    assert_eq!(db.credits().replace(&7).unwrap(), 10);
+    assert_eq!(db.credits().replace(&8).unwrap(), 7);
+    assert_eq!(db.credits().take().unwrap(), 8);
+    assert!(!db.credits().is_set().unwrap());
+
+    assert_eq!(db.visits().replace(&1).unwrap(), 0);
+    assert_eq!(db.visits().take().unwrap(), 1);
+
+    assert_eq!(db.last_seen().take().unwrap(), None);
+    assert_eq!(db.last_seen().replace(&2).unwrap(), None);
+    assert!(db.last_seen().is_set().unwrap());
+    assert_eq!(db.last_seen().take().unwrap(), Some(2));
+    assert!(!db.last_seen().is_set().unwrap());
+}
+
+#[cfg(feature = "async")]
+mod asynchronous {
+    #[dbstruct::dbstruct(db=btreemap, async)]
+    pub struct Test {
+        #[dbstruct(Default = "10")]
+        credits: u32,
+        #[dbstruct(Default)]
+        visits: u32,
+        last_seen: Option<u64>,
+    }
+
+    #[tokio::test]
+    async fn take_clear_replace() {
+        let db = Test::new().await.unwrap();
+        assert_eq!(db.credits().replace(&3).await.unwrap(), 10);
+        assert!(db.credits().is_set().await.unwrap());
+        db.credits().clear().await.unwrap();
+        assert_eq!(db.credits().get().await.unwrap(), 10);
+
+        assert_eq!(db.visits().replace(&2).await.unwrap(), 0);
+        assert_eq!(db.visits().take().await.unwrap(), 2);
+        assert!(!db.visits().is_set().await.unwrap());
+
+        db.last_seen().set(&1).await.unwrap();
+        assert_eq!(db.last_seen().take().await.unwrap(), Some(1));
+        assert_eq!(db.last_seen().take().await.unwrap(), None);
+    }
+}
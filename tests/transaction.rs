@@ -0,0 +1,130 @@
+use dbstruct::TransactionError;
+
+#[dbstruct::dbstruct(db=sled)]
+pub struct Test {
+    queue: Vec<u32>,
+    history: Vec<u32>,
+    retries: VecDeque<u32>,
+    #[dbstruct(Default = "0")]
+    played: u8,
+}
+
+mod generic {
+    #[dbstruct::dbstruct(db=trait)]
+    pub struct Test {
+        pub queue: Vec<u32>,
+    }
+}
+
+type Error = dbstruct::Error<dbstruct::Error<dbstruct::sled::transaction::UnabortableTransactionError>>;
+
+fn play_next(db: &Test) -> Result<Option<u32>, TransactionError<Error, dbstruct::sled::Error>> {
+    db.transaction(|tx| {
+        let Some(song) = tx.queue().pop()? else {
+            return Ok(None);
+        };
+        tx.history().push(&song)?;
+        let played = tx.played().get()?;
+        tx.played().set(&(played + 1))?;
+        Ok(Some(song))
+    })
+}
+
+#[test]
+fn moves_between_fields() {
+    let dir = tempdir::TempDir::new("dbstruct_test").unwrap();
+    let db = Test::new(dir.path().join("db")).unwrap();
+    db.queue().push(&1).unwrap();
+    db.queue().push(&2).unwrap();
+
+    assert_eq!(play_next(&db).unwrap(), Some(2));
+    assert_eq!(play_next(&db).unwrap(), Some(1));
+    assert_eq!(play_next(&db).unwrap(), None);
+
+    assert!(db.queue().is_empty());
+    assert_eq!(db.history().len(), 2);
+    assert_eq!(db.history().get(0).unwrap(), Some(2));
+    assert_eq!(db.played().get().unwrap(), 2);
+}
+
+#[test]
+fn abort_discards_changes() {
+    let dir = tempdir::TempDir::new("dbstruct_test").unwrap();
+    let db = Test::new(dir.path().join("db")).unwrap();
+    db.queue().push(&1).unwrap();
+
+    let res = db.transaction(|tx| {
+        let song = tx.queue().pop().unwrap().unwrap();
+        tx.history().push(&song).unwrap();
+        Err::<(), _>("changed my mind")
+    });
+    assert!(matches!(res, Err(TransactionError::Abort("changed my mind"))));
+
+    assert_eq!(db.queue().len(), 1);
+    assert_eq!(db.queue().get(0).unwrap(), Some(1));
+    assert!(db.history().is_empty());
+}
+
+#[test]
+fn generic_store() {
+    let ds = sled::Config::default()
+        .temporary(true)
+        .open()
+        .unwrap()
+        .open_tree("test")
+        .unwrap();
+    let db = generic::Test::new(ds).unwrap();
+    db.transaction(|tx| {
+        tx.queue().push(&1)?;
+        tx.queue().push(&2)
+    })
+    .unwrap();
+    assert_eq!(db.queue().len(), 2);
+}
+
+#[test]
+fn deque_in_transaction() {
+    let dir = tempdir::TempDir::new("dbstruct_test").unwrap();
+    let db = Test::new(dir.path().join("db")).unwrap();
+    db.retries().push_back(&1).unwrap();
+
+    db.transaction(|tx| {
+        let song = tx.retries().pop_front()?.unwrap();
+        tx.retries().push_back(&(song + 1))?;
+        tx.retries().push_front(&0)
+    })
+    .unwrap();
+    assert_eq!(db.retries().len(), 2);
+    assert_eq!(db.retries().front().unwrap(), Some(0));
+    assert_eq!(db.retries().back().unwrap(), Some(2));
+}
+
+#[cfg(feature = "rocksdb")]
+mod rocksdb {
+    #[dbstruct::dbstruct(db=rocksdb)]
+    pub struct Test {
+        queue: Vec<u32>,
+        #[dbstruct(Default)]
+        played: u8,
+        #[dbstruct(Counter)]
+        plays: u64,
+    }
+
+    #[test]
+    fn atomic_wrappers_in_transaction() {
+        let dir = tempdir::TempDir::new("dbstruct_test").unwrap();
+        let db = Test::new(dir.path().join("db")).unwrap();
+        db.queue().push(&1).unwrap();
+
+        db.transaction(|tx| {
+            tx.queue().pop()?;
+            tx.played().update(|p| p + 1)?;
+            tx.played().conditional_update(1, 2)?;
+            tx.plays().increment()
+        })
+        .unwrap();
+        assert!(db.queue().is_empty());
+        assert_eq!(db.played().get().unwrap(), 2);
+        assert_eq!(db.plays().get().unwrap(), 1);
+    }
+}
@@ -1,4 +1,7 @@
 #[test]
+// the async wrappers share their names with the blocking ones which
+// changes how rustc names them in the expected errors
+#[cfg_attr(feature = "async", ignore = "snapshots are for the default features")]
 fn ui() {
     let t = trybuild::TestCases::new();
     t.compile_fail("tests/ui/*.rs")
@@ -0,0 +1,9 @@
+use dbstruct::dbstruct;
+
+#[dbstruct(db=trait)]
+struct Test {
+    #[dbstruct(codec=json)]
+    field: Option<u8>,
+}
+
+fn main() {}
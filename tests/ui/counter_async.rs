@@ -0,0 +1,9 @@
+use dbstruct::dbstruct;
+
+#[dbstruct(db=sled, async)]
+struct Test {
+    #[dbstruct(Counter)]
+    visits: u64,
+}
+
+fn main() {}
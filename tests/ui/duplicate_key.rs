@@ -0,0 +1,11 @@
+use dbstruct::dbstruct;
+
+#[dbstruct(db=sled)]
+struct Test {
+    #[dbstruct(key = 1)]
+    first: Option<u8>,
+    #[dbstruct(key = 1)]
+    second: Option<u8>,
+}
+
+fn main() {}
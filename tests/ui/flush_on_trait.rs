@@ -0,0 +1,9 @@
+use dbstruct::dbstruct;
+
+#[dbstruct(db=trait)]
+struct Test {
+    #[dbstruct(flush = "every_write")]
+    field: Option<u8>,
+}
+
+fn main() {}
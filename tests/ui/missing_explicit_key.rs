@@ -0,0 +1,10 @@
+use dbstruct::dbstruct;
+
+#[dbstruct(db=sled, explicit_keys)]
+struct Test {
+    #[dbstruct(key = 0)]
+    pinned: Option<u8>,
+    unpinned: Option<u8>,
+}
+
+fn main() {}
@@ -0,0 +1,55 @@
+#[dbstruct::dbstruct(db=btreemap)]
+pub struct Test {
+    #[dbstruct(Default = "10")]
+    credits: u32,
+    #[dbstruct(Default)]
+    visits: u32,
+    last_seen: Option<u64>,
+}
+
+#[test]
+fn missing_values_start_from_default() {
+    let db = Test::new().unwrap();
+    db.credits().update(|c| c - 1).unwrap();
+    assert_eq!(db.credits().get().unwrap(), 9);
+    db.visits().update(|v| v + 1).unwrap();
+    assert_eq!(db.visits().get().unwrap(), 1);
+    db.last_seen().update(|t| t.or(Some(5))).unwrap();
+    assert_eq!(db.last_seen().get().unwrap(), Some(5));
+}
+
+#[test]
+fn fetch_old_or_new() {
+    let db = Test::new().unwrap();
+    assert_eq!(db.credits().fetch_update(|c| c * 2).unwrap(), 10);
+    assert_eq!(db.credits().update_and_fetch(|c| c + 1).unwrap(), 21);
+    assert_eq!(db.visits().fetch_update(|v| v + 1).unwrap(), 0);
+    assert_eq!(db.visits().update_and_fetch(|v| v + 1).unwrap(), 2);
+
+    assert_eq!(db.last_seen().fetch_update(|_| Some(1)).unwrap(), None);
+    assert_eq!(db.last_seen().update_and_fetch(|_| None).unwrap(), None);
+    assert_eq!(db.last_seen().get().unwrap(), None);
+}
+
+#[cfg(feature = "async")]
+mod asynchronous {
+    #[dbstruct::dbstruct(db=btreemap, async)]
+    pub struct Test {
+        #[dbstruct(Default = "10")]
+        credits: u32,
+        #[dbstruct(Default)]
+        visits: u32,
+        last_seen: Option<u64>,
+    }
+
+    #[tokio::test]
+    async fn missing_values_start_from_default() {
+        let db = Test::new().await.unwrap();
+        assert_eq!(db.credits().update_and_fetch(|c| c - 1).await.unwrap(), 9);
+        assert_eq!(db.visits().fetch_update(|v| v + 1).await.unwrap(), 0);
+        assert_eq!(db.visits().get().await.unwrap(), 1);
+        let before = db.last_seen().fetch_update(|_| Some(3)).await.unwrap();
+        assert_eq!(before, None);
+        assert_eq!(db.last_seen().get().await.unwrap(), Some(3));
+    }
+}
@@ -0,0 +1,46 @@
+#[dbstruct::dbstruct(db=sled)]
+pub struct Test {
+    jobs: VecDeque<String>,
+    #[dbstruct(Default)]
+    the_field: u8,
+}
+
+#[test]
+fn both_ends() {
+    let dir = tempdir::TempDir::new("dbstruct_test").unwrap();
+    let db = Test::new(dir.path().join("db")).unwrap();
+    db.the_field().set(&1).unwrap();
+
+    for job in ["b", "c"] {
+        db.jobs().push_back(&job.to_owned()).unwrap();
+    }
+    db.jobs().push_front(&"a".to_owned()).unwrap();
+
+    let jobs: Vec<String> = db.jobs().iter().map(Result::unwrap).collect();
+    assert_eq!(jobs, vec!["a", "b", "c"]);
+    let reversed: Vec<String> = db.jobs().iter().rev().map(Result::unwrap).collect();
+    assert_eq!(reversed, vec!["c", "b", "a"]);
+
+    assert_eq!(db.jobs().pop_front().unwrap(), Some("a".to_owned()));
+    assert_eq!(db.jobs().back().unwrap(), Some("c".to_owned()));
+    assert_eq!(db.jobs().len(), 2);
+    assert_eq!(db.the_field().get().unwrap(), 1);
+}
+
+#[test]
+fn persists() {
+    let dir = tempdir::TempDir::new("dbstruct_test").unwrap();
+    let path = dir.path().join("db");
+    {
+        let db = Test::new(&path).unwrap();
+        db.jobs().push_front(&"b".to_owned()).unwrap();
+        db.jobs().push_front(&"a".to_owned()).unwrap();
+        db.jobs().push_back(&"c".to_owned()).unwrap();
+        db.jobs().pop_back().unwrap();
+    }
+
+    let db = Test::new(&path).unwrap();
+    assert_eq!(db.jobs().len(), 2);
+    assert_eq!(db.jobs().front().unwrap(), Some("a".to_owned()));
+    assert_eq!(db.jobs().get(1).unwrap(), Some("b".to_owned()));
+}
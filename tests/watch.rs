@@ -0,0 +1,236 @@
+use dbstruct::wrapper::{MapEvent, SetEvent, ValueEvent, VecDequeEvent, VecEvent};
+
+#[dbstruct::dbstruct(db=sled)]
+pub struct Test {
+    #[dbstruct(Default)]
+    volume: u8,
+    name: Option<String>,
+    queue: Vec<u32>,
+    jobs: VecDeque<u8>,
+    names: HashMap<u8, String>,
+    seen: HashSet<u32>,
+}
+
+#[test]
+fn values() {
+    let dir = tempdir::TempDir::new("dbstruct_test").unwrap();
+    let db = Test::new(dir.path().join("db")).unwrap();
+    db.volume().set(&1).unwrap();
+
+    let watcher = db.volume().watch().unwrap();
+    let names = db.name().watch().unwrap();
+    db.volume().set(&2).unwrap();
+    db.name().set(&"a".to_owned()).unwrap();
+    db.volume().set(&3).unwrap();
+
+    let volumes: Vec<_> = watcher.take(2).map(Result::unwrap).collect();
+    assert_eq!(volumes, vec![ValueEvent::Set(2), ValueEvent::Set(3)]);
+    let names: Vec<_> = names.take(1).map(Result::unwrap).collect();
+    assert_eq!(names, vec![ValueEvent::Set("a".to_owned())]);
+}
+
+#[test]
+fn vec() {
+    let dir = tempdir::TempDir::new("dbstruct_test").unwrap();
+    let db = Test::new(dir.path().join("db")).unwrap();
+    db.queue().push(&1).unwrap();
+
+    let watcher = db.queue().watch().unwrap();
+    db.queue().push(&2).unwrap();
+    db.queue().set(0, &5).unwrap();
+    db.queue().pop().unwrap();
+    db.queue().clear().unwrap();
+
+    let events: Vec<_> = watcher.take(4).map(Result::unwrap).collect();
+    assert_eq!(
+        events,
+        vec![
+            VecEvent::Pushed(2),
+            VecEvent::Set { index: 0, value: 5 },
+            VecEvent::Popped,
+            VecEvent::Popped,
+        ]
+    );
+}
+
+#[test]
+fn vec_deque() {
+    let dir = tempdir::TempDir::new("dbstruct_test").unwrap();
+    let db = Test::new(dir.path().join("db")).unwrap();
+
+    let watcher = db.jobs().watch().unwrap();
+    db.jobs().push_back(&1).unwrap();
+    db.jobs().push_front(&0).unwrap();
+    db.jobs().pop_back().unwrap();
+
+    let events: Vec<_> = watcher.take(3).map(Result::unwrap).collect();
+    assert_eq!(
+        events,
+        vec![
+            VecDequeEvent::PushedBack(1),
+            VecDequeEvent::PushedFront(0),
+            VecDequeEvent::PoppedBack,
+        ]
+    );
+}
+
+#[test]
+fn map_and_set() {
+    let dir = tempdir::TempDir::new("dbstruct_test").unwrap();
+    let db = Test::new(dir.path().join("db")).unwrap();
+
+    let names = db.names().watch().unwrap();
+    let seen = db.seen().watch().unwrap();
+    db.names().insert(&1, &"one".to_owned()).unwrap();
+    db.seen().insert(&7).unwrap();
+    db.names().remove(&1).unwrap();
+    db.seen().remove(&7).unwrap();
+
+    let names: Vec<_> = names.take(2).map(Result::unwrap).collect();
+    assert_eq!(
+        names,
+        vec![
+            MapEvent::Inserted(1, "one".to_owned()),
+            MapEvent::Removed(1)
+        ]
+    );
+    let seen: Vec<_> = seen.take(2).map(Result::unwrap).collect();
+    assert_eq!(seen, vec![SetEvent::Inserted(7), SetEvent::Removed(7)]);
+}
+
+mod broadcast {
+    use dbstruct::wrapper::{ValueEvent, VecDequeEvent};
+
+    #[dbstruct::dbstruct(db=btreemap)]
+    pub struct Test {
+        #[dbstruct(Default)]
+        volume: u8,
+        jobs: VecDeque<u32>,
+    }
+
+    #[test]
+    fn in_process_changes() {
+        let db = Test::new().unwrap();
+        let volume = db.volume().watch().unwrap();
+        let jobs = db.jobs().watch().unwrap();
+
+        let handle = std::thread::spawn(move || {
+            let volume: Vec<_> = volume.take(1).map(Result::unwrap).collect();
+            let jobs: Vec<_> = jobs.take(2).map(Result::unwrap).collect();
+            (volume, jobs)
+        });
+        db.jobs().push_back(&4).unwrap();
+        db.volume().set(&9).unwrap();
+        db.jobs().pop_front().unwrap();
+
+        let (volume, jobs) = handle.join().unwrap();
+        assert_eq!(volume, vec![ValueEvent::Set(9)]);
+        assert_eq!(
+            jobs,
+            vec![VecDequeEvent::PushedBack(4), VecDequeEvent::PoppedFront]
+        );
+    }
+
+    #[test]
+    fn ends_when_store_is_dropped() {
+        let db = Test::new().unwrap();
+        let watcher = db.volume().watch().unwrap();
+        db.volume().set(&1).unwrap();
+        drop(db);
+
+        let events: Vec<_> = watcher.map(Result::unwrap).collect();
+        assert_eq!(events, vec![ValueEvent::Set(1)]);
+    }
+}
+
+mod hashmap {
+    use dbstruct::wrapper::ValueEvent;
+
+    #[dbstruct::dbstruct(db=hashmap)]
+    pub struct Test {
+        name: Option<String>,
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn removing_nothing_is_not_a_change() {
+        let db = Test::new().unwrap();
+        let watcher = db.name().watch().unwrap();
+        db.name().take().unwrap();
+        db.name().set(&"a".to_owned()).unwrap();
+        db.name().take().unwrap();
+        drop(db);
+
+        let events: Vec<_> = watcher.map(Result::unwrap).collect();
+        assert_eq!(
+            events,
+            vec![ValueEvent::Set("a".to_owned()), ValueEvent::Removed]
+        );
+    }
+}
+
+#[cfg(feature = "rocksdb")]
+mod rocksdb {
+    use dbstruct::wrapper::{ValueEvent, VecEvent};
+
+    #[dbstruct::dbstruct(db=rocksdb)]
+    pub struct Test {
+        #[dbstruct(Default)]
+        volume: u8,
+        queue: Vec<u32>,
+    }
+
+    #[test]
+    fn transactions_are_published_on_commit() {
+        let dir = tempdir::TempDir::new("dbstruct_test").unwrap();
+        let db = Test::new(dir.path().join("db")).unwrap();
+        let volume = db.volume().watch().unwrap();
+        let queue = db.queue().watch().unwrap();
+
+        db.volume().set(&1).unwrap();
+        db.transaction(|tx| {
+            tx.queue().push(&5)?;
+            tx.volume().set(&2)
+        })
+        .unwrap();
+        drop(db);
+
+        let volume: Vec<_> = volume.map(Result::unwrap).collect();
+        assert_eq!(volume, vec![ValueEvent::Set(1), ValueEvent::Set(2)]);
+        let queue: Vec<_> = queue.map(Result::unwrap).collect();
+        assert_eq!(queue, vec![VecEvent::Pushed(5)]);
+    }
+}
+
+#[cfg(feature = "async")]
+mod stream {
+    use dbstruct::wrapper::{MapEvent, VecEvent};
+    use futures_core::Stream;
+
+    #[dbstruct::dbstruct(db=sled, async)]
+    pub struct Test {
+        queue: Vec<u32>,
+        names: BTreeMap<u8, String>,
+    }
+
+    /// polls the stream to its next item
+    async fn next<S: Stream + Unpin>(stream: &mut S) -> Option<S::Item> {
+        core::future::poll_fn(|cx| core::pin::Pin::new(&mut *stream).poll_next(cx)).await
+    }
+
+    #[tokio::test]
+    async fn events_as_stream() {
+        let dir = tempdir::TempDir::new("dbstruct_test").unwrap();
+        let db = Test::new(dir.path().join("db")).await.unwrap();
+
+        let mut queue = db.queue().watch().await.unwrap();
+        let mut names = db.names().watch().await.unwrap();
+        db.queue().push(&3).await.unwrap();
+        db.names().insert(&2, &"two".to_owned()).await.unwrap();
+
+        let event = next(&mut queue).await.unwrap().unwrap();
+        assert_eq!(event, VecEvent::Pushed(3));
+        let event = next(&mut names).await.unwrap().unwrap();
+        assert_eq!(event, MapEvent::Inserted(2, "two".to_owned()));
+    }
+}
@@ -0,0 +1,73 @@
+#[dbstruct::dbstruct(db=sled, wide_keys)]
+pub struct Test {
+    #[dbstruct(Default)]
+    counter: u32,
+    #[dbstruct(key = 1000)]
+    name: Option<String>,
+    #[dbstruct(key = 256)]
+    primes: Vec<u32>,
+    #[dbstruct(key = 255)]
+    lookup: HashMap<u8, String>,
+}
+
+#[test]
+fn keys_above_a_byte() {
+    let dir = tempdir::TempDir::new("dbstruct_test").unwrap();
+    let path = dir.path().join("db");
+    {
+        let db = Test::new(&path).unwrap();
+        db.counter().set(&3).unwrap();
+        db.name().set(&"wide".to_owned()).unwrap();
+        for prime in [2, 3, 5] {
+            db.primes().push(&prime).unwrap();
+        }
+        db.lookup().insert(&1, &"one".to_owned()).unwrap();
+    }
+
+    let db = Test::new(&path).unwrap();
+    assert_eq!(db.counter().get().unwrap(), 3);
+    assert_eq!(db.name().get().unwrap(), Some("wide".to_owned()));
+    assert_eq!(db.primes().len(), 3);
+    assert_eq!(db.primes().pop().unwrap(), Some(5));
+    let lookup: Vec<_> = db.lookup().iter().map(Result::unwrap).collect();
+    assert_eq!(lookup, vec![(1, "one".to_owned())]);
+}
+
+#[test]
+fn stored_under_two_bytes() {
+    let dir = tempdir::TempDir::new("dbstruct_test").unwrap();
+    let path = dir.path().join("db");
+    {
+        let db = Test::new(&path).unwrap();
+        db.name().set(&"wide".to_owned()).unwrap();
+    }
+
+    let tree = dbstruct::sled::open(&path)
+        .unwrap()
+        .open_tree("DbStruct")
+        .unwrap();
+    assert!(tree.contains_key(1000u16.to_be_bytes()).unwrap());
+}
+
+#[dbstruct::dbstruct(db=sled)]
+pub struct Narrow {
+    #[dbstruct(Default)]
+    counter: u32,
+}
+
+#[test]
+fn switching_key_width_is_a_schema_change() {
+    let db = dbstruct::sled::Config::default()
+        .temporary(true)
+        .open()
+        .unwrap();
+    NarrowBuilder::from_db(&db).open().unwrap();
+
+    let Err(err) = TestBuilder::from_db(&db).open() else {
+        panic!("opening should fail as the fields moved to two byte keys");
+    };
+    assert!(matches!(
+        err,
+        dbstruct::Error::Schema(dbstruct::schema::Error::KeyWidthChanged)
+    ));
+}
@@ -1,6 +1,5 @@
 use std::error::Error;
-use std::sync::atomic::AtomicUsize;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 use dbstruct::traits::data_store;
 use dbstruct::traits::DataStore;
@@ -37,7 +36,8 @@ struct MacroInput {
 // note the macro would use absolute paths for everything
 pub struct MacroOutput<DS: DataStore> {
     ds: DS,
-    queue_len: Arc<AtomicUsize>,
+    prefix: wrapper::Prefix,
+    queue_len: Arc<Mutex<usize>>,
 }
 
 impl<DS> MacroOutput<DS>
@@ -45,31 +45,31 @@ where
     DS: DataStore + data_store::Ordered + Clone,
 {
     pub fn new(ds: DS) -> Result<Self, dbstruct::Error<<DS as DataStore>::Error>> {
-        let queue_len = data_store::Ordered::get_lt(&ds, &(1+1))?
-            .map(|(len, _): (usize, Song)| len)
-            .unwrap_or(0);
+        let prefix = wrapper::Prefix::root();
+        let queue_len = wrapper::stored_len::<Song, _>(&ds, prefix.field(1))?;
         tracing::debug!("opening vector queue with len: {queue_len}");
         Ok(Self {
             ds,
-            queue_len: Arc::new(AtomicUsize::new(queue_len)),
+            prefix,
+            queue_len: Arc::new(Mutex::new(queue_len)),
         })
     }
 
     #[instrument(skip_all)]
     pub fn queue(&self) -> wrapper::Vec<Song, DS> {
-        wrapper::Vec::new(self.ds.clone(), 1, self.queue_len.clone())
+        wrapper::Vec::new(self.ds.clone(), self.prefix.field(1), self.queue_len.clone())
     }
     #[instrument(skip_all)]
     pub fn playing(&self) -> wrapper::DefaultValue<bool, DS> {
-        wrapper::DefaultValue::new(self.ds.clone(), 2, PLAYING_DEFAULT)
+        wrapper::DefaultValue::new(self.ds.clone(), self.prefix.field(2), PLAYING_DEFAULT)
     }
     #[instrument(skip_all)]
     pub fn preferences(&self) -> wrapper::DefaultTrait<Preferences, DS> {
-        wrapper::DefaultTrait::new(self.ds.clone(), 3)
+        wrapper::DefaultTrait::new(self.ds.clone(), self.prefix.field(3))
     }
     #[instrument(skip_all)]
     pub fn account(&self) -> wrapper::OptionValue<Account, DS> {
-        wrapper::OptionValue::new(self.ds.clone(), 4)
+        wrapper::OptionValue::new(self.ds.clone(), self.prefix.field(4))
     }
 }
 // end macro output
@@ -98,7 +98,10 @@ pub fn main() -> Result<(), Box<dyn Error>> {
     let account = db.account().get()?;
     assert_eq!(account, Some(Account {}));
 
-    db.account().conditional_update(Account {}, Account {})?;
+    let outcome = db.account().conditional_update(Some(Account {}), None)?;
+    assert_eq!(outcome, dbstruct::CasOutcome::Swapped);
+    let outcome = db.account().conditional_update(Some(Account {}), None)?;
+    assert_eq!(outcome, dbstruct::CasOutcome::Mismatch { current: None });
 
     Ok(())
 }